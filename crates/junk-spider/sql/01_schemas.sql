@@ -4,6 +4,7 @@ CREATE SCHEMA IF NOT EXISTS common;
 CREATE SCHEMA IF NOT EXISTS crypto;
 CREATE SCHEMA IF NOT EXISTS econ;
 -- CREATE SCHEMA IF NOT EXISTS forex;
+CREATE SCHEMA IF NOT EXISTS portfolio;
 CREATE SCHEMA IF NOT EXISTS stock;
 
 CREATE SCHEMA IF NOT EXISTS test;
@@ -3,7 +3,8 @@
 --------------------------------------------------------------------------------------- 
 
 -- data intervals, e.g., 1m, 5m, 1hr, 1d, 1wk, 1mo, 1yr
--- currently only supporting 30m, 1h, 1d, 1w; only 1d being used
+-- currently only supporting 30m, 1h, 1d, 1w, 1m, 5m & 15m; 1m from the Binance archive & Yahoo,
+-- 5m & 15m only from Yahoo
 CREATE TABLE IF NOT EXISTS common.intervals (
 	pk SMALLSERIAL PRIMARY KEY,
 	interval CHAR(3) NOT NULL
@@ -16,6 +17,23 @@ WHERE NOT EXISTS (
     SELECT 1 FROM common.intervals WHERE interval IN ('30m', '1h', '1d', '1w')
 );
 
+-- 1m candles, backfilled from the Binance public data archive; an explicit pk, as the code
+-- hard-codes it
+INSERT INTO common.intervals (pk, interval)
+VALUES (5, '1m')
+ON CONFLICT DO NOTHING;
+
+-- 5m & 15m intraday stock candles, with explicit pks as conflicts still consume the serial
+INSERT INTO common.intervals (pk, interval)
+VALUES (6, '5m'), (7, '15m')
+ON CONFLICT DO NOTHING;
+
+-- move the serial past the explicit pks, so that later intervals don't collide with them
+SELECT setval(
+    pg_get_serial_sequence('common.intervals', 'pk'),
+    (SELECT MAX(pk) FROM common.intervals)
+);
+
 --------------------------------------------------------------------------------------
 -- CRYPTO
 --------------------------------------------------------------------------------------
@@ -39,12 +57,34 @@ CREATE TABLE IF NOT EXISTS crypto.prices (
 	volume FLOAT,
 	trades BIGINT,
 	source_pk SMALLINT,
+	closed BOOLEAN NOT NULL DEFAULT FALSE,
 	PRIMARY KEY (symbol_pk, dt, interval_pk, source_pk)
 );
 CREATE INDEX IF NOT EXISTS idx_symbol_pk ON crypto.prices(symbol_pk);
 CREATE INDEX IF NOT EXISTS idx_interval_pk ON crypto.prices(interval_pk);
 CREATE INDEX IF NOT EXISTS idx_dt ON crypto.prices(dt);
 
+-- whether the candle's interval had ended when it was fetched; open candles are revised on every
+-- fetch, and existing rows are assumed open until the source confirms them
+ALTER TABLE crypto.prices
+ADD COLUMN IF NOT EXISTS closed BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- previous values of every candle overwritten by a later fetch, e.g. the still-open daily candle
+CREATE TABLE IF NOT EXISTS crypto.price_revisions (
+	symbol_pk INT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	interval_pk SMALLINT,
+	source_pk SMALLINT,
+	revised TIMESTAMP WITH TIME ZONE NOT NULL,
+	opening FLOAT,
+	high FLOAT,
+	low FLOAT,
+	closing FLOAT,
+	volume FLOAT,
+	trades BIGINT,
+	PRIMARY KEY (symbol_pk, dt, interval_pk, source_pk, revised)
+);
+
 -- which broker the data came from, e.g. binance, kucoin, mexc
 CREATE TABLE IF NOT EXISTS crypto.sources (
 	pk SMALLSERIAL PRIMARY KEY,
@@ -52,6 +92,167 @@ CREATE TABLE IF NOT EXISTS crypto.sources (
 );
 CREATE UNIQUE INDEX IF NOT EXISTS idx_source ON crypto.sources(source);
 
+-- 24h ticker statistics, snapshotted from each broker on every run
+CREATE TABLE IF NOT EXISTS crypto.tickers_24h (
+	symbol_pk INT,
+	source_pk SMALLINT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	last_price FLOAT,
+	price_change FLOAT,
+	price_change_perc FLOAT,
+	high FLOAT,
+	low FLOAT,
+	volume FLOAT,
+	quote_volume FLOAT,
+	trades BIGINT,
+	PRIMARY KEY (symbol_pk, source_pk, dt)
+);
+CREATE INDEX IF NOT EXISTS idx_tickers_24h_dt ON crypto.tickers_24h(dt);
+
+-- exchange instrument metadata; `status` is as the broker names it, or 'delisted' once the
+-- pair no longer appears in the broker's listing
+CREATE TABLE IF NOT EXISTS crypto.instruments (
+	symbol_pk INT,
+	source_pk SMALLINT,
+	exchange_symbol VARCHAR NOT NULL,
+	base_asset VARCHAR,
+	quote_asset VARCHAR,
+	status VARCHAR NOT NULL,
+	is_trading BOOL NOT NULL,
+	tick_size FLOAT,
+	lot_size FLOAT,
+	first_seen DATE NOT NULL,
+	last_seen DATE NOT NULL,
+	PRIMARY KEY (symbol_pk, source_pk)
+);
+CREATE INDEX IF NOT EXISTS idx_instruments_trading ON crypto.instruments(source_pk, is_trading);
+
+-- perpetual futures funding rates, per funding event
+CREATE TABLE IF NOT EXISTS crypto.funding_rates (
+	symbol_pk INT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	funding_rate FLOAT NOT NULL,
+	mark_price FLOAT,
+	source_pk SMALLINT,
+	PRIMARY KEY (symbol_pk, dt, source_pk)
+);
+CREATE INDEX IF NOT EXISTS idx_funding_rates_dt ON crypto.funding_rates(dt);
+
+-- perpetual futures open interest, in base units & quote value
+CREATE TABLE IF NOT EXISTS crypto.open_interest (
+	symbol_pk INT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	interval_pk SMALLINT,
+	open_interest FLOAT,
+	open_interest_value FLOAT,
+	source_pk SMALLINT,
+	PRIMARY KEY (symbol_pk, dt, interval_pk, source_pk)
+);
+CREATE INDEX IF NOT EXISTS idx_open_interest_dt ON crypto.open_interest(dt);
+
+-- volume-weighted reference price per market (base/quote), across every source; `symbol_pk`
+-- is the market's canonical symbol, e.g. BTCUSDT
+CREATE TABLE IF NOT EXISTS crypto.composite_prices (
+	symbol_pk INT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	interval_pk SMALLINT,
+	opening FLOAT,
+	high FLOAT,
+	low FLOAT,
+	closing FLOAT,
+	volume FLOAT,
+	sources SMALLINT NOT NULL,
+	PRIMARY KEY (symbol_pk, dt, interval_pk)
+);
+CREATE INDEX IF NOT EXISTS idx_composite_prices_dt ON crypto.composite_prices(dt);
+
+-- each source's close & its deviation from the composite close, in %
+CREATE TABLE IF NOT EXISTS crypto.composite_deviations (
+	symbol_pk INT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	interval_pk SMALLINT,
+	source_pk SMALLINT,
+	closing FLOAT,
+	deviation_perc FLOAT,
+	PRIMARY KEY (symbol_pk, dt, interval_pk, source_pk)
+);
+
+-- normalised base & quote asset of each symbol, per source, e.g. Kraken's XBTUSDT = BTC/USDT
+CREATE TABLE IF NOT EXISTS crypto.markets (
+	symbol_pk INT,
+	source_pk SMALLINT,
+	base_asset VARCHAR NOT NULL,
+	quote_asset VARCHAR NOT NULL,
+	PRIMARY KEY (symbol_pk, source_pk)
+);
+CREATE INDEX IF NOT EXISTS idx_markets_quote_asset ON crypto.markets(quote_asset);
+
+-- USD value of one unit of an asset, and the path of markets it was derived from,
+-- e.g. 'EUR/USDT * USDT=1' or 'ETH/BTC * BTC/USD'
+CREATE TABLE IF NOT EXISTS crypto.usd_rates (
+	asset VARCHAR,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	interval_pk SMALLINT,
+	rate FLOAT NOT NULL,
+	path VARCHAR NOT NULL,
+	PRIMARY KEY (asset, dt, interval_pk)
+);
+
+-- candle gaps the source itself could not fill when repaired, e.g. exchange downtime or history
+-- beyond the source's limit; `gap_start` & `gap_end` are the first & last missing candles
+CREATE TABLE IF NOT EXISTS crypto.price_gaps (
+	symbol_pk INT,
+	source_pk SMALLINT,
+	interval_pk SMALLINT,
+	gap_start TIMESTAMP WITH TIME ZONE NOT NULL,
+	gap_end TIMESTAMP WITH TIME ZONE NOT NULL,
+	missing BIGINT NOT NULL,
+	checked TIMESTAMP WITH TIME ZONE NOT NULL,
+	PRIMARY KEY (symbol_pk, source_pk, interval_pk, gap_start)
+);
+
+--------------------------------------------------------------------------------------
+-- PORTFOLIO
+--------------------------------------------------------------------------------------
+
+-- exchange account balances, snapshotted on every sync; `source_pk` is from crypto.sources
+CREATE TABLE IF NOT EXISTS portfolio.balances (
+	source_pk SMALLINT,
+	asset VARCHAR,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	free FLOAT NOT NULL,
+	locked FLOAT NOT NULL,
+	PRIMARY KEY (source_pk, asset, dt)
+);
+
+-- deposits & withdrawals; `status` is as the exchange names it
+CREATE TABLE IF NOT EXISTS portfolio.transfers (
+	source_pk SMALLINT,
+	tx_id VARCHAR,
+	kind VARCHAR,
+	asset VARCHAR NOT NULL,
+	amount FLOAT NOT NULL,
+	fee FLOAT,
+	status VARCHAR NOT NULL,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	PRIMARY KEY (source_pk, tx_id, kind)
+);
+
+-- trade executions; `side` is 'buy' or 'sell', `quantity` in the base asset
+CREATE TABLE IF NOT EXISTS portfolio.fills (
+	source_pk SMALLINT,
+	symbol_pk INT,
+	trade_id VARCHAR,
+	side VARCHAR NOT NULL,
+	price FLOAT NOT NULL,
+	quantity FLOAT NOT NULL,
+	fee FLOAT,
+	fee_asset VARCHAR,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	PRIMARY KEY (source_pk, symbol_pk, trade_id)
+);
+CREATE INDEX IF NOT EXISTS idx_fills_dt ON portfolio.fills(dt);
+
 --------------------------------------------------------------------------------------
 -- STOCK
 --------------------------------------------------------------------------------------
@@ -79,6 +280,30 @@ CREATE TABLE IF NOT EXISTS stock.symbols (
 	PRIMARY KEY (symbol, title, nation, industry)
 );
 CREATE INDEX IF NOT EXISTS idx_symbol ON stock.symbols(symbol);
+
+-- where the symbol is listed, e.g. LSE, its trading currency & the suffix Yahoo appends to it,
+-- e.g. ".L"; SEC symbols are all US listings, in USD, without a suffix
+ALTER TABLE stock.symbols
+ADD COLUMN IF NOT EXISTS exchange VARCHAR,
+ADD COLUMN IF NOT EXISTS currency VARCHAR NOT NULL DEFAULT 'USD',
+ADD COLUMN IF NOT EXISTS yahoo_suffix VARCHAR NOT NULL DEFAULT '';
+
+-- the exchange's timezone & local time its session opens, which dates the daily prices of the
+-- fallback providers as Yahoo does; SEC symbols trade in New York
+ALTER TABLE stock.symbols
+ADD COLUMN IF NOT EXISTS timezone VARCHAR NOT NULL DEFAULT 'America/New_York',
+ADD COLUMN IF NOT EXISTS session_open TIME NOT NULL DEFAULT '09:30';
+
+-- 'equity', or a benchmark's 'index' or 'etf'; & the SEC's SIC code of an equity, e.g. 3571
+ALTER TABLE stock.symbols
+ADD COLUMN IF NOT EXISTS asset_class VARCHAR NOT NULL DEFAULT 'equity',
+ADD COLUMN IF NOT EXISTS sic SMALLINT;
+
+-- the default benchmark of each US equity, i.e. its sector SPDR by SIC code, else the S&P 500
+CREATE TABLE IF NOT EXISTS stock.benchmarks (
+	symbol_pk INT PRIMARY KEY,
+	benchmark_pk INT NOT NULL
+);
 CREATE INDEX IF NOT EXISTS idx_title ON stock.symbols(title);
 
 -- prices value table
@@ -92,9 +317,165 @@ CREATE TABLE IF NOT EXISTS stock.prices (
 	closing FLOAT,
 	adj_close FLOAT,
 	volume BIGINT,
+	closed BOOLEAN NOT NULL DEFAULT FALSE,
+	local_dt TIMESTAMP,
+	currency VARCHAR,
+	provider VARCHAR NOT NULL DEFAULT 'Yahoo',
 	PRIMARY KEY (symbol_pk, interval_pk, dt)
 );
 
+-- whether the trading day had ended when the price was fetched; see crypto.prices
+ALTER TABLE stock.prices
+ADD COLUMN IF NOT EXISTS closed BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- `dt` as the exchange's wall clock (meta.exchangeTimezoneName), so that intraday candles group
+-- into trading sessions by `local_dt::DATE`
+ALTER TABLE stock.prices
+ADD COLUMN IF NOT EXISTS local_dt TIMESTAMP;
+
+-- the currency the price is quoted in (meta.currency), e.g. "USD", or "GBp" for pence
+ALTER TABLE stock.prices
+ADD COLUMN IF NOT EXISTS currency VARCHAR;
+
+-- the provider the price was collected from, i.e. "Yahoo", else a fallback such as "Stooq"
+ALTER TABLE stock.prices
+ADD COLUMN IF NOT EXISTS provider VARCHAR NOT NULL DEFAULT 'Yahoo';
+
+-- previous values of every price overwritten by a later fetch, i.e. the still-open trading day,
+-- or `adj_close` after a dividend or split
+CREATE TABLE IF NOT EXISTS stock.price_revisions (
+	symbol_pk INT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	interval_pk SMALLINT,
+	revised TIMESTAMP WITH TIME ZONE NOT NULL,
+	opening FLOAT,
+	high FLOAT,
+	low FLOAT,
+	closing FLOAT,
+	adj_close FLOAT,
+	volume BIGINT,
+	PRIMARY KEY (symbol_pk, dt, interval_pk, revised)
+);
+
+-- dividends per ex-date, split-adjusted as Yahoo reports them
+CREATE TABLE IF NOT EXISTS stock.dividends (
+	symbol_pk INT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	amount FLOAT NOT NULL,
+	PRIMARY KEY (symbol_pk, dt)
+);
+
+-- stock splits, e.g. a 4:1 split has a numerator of 4 & a denominator of 1
+CREATE TABLE IF NOT EXISTS stock.splits (
+	symbol_pk INT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	numerator FLOAT NOT NULL,
+	denominator FLOAT NOT NULL,
+	ratio VARCHAR,
+	PRIMARY KEY (symbol_pk, dt)
+);
+
+-- cumulative adjustment factors per price, computed from stock.splits & stock.dividends; only
+-- prices before a symbol's latest event are stored, as every later factor is 1
+CREATE TABLE IF NOT EXISTS stock.adjustments (
+	symbol_pk INT,
+	interval_pk SMALLINT,
+	dt TIMESTAMP WITH TIME ZONE NOT NULL,
+	split_factor FLOAT NOT NULL,
+	dividend_factor FLOAT NOT NULL,
+	PRIMARY KEY (symbol_pk, interval_pk, dt)
+);
+
+-- trading days missing from stock.prices that the source itself could not fill when repaired;
+-- `gap_start` & `gap_end` are the first & last missing trading days
+CREATE TABLE IF NOT EXISTS stock.price_gaps (
+	symbol_pk INT,
+	interval_pk SMALLINT,
+	gap_start DATE NOT NULL,
+	gap_end DATE NOT NULL,
+	missing INT NOT NULL,
+	checked TIMESTAMP WITH TIME ZONE NOT NULL,
+	PRIMARY KEY (symbol_pk, interval_pk, gap_start)
+);
+
+-- the latest error Yahoo's chart responded with per symbol, e.g. "Not Found"; cleared once the
+-- symbol's prices are fetched again
+CREATE TABLE IF NOT EXISTS stock.chart_errors (
+	symbol_pk INT PRIMARY KEY,
+	code VARCHAR NOT NULL,
+	description VARCHAR NOT NULL,
+	checked TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- days on which a fallback provider's adjusted close disagrees with the primary's (Yahoo) beyond
+-- the tolerance of the latest reconciliation
+CREATE TABLE IF NOT EXISTS stock.price_discrepancies (
+	symbol_pk INT,
+	dated DATE NOT NULL,
+	provider VARCHAR NOT NULL,
+	primary_close FLOAT NOT NULL,
+	closing FLOAT NOT NULL,
+	checked TIMESTAMP WITH TIME ZONE NOT NULL,
+	PRIMARY KEY (symbol_pk, dated, provider)
+);
+
+-- company profiles & key statistics from Yahoo's quoteSummary, refreshed weekly; a symbol Yahoo
+-- has no profile for is stored with every field NULL, so it isn't requested again until stale
+CREATE TABLE IF NOT EXISTS stock.profiles (
+	symbol_pk INT PRIMARY KEY,
+	sector VARCHAR,
+	industry VARCHAR,
+	employees INT,
+	beta FLOAT,
+	float_shares BIGINT,
+	shares_short BIGINT,
+	short_ratio FLOAT,
+	short_percent_of_float FLOAT,
+	target_low FLOAT,
+	target_mean FLOAT,
+	target_median FLOAT,
+	target_high FLOAT,
+	analysts INT,
+	recommendation VARCHAR,
+	updated TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- option chain snapshots from Yahoo, every contract of every expiry
+CREATE TABLE IF NOT EXISTS stock.option_chains (
+	symbol_pk INT,
+	snapshot TIMESTAMP WITH TIME ZONE NOT NULL,
+	expiry DATE NOT NULL,
+	put BOOLEAN NOT NULL,
+	contract VARCHAR NOT NULL,
+	strike FLOAT NOT NULL,
+	bid FLOAT,
+	ask FLOAT,
+	last_price FLOAT,
+	volume BIGINT,
+	open_interest BIGINT,
+	implied_volatility FLOAT,
+	in_the_money BOOLEAN,
+	PRIMARY KEY (symbol_pk, snapshot, contract)
+);
+
+-- per expiry aggregates of each option chain snapshot; atm_iv across expiries is the ATM IV term
+-- structure
+CREATE TABLE IF NOT EXISTS stock.option_expiries (
+	symbol_pk INT,
+	snapshot TIMESTAMP WITH TIME ZONE NOT NULL,
+	expiry DATE NOT NULL,
+	underlying FLOAT,
+	call_volume BIGINT NOT NULL,
+	put_volume BIGINT NOT NULL,
+	call_open_interest BIGINT NOT NULL,
+	put_open_interest BIGINT NOT NULL,
+	put_call_volume FLOAT,
+	put_call_open_interest FLOAT,
+	atm_strike FLOAT,
+	atm_iv FLOAT,
+	PRIMARY KEY (symbol_pk, snapshot, expiry)
+);
+
 -- metrics value table
 CREATE TABLE IF NOT EXISTS stock.metrics (
 	symbol_pk INT NOT NULL,
@@ -123,6 +504,39 @@ CREATE TABLE IF NOT EXISTS stock.metrics_lib (
 );
 CREATE UNIQUE INDEX IF NOT EXISTS idx_metric ON stock.metrics_lib(metric);
 
+-- the validators (ETag & Last-Modified) of each SEC bulk file's last download, e.g. companyfacts.zip
+CREATE TABLE IF NOT EXISTS stock.sec_downloads (
+	url VARCHAR PRIMARY KEY,
+	etag VARCHAR,
+	last_modified VARCHAR,
+	downloaded TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- the SHA-256 of each CIK's JSON, as of its last successful load by `loader`, e.g. "sec_metrics"
+CREATE TABLE IF NOT EXISTS stock.sec_hashes (
+	cik CHAR(10) NOT NULL,
+	loader VARCHAR NOT NULL,
+	hash CHAR(64) NOT NULL,
+	loaded TIMESTAMP WITH TIME ZONE NOT NULL,
+	PRIMARY KEY (cik, loader)
+);
+
+-- each day of the EDGAR daily index whose filers' companyfacts were refreshed into `stock.metrics`,
+-- & the number of those filers in `stock.symbols`
+CREATE TABLE IF NOT EXISTS stock.metric_refreshes (
+	dated DATE PRIMARY KEY,
+	filers INT NOT NULL,
+	refreshed TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- the filers whose companyfacts failed to refresh, retried by each refresh from `since` until
+-- they succeed, so that one company doesn't hold back recording the days it filed on
+CREATE TABLE IF NOT EXISTS stock.metric_refresh_failures (
+	cik CHAR(10) PRIMARY KEY,
+	since DATE NOT NULL,
+	failed TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
 -- accounting standards (e.g., pk: 1 -> "US-GAAP")
 CREATE TABLE IF NOT EXISTS stock.acc_stds (
 	pk SERIAL PRIMARY KEY,
@@ -130,7 +544,127 @@ CREATE TABLE IF NOT EXISTS stock.acc_stds (
 );
 CREATE UNIQUE INDEX IF NOT EXISTS idx_accounting ON stock.acc_stds(accounting);
 
--- CREATE TABLE IF NOT EXISTS stock.filings;
+-- standardized line items of each quarter, from `stock.metrics`; `concept` is the concept(s) the
+-- value came from, & `derivation` how, e.g. "reported", "FY - 9M" or "computed"
+CREATE TABLE IF NOT EXISTS stock.std_financial_items (
+	symbol_pk INT NOT NULL,
+	end_date DATE NOT NULL,
+	line_item VARCHAR NOT NULL,
+	val FLOAT NOT NULL,
+	concept VARCHAR NOT NULL,
+	derivation VARCHAR NOT NULL,
+	PRIMARY KEY (symbol_pk, end_date, line_item)
+);
+
+-- the text of filing documents, under a symbol of their CIK; `filename` is the accession number &
+-- `content_ts` the full-text search vector of `content`
+CREATE TABLE IF NOT EXISTS stock.filings (
+	stock_id INT NOT NULL,
+	dated DATE NOT NULL,
+	filename VARCHAR NOT NULL,
+	filetype VARCHAR NOT NULL,
+	url VARCHAR NOT NULL,
+	content TEXT,
+	content_ts TSVECTOR,
+	PRIMARY KEY (stock_id, filename)
+);
+CREATE INDEX IF NOT EXISTS idx_filings_content_ts ON stock.filings USING GIN(content_ts);
+
+-- transactions & holdings of insiders (Forms 3, 4 & 5), per line of the filing; `symbol_pk` is the
+-- issuer's first symbol, & holdings have no `transaction_code`, dated to the period of the report
+CREATE TABLE IF NOT EXISTS stock.insider_transactions (
+	accession VARCHAR NOT NULL,
+	seq SMALLINT NOT NULL,
+	symbol_pk INT,
+	issuer_cik CHAR(10) NOT NULL,
+	owner_cik CHAR(10) NOT NULL,
+	owner VARCHAR NOT NULL,
+	relationship VARCHAR NOT NULL,
+	security VARCHAR NOT NULL,
+	derivative BOOLEAN NOT NULL,
+	transaction_code CHAR(1),
+	dated DATE,
+	shares FLOAT,
+	price FLOAT,
+	acquired BOOLEAN,
+	shares_after FLOAT,
+	direct BOOLEAN,
+	PRIMARY KEY (accession, seq)
+);
+CREATE INDEX IF NOT EXISTS idx_insider_symbol ON stock.insider_transactions(symbol_pk, dated);
+
+-- every ownership filing parsed, including those without any lines
+CREATE TABLE IF NOT EXISTS stock.insider_filings (
+	accession VARCHAR PRIMARY KEY,
+	issuer_cik CHAR(10) NOT NULL,
+	form VARCHAR NOT NULL,
+	filing_date DATE NOT NULL,
+	lines INT NOT NULL,
+	parsed TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- positions of investment managers at the end of each period (13F-HR), summed per CUSIP; `value`
+-- is in dollars
+CREATE TABLE IF NOT EXISTS stock.institutional_holdings (
+	filer_cik CHAR(10) NOT NULL,
+	period DATE NOT NULL,
+	cusip CHAR(9) NOT NULL,
+	issuer VARCHAR NOT NULL,
+	class VARCHAR NOT NULL,
+	value FLOAT NOT NULL,
+	shares FLOAT NOT NULL,
+	share_type VARCHAR NOT NULL,
+	PRIMARY KEY (filer_cik, period, cusip)
+);
+CREATE INDEX IF NOT EXISTS idx_institutional_cusip ON stock.institutional_holdings(cusip, period);
+
+-- each position's change in shares since the filer's previous period; 'new', 'increased',
+-- 'decreased', 'unchanged', 'exited' (no longer held, with 0 shares), or 'baseline' (the filer's
+-- first period parsed)
+CREATE TABLE IF NOT EXISTS stock.institutional_changes (
+	filer_cik CHAR(10) NOT NULL,
+	period DATE NOT NULL,
+	cusip CHAR(9) NOT NULL,
+	shares FLOAT NOT NULL,
+	prev_shares FLOAT NOT NULL,
+	change FLOAT NOT NULL,
+	status VARCHAR NOT NULL,
+	PRIMARY KEY (filer_cik, period, cusip)
+);
+
+-- every 13F-HR parsed, including those without any positions
+CREATE TABLE IF NOT EXISTS stock.institutional_filings (
+	accession VARCHAR PRIMARY KEY,
+	filer_cik CHAR(10) NOT NULL,
+	filer VARCHAR NOT NULL,
+	period DATE NOT NULL,
+	filing_date DATE NOT NULL,
+	positions INT NOT NULL,
+	parsed TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- CUSIP = US ticker & FIGI, from OpenFIGI, & the symbol of the ticker; NULL if not found
+CREATE TABLE IF NOT EXISTS stock.cusips (
+	cusip CHAR(9) PRIMARY KEY,
+	ticker VARCHAR,
+	figi VARCHAR,
+	symbol_pk INT,
+	checked TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- every filing per CIK, from the SEC's submission files; `url` is the primary document, else the
+-- filing's folder, & `report_date` is NULL for forms without a reporting period, e.g. 8-K
+CREATE TABLE IF NOT EXISTS stock.filing_index (
+	cik CHAR(10) NOT NULL,
+	accession VARCHAR NOT NULL,
+	form VARCHAR NOT NULL,
+	filing_date DATE NOT NULL,
+	report_date DATE,
+	primary_document VARCHAR,
+	url VARCHAR NOT NULL,
+	PRIMARY KEY (cik, accession)
+);
+CREATE INDEX IF NOT EXISTS idx_filing_form ON stock.filing_index(form, filing_date);
 
 --------------------------------------------------------------------------------------
 -- ECONOMIC
@@ -163,6 +163,33 @@ LEFT JOIN percentage_change_cte AS pc
 	AND pr.dt = pc.dt
 ;
 
+-- Stock Adjusted Prices
+--
+-- Yahoo's quotes are already split-adjusted, so the as-traded prices are
+-- recovered with the split factor, and the total return series (our own
+-- adj. close) with the dividend factor; see stock.adjustments. Fallback
+-- providers' prices are already adjusted, so have no factors.
+DROP VIEW IF EXISTS stock.adjusted_prices;
+CREATE VIEW stock.adjusted_prices AS (
+SELECT
+	pr.symbol_pk,
+	pr.interval_pk,
+	pr.dt,
+	pr.opening,
+	pr.high,
+	pr.low,
+	pr.closing,
+	pr.closing * COALESCE(adj.split_factor, 1) AS raw_closing,
+	pr.closing * COALESCE(adj.dividend_factor, 1) AS adj_closing,
+	pr.adj_close AS yahoo_adj_close,
+	pr.volume
+FROM stock.prices AS pr
+LEFT JOIN stock.adjustments AS adj
+	ON adj.symbol_pk = pr.symbol_pk
+	AND adj.interval_pk = pr.interval_pk
+	AND adj.dt = pr.dt
+);
+
 --------------------------------------------------------------------------
 
 -- =====================================================================
@@ -260,3 +287,116 @@ LEFT JOIN percentage_change_cte AS pc
 	AND pr.source_pk = pc.source_pk
 	AND pr.dt = pc.dt
 ;
+
+-- Crypto Liquidity
+--
+-- Latest 24h snapshot per symbol & source, ranked by quote volume; used to
+-- screen the most liquid pairs before fetching candles.
+DROP VIEW IF EXISTS crypto.liquidity_rank;
+CREATE VIEW crypto.liquidity_rank AS (
+WITH latest_cte AS (
+	SELECT DISTINCT ON (tk.symbol_pk, tk.source_pk)
+		tk.*
+	FROM crypto.tickers_24h AS tk
+	ORDER BY tk.symbol_pk, tk.source_pk, tk.dt DESC
+)
+SELECT
+	sy.symbol,
+	so.source,
+	lt.dt,
+	lt.last_price,
+	lt.price_change_perc,
+	lt.volume,
+	lt.quote_volume,
+	lt.trades,
+	RANK() OVER (
+		PARTITION BY lt.source_pk
+		ORDER BY lt.quote_volume DESC NULLS LAST
+	) AS liquidity_rank
+FROM latest_cte AS lt
+INNER JOIN crypto.symbols AS sy
+	ON sy.pk = lt.symbol_pk
+INNER JOIN crypto.sources AS so
+	ON so.pk = lt.source_pk
+);
+
+-- Crypto USD Prices
+--
+-- Every candle converted to USD through its quote asset's rate, so that pairs
+-- quoted in BTC, ETH, USDT, EUR, TRY, ... can be compared with each other.
+DROP VIEW IF EXISTS crypto.usd_prices;
+CREATE VIEW crypto.usd_prices AS (
+SELECT
+	sy.symbol,
+	so.source,
+	mk.base_asset,
+	mk.quote_asset,
+	pr.dt,
+	it.interval,
+	pr.opening * ur.rate AS opening,
+	pr.high * ur.rate AS high,
+	pr.low * ur.rate AS low,
+	pr.closing * ur.rate AS closing,
+	pr.volume,
+	pr.volume * pr.closing * ur.rate AS usd_volume,
+	ur.path
+FROM crypto.prices AS pr
+INNER JOIN crypto.markets AS mk
+	ON mk.symbol_pk = pr.symbol_pk
+	AND mk.source_pk = pr.source_pk
+INNER JOIN crypto.usd_rates AS ur
+	ON ur.asset = mk.quote_asset
+	AND ur.dt = pr.dt
+	AND ur.interval_pk = pr.interval_pk
+INNER JOIN crypto.symbols AS sy
+	ON sy.pk = pr.symbol_pk
+INNER JOIN crypto.sources AS so
+	ON so.pk = pr.source_pk
+INNER JOIN common.intervals AS it
+	ON it.pk = pr.interval_pk
+);
+
+-- Portfolio Holdings
+--
+-- Latest balance per exchange & asset, valued in USD at the latest close of
+-- the asset's most liquid pair (or its quote rate, for USD, USDT, EUR, ...).
+DROP VIEW IF EXISTS portfolio.holdings;
+CREATE VIEW portfolio.holdings AS (
+WITH balance_cte AS (
+	SELECT DISTINCT ON (ba.source_pk, ba.asset)
+		ba.*
+	FROM portfolio.balances AS ba
+	ORDER BY ba.source_pk, ba.asset, ba.dt DESC
+),
+rate_cte AS (
+	SELECT DISTINCT ON (ur.asset)
+		ur.asset,
+		ur.rate
+	FROM crypto.usd_rates AS ur
+	ORDER BY ur.asset, ur.dt DESC
+),
+price_cte AS (
+	SELECT DISTINCT ON (up.base_asset)
+		up.base_asset AS asset,
+		up.closing
+	FROM crypto.usd_prices AS up
+	ORDER BY up.base_asset, up.dt DESC, up.usd_volume DESC NULLS LAST
+)
+SELECT
+	so.source,
+	ba.asset,
+	ba.dt,
+	ba.free,
+	ba.locked,
+	ba.free + ba.locked AS total,
+	COALESCE(ra.rate, pr.closing) AS usd_price,
+	(ba.free + ba.locked) * COALESCE(ra.rate, pr.closing) AS usd_value
+FROM balance_cte AS ba
+INNER JOIN crypto.sources AS so
+	ON so.pk = ba.source_pk
+LEFT JOIN rate_cte AS ra
+	ON ra.asset = ba.asset
+LEFT JOIN price_cte AS pr
+	ON pr.asset = ba.asset
+WHERE ba.free + ba.locked > 0
+);
@@ -0,0 +1,136 @@
+--------------------------------------------------------------------------
+--
+-- RELATION TO THE DATABASE
+-- ========================
+--
+-- `stock.metrics` keeps every value filed for a period, e.g.:
+--
+-- 		> the original 10-Q or 10-K;
+-- 		> amendments, e.g. a 10-K/A restating the period;
+-- 		> comparatives of later filings, which may also be restated.
+--
+-- A backtest must only see what was known at the time; these functions
+-- & VIEWs pick a single value per period (symbol, metric, accounting
+-- standard, start & end date) by its `filing_date`:
+--
+-- 		1. `stock.metrics_as_of(date)`, values filed on or before the date,
+--		   as latest restated (default) or as first reported;
+-- 		2. `stock.metrics_first_reported`, as first reported;
+-- 		3. `stock.metrics_latest`, as latest restated, i.e. as of today.
+--
+-- Inferred entries (see 04_stock_metrics.sql) are excluded, since they
+-- are dated 1970-01-01, and so would be known before they were filed.
+-- Filings of the same day are ordered by accession number.
+--
+--------------------------------------------------------------------------
+
+CREATE INDEX IF NOT EXISTS idx_metrics_point_in_time
+ON stock.metrics(symbol_pk, metric_pk, filing_date);
+
+-- the values known at `as_of_date`, of every symbol or a single one; the
+-- latest filed of each period, else the first reported if `first_reported`
+--
+-- e.g. SELECT * FROM stock.metrics_as_of('2020-06-30', symbol => 1);
+CREATE OR REPLACE FUNCTION stock.metrics_as_of(
+	as_of_date DATE,
+	symbol INT DEFAULT NULL,
+	first_reported BOOLEAN DEFAULT FALSE
+)
+RETURNS SETOF stock.metrics
+LANGUAGE SQL STABLE
+AS $$
+	SELECT DISTINCT ON (symbol_pk, metric_pk, acc_pk, start_date, end_date) *
+	FROM stock.metrics
+	WHERE
+			period <> 'I'
+		AND filing_date <= as_of_date
+		AND (symbol IS NULL OR symbol_pk = symbol)
+	ORDER BY
+		symbol_pk,
+		metric_pk,
+		acc_pk,
+		start_date,
+		end_date,
+		CASE WHEN first_reported THEN filing_date END ASC,
+		CASE WHEN first_reported THEN accn END ASC,
+		filing_date DESC,
+		accn DESC
+$$;
+
+-- every period, as first reported
+DROP VIEW IF EXISTS stock.metrics_first_reported;
+CREATE VIEW stock.metrics_first_reported AS (
+SELECT DISTINCT ON (symbol_pk, metric_pk, acc_pk, start_date, end_date) *
+FROM stock.metrics
+WHERE period <> 'I'
+ORDER BY
+	symbol_pk,
+	metric_pk,
+	acc_pk,
+	start_date,
+	end_date,
+	filing_date ASC,
+	accn ASC
+);
+
+-- every period, as latest restated; `first_filing_date` & `first_val` are
+-- of the period as first reported, so `val <> first_val` when restated
+DROP VIEW IF EXISTS stock.metrics_latest;
+CREATE VIEW stock.metrics_latest AS (
+SELECT DISTINCT ON (m.symbol_pk, m.metric_pk, m.acc_pk, m.start_date, m.end_date)
+	m.*,
+	FIRST_VALUE(m.filing_date) OVER periods	AS first_filing_date,
+	FIRST_VALUE(m.val) OVER periods			AS first_val
+FROM stock.metrics m
+WHERE m.period <> 'I'
+WINDOW periods AS (
+	PARTITION BY m.symbol_pk, m.metric_pk, m.acc_pk, m.start_date, m.end_date
+	ORDER BY m.filing_date ASC, m.accn ASC
+)
+ORDER BY
+	m.symbol_pk,
+	m.metric_pk,
+	m.acc_pk,
+	m.start_date,
+	m.end_date,
+	m.filing_date DESC,
+	m.accn DESC
+);
+
+--------------------------------------------------------------------------
+--
+-- EXAMPLE: A RESTATED METRIC
+-- ==========================
+--
+-- FY2019 revenue is first reported as 100 in the FY2019 10-K, then
+-- restated as 90 by the comparatives of the FY2020 10-K. Run as a check,
+-- in a transaction that is rolled back:
+--
+--	BEGIN;
+--	INSERT INTO stock.metrics (symbol_pk, metric_pk, acc_pk, start_date, end_date, filing_date, year, period, form, val, accn, frame)
+--	VALUES
+--		(-1, 1, 1, '2019-01-01', '2019-12-31', '2020-02-14', 2019, 'FY', '10-K', 100, '0000000000-20-000001', 'CY2019'),
+--		(-1, 1, 1, '2019-01-01', '2019-12-31', '2021-02-12', 2020, 'FY', '10-K', 90, '0000000000-21-000001', NULL);
+--
+--	-- before the 10-K is filed, nothing is known
+--	SELECT filing_date, val FROM stock.metrics_as_of('2020-01-31', symbol => -1);
+--		-- (0 rows)
+--
+--	-- before the restatement, as latest restated & as first reported agree
+--	SELECT filing_date, val FROM stock.metrics_as_of('2020-06-30', symbol => -1);
+--		-- 2020-02-14 | 100
+--
+--	-- after it, they differ
+--	SELECT filing_date, val FROM stock.metrics_as_of('2021-06-30', symbol => -1);
+--		-- 2021-02-12 | 90
+--	SELECT filing_date, val FROM stock.metrics_as_of('2021-06-30', symbol => -1, first_reported => TRUE);
+--		-- 2020-02-14 | 100
+--
+--	-- the VIEWs are as of today
+--	SELECT filing_date, val FROM stock.metrics_first_reported WHERE symbol_pk = -1;
+--		-- 2020-02-14 | 100
+--	SELECT filing_date, val, first_filing_date, first_val FROM stock.metrics_latest WHERE symbol_pk = -1;
+--		-- 2021-02-12 | 90 | 2020-02-14 | 100
+--	ROLLBACK;
+--
+--------------------------------------------------------------------------
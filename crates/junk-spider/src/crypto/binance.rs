@@ -1,5 +1,6 @@
-use super::sql;
+use super::{portfolio, repair, sql, util};
 use crate::http::*;
+use chrono::{DateTime, TimeDelta, Utc};
 use deadpool_postgres::Pool;
 use futures::{stream, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
@@ -7,7 +8,7 @@ use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::ClientBuilder;
 use serde::de::{IgnoredAny, SeqAccess, Visitor};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, info, trace};
@@ -18,6 +19,10 @@ const BROKERAGE: &str = "Binance";
 //
 // tickers = `https://api.binance.com/api/v1/ticker/allBookTickers`
 //
+// 24h tickers = `https://api.binance.com/api/v3/ticker/24hr`
+//
+// instruments = `https://api.binance.com/api/v3/exchangeInfo`
+//
 // klines = `https://api.binance.com/api/v3/klines`, per symbol
 
 /////////////////////////////////////////////////////////////////////////////////
@@ -113,18 +118,41 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
         }
     };
 
+    // 3c. upsert instrument metadata, so that non-trading pairs can be skipped
+    info!("fetching instruments ...");
+    let trading = match instruments(&http_client, &mut pg_client, &symbol_pks, source_pk).await {
+        Ok(trading) => Some(trading),
+        Err(err) => {
+            error!("failed to collect {BROKERAGE} instruments, error({err})");
+            None
+        }
+    };
+
+    // 3d. snapshot 24h ticker statistics
+    info!("fetching 24h ticker statistics ...");
+    if let Err(err) = snapshot_24h(&http_client, &mut pg_client, &symbol_pks, source_pk).await {
+        error!("failed to snapshot {BROKERAGE} 24h ticker statistics, error({err})");
+    }
+
     drop(pg_client);
 
+    // skip any pair that is not currently trading
+    let tickers: Vec<&Ticker> = tickers
+        .0
+        .iter()
+        .filter(|ticker| util::is_trading(&trading, &ticker.symbol))
+        .collect();
+
     // progress bar
     let (multi, total, success, fail) = if tui {
-        crate::tui::multi_progress(tickers.0.len())?
+        crate::tui::multi_progress(tickers.len())?
     } else {
         (None, None, None, None)
     };
 
-    // 3c. fetch prices for tickers
+    // 3e. fetch prices for tickers
     info!("fetching prices ...");
-    let stream = stream::iter(&tickers.0);
+    let stream = stream::iter(tickers);
     stream
         .for_each_concurrent(num_cpus::get(), |ticker| {
             let http_client = &http_client;
@@ -242,7 +270,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
 }
 
 // binance http client requires "X-MBX-APIKEY"
-fn build_client() -> HttpClient {
+pub(super) fn build_client() -> HttpClient {
     let mut headers = HeaderMap::new();
     headers.insert(
         "X-MBX-APIKEY",
@@ -256,6 +284,71 @@ fn build_client() -> HttpClient {
     client
 }
 
+/// Fetch the exchange metadata for every pair, upsert it to `crypto.instruments`, and return the
+/// set of symbols currently trading.
+async fn instruments(
+    http_client: &HttpClient,
+    pg_client: &mut PgClient,
+    symbol_pks: &HashMap<String, i32>,
+    source_pk: i16,
+) -> anyhow::Result<HashSet<String>> {
+    let info: ExchangeInfo = http_client
+        .get("https://api.binance.com/api/v3/exchangeInfo")
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} instruments, error({err})");
+            err
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} instruments, error({err})");
+            err
+        })?;
+
+    util::upsert_instruments(
+        pg_client,
+        &info.normalise(),
+        symbol_pks,
+        source_pk,
+        BROKERAGE,
+    )
+    .await
+}
+
+/// Fetch the 24h ticker statistics for every pair, and insert them as a snapshot.
+async fn snapshot_24h(
+    http_client: &HttpClient,
+    pg_client: &mut PgClient,
+    symbol_pks: &HashMap<String, i32>,
+    source_pk: i16,
+) -> anyhow::Result<()> {
+    let stats: Tickers24h = http_client
+        .get("https://api.binance.com/api/v3/ticker/24hr")
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} 24h tickers, error({err})");
+            err
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} 24h tickers, error({err})");
+            err
+        })?;
+
+    super::util::insert_tickers_24h(
+        pg_client,
+        &stats.normalise(),
+        symbol_pks,
+        source_pk,
+        BROKERAGE,
+    )
+    .await
+}
+
 /////////////////////////////////////////////////////////////////////////////////
 // endpoints
 /////////////////////////////////////////////////////////////////////////////////
@@ -339,6 +432,136 @@ impl Tickers {
     }
 }
 
+// instruments
+// ----------------------------------------------------------------
+// {
+//      "timezone": "UTC",
+//      "serverTime": 1565246363776,
+//      "symbols": [
+//          {
+//              "symbol": "ETHBTC",
+//              "status": "TRADING",
+//              "baseAsset": "ETH",
+//              "quoteAsset": "BTC",
+//              "filters": [
+//                  { "filterType": "PRICE_FILTER", "tickSize": "0.00001000", ... },
+//                  { "filterType": "LOT_SIZE", "stepSize": "0.00010000", ... },
+//                  ...
+//              ],
+//              ...
+//          },
+//          ...
+//      ]
+// }
+#[derive(Debug, Deserialize)]
+struct ExchangeInfo {
+    symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SymbolInfo {
+    symbol: String,
+    status: String,
+    base_asset: String,
+    quote_asset: String,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "filterType")]
+enum Filter {
+    #[serde(rename = "PRICE_FILTER", rename_all = "camelCase")]
+    Price { tick_size: String },
+    #[serde(rename = "LOT_SIZE", rename_all = "camelCase")]
+    LotSize { step_size: String },
+    #[serde(other)]
+    Other,
+}
+
+impl ExchangeInfo {
+    fn normalise(self) -> Vec<util::Instrument> {
+        self.symbols
+            .into_iter()
+            .map(|info| {
+                let mut tick_size = None;
+                let mut lot_size = None;
+                for filter in &info.filters {
+                    match filter {
+                        Filter::Price { tick_size: size } => tick_size = size.parse::<f64>().ok(),
+                        Filter::LotSize { step_size: size } => lot_size = size.parse::<f64>().ok(),
+                        Filter::Other => (),
+                    }
+                }
+
+                util::Instrument {
+                    exchange_symbol: info.symbol.clone(),
+                    is_trading: info.status == "TRADING",
+                    symbol: info.symbol,
+                    base_asset: Some(info.base_asset),
+                    quote_asset: Some(info.quote_asset),
+                    status: info.status,
+                    tick_size,
+                    lot_size,
+                }
+            })
+            .collect()
+    }
+}
+
+// 24h tickers
+// ----------------------------------------------------------------
+// [
+//  {
+//      "symbol": "BNBBTC",
+//      "priceChange": "-94.99999800",
+//      "priceChangePercent": "-95.960",
+//      "lastPrice": "4.00000200",
+//      "highPrice": "100.00000000",
+//      "lowPrice": "0.10000000",
+//      "volume": "8913.30000000",
+//      "quoteVolume": "15.30000000",
+//      "count": 76,
+//      ...
+//  },
+//  ...
+// ]
+#[derive(Debug, Deserialize)]
+struct Tickers24h(Vec<Ticker24h>);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Ticker24h {
+    symbol: String,
+    price_change: Option<String>,
+    price_change_percent: Option<String>,
+    last_price: Option<String>,
+    high_price: Option<String>,
+    low_price: Option<String>,
+    volume: Option<String>,
+    quote_volume: Option<String>,
+    count: Option<i64>,
+}
+
+impl Tickers24h {
+    fn normalise(self) -> Vec<util::Ticker24h> {
+        self.0
+            .into_iter()
+            .map(|ticker| util::Ticker24h {
+                last_price: util::parse_f64(&ticker.last_price),
+                price_change: util::parse_f64(&ticker.price_change),
+                price_change_perc: util::parse_f64(&ticker.price_change_percent),
+                high: util::parse_f64(&ticker.high_price),
+                low: util::parse_f64(&ticker.low_price),
+                volume: util::parse_f64(&ticker.volume),
+                quote_volume: util::parse_f64(&ticker.quote_volume),
+                trades: ticker.count,
+                symbol: ticker.symbol,
+            })
+            .collect()
+    }
+}
+
 // prices
 // ----------------------------------------------------------------
 //
@@ -432,7 +655,7 @@ impl Klines {
         let time = std::time::Instant::now();
 
         // preprocess pg query as transaction
-        let query = pg_client.prepare(sql::INSERT_PRICE).await?;
+        let query = pg_client.prepare(sql::UPSERT_PRICE).await?;
         let transaction = Arc::new(pg_client.transaction().await?);
 
         // iterate over the data stream and execute pg rows
@@ -443,13 +666,14 @@ impl Klines {
             let symbol = &symbol;
             let interval_pk: i16 = 3;
             async move {
+                let dt = DateTime::from_timestamp_millis(cell.timestamp).expect("i64 -> DateTime");
+                let closed = util::is_closed(dt, TimeDelta::days(1));
                 let result = transaction
                     .execute(
                         &query,
                         &[
                             &symbol_pk,
-                            &chrono::DateTime::from_timestamp_millis(cell.timestamp)
-                                .expect("i64 -> DateTime"),
+                            &dt,
                             &interval_pk,
                             &cell.opening.parse::<f64>().expect("String -> f64 Opening"),
                             &cell.high.parse::<f64>().expect("String -> f64 High"),
@@ -458,6 +682,7 @@ impl Klines {
                             &cell.volume.parse::<f64>().expect("String -> f64 Volume"),
                             &cell.trades,
                             &source_pk,
+                            &closed,
                         ],
                     )
                     .await;
@@ -492,3 +717,427 @@ impl Klines {
         Ok(())
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////////
+// account
+/////////////////////////////////////////////////////////////////////////////////
+//
+// https://developers.binance.com/docs/binance-spot-api-docs/rest-api/account-endpoints
+//
+// balances = `https://api.binance.com/api/v3/account`
+//
+// deposits = `https://api.binance.com/sapi/v1/capital/deposit/hisrec`, per 90 days
+//
+// withdrawals = `https://api.binance.com/sapi/v1/capital/withdraw/history`, per 90 days
+//
+// fills = `https://api.binance.com/api/v3/myTrades`, per symbol
+//
+// NOTE: fills are paged by `fromId`, since a `startTime` alone only spans 24 hours; every pair
+// traded on is of an asset once in the account, so the pairs of the assets previously held,
+// transferred or traded are requested, and the assets of each pair with new fills are added
+// until no pairs are left
+
+const TRANSFER_WINDOW: TimeDelta = TimeDelta::days(90);
+const FILL_LIMIT: usize = 1000;
+
+/// Fetch the account's balances, transfers & the fills of every pair traded on; `markets` are
+/// the `(symbol, base asset, quote asset)` of every Binance pair, `assets` every asset ever in
+/// the account & `trade_ids` the latest synced trade of each symbol.
+pub(super) async fn account(
+    markets: &[(String, String, String)],
+    assets: &HashSet<String>,
+    trade_ids: &HashMap<String, i64>,
+    since: &portfolio::Since,
+) -> anyhow::Result<portfolio::Account> {
+    let http_client = build_client();
+    let secret = var("BINANCE_PRIVATE")?;
+
+    // balances
+    let info: AccountInfo = signed(
+        &http_client,
+        &secret,
+        "/api/v3/account",
+        "omitZeroBalances=true",
+    )
+    .await?;
+    let balances: Vec<portfolio::Balance> = info
+        .balances
+        .into_iter()
+        .filter_map(|balance| {
+            Some(portfolio::Balance {
+                asset: balance.asset,
+                free: balance.free.parse().ok()?,
+                locked: balance.locked.parse().ok()?,
+            })
+        })
+        .collect();
+
+    // transfers
+    let mut transfers = Vec::new();
+    for (start, end) in portfolio::windows(since.transfers, TRANSFER_WINDOW) {
+        let params = format!(
+            "startTime={}&endTime={}",
+            start.timestamp_millis(),
+            end.timestamp_millis()
+        );
+
+        let deposits: Vec<Deposit> = signed(
+            &http_client,
+            &secret,
+            "/sapi/v1/capital/deposit/hisrec",
+            &params,
+        )
+        .await?;
+        transfers.extend(deposits.into_iter().filter_map(Deposit::normalise));
+
+        let withdrawals: Vec<Withdrawal> = signed(
+            &http_client,
+            &secret,
+            "/sapi/v1/capital/withdraw/history",
+            &params,
+        )
+        .await?;
+        transfers.extend(withdrawals.into_iter().filter_map(Withdrawal::normalise));
+    }
+
+    // fills
+    let mut assets: HashSet<String> = assets.clone();
+    assets.extend(balances.iter().map(|b| b.asset.clone()));
+    assets.extend(transfers.iter().map(|t| t.asset.clone()));
+    let mut walked: HashSet<&str> = HashSet::new();
+    let mut fills = Vec::new();
+    loop {
+        let pending: Vec<&(String, String, String)> = markets
+            .iter()
+            .filter(|(symbol, base, quote)| {
+                !walked.contains(symbol.as_str())
+                    && (assets.contains(base) || assets.contains(quote))
+            })
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+
+        for (symbol, base, quote) in pending {
+            walked.insert(symbol);
+
+            let mut from_id = trade_ids.get(symbol).map_or(0, |id| id + 1);
+            loop {
+                let params = format!("symbol={symbol}&fromId={from_id}&limit={FILL_LIMIT}");
+                let trades: Vec<Trade> =
+                    signed(&http_client, &secret, "/api/v3/myTrades", &params).await?;
+                let (len, last) = (trades.len(), trades.last().map(|trade| trade.id));
+                fills.extend(trades.into_iter().filter_map(Trade::normalise));
+
+                match last {
+                    Some(id) => {
+                        assets.insert(base.clone());
+                        assets.insert(quote.clone());
+                        if len < FILL_LIMIT {
+                            break;
+                        }
+                        from_id = id + 1;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    Ok(portfolio::Account {
+        balances,
+        transfers,
+        fills,
+    })
+}
+
+/// Send a signed request to a private endpoint.
+async fn signed<T: serde::de::DeserializeOwned>(
+    http_client: &HttpClient,
+    secret: &str,
+    path: &str,
+    params: &str,
+) -> anyhow::Result<T> {
+    let query = format!("{params}&timestamp={}", Utc::now().timestamp_millis());
+    let signature = util::hmac_sha256_hex(secret, &query);
+    let data = http_client
+        .get(format!(
+            "https://api.binance.com{path}?{query}&signature={signature}"
+        ))
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} {path}, error({err})");
+            err
+        })?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} {path}, error({err})");
+            err
+        })?;
+    Ok(data)
+}
+
+// {
+//   "balances": [
+//     {
+//       "asset": "BTC",
+//       "free": "4723846.89208129",
+//       "locked": "0.00000000"
+//     }
+//   ]
+// }
+#[derive(Debug, Deserialize)]
+struct AccountInfo {
+    balances: Vec<AccountBalance>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountBalance {
+    asset: String,
+    free: String,
+    locked: String,
+}
+
+// [
+//   {
+//     "id": "769800519366885376",
+//     "amount": "0.001",
+//     "coin": "BNB",
+//     "status": 1,
+//     "insertTime": 1599621997000,
+//     "txId": "98A3EA560C6B3336D348B6C83F0F95ECE4F1F5919E94BD006E5BF3BF264FACFC"
+//   }
+// ]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Deposit {
+    id: String,
+    amount: String,
+    coin: String,
+    status: i32,
+    insert_time: i64,
+}
+
+impl Deposit {
+    fn normalise(self) -> Option<portfolio::Transfer> {
+        Some(portfolio::Transfer {
+            tx_id: self.id,
+            kind: portfolio::DEPOSIT,
+            asset: self.coin,
+            amount: self.amount.parse().ok()?,
+            fee: None,
+            status: self.status.to_string(),
+            dt: DateTime::from_timestamp_millis(self.insert_time)?,
+        })
+    }
+}
+
+// [
+//   {
+//     "id": "b6ae22b3aa844210a7041aee7589627c",
+//     "amount": "8.91000000",
+//     "transactionFee": "0.004",
+//     "coin": "USDT",
+//     "status": 6,
+//     "applyTime": "2019-10-12 11:12:02"
+//   }
+// ]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Withdrawal {
+    id: String,
+    amount: String,
+    transaction_fee: Option<String>,
+    coin: String,
+    status: i32,
+    apply_time: String,
+}
+
+impl Withdrawal {
+    fn normalise(self) -> Option<portfolio::Transfer> {
+        let dt = chrono::NaiveDateTime::parse_from_str(&self.apply_time, "%Y-%m-%d %H:%M:%S")
+            .ok()?
+            .and_utc();
+        Some(portfolio::Transfer {
+            tx_id: self.id,
+            kind: portfolio::WITHDRAWAL,
+            asset: self.coin,
+            amount: self.amount.parse().ok()?,
+            fee: util::parse_f64(&self.transaction_fee),
+            status: self.status.to_string(),
+            dt,
+        })
+    }
+}
+
+// [
+//   {
+//     "symbol": "BNBBTC",
+//     "id": 28457,
+//     "price": "4.00000100",
+//     "qty": "12.00000000",
+//     "commission": "10.10000000",
+//     "commissionAsset": "BNB",
+//     "time": 1499865549590,
+//     "isBuyer": true
+//   }
+// ]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Trade {
+    symbol: String,
+    id: i64,
+    price: String,
+    qty: String,
+    commission: Option<String>,
+    commission_asset: Option<String>,
+    time: i64,
+    is_buyer: bool,
+}
+
+impl Trade {
+    fn normalise(self) -> Option<portfolio::Fill> {
+        Some(portfolio::Fill {
+            trade_id: self.id.to_string(),
+            symbol: self.symbol,
+            side: if self.is_buyer {
+                portfolio::BUY
+            } else {
+                portfolio::SELL
+            },
+            price: self.price.parse().ok()?,
+            quantity: self.qty.parse().ok()?,
+            fee: util::parse_f64(&self.commission),
+            fee_asset: self.commission_asset,
+            dt: DateTime::from_timestamp_millis(self.time)?,
+        })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// repair
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Refetch the daily klines missing within a [Gap](repair::Gap), a page at a time.
+pub(super) async fn refetch(
+    http_client: &HttpClient,
+    pool: &Pool,
+    gap: &repair::Gap,
+) -> anyhow::Result<()> {
+    let mut pg_client = pool.get().await?;
+
+    let end = gap.end.timestamp_millis();
+    let mut start = gap.start.timestamp_millis();
+    while start <= end {
+        let url = format!(
+            "https://api.binance.com/api/v3/klines?symbol={}&interval=1d&startTime={start}&endTime={end}&limit=1000",
+            gap.symbol
+        );
+        let klines: Klines = http_client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // an empty page means the source has nothing left within the gap
+        let last = match klines.0.last() {
+            Some(kline) => kline.timestamp,
+            None => break,
+        };
+        klines
+            .insert(&mut pg_client, &gap.symbol, gap.symbol_pk, gap.source_pk)
+            .await?;
+        start = last + 1;
+    }
+
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn tickers_24h_normalise() {
+    let json = r#"[
+        {
+            "symbol": "BNBBTC",
+            "priceChange": "-94.99999800",
+            "priceChangePercent": "-95.960",
+            "lastPrice": "4.00000200",
+            "highPrice": "100.00000000",
+            "lowPrice": "0.10000000",
+            "volume": "8913.30000000",
+            "quoteVolume": "15.30000000",
+            "count": 76
+        },
+        { "symbol": "ETHBTC", "lastPrice": "", "count": null }
+    ]"#;
+    let tickers: Tickers24h = serde_json::from_str(json).expect("valid 24h tickers");
+    let tickers = tickers.normalise();
+
+    assert_eq!(tickers[0].symbol, "BNBBTC");
+    assert_eq!(tickers[0].last_price, Some(4.000002));
+    assert_eq!(tickers[0].price_change_perc, Some(-95.96));
+    assert_eq!((tickers[0].high, tickers[0].low), (Some(100.0), Some(0.1)));
+    assert_eq!(tickers[0].quote_volume, Some(15.3));
+    assert_eq!(tickers[0].trades, Some(76));
+
+    // missing & unparseable fields are left empty
+    assert_eq!(tickers[1].last_price, None);
+    assert_eq!(tickers[1].volume, None);
+    assert_eq!(tickers[1].trades, None);
+}
+
+#[test]
+fn instruments_normalise() {
+    let json = r#"{
+        "timezone": "UTC",
+        "serverTime": 1565246363776,
+        "symbols": [
+            {
+                "symbol": "ETHBTC",
+                "status": "TRADING",
+                "baseAsset": "ETH",
+                "quoteAsset": "BTC",
+                "filters": [
+                    { "filterType": "PRICE_FILTER", "minPrice": "0.00001000", "tickSize": "0.00001000" },
+                    { "filterType": "LOT_SIZE", "minQty": "0.00010000", "stepSize": "0.00010000" },
+                    { "filterType": "NOTIONAL", "minNotional": "0.00010000" }
+                ]
+            },
+            {
+                "symbol": "BCCBTC",
+                "status": "BREAK",
+                "baseAsset": "BCC",
+                "quoteAsset": "BTC",
+                "filters": []
+            }
+        ]
+    }"#;
+    let info: ExchangeInfo = serde_json::from_str(json).expect("valid exchange info");
+    let instruments = info.normalise();
+
+    let eth = &instruments[0];
+    assert_eq!(
+        (eth.symbol.as_str(), eth.exchange_symbol.as_str()),
+        ("ETHBTC", "ETHBTC")
+    );
+    assert_eq!(
+        (eth.base_asset.as_deref(), eth.quote_asset.as_deref()),
+        (Some("ETH"), Some("BTC"))
+    );
+    assert!(eth.is_trading);
+    assert_eq!((eth.tick_size, eth.lot_size), (Some(0.00001), Some(0.0001)));
+
+    // pairs not trading are kept, but marked as such
+    let bcc = &instruments[1];
+    assert_eq!(bcc.status, "BREAK");
+    assert!(!bcc.is_trading);
+    assert_eq!((bcc.tick_size, bcc.lot_size), (None, None));
+}
@@ -0,0 +1,484 @@
+use super::{repair, sql, util};
+use crate::fs::{download_file, unzip};
+use crate::http::*;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::types::Type;
+use tracing::{debug, error, info, trace};
+
+const BROKERAGE: &str = "Binance";
+
+// Binance publishes every spot kline as zipped CSVs, one per month & one per day, each alongside
+// a `.CHECKSUM` (sha256) file
+//
+// monthly = `https://data.binance.vision/data/spot/monthly/klines/BTCUSDT/1m/BTCUSDT-1m-2024-01.zip`
+//
+// daily = `https://data.binance.vision/data/spot/daily/klines/BTCUSDT/1m/BTCUSDT-1m-2024-02-01.zip`
+//
+// Completed months are loaded from the monthly files, and the current month from the daily ones;
+// each run resumes from the month of the latest candle already in `crypto.prices`. Last month's
+// file is only published a few days into the month, so until then it's loaded from its daily
+// files instead.
+//
+// NOTE: from 2025 onwards, spot timestamps are in microseconds, rather than milliseconds
+
+const ARCHIVE_URL: &str = "https://data.binance.vision/data/spot";
+const ARCHIVE_INTERVAL: &str = "1m";
+const BUFFER: &str = "./buffer/binance";
+const CONCURRENCY: usize = 4;
+
+/// Pairs to backfill, unless set (comma separated) by `BINANCE_ARCHIVE_SYMBOLS`.
+const DEFAULT_SYMBOLS: [&str; 2] = ["BTCUSDT", "ETHUSDT"];
+
+/////////////////////////////////////////////////////////////////////////////////
+// core
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Backfill the 1m klines of every archive symbol, from the Binance public data archive.
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    // wait for a pg client from the pool
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{:^40}\n{bar}",
+            format!("{BROKERAGE} Archive"),
+            bar = "=".repeat(40)
+        )
+    }
+
+    let symbols: Vec<String> = match var("BINANCE_ARCHIVE_SYMBOLS") {
+        Ok(symbols) => symbols
+            .split(',')
+            .map(|symbol| symbol.trim().to_uppercase())
+            .filter(|symbol| !symbol.is_empty())
+            .collect(),
+        Err(_) => DEFAULT_SYMBOLS.iter().map(|s| s.to_string()).collect(),
+    };
+
+    // 1. insert source & symbols
+    let source_pk = util::source_pk(&mut pg_client, BROKERAGE).await?;
+    util::insert_symbols(&mut pg_client, &symbols, BROKERAGE).await?;
+
+    // 2. fetch symbols & the latest timestamps, so that only new files are requested
+    info!("fetching symbols ...");
+    let symbol_pks: HashMap<String, i32> = util::fetch_pks(
+        &mut pg_client,
+        "SELECT pk, symbol FROM crypto.symbols",
+        "symbol",
+        "pk",
+    )
+    .await?;
+    let interval_pk = sql::INTERVAL_PKS[ARCHIVE_INTERVAL] as i16;
+    let latest: HashMap<i32, DateTime<Utc>> = pg_client
+        .query(sql::LATEST_PRICES, &[&source_pk, &interval_pk])
+        .await?
+        .into_iter()
+        .map(|row| (row.get("symbol_pk"), row.get("dt")))
+        .collect();
+
+    drop(pg_client);
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(symbols.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    // 3. download, verify & load the archive files per symbol
+    info!("backfilling {BROKERAGE} archive ...");
+    let http_client = crate::std_client_build();
+    stream::iter(&symbols)
+        .for_each_concurrent(CONCURRENCY, |symbol| {
+            let http_client = &http_client;
+            let symbol_pks = &symbol_pks;
+            let latest = &latest;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                let symbol_pk = match symbol_pks.get(symbol) {
+                    Some(pk) => *pk,
+                    None => {
+                        error!("failed to find symbol pk for {symbol}");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner =
+                    crate::tui::multi_progress_spinner(multi, format!("backfilling {symbol}"));
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                let since = latest.get(&symbol_pk).map(|dt| dt.date_naive());
+                let files = archive_files(symbol, since, Utc::now().date_naive());
+                let mut loaded = 0;
+                let mut failed = false;
+                for (url, fallbacks) in &files {
+                    spinner.set_message(format!("loading {url}"));
+                    let urls =
+                        match load_file(http_client, pool, url, symbol_pk, interval_pk, source_pk)
+                            .await
+                        {
+                            Ok(Some(rows)) => {
+                                loaded += rows;
+                                continue;
+                            }
+                            Ok(None) => fallbacks,
+                            Err(err) => {
+                                error!("failed to load {url}, error({err})");
+                                failed = true;
+                                continue;
+                            }
+                        };
+
+                    // e.g. last month's file, not yet published
+                    for url in urls {
+                        spinner.set_message(format!("loading {url}"));
+                        match load_file(http_client, pool, url, symbol_pk, interval_pk, source_pk)
+                            .await
+                        {
+                            Ok(rows) => loaded += rows.unwrap_or_default(),
+                            Err(err) => {
+                                error!("failed to load {url}, error({err})");
+                                failed = true;
+                            }
+                        }
+                    }
+                }
+                debug!("{loaded} {BROKERAGE} archive candles loaded for {symbol}");
+
+                if tui {
+                    if failed {
+                        fail.expect("failbar should have unwrapped").inc(1);
+                    } else {
+                        success.expect("successbar should have unwrapped").inc(1);
+                    }
+                    total.expect("totalbar should have unwrapped").inc(1);
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+    }
+
+    Ok(())
+}
+
+/// Every archive file needed to catch up from `since` (the date of the latest candle) to
+/// `today`; monthly files for completed months, then daily files up to yesterday. Each is paired
+/// with the files to load if it isn't published, i.e. the daily files of last month.
+fn archive_files(
+    symbol: &str,
+    since: Option<NaiveDate>,
+    today: NaiveDate,
+) -> Vec<(String, Vec<String>)> {
+    let start = since.unwrap_or(NaiveDate::from_ymd_opt(2017, 7, 1).expect("valid date"));
+    let this_month = today.with_day(1).expect("first of the month");
+
+    let mut files = Vec::new();
+    let mut month = start.with_day(1).expect("first of the month");
+    while month < this_month {
+        let next = month + Months::new(1);
+        let fallbacks = match next == this_month {
+            true => daily_files(symbol, start.max(month), next),
+            false => Vec::new(),
+        };
+        files.push((
+            format!(
+                "{ARCHIVE_URL}/monthly/klines/{symbol}/{ARCHIVE_INTERVAL}/{symbol}-{ARCHIVE_INTERVAL}-{}.zip",
+                month.format("%Y-%m")
+            ),
+            fallbacks,
+        ));
+        month = next;
+    }
+
+    files.extend(
+        daily_files(symbol, start.max(this_month), today)
+            .into_iter()
+            .map(|url| (url, Vec::new())),
+    );
+
+    files
+}
+
+/// The daily archive files from `start` up to (excluding) `end`.
+fn daily_files(symbol: &str, start: NaiveDate, end: NaiveDate) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut day = start;
+    while day < end {
+        files.push(format!(
+            "{ARCHIVE_URL}/daily/klines/{symbol}/{ARCHIVE_INTERVAL}/{symbol}-{ARCHIVE_INTERVAL}-{}.zip",
+            day.format("%Y-%m-%d")
+        ));
+        day = day + Days::new(1);
+    }
+
+    files
+}
+
+/// Download, verify & bulk load a single archive file, returning the number of new candles;
+/// `None` if the file isn't published.
+async fn load_file(
+    http_client: &HttpClient,
+    pool: &Pool,
+    url: &str,
+    symbol_pk: i32,
+    interval_pk: i16,
+    source_pk: i16,
+) -> anyhow::Result<Option<u64>> {
+    // a missing checksum means the file isn't published, e.g. before the pair was listed
+    let response = http_client.get(format!("{url}.CHECKSUM")).send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        trace!("{url} not found in the archive");
+        return Ok(None);
+    }
+    let checksum = response.error_for_status()?.text().await?;
+    let checksum = checksum
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("empty checksum for {url}"))?
+        .to_lowercase();
+
+    // download & verify
+    let name = url.rsplit('/').next().expect("file name of url");
+    let zip_path = format!("{BUFFER}/{name}");
+    download_file(http_client, url, &zip_path, false).await?;
+
+    let digest = hex::encode(Sha256::digest(tokio::fs::read(&zip_path).await?));
+    if digest != checksum {
+        tokio::fs::remove_file(&zip_path).await?;
+        anyhow::bail!("checksum mismatch for {url}, expected {checksum} but found {digest}");
+    }
+
+    // unzip & parse
+    let dir = format!("{BUFFER}/{}", name.trim_end_matches(".zip"));
+    unzip(&zip_path, &dir, false).await?;
+    let csv_path = format!("{dir}/{}", name.replace(".zip", ".csv"));
+    let klines: Vec<Kline> = tokio::fs::read_to_string(&csv_path)
+        .await?
+        .lines()
+        .filter_map(Kline::parse)
+        .collect();
+
+    tokio::fs::remove_file(&zip_path).await?;
+    tokio::fs::remove_dir_all(&dir).await?;
+
+    // bulk load
+    let mut pg_client = pool.get().await?;
+    let rows = copy_prices(&mut pg_client, &klines, symbol_pk, interval_pk, source_pk).await?;
+    trace!("{rows} of {} candles loaded from {url}", klines.len());
+
+    Ok(Some(rows))
+}
+
+/// COPY the klines into a temporary table, then merge them into `crypto.prices`.
+///
+/// ### **NOTE**
+///
+/// A COPY statement can't skip conflicts, hence the temporary table.
+async fn copy_prices(
+    pg_client: &mut PgClient,
+    klines: &[Kline],
+    symbol_pk: i32,
+    interval_pk: i16,
+    source_pk: i16,
+) -> anyhow::Result<u64> {
+    let tx = pg_client.transaction().await?;
+    tx.execute(sql::CREATE_PRICES_BUFFER, &[]).await?;
+
+    let sink = tx.copy_in(sql::COPY_PRICES_BUFFER).await?;
+    let writer = BinaryCopyInWriter::new(
+        sink,
+        &[
+            Type::INT4,
+            Type::TIMESTAMPTZ,
+            Type::INT2,
+            Type::FLOAT8,
+            Type::FLOAT8,
+            Type::FLOAT8,
+            Type::FLOAT8,
+            Type::FLOAT8,
+            Type::INT8,
+            Type::INT2,
+        ],
+    );
+    futures::pin_mut!(writer);
+
+    for kline in klines {
+        writer
+            .as_mut()
+            .write(&[
+                &symbol_pk,
+                &kline.dt,
+                &interval_pk,
+                &kline.opening,
+                &kline.high,
+                &kline.low,
+                &kline.closing,
+                &kline.volume,
+                &kline.trades,
+                &source_pk,
+            ])
+            .await?;
+    }
+    writer.finish().await?;
+
+    let rows = tx.execute(sql::INSERT_PRICES_BUFFER, &[]).await?;
+    tx.commit().await?;
+
+    Ok(rows)
+}
+
+/// Reload the daily archive files covering a [Gap](repair::Gap) in the 1m klines.
+///
+/// The archive is published a day behind, so a gap within today can't be refetched yet.
+pub(super) async fn refetch(
+    http_client: &HttpClient,
+    pool: &Pool,
+    gap: &repair::Gap,
+) -> anyhow::Result<()> {
+    let end = gap.end.date_naive() + Days::new(1);
+    for url in daily_files(&gap.symbol, gap.start.date_naive(), end) {
+        let rows = load_file(
+            http_client,
+            pool,
+            &url,
+            gap.symbol_pk,
+            gap.interval_pk,
+            gap.source_pk,
+        )
+        .await?;
+        trace!(
+            "{} missing candles reloaded from {url}",
+            rows.unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// endpoints
+/////////////////////////////////////////////////////////////////////////////////
+//
+// klines
+// ----------------------------------------------------------------
+//
+// open_time, open, high, low, close, volume, close_time, quote_volume, trades,
+// taker_buy_base_volume, taker_buy_quote_volume, ignore
+//
+// 1704067200000,42283.58,42298.62,42261.02,42298.61,35.92724,1704067259999,1519032.47,1327,...
+
+#[derive(Debug, PartialEq)]
+struct Kline {
+    dt: DateTime<Utc>,
+    opening: f64,
+    high: f64,
+    low: f64,
+    closing: f64,
+    volume: f64,
+    trades: i64,
+}
+
+impl Kline {
+    /// Parse a CSV line, skipping the header (included in some files).
+    fn parse(line: &str) -> Option<Self> {
+        let cells: Vec<&str> = line.trim().split(',').collect();
+        if cells.len() < 9 {
+            return None;
+        }
+
+        let open_time = cells[0].parse::<i64>().ok()?;
+        let dt = if open_time > 10_i64.pow(14) {
+            DateTime::from_timestamp_micros(open_time)?
+        } else {
+            DateTime::from_timestamp_millis(open_time)?
+        };
+
+        Some(Kline {
+            dt,
+            opening: cells[1].parse().ok()?,
+            high: cells[2].parse().ok()?,
+            low: cells[3].parse().ok()?,
+            closing: cells[4].parse().ok()?,
+            volume: cells[5].parse().ok()?,
+            trades: cells[8].parse().ok()?,
+        })
+    }
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn archive_files_resume_from_latest_month() {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    let files = archive_files("BTCUSDT", Some(date(2024, 11, 15)), date(2025, 1, 3));
+
+    assert_eq!(files.len(), 4);
+    assert!(files[0]
+        .0
+        .ends_with("/monthly/klines/BTCUSDT/1m/BTCUSDT-1m-2024-11.zip"));
+    assert!(files[1]
+        .0
+        .ends_with("/monthly/klines/BTCUSDT/1m/BTCUSDT-1m-2024-12.zip"));
+    assert!(files[2]
+        .0
+        .ends_with("/daily/klines/BTCUSDT/1m/BTCUSDT-1m-2025-01-01.zip"));
+    assert!(files[3]
+        .0
+        .ends_with("/daily/klines/BTCUSDT/1m/BTCUSDT-1m-2025-01-02.zip"));
+
+    // early in the month, last month's file may not be published yet, so falls back to its days
+    assert!(files[0].1.is_empty());
+    assert_eq!(files[1].1.len(), 31);
+    assert!(files[1].1[0].ends_with("/daily/klines/BTCUSDT/1m/BTCUSDT-1m-2024-12-01.zip"));
+    assert!(files[1].1[30].ends_with("/daily/klines/BTCUSDT/1m/BTCUSDT-1m-2024-12-31.zip"));
+    assert!(files[2..].iter().all(|(_, fallbacks)| fallbacks.is_empty()));
+
+    // resuming within last month only falls back to the days since
+    let files = archive_files("BTCUSDT", Some(date(2024, 12, 30)), date(2025, 1, 3));
+    assert_eq!(files[0].1.len(), 2);
+    assert!(files[0].1[0].ends_with("/daily/klines/BTCUSDT/1m/BTCUSDT-1m-2024-12-30.zip"));
+
+    let ms = Kline::parse("1704067200000,42283.58,42298.62,42261.02,42298.61,35.92,1704067259999,1519032.47,1327,17.1,723612.8,0").unwrap();
+    let us = Kline::parse("1735689600000000,93576.00,93610.93,93537.50,93610.93,8.21,1735689659999999,768555.93,1542,4.7,440000.1,0").unwrap();
+    assert_eq!(ms.dt.timestamp(), 1704067200);
+    assert_eq!(us.dt.timestamp(), 1735689600);
+    assert!(Kline::parse(
+        "open_time,open,high,low,close,volume,close_time,quote_volume,count,a,b,ignore"
+    )
+    .is_none());
+}
@@ -0,0 +1,405 @@
+use super::{sql, util};
+use crate::http::*;
+use chrono::{DateTime, TimeDelta, Utc};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, trace};
+
+const BROKERAGE: &str = "Binance USD-M";
+
+// RATE_LIMIT = 2400 /60s, and funding rates share a separate 500 /5m
+//
+// contracts = `https://fapi.binance.com/fapi/v1/exchangeInfo`
+//
+// funding rates = `https://fapi.binance.com/fapi/v1/fundingRate`, per symbol
+//
+// open interest = `https://fapi.binance.com/futures/data/openInterestHist`, per symbol
+//
+// NOTE: open interest history is only available for the latest 30 days
+
+const FUNDING_LIMIT: usize = 1000;
+const OPEN_INTEREST_LIMIT: usize = 500;
+const OPEN_INTEREST_INTERVAL: &str = "1h";
+
+/////////////////////////////////////////////////////////////////////////////////
+// core
+/////////////////////////////////////////////////////////////////////////////////
+
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    // wait for a pg client from the pool
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    // fetch the perpetual contracts
+    if tui {
+        println!("{bar}\n{BROKERAGE:^40}\n{bar}", bar = "=".repeat(40))
+    }
+    let pb = if tui {
+        let pb = ProgressBar::new_spinner()
+            .with_message("fetching contracts ...")
+            .with_style(ProgressStyle::default_spinner().template("{msg} {spinner:.magenta}")?);
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb
+    } else {
+        ProgressBar::hidden()
+    };
+    let http_client = super::binance::build_client();
+    let info: ExchangeInfo = http_client
+        .get("https://fapi.binance.com/fapi/v1/exchangeInfo")
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} contracts, error({err})");
+            err
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} contracts, error({err})");
+            err
+        })?;
+    let symbols: Vec<String> = info
+        .symbols
+        .into_iter()
+        .filter(|contract| contract.contract_type == "PERPETUAL" && contract.status == "TRADING")
+        .map(|contract| contract.symbol)
+        .collect();
+
+    // 1. insert source
+    pb.set_message("inserting contracts ...");
+    let source_pk = util::source_pk(&mut pg_client, BROKERAGE).await?;
+
+    // 2. insert contracts as symbols
+    util::insert_symbols(&mut pg_client, &symbols, BROKERAGE).await?;
+    pb.finish_with_message("inserting contracts ... done");
+
+    // 3. fetch & insert funding rates and open interest
+    // 3a. fetch symbols
+    info!("fetching symbols ...");
+    let symbol_pks: HashMap<String, i32> = util::fetch_pks(
+        &mut pg_client,
+        "SELECT pk, symbol FROM crypto.symbols",
+        "symbol",
+        "pk",
+    )
+    .await?;
+    let symbol_pks = Arc::new(symbol_pks);
+
+    // 3b. fetch the latest timestamps, so that only new data is requested
+    info!("fetching latest timestamps ...");
+    let latest_funding =
+        util::fetch_latest_dts(&mut pg_client, sql::LATEST_FUNDING_RATES, source_pk).await?;
+    let latest_open_interest =
+        util::fetch_latest_dts(&mut pg_client, sql::LATEST_OPEN_INTEREST, source_pk).await?;
+    let interval_pk = sql::INTERVAL_PKS[OPEN_INTEREST_INTERVAL] as i16;
+
+    drop(pg_client);
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(symbols.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    // 3c. fetch funding rates & open interest for contracts
+    info!("fetching funding rates & open interest ...");
+    stream::iter(&symbols)
+        .for_each_concurrent(num_cpus::get(), |symbol| {
+            let http_client = &http_client;
+            let symbol_pks = &symbol_pks;
+            let latest_funding = &latest_funding;
+            let latest_open_interest = &latest_open_interest;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                let symbol_pk = match symbol_pks.get(symbol) {
+                    Some(pk) => *pk,
+                    None => {
+                        error!("failed to find symbol pk for {symbol}");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("fetching funding rates for {symbol}"),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                trace!("fetching funding rates for {symbol}");
+                let since = latest_funding.get(&symbol_pk).copied();
+                let rates = match fetch_funding_rates(http_client, symbol, since).await {
+                    Ok(rates) => rates,
+                    Err(err) => {
+                        error!("failed to fetch {BROKERAGE} funding rates for {symbol}, error({err})");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+
+                trace!("fetching open interest for {symbol}");
+                spinner.set_message(format!("fetching open interest for {symbol}"));
+                let since = latest_open_interest.get(&symbol_pk).copied();
+                let open_interest = match fetch_open_interest(http_client, symbol, since).await {
+                    Ok(open_interest) => open_interest,
+                    Err(err) => {
+                        error!("failed to fetch {BROKERAGE} open interest for {symbol}, error({err})");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+
+                // wait for a client
+                spinner.set_message(format!("waiting to insert {symbol}"));
+                let mut pg_client = match pool.get().await {
+                    Ok(client) => client,
+                    Err(err) => {
+                        error!("failed to get pg client from pool, error({err})");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+
+                spinner.set_message(format!("inserting {symbol}"));
+                let result = match util::insert_funding_rates(
+                    &mut pg_client,
+                    &rates,
+                    symbol,
+                    symbol_pk,
+                    source_pk,
+                    BROKERAGE,
+                )
+                .await
+                {
+                    Ok(_) => {
+                        util::insert_open_interest(
+                            &mut pg_client,
+                            &open_interest,
+                            symbol,
+                            symbol_pk,
+                            interval_pk,
+                            source_pk,
+                            BROKERAGE,
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok(_) => {
+                        trace!("inserted funding rates & open interest for {symbol}");
+
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!("failed to insert funding rates & open interest for {symbol}, error({err})");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("fail bar should have unwrapped")
+            .finish_and_clear();
+        success
+            .expect("success bar should have unwrapped")
+            .finish_and_clear();
+        total
+            .expect("total bar should have unwrapped")
+            .finish_and_clear();
+        println!("collecting funding rates & open interest ... done\n");
+    }
+
+    Ok(())
+}
+
+/// Page through the funding rate history of `symbol`, starting after `since` (or from the
+/// listing of the contract, if there is no history yet).
+async fn fetch_funding_rates(
+    http_client: &HttpClient,
+    symbol: &str,
+    since: Option<DateTime<Utc>>,
+) -> anyhow::Result<Vec<util::FundingRate>> {
+    let mut rates = vec![];
+    let mut start = since.map(|dt| dt.timestamp_millis() + 1).unwrap_or(0);
+
+    loop {
+        let url = format!(
+            "https://fapi.binance.com/fapi/v1/fundingRate?symbol={symbol}&startTime={start}&limit={FUNDING_LIMIT}"
+        );
+        let page: Vec<FundingRate> = http_client.get(url).send().await?.json().await?;
+        let len = page.len();
+
+        for rate in page {
+            start = start.max(rate.funding_time + 1);
+            rates.push(util::FundingRate {
+                dt: DateTime::from_timestamp_millis(rate.funding_time)
+                    .ok_or_else(|| anyhow::anyhow!("invalid funding time for {symbol}"))?,
+                funding_rate: rate.funding_rate.parse::<f64>()?,
+                mark_price: util::parse_f64(&rate.mark_price),
+            });
+        }
+
+        if len < FUNDING_LIMIT {
+            break;
+        }
+    }
+
+    Ok(rates)
+}
+
+/// Page through the open interest history of `symbol`, starting after `since` (clamped to the
+/// 30 days that Binance keeps).
+async fn fetch_open_interest(
+    http_client: &HttpClient,
+    symbol: &str,
+    since: Option<DateTime<Utc>>,
+) -> anyhow::Result<Vec<util::OpenInterest>> {
+    let mut rows = vec![];
+    let earliest = (Utc::now() - TimeDelta::days(29)).timestamp_millis();
+    let mut start = since
+        .map(|dt| dt.timestamp_millis() + 1)
+        .unwrap_or(earliest)
+        .max(earliest);
+
+    loop {
+        let url = format!(
+            "https://fapi.binance.com/futures/data/openInterestHist?symbol={symbol}&period={OPEN_INTEREST_INTERVAL}&startTime={start}&limit={OPEN_INTEREST_LIMIT}"
+        );
+        let page: Vec<OpenInterest> = http_client.get(url).send().await?.json().await?;
+        let len = page.len();
+
+        for row in page {
+            start = start.max(row.timestamp + 1);
+            rows.push(util::OpenInterest {
+                dt: DateTime::from_timestamp_millis(row.timestamp)
+                    .ok_or_else(|| anyhow::anyhow!("invalid open interest time for {symbol}"))?,
+                open_interest: row.sum_open_interest.parse::<f64>().ok(),
+                open_interest_value: row.sum_open_interest_value.parse::<f64>().ok(),
+            });
+        }
+
+        if len < OPEN_INTEREST_LIMIT {
+            break;
+        }
+    }
+
+    Ok(rows)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// endpoints
+/////////////////////////////////////////////////////////////////////////////////
+//
+// contracts
+// ----------------------------------------------------------------
+// {
+//      "timezone": "UTC",
+//      "serverTime": 1565613908500,
+//      "symbols": [
+//          {
+//              "symbol": "BTCUSDT",
+//              "pair": "BTCUSDT",
+//              "contractType": "PERPETUAL",
+//              "status": "TRADING",
+//              "baseAsset": "BTC",
+//              "quoteAsset": "USDT",
+//              ...
+//          },
+//          ...
+//      ]
+// }
+#[derive(Debug, Deserialize)]
+struct ExchangeInfo {
+    symbols: Vec<Contract>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Contract {
+    symbol: String,
+    contract_type: String,
+    status: String,
+}
+
+// funding rates
+// ----------------------------------------------------------------
+// [
+//  {
+//      "symbol": "BTCUSDT",
+//      "fundingRate": "-0.03750000",
+//      "fundingTime": 1570608000000,
+//      "markPrice": "34287.54619963"
+//  },
+//  ...
+// ]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FundingRate {
+    funding_rate: String,
+    funding_time: i64,
+    mark_price: Option<String>,
+}
+
+// open interest
+// ----------------------------------------------------------------
+// [
+//  {
+//      "symbol": "BTCUSDT",
+//      "sumOpenInterest": "20403.63700000",
+//      "sumOpenInterestValue": "150570784.07809979",
+//      "timestamp": 1583127900000
+//  },
+//  ...
+// ]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenInterest {
+    sum_open_interest: String,
+    sum_open_interest_value: String,
+    timestamp: i64,
+}
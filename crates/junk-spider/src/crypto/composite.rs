@@ -0,0 +1,473 @@
+use super::markets::{self, Market};
+use super::{sql, util};
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, error, info, trace, warn};
+
+const SOURCE: &str = "Composite";
+
+// Every source writes its own candles to `crypto.prices`, so the same market is priced once per
+// exchange. Candles are grouped by market (see `markets`), then per timestamp
+//
+//      composite OHLC = sum(price * volume) / sum(volume), across sources
+//      deviation      = (source close - composite close) / composite close * 100
+//
+// The latest composite candle of each market is rebuilt on every run, as it may still be in
+// progress; so is every candle since the earliest one with more source candles than it was built
+// from, e.g. candles backfilled since.
+
+const COMPOSITE_INTERVAL: &str = "1d";
+
+/// Sources further than this from the composite close (in %) are reported as diverging.
+pub const DIVERGENCE_THRESHOLD: f64 = 2.0;
+
+/// A source diverging from the composite price of its market, on the latest candle.
+#[derive(Debug)]
+pub struct Divergence {
+    pub market: String,
+    pub source: String,
+    pub dt: DateTime<Utc>,
+    pub closing: f64,
+    pub composite: f64,
+    pub deviation_perc: f64,
+}
+
+#[derive(Debug)]
+struct Candle {
+    source_pk: i16,
+    opening: f64,
+    high: f64,
+    low: f64,
+    closing: f64,
+    volume: f64,
+}
+
+#[derive(Debug, PartialEq)]
+struct Composite {
+    opening: f64,
+    high: f64,
+    low: f64,
+    closing: f64,
+    volume: f64,
+    sources: i16,
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// core
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Build the composite prices of every market priced by any source, returning the sources
+/// that diverge from their market's latest composite close by more than `threshold_perc`.
+pub async fn build(pool: &Pool, tui: bool, threshold_perc: f64) -> anyhow::Result<Vec<Divergence>> {
+    // wait for a pg client from the pool
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!("{bar}\n{SOURCE:^40}\n{bar}", bar = "=".repeat(40))
+    }
+
+    // 1. group each source's pairs by market
+    info!("matching markets across sources ...");
+    let mut members: HashMap<Market, Vec<(i32, i16)>> = HashMap::new();
+    for (key, market) in markets::fetch_markets(&mut pg_client).await? {
+        members.entry(market).or_default().push(key);
+    }
+
+    // 2. insert the canonical symbol of each market
+    let symbols: Vec<String> = members.keys().map(Market::symbol).collect();
+    util::insert_symbols(&mut pg_client, &symbols, SOURCE).await?;
+
+    // 3. fetch symbols, sources & the latest composite timestamps
+    let symbol_pks: HashMap<String, i32> = util::fetch_pks(
+        &mut pg_client,
+        "SELECT pk, symbol FROM crypto.symbols",
+        "symbol",
+        "pk",
+    )
+    .await?;
+    let sources: HashMap<i16, String> = util::fetch_pks(
+        &mut pg_client,
+        "SELECT pk, source FROM crypto.sources",
+        "pk",
+        "source",
+    )
+    .await?;
+    let interval_pk = sql::INTERVAL_PKS[COMPOSITE_INTERVAL] as i16;
+    let latest: HashMap<i32, DateTime<Utc>> = pg_client
+        .query(sql::LATEST_COMPOSITE_PRICES, &[&interval_pk])
+        .await?
+        .into_iter()
+        .map(|row| (row.get("symbol_pk"), row.get("dt")))
+        .collect();
+
+    drop(pg_client);
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(members.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    // 4. build & insert the composite prices per market
+    info!("building composite prices ...");
+    let divergences = Arc::new(Mutex::new(Vec::new()));
+    stream::iter(&members)
+        .for_each_concurrent(num_cpus::get(), |(market, members)| {
+            let symbol_pks = &symbol_pks;
+            let sources = &sources;
+            let latest = &latest;
+            let divergences = divergences.clone();
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                let symbol_pk = match symbol_pks.get(&market.symbol()) {
+                    Some(pk) => *pk,
+                    None => {
+                        error!("failed to find symbol pk for {market}");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("building composite prices for {market}"),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                let since = latest.get(&symbol_pk).copied();
+                let result = build_market(
+                    pool,
+                    market,
+                    members,
+                    symbol_pk,
+                    interval_pk,
+                    since,
+                    sources,
+                    threshold_perc,
+                )
+                .await;
+
+                match result {
+                    Ok(diverging) => {
+                        divergences
+                            .lock()
+                            .expect("divergences should not be poisoned")
+                            .extend(diverging);
+
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!("failed to build composite prices for {market}, error({err})");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+    }
+
+    let mut divergences = Arc::try_unwrap(divergences)
+        .expect("every task should have finished")
+        .into_inner()
+        .expect("divergences should not be poisoned");
+    divergences.sort_by(|a, b| b.deviation_perc.abs().total_cmp(&a.deviation_perc.abs()));
+
+    // 5. report the diverging sources
+    for div in &divergences {
+        warn!(
+            "{} on {} diverges from the composite by {:.2}% ({} vs {})",
+            div.market, div.source, div.deviation_perc, div.closing, div.composite
+        );
+        if tui {
+            println!(
+                "{:<16} {:<16} {:>8.2}% {:>16} {:>16}",
+                div.market, div.source, div.deviation_perc, div.closing, div.composite
+            );
+        }
+    }
+
+    Ok(divergences)
+}
+
+/// Build & insert the composite prices of a single market from its latest composite candle
+/// (`since`) onwards, or from any earlier candle missing from it.
+#[allow(clippy::too_many_arguments)]
+async fn build_market(
+    pool: &Pool,
+    market: &Market,
+    members: &[(i32, i16)],
+    symbol_pk: i32,
+    interval_pk: i16,
+    since: Option<DateTime<Utc>>,
+    sources: &HashMap<i16, String>,
+    threshold_perc: f64,
+) -> anyhow::Result<Vec<Divergence>> {
+    let time = std::time::Instant::now();
+    let mut pg_client = pool.get().await?;
+
+    // rebuild from the earliest candle missing from the composite, if before the latest
+    let member_pks: Vec<i32> = members.iter().map(|(symbol_pk, _)| *symbol_pk).collect();
+    let source_pks: Vec<i16> = members.iter().map(|(_, source_pk)| *source_pk).collect();
+    let stale: Option<DateTime<Utc>> = pg_client
+        .query_one(
+            sql::EARLIEST_STALE_COMPOSITE,
+            &[&member_pks, &source_pks, &interval_pk, &symbol_pk],
+        )
+        .await?
+        .get("dt");
+    let since = rebuild_from(since, stale);
+
+    // fetch every member's candles, as a single symbol may be priced by unrelated sources
+    let mut candles: BTreeMap<DateTime<Utc>, Vec<Candle>> = BTreeMap::new();
+    for row in pg_client
+        .query(sql::MARKET_PRICES, &[&member_pks, &interval_pk, &since])
+        .await?
+    {
+        let key: (i32, i16) = (row.get("symbol_pk"), row.get("source_pk"));
+        if !members.contains(&key) {
+            continue;
+        }
+
+        let closing: Option<f64> = row.get("closing");
+        let closing = match closing {
+            Some(closing) => closing,
+            None => continue,
+        };
+
+        let price = |col: &str| -> f64 { row.get::<_, Option<f64>>(col).unwrap_or(closing) };
+        candles.entry(row.get("dt")).or_default().push(Candle {
+            source_pk: key.1,
+            opening: price("opening"),
+            high: price("high"),
+            low: price("low"),
+            closing,
+            volume: row.get::<_, Option<f64>>("volume").unwrap_or_default(),
+        });
+    }
+
+    // preprocess pg queries as transaction
+    let price_query = pg_client.prepare(sql::UPSERT_COMPOSITE_PRICE).await?;
+    let deviation_query = pg_client.prepare(sql::UPSERT_COMPOSITE_DEVIATION).await?;
+    let transaction = pg_client.transaction().await?;
+
+    let mut divergences = Vec::new();
+    let last_dt = candles.keys().next_back().copied();
+    for (dt, candles) in &candles {
+        let comp = match composite(candles) {
+            Some(comp) => comp,
+            None => continue,
+        };
+
+        transaction
+            .execute(
+                &price_query,
+                &[
+                    &symbol_pk,
+                    dt,
+                    &interval_pk,
+                    &comp.opening,
+                    &comp.high,
+                    &comp.low,
+                    &comp.closing,
+                    &comp.volume,
+                    &comp.sources,
+                ],
+            )
+            .await
+            .map_err(|err| {
+                error!("failed to insert composite price for {market} at {dt}, error({err})");
+                err
+            })?;
+
+        // a lone source can't deviate from itself
+        if comp.sources < 2 {
+            continue;
+        }
+
+        for candle in candles {
+            let deviation_perc = deviation(candle.closing, comp.closing);
+            transaction
+                .execute(
+                    &deviation_query,
+                    &[
+                        &symbol_pk,
+                        dt,
+                        &interval_pk,
+                        &candle.source_pk,
+                        &candle.closing,
+                        &deviation_perc,
+                    ],
+                )
+                .await
+                .map_err(|err| {
+                    error!(
+                        "failed to insert composite deviation for {market} at {dt}, error({err})"
+                    );
+                    err
+                })?;
+
+            if Some(*dt) == last_dt && deviation_perc.abs() > threshold_perc {
+                divergences.push(Divergence {
+                    market: market.to_string(),
+                    source: sources
+                        .get(&candle.source_pk)
+                        .cloned()
+                        .unwrap_or_else(|| candle.source_pk.to_string()),
+                    dt: *dt,
+                    closing: candle.closing,
+                    composite: comp.closing,
+                    deviation_perc,
+                });
+            }
+        }
+    }
+
+    transaction.commit().await?;
+
+    trace!("composite prices built for {market}");
+    debug!(
+        "{} composite candles built for {market}, {}",
+        candles.len(),
+        crate::time_elapsed(time)
+    );
+
+    Ok(divergences)
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// calculations
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Volume-weighted OHLC across sources, weighting equally when no source reports volume.
+fn composite(candles: &[Candle]) -> Option<Composite> {
+    if candles.is_empty() {
+        return None;
+    }
+
+    let volume: f64 = candles.iter().map(|c| c.volume.max(0.0)).sum();
+    let weight = |c: &Candle| {
+        if volume > 0.0 {
+            c.volume.max(0.0) / volume
+        } else {
+            1.0 / candles.len() as f64
+        }
+    };
+    let weighted =
+        |price: fn(&Candle) -> f64| -> f64 { candles.iter().map(|c| price(c) * weight(c)).sum() };
+
+    Some(Composite {
+        opening: weighted(|c| c.opening),
+        high: weighted(|c| c.high),
+        low: weighted(|c| c.low),
+        closing: weighted(|c| c.closing),
+        volume,
+        sources: candles.len() as i16,
+    })
+}
+
+/// The timestamp to rebuild a market's composite from; its latest composite candle, else the
+/// earliest candle missing from it.
+fn rebuild_from(latest: Option<DateTime<Utc>>, stale: Option<DateTime<Utc>>) -> DateTime<Utc> {
+    match (latest, stale) {
+        (Some(latest), Some(stale)) => latest.min(stale),
+        (Some(latest), None) => latest,
+        (None, _) => DateTime::<Utc>::default(),
+    }
+}
+
+/// Deviation of a source's price from the composite price, in %.
+fn deviation(price: f64, composite: f64) -> f64 {
+    if composite == 0.0 {
+        return 0.0;
+    }
+    (price - composite) / composite * 100.0
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn composite_is_volume_weighted() {
+    let candle = |source_pk, closing, volume| Candle {
+        source_pk,
+        opening: closing,
+        high: closing,
+        low: closing,
+        closing,
+        volume,
+    };
+
+    let comp = composite(&[candle(1, 100.0, 3.0), candle(2, 104.0, 1.0)]).unwrap();
+    assert_eq!(comp.closing, 101.0);
+    assert_eq!(comp.volume, 4.0);
+    assert_eq!(comp.sources, 2);
+
+    // no volume, equal weights
+    let comp = composite(&[candle(1, 100.0, 0.0), candle(2, 104.0, 0.0)]).unwrap();
+    assert_eq!(comp.closing, 102.0);
+
+    assert_eq!(deviation(104.0, 100.0), 4.0);
+    assert!(composite(&[]).is_none());
+}
+
+#[test]
+fn composite_rebuilds_from_backfilled_candles() {
+    let dt = |day| {
+        chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+            .expect("valid date")
+            .and_hms_opt(0, 0, 0)
+            .expect("valid time")
+            .and_utc()
+    };
+
+    // never built
+    assert_eq!(rebuild_from(None, Some(dt(3))), DateTime::<Utc>::default());
+
+    // only the latest, in progress, candle
+    assert_eq!(rebuild_from(Some(dt(10)), None), dt(10));
+
+    // a candle backfilled before the latest
+    assert_eq!(rebuild_from(Some(dt(10)), Some(dt(3))), dt(3));
+
+    // new candles after the latest are fetched anyway
+    assert_eq!(rebuild_from(Some(dt(10)), Some(dt(11))), dt(10));
+}
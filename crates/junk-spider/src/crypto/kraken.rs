@@ -1,12 +1,16 @@
-use super::sql;
+use super::{portfolio, repair, sql, util};
 use crate::http::*;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::{DateTime, TimeDelta};
 use deadpool_postgres::Pool;
 use dotenv::var;
 use futures::{stream, StreamExt};
+use hmac::{Hmac, Mac};
 use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::header::HeaderValue;
 use serde::de::{IgnoredAny, SeqAccess, Visitor};
 use serde::Deserialize;
+use sha2::{Digest, Sha256, Sha512};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
@@ -16,6 +20,8 @@ const BROKERAGE: &str = "Kraken";
 
 // RATE_LIMIT = 15 /1s
 //
+// 24h tickers = `https://api.kraken.com/0/public/Ticker`
+//
 // tickers = `https://api.kucoin.com/api/v1/market/allTickers`
 //
 // NOTE: KuCoin symbols include a dash, e.g. BTC-USDT, or ETH-BTC
@@ -108,18 +114,58 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
         }
     };
 
+    // 3c. upsert instrument metadata (already included in `AssetPairs`), so that non-trading
+    // pairs can be skipped
+    info!("inserting instruments ...");
+    let trading = match util::upsert_instruments(
+        &mut pg_client,
+        &tickers.normalise(),
+        &symbol_pks,
+        source_pk,
+        BROKERAGE,
+    )
+    .await
+    {
+        Ok(trading) => Some(trading),
+        Err(err) => {
+            error!("failed to collect {BROKERAGE} instruments, error({err})");
+            None
+        }
+    };
+
+    // 3d. snapshot 24h ticker statistics
+    info!("fetching 24h ticker statistics ...");
+    if let Err(err) = snapshot_24h(
+        &http_client,
+        &mut pg_client,
+        &tickers,
+        &symbol_pks,
+        source_pk,
+    )
+    .await
+    {
+        error!("failed to snapshot {BROKERAGE} 24h ticker statistics, error({err})");
+    }
+
     drop(pg_client);
 
+    // skip any pair that is not currently trading
+    let pairs: Vec<(&String, &Pair)> = tickers
+        .result
+        .iter()
+        .filter(|(_key, value)| util::is_trading(&trading, &value.altname))
+        .collect();
+
     // progress bars
     let (multi, total, success, fail) = if tui {
-        crate::tui::multi_progress(tickers.result.len())?
+        crate::tui::multi_progress(pairs.len())?
     } else {
         (None, None, None, None)
     };
 
-    // 3c. fetch prices for tickers
+    // 3e. fetch prices for tickers
     info!("fetching prices ...");
-    let stream = stream::iter(&tickers.result);
+    let stream = stream::iter(pairs);
     stream
         .for_each_concurrent(num_cpus::get(), |(_key, value)| {
             let http_client = &http_client;
@@ -248,7 +294,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn build_client() -> HttpClient {
+pub(super) fn build_client() -> HttpClient {
     let mut headers = reqwest::header::HeaderMap::new();
     headers.insert(
         "API-Key",
@@ -262,29 +308,60 @@ fn build_client() -> HttpClient {
     client
 }
 
-// // security
-// // ----------------------------------------------------------------
-// //
-// // https://docs.kraken.com/api/docs/guides/spot-rest-auth
-//
-// fn encrypt(secret: String, input: String) -> String {
-//     type HmacSha256 = Hmac<Sha256>;
-//     let mut mac = HmacSha256::new_from_slice(&secret.as_bytes()).unwrap();
-//     mac.update(input.as_bytes());
-//     let result = mac.finalize().into_bytes();
-//     let b64 = BASE64_STANDARD.encode(&result);
-//     b64
-// }
-//
-// fn sign(url: &String, secret: String, nonce: &String) -> String {
-//     let url = url.replace("https://api.kraken.com", "");
-//     let input = format!("{}{}{}", nonce, "GET", url);
-//     encrypt(secret, input)
-// }
+/// Fetch the 24h ticker statistics for every pair, and insert them as a snapshot.
+///
+/// The `Ticker` endpoint is keyed by the pair name (e.g. "XXBTZUSD"), so `pairs` is used to map
+/// each back to the `altname` stored in `crypto.symbols`.
+async fn snapshot_24h(
+    http_client: &HttpClient,
+    pg_client: &mut PgClient,
+    pairs: &KrakenSymbols,
+    symbol_pks: &HashMap<String, i32>,
+    source_pk: i16,
+) -> anyhow::Result<()> {
+    let stats: KrakenTickers = http_client
+        .get("https://api.kraken.com/0/public/Ticker")
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} 24h tickers, error({err})");
+            err
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} 24h tickers, error({err})");
+            err
+        })?;
+
+    util::insert_tickers_24h(
+        pg_client,
+        &stats.normalise(pairs),
+        symbol_pks,
+        source_pk,
+        BROKERAGE,
+    )
+    .await
+}
+
+// security
+// ----------------------------------------------------------------
 //
-// fn nonce() -> String {
-//     chrono::Utc::now().timestamp_millis().to_string()
-// }
+// https://docs.kraken.com/api/docs/guides/spot-rest-auth
+
+fn sign(path: &str, secret: &str, nonce: &str, post_data: &str) -> anyhow::Result<String> {
+    let mut sha256 = Sha256::new();
+    sha256.update(format!("{nonce}{post_data}").as_bytes());
+
+    let mut mac = Hmac::<Sha512>::new_from_slice(&BASE64_STANDARD.decode(secret)?)?;
+    mac.update(path.as_bytes());
+    mac.update(&sha256.finalize());
+    Ok(BASE64_STANDARD.encode(mac.finalize().into_bytes()))
+}
+
+fn nonce() -> String {
+    chrono::Utc::now().timestamp_millis().to_string()
+}
 
 /////////////////////////////////////////////////////////////////////////////////
 // endpoints
@@ -302,6 +379,16 @@ fn build_client() -> HttpClient {
 //              "altname": "ETHXBT",
 //              "wsname": "ETH/XBT",
 //              "aclass_base": "currency",
+//              "base": "XETH",
+//              "quote": "XXBT",
+//              "lot_decimals": 8,
+//              "tick_size": "0.00001",
+//              "status": "online",
+//              ...
+//          },
+//          ...
+//      }
+//  }
 #[derive(Debug, Deserialize)]
 struct KrakenSymbols {
     // error: Vec<String>,
@@ -311,9 +398,34 @@ struct KrakenSymbols {
 #[derive(Debug, Deserialize)]
 struct Pair {
     altname: String,
+    base: Option<String>,
+    quote: Option<String>,
+    tick_size: Option<String>,
+    lot_decimals: Option<i32>,
+    status: Option<String>,
 }
 
 impl KrakenSymbols {
+    // Kraken gives the lot size as a number of decimals, e.g. 8 -> 0.00000001
+    fn normalise(&self) -> Vec<util::Instrument> {
+        self.result
+            .iter()
+            .map(|(key, pair)| {
+                let status = pair.status.clone().unwrap_or_else(|| "online".to_string());
+                util::Instrument {
+                    symbol: pair.altname.clone(),
+                    exchange_symbol: key.clone(),
+                    base_asset: pair.base.clone(),
+                    quote_asset: pair.quote.clone(),
+                    is_trading: status == "online",
+                    status,
+                    tick_size: util::parse_f64(&pair.tick_size),
+                    lot_size: pair.lot_decimals.map(|decimals| 10f64.powi(-decimals)),
+                }
+            })
+            .collect()
+    }
+
     async fn insert(&self, pg_client: &mut PgClient) -> anyhow::Result<()> {
         let time = std::time::Instant::now();
 
@@ -365,6 +477,76 @@ impl KrakenSymbols {
     }
 }
 
+// 24h tickers
+// ----------------------------------------------------------------
+//
+// NOTE: every array is [today, last 24 hours], except `c` which is [price, lot volume]
+//
+//  {
+//      "error": [],
+//      "result": {
+//          "XXBTZUSD": {
+//              "a": ["30300.10000", "1", "1.000"],
+//              "b": ["30300.00000", "1", "1.000"],
+//              "c": ["30303.20000", "0.00067643"],
+//              "v": ["4083.67001100", "4412.73601799"],
+//              "p": ["30706.77771", "30689.13205"],
+//              "t": [34619, 38907],
+//              "l": ["29868.30000", "29868.30000"],
+//              "h": ["31631.00000", "31631.00000"],
+//              "o": "30502.80000"
+//          },
+//          ...
+//      }
+//  }
+#[derive(Debug, Deserialize)]
+struct KrakenTickers {
+    result: HashMap<String, KrakenTicker>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenTicker {
+    c: (String, String),
+    v: (String, String),
+    p: (String, String),
+    t: (i64, i64),
+    l: (String, String),
+    h: (String, String),
+    o: String,
+}
+
+impl KrakenTickers {
+    // Kraken gives no quote volume, nor a 24h change; the former is estimated with the 24h VWAP,
+    // and the latter is measured from today's opening price
+    fn normalise(self, pairs: &KrakenSymbols) -> Vec<util::Ticker24h> {
+        self.result
+            .into_iter()
+            .filter_map(|(key, ticker)| {
+                let symbol = pairs.result.get(&key)?.altname.clone();
+                let last = ticker.c.0.parse::<f64>().ok();
+                let open = ticker.o.parse::<f64>().ok();
+                let volume = ticker.v.1.parse::<f64>().ok();
+                let vwap = ticker.p.1.parse::<f64>().ok();
+                let change = last.zip(open).map(|(last, open)| last - open);
+                Some(util::Ticker24h {
+                    symbol,
+                    last_price: last,
+                    price_change: change,
+                    price_change_perc: change
+                        .zip(open)
+                        .filter(|(_, open)| *open != 0.0)
+                        .map(|(change, open)| change / open * 100.0),
+                    high: ticker.h.1.parse::<f64>().ok(),
+                    low: ticker.l.1.parse::<f64>().ok(),
+                    volume,
+                    quote_volume: volume.zip(vwap).map(|(volume, vwap)| volume * vwap),
+                    trades: Some(ticker.t.1),
+                })
+            })
+            .collect()
+    }
+}
+
 // prices
 // ----------------------------------------------------------------
 //
@@ -451,7 +633,7 @@ impl Klines {
         // iterate over the data stream and execute pg rows
         for (_result_symbol, klines) in self.result.pairs {
             // preprocess pg query as transaction
-            let query = pg_client.prepare(&sql::INSERT_PRICE).await?;
+            let query = pg_client.prepare(&sql::UPSERT_PRICE).await?;
             let transaction = Arc::new(pg_client.transaction().await?);
 
             let mut stream = stream::iter(klines);
@@ -461,15 +643,14 @@ impl Klines {
                 let symbol = &symbol;
                 let interval_pk: i16 = 3;
                 async move {
+                    let dt = DateTime::from_timestamp(cell.time, 0);
+                    let closed = dt.is_some_and(|dt| util::is_closed(dt, TimeDelta::days(1)));
                     let result = transaction
                         .execute(
                             &query,
                             &[
                                 &symbol_pk,
-                                &chrono::DateTime::from_timestamp(
-                                    cell.time,
-                                    0,
-                                ),
+                                &dt,
                                 &interval_pk,
                                 &cell.opening.parse::<f64>().expect("String -> f64 Opening"),
                                 &cell.high.parse::<f64>().expect("String -> f64 High"),
@@ -478,6 +659,7 @@ impl Klines {
                                 &cell.volume.parse::<f64>().expect("String -> f64 volume"),
                                 &cell.trades,
                                 &source_pk,
+                                &closed,
                             ],
                         )
                         .await;
@@ -513,3 +695,428 @@ impl Klines {
         Ok(())
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////////
+// account
+/////////////////////////////////////////////////////////////////////////////////
+//
+// https://docs.kraken.com/api/docs/rest-api/get-extended-balance
+//
+// balances = `https://api.kraken.com/0/private/BalanceEx`
+//
+// deposits & withdrawals = `https://api.kraken.com/0/private/Ledgers`, 50 per page
+//
+// fills = `https://api.kraken.com/0/private/TradesHistory`, 50 per page
+//
+// NOTE: private endpoints are POST only, and share a slower rate limit that decays at
+// ~0.33 /1s, hence the delay between pages
+
+const PRIVATE_DELAY: Duration = Duration::from_secs(3);
+
+/// Fetch the account's balances, transfers & fills.
+pub(super) async fn account(since: &portfolio::Since) -> anyhow::Result<portfolio::Account> {
+    let http_client = build_client();
+    let secret = var("KRAKEN_PRIVATE")?;
+
+    // pairs are keyed by name (e.g. "XXBTZUSD"), but stored by `altname`
+    let pairs: KrakenSymbols = http_client
+        .get("https://api.kraken.com/0/public/AssetPairs")
+        .send()
+        .await?
+        .json()
+        .await?;
+
+    // balances
+    let balances: HashMap<String, ExtendedBalance> =
+        signed(&http_client, &secret, "/0/private/BalanceEx", "").await?;
+    let balances: Vec<portfolio::Balance> = balances
+        .into_iter()
+        .filter_map(|(asset, balance)| {
+            let total = balance.balance.parse::<f64>().ok()?;
+            let locked = util::parse_f64(&balance.hold_trade).unwrap_or_default();
+            Some(portfolio::Balance {
+                asset,
+                free: total - locked,
+                locked,
+            })
+        })
+        .collect();
+
+    // transfers
+    let mut transfers = Vec::new();
+    for kind in [portfolio::DEPOSIT, portfolio::WITHDRAWAL] {
+        let params = format!("type={kind}&start={}", since.transfers.timestamp());
+        let ledger: Vec<(String, LedgerEntry)> = paged(
+            &http_client,
+            &secret,
+            "/0/private/Ledgers",
+            &params,
+            |page: Ledgers| (page.ledger, page.count),
+        )
+        .await?;
+        transfers.extend(
+            ledger
+                .into_iter()
+                .filter_map(|(id, entry)| entry.normalise(id, kind)),
+        );
+    }
+
+    // fills
+    let params = format!("start={}", since.fills.timestamp());
+    let trades: Vec<(String, Trade)> = paged(
+        &http_client,
+        &secret,
+        "/0/private/TradesHistory",
+        &params,
+        |page: Trades| (page.trades, page.count),
+    )
+    .await?;
+    let fills = trades
+        .into_iter()
+        .filter_map(|(id, trade)| trade.normalise(id, &pairs))
+        .collect();
+
+    Ok(portfolio::Account {
+        balances,
+        transfers,
+        fills,
+    })
+}
+
+/// Send a signed request to a private endpoint, unwrapping its `result`.
+async fn signed<T: serde::de::DeserializeOwned>(
+    http_client: &HttpClient,
+    secret: &str,
+    path: &str,
+    params: &str,
+) -> anyhow::Result<T> {
+    let nonce = nonce();
+    let post_data = if params.is_empty() {
+        format!("nonce={nonce}")
+    } else {
+        format!("nonce={nonce}&{params}")
+    };
+    let response: KrakenResponse<T> = http_client
+        .post(format!("https://api.kraken.com{path}"))
+        .header("API-Sign", sign(path, secret, &nonce, &post_data)?)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(post_data)
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} {path}, error({err})");
+            err
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} {path}, error({err})");
+            err
+        })?;
+
+    match response.result {
+        Some(result) if response.error.is_empty() => Ok(result),
+        _ => anyhow::bail!("{BROKERAGE} {path} failed, error({:?})", response.error),
+    }
+}
+
+/// Fetch every page of a paginated private endpoint, i.e. a map of entries plus their `count`.
+async fn paged<P, T>(
+    http_client: &HttpClient,
+    secret: &str,
+    path: &str,
+    params: &str,
+    entries: fn(P) -> (HashMap<String, T>, usize),
+) -> anyhow::Result<Vec<(String, T)>>
+where
+    P: serde::de::DeserializeOwned,
+{
+    let mut items = Vec::new();
+    loop {
+        let page: P = signed(
+            http_client,
+            secret,
+            path,
+            &format!("{params}&ofs={}", items.len()),
+        )
+        .await?;
+        let (page, count) = entries(page);
+        if page.is_empty() {
+            break;
+        }
+        items.extend(page);
+
+        if items.len() >= count {
+            break;
+        }
+        tokio::time::sleep(PRIVATE_DELAY).await;
+    }
+    Ok(items)
+}
+
+// {
+//      "error": [],
+//      "result": ...
+// }
+#[derive(Debug, Deserialize)]
+struct KrakenResponse<T> {
+    error: Vec<String>,
+    result: Option<T>,
+}
+
+// {
+//      "ZUSD": {
+//          "balance": "25435.21",
+//          "hold_trade": "8249.76"
+//      },
+//      ...
+// }
+#[derive(Debug, Deserialize)]
+struct ExtendedBalance {
+    balance: String,
+    hold_trade: Option<String>,
+}
+
+// {
+//      "ledger": {
+//          "L4UESK-KG3EQ-UFO4T5": {
+//              "refid": "TJKLXX-PGMUI-4NTLXU",
+//              "time": 1688464484.1787,
+//              "type": "deposit",
+//              "asset": "ZUSD",
+//              "amount": "-24.5000",
+//              "fee": "0.0490"
+//          },
+//          ...
+//      },
+//      "count": 2
+// }
+#[derive(Debug, Deserialize)]
+struct Ledgers {
+    ledger: HashMap<String, LedgerEntry>,
+    count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct LedgerEntry {
+    time: f64,
+    asset: String,
+    amount: String,
+    fee: Option<String>,
+}
+
+impl LedgerEntry {
+    // ledger entries are only written once settled, and withdrawals are negative
+    fn normalise(self, id: String, kind: &'static str) -> Option<portfolio::Transfer> {
+        Some(portfolio::Transfer {
+            tx_id: id,
+            kind,
+            asset: self.asset,
+            amount: self.amount.parse::<f64>().ok()?.abs(),
+            fee: util::parse_f64(&self.fee),
+            status: "settled".to_string(),
+            dt: DateTime::from_timestamp_millis((self.time * 1000.0) as i64)?,
+        })
+    }
+}
+
+// {
+//      "trades": {
+//          "THVRQM-33VKH-UCI7BS": {
+//              "pair": "XXBTZUSD",
+//              "time": 1688667796.8802,
+//              "type": "buy",
+//              "price": "30010.00000",
+//              "fee": "0.03000",
+//              "vol": "0.00001000"
+//          },
+//          ...
+//      },
+//      "count": 2
+// }
+#[derive(Debug, Deserialize)]
+struct Trades {
+    trades: HashMap<String, Trade>,
+    count: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Trade {
+    pair: String,
+    time: f64,
+    #[serde(rename = "type")]
+    side: String,
+    price: String,
+    fee: Option<String>,
+    vol: String,
+}
+
+impl Trade {
+    // fees are charged in the quote currency by default
+    fn normalise(self, id: String, pairs: &KrakenSymbols) -> Option<portfolio::Fill> {
+        let pair = pairs.result.get(&self.pair)?;
+        Some(portfolio::Fill {
+            trade_id: id,
+            symbol: pair.altname.clone(),
+            side: if self.side == "buy" {
+                portfolio::BUY
+            } else {
+                portfolio::SELL
+            },
+            price: self.price.parse().ok()?,
+            quantity: self.vol.parse().ok()?,
+            fee: util::parse_f64(&self.fee),
+            fee_asset: pair.quote.clone(),
+            dt: DateTime::from_timestamp_millis((self.time * 1000.0) as i64)?,
+        })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// repair
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Refetch the daily klines missing within a [Gap](repair::Gap).
+///
+/// ### **NOTE**
+///
+/// Kraken only serves the latest 720 candles of an interval, regardless of `since`; anything
+/// older can't be refetched.
+pub(super) async fn refetch(
+    http_client: &HttpClient,
+    pool: &Pool,
+    gap: &repair::Gap,
+) -> anyhow::Result<()> {
+    let mut pg_client = pool.get().await?;
+
+    let url = format!(
+        "https://api.kraken.com/0/public/OHLC?interval=1440&pair={}&since={}",
+        gap.symbol,
+        gap.start.timestamp() - 1
+    );
+    let mut klines: Klines = http_client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    // the latest 720 candles are returned, so keep only the missing ones
+    for klines in klines.result.pairs.values_mut() {
+        klines.retain(|kline| {
+            DateTime::from_timestamp(kline.time, 0).is_some_and(|dt| gap.covers(dt))
+        });
+    }
+    klines
+        .insert(
+            &mut pg_client,
+            gap.symbol.clone(),
+            gap.symbol_pk,
+            gap.source_pk,
+        )
+        .await?;
+
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn tickers_24h_normalise() {
+    let pairs = r#"{
+        "error": [],
+        "result": {
+            "XXBTZUSD": { "altname": "XBTUSD", "base": "XXBT", "quote": "ZUSD" }
+        }
+    }"#;
+    let pairs: KrakenSymbols = serde_json::from_str(pairs).expect("valid pairs");
+    let json = r#"{
+        "error": [],
+        "result": {
+            "XXBTZUSD": {
+                "a": ["30300.10000", "1", "1.000"],
+                "b": ["30300.00000", "1", "1.000"],
+                "c": ["30303.20000", "0.00067643"],
+                "v": ["4083.67001100", "4412.73601799"],
+                "p": ["30706.77771", "30689.13205"],
+                "t": [34619, 38907],
+                "l": ["29868.30000", "29868.30000"],
+                "h": ["31631.00000", "31631.00000"],
+                "o": "30502.80000"
+            },
+            "UNKNOWN": {
+                "c": ["1.0", "1.0"],
+                "v": ["1.0", "1.0"],
+                "p": ["1.0", "1.0"],
+                "t": [1, 1],
+                "l": ["1.0", "1.0"],
+                "h": ["1.0", "1.0"],
+                "o": "1.0"
+            }
+        }
+    }"#;
+    let tickers: KrakenTickers = serde_json::from_str(json).expect("valid 24h tickers");
+    let tickers = tickers.normalise(&pairs);
+
+    // tickers of unknown pairs are dropped
+    assert_eq!(tickers.len(), 1);
+    let ticker = &tickers[0];
+    assert_eq!(ticker.symbol, "XBTUSD");
+    assert_eq!(ticker.last_price, Some(30303.2));
+    assert_eq!(ticker.volume, Some(4412.73601799));
+    assert_eq!(ticker.trades, Some(38907));
+
+    // the change is measured from today's open, & the quote volume estimated with the 24h VWAP
+    let change = ticker.price_change.expect("price change");
+    assert!((change - -199.6).abs() < 1e-6);
+    let perc = ticker.price_change_perc.expect("price change percent");
+    assert!((perc - -199.6 / 30502.8 * 100.0).abs() < 1e-9);
+    let quote_volume = ticker.quote_volume.expect("quote volume");
+    assert!((quote_volume - 4412.73601799 * 30689.13205).abs() < 1e-6);
+}
+
+#[test]
+fn instruments_normalise() {
+    let json = r#"{
+        "error": [],
+        "result": {
+            "XETHXXBT": {
+                "altname": "ETHXBT",
+                "wsname": "ETH/XBT",
+                "aclass_base": "currency",
+                "base": "XETH",
+                "quote": "XXBT",
+                "lot_decimals": 8,
+                "tick_size": "0.00001",
+                "status": "online"
+            },
+            "XXBTZUSD": {
+                "altname": "XBTUSD",
+                "base": "XXBT",
+                "quote": "ZUSD",
+                "status": "cancel_only"
+            }
+        }
+    }"#;
+    let pairs: KrakenSymbols = serde_json::from_str(json).expect("valid pairs");
+    let mut instruments = pairs.normalise();
+    instruments.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    let eth = &instruments[0];
+    assert_eq!(
+        (eth.symbol.as_str(), eth.exchange_symbol.as_str()),
+        ("ETHXBT", "XETHXXBT")
+    );
+    assert!(eth.is_trading);
+    assert_eq!(eth.tick_size, Some(0.00001));
+    let lot_size = eth.lot_size.expect("lot size");
+    assert!((lot_size - 0.00000001).abs() < 1e-18);
+
+    let btc = &instruments[1];
+    assert_eq!(btc.status, "cancel_only");
+    assert!(!btc.is_trading);
+    assert_eq!((btc.tick_size, btc.lot_size), (None, None));
+}
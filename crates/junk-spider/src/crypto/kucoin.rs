@@ -1,6 +1,7 @@
-use super::sql;
+use super::{portfolio, repair, sql, util};
 use crate::http::*;
 use base64::prelude::{Engine, BASE64_STANDARD};
+use chrono::{DateTime, TimeDelta};
 use deadpool_postgres::Pool;
 use dotenv::var;
 use futures::{stream, StreamExt};
@@ -10,7 +11,7 @@ use reqwest::header::HeaderValue;
 use serde::de::{IgnoredAny, SeqAccess, Visitor};
 use serde::Deserialize;
 use sha2::Sha256;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, info, trace};
@@ -24,6 +25,8 @@ const BROKERAGE: &str = "KuCoin";
 // NOTE: KuCoin symbols include a dash, e.g. BTC-USDT, or ETH-BTC
 //
 // klines = `https://api.kucoin.com/api/v1/market/candles?type=1day&symbol=BTC-USDT`, per symbol
+//
+// instruments = `https://api.kucoin.com/api/v2/symbols`
 
 /////////////////////////////////////////////////////////////////////////////////
 // core
@@ -111,18 +114,50 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
         }
     };
 
+    // 3c. upsert instrument metadata, so that non-trading pairs can be skipped
+    info!("fetching instruments ...");
+    let trading = match instruments(&http_client, &mut pg_client, &symbol_pks, source_pk).await {
+        Ok(trading) => Some(trading),
+        Err(err) => {
+            error!("failed to collect {BROKERAGE} instruments, error({err})");
+            None
+        }
+    };
+
+    // 3d. snapshot 24h ticker statistics (already included in `allTickers`)
+    info!("inserting 24h ticker statistics ...");
+    if let Err(err) = util::insert_tickers_24h(
+        &mut pg_client,
+        &tickers.normalise(),
+        &symbol_pks,
+        source_pk,
+        BROKERAGE,
+    )
+    .await
+    {
+        error!("failed to snapshot {BROKERAGE} 24h ticker statistics, error({err})");
+    }
+
     drop(pg_client);
 
+    // skip any pair that is not currently trading
+    let tickers: Vec<&Ticker> = tickers
+        .data
+        .ticker
+        .iter()
+        .filter(|ticker| util::is_trading(&trading, &ticker.symbol.replace("-", "")))
+        .collect();
+
     // progress bars
     let (multi, total, success, fail) = if tui {
-        crate::tui::multi_progress(tickers.data.ticker.len())?
+        crate::tui::multi_progress(tickers.len())?
     } else {
         (None, None, None, None)
     };
 
-    // 3c. fetch prices for tickers
+    // 3e. fetch prices for tickers
     info!("fetching prices ...");
-    let stream = stream::iter(&tickers.data.ticker);
+    let stream = stream::iter(tickers);
     stream
         .for_each_concurrent(num_cpus::get(), |ticker| {
             let http_client = &http_client;
@@ -265,7 +300,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn build_client() -> HttpClient {
+pub(super) fn build_client() -> HttpClient {
     let mut headers = reqwest::header::HeaderMap::new();
     headers.insert(
         "KC-API-KEY",
@@ -283,6 +318,39 @@ fn build_client() -> HttpClient {
     client
 }
 
+/// Fetch the exchange metadata for every pair, upsert it to `crypto.instruments`, and return the
+/// set of symbols currently trading.
+async fn instruments(
+    http_client: &HttpClient,
+    pg_client: &mut PgClient,
+    symbol_pks: &HashMap<String, i32>,
+    source_pk: i16,
+) -> anyhow::Result<HashSet<String>> {
+    let info: KuCoinSymbols = http_client
+        .get("https://api.kucoin.com/api/v2/symbols")
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} instruments, error({err})");
+            err
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} instruments, error({err})");
+            err
+        })?;
+
+    util::upsert_instruments(
+        pg_client,
+        &info.normalise(),
+        symbol_pks,
+        source_pk,
+        BROKERAGE,
+    )
+    .await
+}
+
 // security
 // ----------------------------------------------------------------
 //
@@ -356,11 +424,38 @@ struct KuCoinData {
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct Ticker {
     symbol: String,
+    change_rate: Option<String>,
+    change_price: Option<String>,
+    high: Option<String>,
+    low: Option<String>,
+    vol: Option<String>,
+    vol_value: Option<String>,
+    last: Option<String>,
 }
 
 impl KuCoinTickerResponse {
+    // KuCoin reports `changeRate` as a fraction, and does not provide a trade count
+    fn normalise(&self) -> Vec<util::Ticker24h> {
+        self.data
+            .ticker
+            .iter()
+            .map(|ticker| util::Ticker24h {
+                symbol: ticker.symbol.replace("-", ""),
+                last_price: util::parse_f64(&ticker.last),
+                price_change: util::parse_f64(&ticker.change_price),
+                price_change_perc: util::parse_f64(&ticker.change_rate).map(|rate| rate * 100.0),
+                high: util::parse_f64(&ticker.high),
+                low: util::parse_f64(&ticker.low),
+                volume: util::parse_f64(&ticker.vol),
+                quote_volume: util::parse_f64(&ticker.vol_value),
+                trades: None,
+            })
+            .collect()
+    }
+
     async fn insert(&self, pg_client: &mut PgClient) -> anyhow::Result<()> {
         let time = std::time::Instant::now();
 
@@ -412,6 +507,60 @@ impl KuCoinTickerResponse {
     }
 }
 
+// instruments
+// ----------------------------------------------------------------
+// {
+//      "code": "200000",
+//      "data": [
+//          {
+//              "symbol": "BTC-USDT",
+//              "baseCurrency": "BTC",
+//              "quoteCurrency": "USDT",
+//              "baseIncrement": "0.00000001",
+//              "priceIncrement": "0.1",
+//              "enableTrading": true,
+//              ...
+//          },
+//          ...
+//      ]
+// }
+#[derive(Debug, Deserialize)]
+struct KuCoinSymbols {
+    data: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SymbolInfo {
+    symbol: String,
+    base_currency: String,
+    quote_currency: String,
+    base_increment: Option<String>,
+    price_increment: Option<String>,
+    enable_trading: bool,
+}
+
+impl KuCoinSymbols {
+    fn normalise(self) -> Vec<util::Instrument> {
+        self.data
+            .into_iter()
+            .map(|info| util::Instrument {
+                symbol: info.symbol.replace("-", ""),
+                exchange_symbol: info.symbol,
+                base_asset: Some(info.base_currency),
+                quote_asset: Some(info.quote_currency),
+                status: match info.enable_trading {
+                    true => "enabled".to_string(),
+                    false => "disabled".to_string(),
+                },
+                is_trading: info.enable_trading,
+                tick_size: util::parse_f64(&info.price_increment),
+                lot_size: util::parse_f64(&info.base_increment),
+            })
+            .collect()
+    }
+}
+
 // prices
 // ----------------------------------------------------------------
 //
@@ -481,7 +630,7 @@ impl Klines {
         let time = ::std::time::Instant::now();
 
         // preprocess pg query as transaction
-        let query = pg_client.prepare(&sql::INSERT_PRICE).await?;
+        let query = pg_client.prepare(&sql::UPSERT_PRICE).await?;
         let transaction = Arc::new(pg_client.transaction().await?);
 
         // iterate over the data stream and execute pg rows
@@ -492,15 +641,17 @@ impl Klines {
             let symbol = &symbol;
             let interval_pk: i16 = 3;
             async move {
+                let dt = DateTime::from_timestamp(
+                    cell.time.parse::<i64>().expect("String -> i64 Time"),
+                    0,
+                );
+                let closed = dt.is_some_and(|dt| util::is_closed(dt, TimeDelta::days(1)));
                 let result = transaction
                     .execute(
                         &query,
                         &[
                             &symbol_pk,
-                            &chrono::DateTime::from_timestamp(
-                                cell.time.parse::<i64>().expect("String -> i64 Time"),
-                                0,
-                            ),
+                            &dt,
                             &interval_pk,
                             &cell.opening.parse::<f64>().expect("String -> f64 Opening"),
                             &cell.high.parse::<f64>().expect("String -> f64 Opening"),
@@ -509,6 +660,7 @@ impl Klines {
                             &cell.volume.parse::<f64>().expect("String -> f64 Opening"),
                             &None::<i64>,
                             &source_pk,
+                            &closed,
                         ],
                     )
                     .await;
@@ -541,3 +693,429 @@ impl Klines {
         Ok(())
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////////
+// account
+/////////////////////////////////////////////////////////////////////////////////
+//
+// https://www.kucoin.com/docs/rest/account/basic-info/get-account-list-spot-margin-trade_hf
+//
+// balances = `https://api.kucoin.com/api/v1/accounts`
+//
+// deposits = `https://api.kucoin.com/api/v1/deposits`, per 7 days
+//
+// withdrawals = `https://api.kucoin.com/api/v1/withdrawals`, per 7 days
+//
+// fills = `https://api.kucoin.com/api/v1/fills`, per 7 days
+//
+// NOTE: balances are per account type (main, trade, margin, ...), so they are summed per asset
+
+const ACCOUNT_WINDOW: TimeDelta = TimeDelta::days(7);
+const PAGE_SIZE: usize = 500;
+
+/// Fetch the account's balances, transfers & fills.
+pub(super) async fn account(since: &portfolio::Since) -> anyhow::Result<portfolio::Account> {
+    let http_client = build_client();
+    let private = var("KUCOIN_PRIVATE")?;
+    let passphrase = var("KUCOIN_PASSPHRASE")?;
+
+    // balances
+    let accounts: Vec<AccountBalance> = signed(
+        &http_client,
+        &private,
+        &passphrase,
+        "/api/v1/accounts".to_string(),
+    )
+    .await?;
+    let mut balances: HashMap<String, portfolio::Balance> = HashMap::new();
+    for account in accounts {
+        let balance = balances
+            .entry(account.currency.clone())
+            .or_insert(portfolio::Balance {
+                asset: account.currency,
+                free: 0.0,
+                locked: 0.0,
+            });
+        balance.free += account.available.parse::<f64>().unwrap_or_default();
+        balance.locked += account.holds.parse::<f64>().unwrap_or_default();
+    }
+
+    // transfers
+    let mut transfers = Vec::new();
+    for (start, end) in portfolio::windows(since.transfers, ACCOUNT_WINDOW) {
+        let params = format!(
+            "startAt={}&endAt={}",
+            start.timestamp_millis(),
+            end.timestamp_millis()
+        );
+
+        let deposits: Vec<Transfer> = paged(
+            &http_client,
+            &private,
+            &passphrase,
+            "/api/v1/deposits",
+            &params,
+        )
+        .await?;
+        transfers.extend(
+            deposits
+                .into_iter()
+                .filter_map(|transfer| transfer.normalise(portfolio::DEPOSIT)),
+        );
+
+        let withdrawals: Vec<Transfer> = paged(
+            &http_client,
+            &private,
+            &passphrase,
+            "/api/v1/withdrawals",
+            &params,
+        )
+        .await?;
+        transfers.extend(
+            withdrawals
+                .into_iter()
+                .filter_map(|transfer| transfer.normalise(portfolio::WITHDRAWAL)),
+        );
+    }
+
+    // fills
+    let mut fills = Vec::new();
+    for (start, end) in portfolio::windows(since.fills, ACCOUNT_WINDOW) {
+        let params = format!(
+            "startAt={}&endAt={}",
+            start.timestamp_millis(),
+            end.timestamp_millis()
+        );
+        let trades: Vec<Fill> = paged(
+            &http_client,
+            &private,
+            &passphrase,
+            "/api/v1/fills",
+            &params,
+        )
+        .await?;
+        fills.extend(trades.into_iter().filter_map(Fill::normalise));
+    }
+
+    Ok(portfolio::Account {
+        balances: balances.into_values().collect(),
+        transfers,
+        fills,
+    })
+}
+
+/// Send a signed request to a private endpoint, unwrapping its `data`.
+async fn signed<T: serde::de::DeserializeOwned>(
+    http_client: &HttpClient,
+    private: &str,
+    passphrase: &str,
+    path: String,
+) -> anyhow::Result<T> {
+    let url = format!("https://api.kucoin.com{path}");
+    let timestamp = timestamp();
+    let passphrase = encrypt(private.to_string(), passphrase.to_string());
+    let sign = sign(&url, private.to_string(), timestamp.clone());
+    let response: KuCoinResponse<T> = http_client
+        .get(url)
+        .header("KC-API-TIMESTAMP", timestamp)
+        .header("KC-API-PASSPHRASE", passphrase)
+        .header("KC-API-SIGN", sign)
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} {path}, error({err})");
+            err
+        })?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} {path}, error({err})");
+            err
+        })?;
+    Ok(response.data)
+}
+
+/// Fetch every page of a paginated private endpoint.
+async fn paged<T: serde::de::DeserializeOwned>(
+    http_client: &HttpClient,
+    private: &str,
+    passphrase: &str,
+    path: &str,
+    params: &str,
+) -> anyhow::Result<Vec<T>> {
+    let mut items = Vec::new();
+    let mut current_page = 1;
+    loop {
+        let page: Page<T> = signed(
+            http_client,
+            private,
+            passphrase,
+            format!("{path}?{params}&currentPage={current_page}&pageSize={PAGE_SIZE}"),
+        )
+        .await?;
+        items.extend(page.items);
+
+        if current_page >= page.total_page {
+            break;
+        }
+        current_page += 1;
+    }
+    Ok(items)
+}
+
+// {
+//      "code": "200000",
+//      "data": ...
+// }
+#[derive(Debug, Deserialize)]
+struct KuCoinResponse<T> {
+    data: T,
+}
+
+// {
+//      "currentPage": 1,
+//      "pageSize": 500,
+//      "totalNum": 2,
+//      "totalPage": 1,
+//      "items": [...]
+// }
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Page<T> {
+    total_page: i64,
+    items: Vec<T>,
+}
+
+// [
+//      {
+//          "id": "5bd6e9286d99522a52e458de",
+//          "currency": "BTC",
+//          "type": "main",
+//          "balance": "237582.04299",
+//          "available": "237582.032",
+//          "holds": "0.01099"
+//      },
+//      ...
+// ]
+#[derive(Debug, Deserialize)]
+struct AccountBalance {
+    currency: String,
+    available: String,
+    holds: String,
+}
+
+// {
+//      "id": "5c2dc64e03aa675aa263f1ac",
+//      "currency": "XRP",
+//      "status": "SUCCESS",
+//      "amount": "1.00000000",
+//      "fee": "0.00000000",
+//      "walletTxId": "3e2414d82acce78d38be7fe9",
+//      "createdAt": 1546503758000
+// }
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transfer {
+    id: Option<String>,
+    currency: String,
+    status: String,
+    amount: String,
+    fee: Option<String>,
+    wallet_tx_id: Option<String>,
+    created_at: i64,
+}
+
+impl Transfer {
+    // deposits have no `id`, and internal transfers have no `walletTxId`
+    fn normalise(self, kind: &'static str) -> Option<portfolio::Transfer> {
+        let tx_id = self
+            .id
+            .or(self.wallet_tx_id)
+            .unwrap_or_else(|| format!("{}-{}", self.currency, self.created_at));
+        Some(portfolio::Transfer {
+            tx_id,
+            kind,
+            asset: self.currency,
+            amount: self.amount.parse().ok()?,
+            fee: util::parse_f64(&self.fee),
+            status: self.status,
+            dt: DateTime::from_timestamp_millis(self.created_at)?,
+        })
+    }
+}
+
+// {
+//      "symbol": "BTC-USDT",
+//      "tradeId": "5c35c02709e4f67d5266954e",
+//      "side": "buy",
+//      "price": "0.001",
+//      "size": "0.0001",
+//      "fee": "0",
+//      "feeCurrency": "USDT",
+//      "createdAt": 1547026472000
+// }
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Fill {
+    symbol: String,
+    trade_id: String,
+    side: String,
+    price: String,
+    size: String,
+    fee: Option<String>,
+    fee_currency: Option<String>,
+    created_at: i64,
+}
+
+impl Fill {
+    fn normalise(self) -> Option<portfolio::Fill> {
+        Some(portfolio::Fill {
+            trade_id: self.trade_id,
+            symbol: self.symbol.replace('-', ""),
+            side: if self.side == "buy" {
+                portfolio::BUY
+            } else {
+                portfolio::SELL
+            },
+            price: self.price.parse().ok()?,
+            quantity: self.size.parse().ok()?,
+            fee: util::parse_f64(&self.fee),
+            fee_asset: self.fee_currency,
+            dt: DateTime::from_timestamp_millis(self.created_at)?,
+        })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// repair
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Refetch the daily klines missing within a [Gap](repair::Gap).
+///
+/// KuCoin returns at most 1500 candles per request, newest first, so the gap is paged backwards
+/// from its end.
+pub(super) async fn refetch(
+    http_client: &HttpClient,
+    pool: &Pool,
+    gap: &repair::Gap,
+) -> anyhow::Result<()> {
+    let private = var("KUCOIN_PRIVATE")?;
+    let passphrase = var("KUCOIN_PASSPHRASE")?;
+    let mut pg_client = pool.get().await?;
+
+    let start = gap.start.timestamp();
+    let mut end = gap.end.timestamp();
+    while start <= end {
+        let data: Vec<Kline> = signed(
+            http_client,
+            &private,
+            &passphrase,
+            format!(
+                "/api/v1/market/candles?type=1day&symbol={}&startAt={start}&endAt={end}",
+                gap.symbol
+            ),
+        )
+        .await?;
+
+        // an empty page means the source has nothing left within the gap
+        let first = match data
+            .iter()
+            .filter_map(|kline| kline.time.parse::<i64>().ok())
+            .min()
+        {
+            Some(first) => first,
+            None => break,
+        };
+        Klines { data }
+            .insert(
+                &mut pg_client,
+                gap.symbol.clone(),
+                gap.symbol_pk,
+                gap.source_pk,
+            )
+            .await?;
+        end = first - 1;
+    }
+
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn tickers_24h_normalise() {
+    let json = r#"{
+        "code": "200000",
+        "data": {
+            "time": 1602832092060,
+            "ticker": [
+                {
+                    "symbol": "BTC-USDT",
+                    "symbolName": "BTC-USDT",
+                    "buy": "11328.9",
+                    "sell": "11329",
+                    "changeRate": "-0.0055",
+                    "changePrice": "-63.6",
+                    "high": "11610",
+                    "low": "11200",
+                    "vol": "2282.70993217",
+                    "volValue": "25984946.15779",
+                    "last": "11328.9"
+                }
+            ]
+        }
+    }"#;
+    let tickers: KuCoinTickerResponse = serde_json::from_str(json).expect("valid tickers");
+    let tickers = tickers.normalise();
+
+    assert_eq!(tickers[0].symbol, "BTCUSDT");
+    assert_eq!(tickers[0].last_price, Some(11328.9));
+    assert_eq!(tickers[0].price_change, Some(-63.6));
+    assert_eq!(tickers[0].quote_volume, Some(25984946.15779));
+
+    // the change rate is a fraction, & there is no trade count
+    let perc = tickers[0].price_change_perc.expect("price change percent");
+    assert!((perc - -0.55).abs() < 1e-9);
+    assert_eq!(tickers[0].trades, None);
+}
+
+#[test]
+fn instruments_normalise() {
+    let json = r#"{
+        "code": "200000",
+        "data": [
+            {
+                "symbol": "BTC-USDT",
+                "name": "BTC-USDT",
+                "baseCurrency": "BTC",
+                "quoteCurrency": "USDT",
+                "baseIncrement": "0.00000001",
+                "priceIncrement": "0.1",
+                "enableTrading": true
+            },
+            {
+                "symbol": "LUNA-USDT",
+                "baseCurrency": "LUNA",
+                "quoteCurrency": "USDT",
+                "enableTrading": false
+            }
+        ]
+    }"#;
+    let symbols: KuCoinSymbols = serde_json::from_str(json).expect("valid symbols");
+    let instruments = symbols.normalise();
+
+    let btc = &instruments[0];
+    assert_eq!(
+        (btc.symbol.as_str(), btc.exchange_symbol.as_str()),
+        ("BTCUSDT", "BTC-USDT")
+    );
+    assert_eq!((btc.status.as_str(), btc.is_trading), ("enabled", true));
+    assert_eq!((btc.tick_size, btc.lot_size), (Some(0.1), Some(0.00000001)));
+
+    let luna = &instruments[1];
+    assert_eq!((luna.status.as_str(), luna.is_trading), ("disabled", false));
+    assert_eq!((luna.tick_size, luna.lot_size), (None, None));
+}
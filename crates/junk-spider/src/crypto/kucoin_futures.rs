@@ -0,0 +1,470 @@
+use super::{sql, util};
+use crate::http::*;
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, trace};
+
+const BROKERAGE: &str = "KuCoin Futures";
+
+// RATE_LIMIT = 2000 /30s
+//
+// contracts = `https://api-futures.kucoin.com/api/v1/contracts/active`
+//
+// funding rates = `https://api-futures.kucoin.com/api/v1/contract/funding-rates?symbol=XBTUSDTM&from=..&to=..`, per symbol
+//
+// NOTE: KuCoin Futures symbols use XBT for bitcoin and a trailing M, e.g. XBTUSDTM; these are
+// mapped to the spot form, e.g. BTCUSDT, so both share a symbol pk.
+//
+// NOTE: there is no open interest history; the current value (from the contract list) is
+// snapshotted on each run, per hour.
+
+// each funding history request returns, at most, 100 funding events; a full page is split around
+// the events returned, & the rest of the window requested again
+const FUNDING_WINDOW_DAYS: i64 = 30;
+const FUNDING_PAGE: usize = 100;
+const OPEN_INTEREST_INTERVAL: &str = "1h";
+
+/////////////////////////////////////////////////////////////////////////////////
+// core
+/////////////////////////////////////////////////////////////////////////////////
+
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    // wait for a pg client from the pool
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from the pool, error({err})");
+        err
+    })?;
+
+    // fetch the perpetual contracts
+    if tui {
+        println!("{bar}\n{BROKERAGE:^40}\n{bar}", bar = "=".repeat(40))
+    }
+    let pb = if tui {
+        let pb = ProgressBar::new_spinner()
+            .with_message("fetching contracts ...")
+            .with_style(ProgressStyle::default_spinner().template("{msg} {spinner:.magenta}")?);
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb
+    } else {
+        ProgressBar::hidden()
+    };
+    let http_client = super::kucoin::build_client();
+    let contracts: Contracts = http_client
+        .get("https://api-futures.kucoin.com/api/v1/contracts/active")
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} contracts, error({err})");
+            err
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} contracts, error({err})");
+            err
+        })?;
+    let perpetuals: Vec<Contract> = contracts
+        .data
+        .into_iter()
+        .filter(|contract| contract.r#type == "FFWCSX" && contract.status == "Open")
+        .collect();
+    let symbols: Vec<String> = perpetuals.iter().map(|c| c.spot_symbol()).collect();
+
+    // 1. insert source
+    pb.set_message("inserting contracts ...");
+    let source_pk = util::source_pk(&mut pg_client, BROKERAGE).await?;
+
+    // 2. insert contracts as symbols
+    util::insert_symbols(&mut pg_client, &symbols, BROKERAGE).await?;
+    pb.finish_with_message("inserting contracts ... done");
+
+    // 3. fetch & insert funding rates and open interest
+    // 3a. fetch symbols
+    info!("fetching symbols ...");
+    let symbol_pks: HashMap<String, i32> = util::fetch_pks(
+        &mut pg_client,
+        "SELECT pk, symbol FROM crypto.symbols",
+        "symbol",
+        "pk",
+    )
+    .await?;
+    let symbol_pks = Arc::new(symbol_pks);
+
+    // 3b. fetch the latest timestamps, so that only new data is requested
+    info!("fetching latest timestamps ...");
+    let latest_funding =
+        util::fetch_latest_dts(&mut pg_client, sql::LATEST_FUNDING_RATES, source_pk).await?;
+    let interval_pk = sql::INTERVAL_PKS[OPEN_INTEREST_INTERVAL] as i16;
+    let snapshot_dt = Utc::now().duration_trunc(TimeDelta::hours(1))?;
+
+    drop(pg_client);
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(perpetuals.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    // 3c. fetch funding rates & snapshot open interest for contracts
+    info!("fetching funding rates & open interest ...");
+    stream::iter(&perpetuals)
+        .for_each_concurrent(num_cpus::get(), |contract| {
+            let http_client = &http_client;
+            let symbol_pks = &symbol_pks;
+            let latest_funding = &latest_funding;
+            let symbol = contract.spot_symbol();
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                let symbol_pk = match symbol_pks.get(&symbol) {
+                    Some(pk) => *pk,
+                    None => {
+                        error!("failed to find symbol pk for {symbol}");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("fetching funding rates for {}", contract.symbol),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                trace!("fetching funding rates for {}", contract.symbol);
+                let since = latest_funding.get(&symbol_pk).copied();
+                let rates = match fetch_funding_rates(http_client, contract, since).await {
+                    Ok(rates) => rates,
+                    Err(err) => {
+                        error!(
+                            "failed to fetch {BROKERAGE} funding rates for {}, error({err})",
+                            contract.symbol
+                        );
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+                let open_interest = [contract.open_interest(snapshot_dt)];
+
+                // wait for a client
+                spinner.set_message(format!("waiting to insert {symbol}"));
+                let mut pg_client = match pool.get().await {
+                    Ok(client) => client,
+                    Err(err) => {
+                        error!("failed to get pg client from the pool, error({err})");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+
+                spinner.set_message(format!("inserting {symbol}"));
+                let result = match util::insert_funding_rates(
+                    &mut pg_client,
+                    &rates,
+                    &symbol,
+                    symbol_pk,
+                    source_pk,
+                    BROKERAGE,
+                )
+                .await
+                {
+                    Ok(_) => {
+                        util::insert_open_interest(
+                            &mut pg_client,
+                            &open_interest,
+                            &symbol,
+                            symbol_pk,
+                            interval_pk,
+                            source_pk,
+                            BROKERAGE,
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                };
+
+                match result {
+                    Ok(_) => {
+                        trace!("inserted funding rates & open interest for {symbol}");
+
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!("failed to insert funding rates & open interest for {symbol}, error({err})");
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("fail bar should have unwrapped")
+            .finish_and_clear();
+        success
+            .expect("success bar should have unwrapped")
+            .finish_and_clear();
+        total
+            .expect("total bar should have unwrapped")
+            .finish_and_clear();
+        println!("collecting funding rates & open interest ... done\n");
+    }
+
+    Ok(())
+}
+
+/// Walk the funding rate history of `contract` in fixed windows, starting after `since` (or from
+/// the contract's first open date, if there is no history yet).
+async fn fetch_funding_rates(
+    http_client: &HttpClient,
+    contract: &Contract,
+    since: Option<DateTime<Utc>>,
+) -> anyhow::Result<Vec<util::FundingRate>> {
+    let mut rates = vec![];
+    let now = Utc::now().timestamp_millis();
+    let from = since
+        .map(|dt| dt.timestamp_millis() + 1)
+        .unwrap_or(contract.first_open_date);
+
+    // windows still to request, latest first, so they're popped oldest first
+    let mut windows = windows(from, now);
+    windows.reverse();
+    while let Some((from, to)) = windows.pop() {
+        let url = format!(
+            "https://api-futures.kucoin.com/api/v1/contract/funding-rates?symbol={}&from={from}&to={to}",
+            contract.symbol
+        );
+        let page: FundingRates = http_client.get(url).send().await?.json().await?;
+
+        let timepoints: Vec<i64> = page.data.iter().map(|rate| rate.timepoint).collect();
+        windows.extend(remaining(from, to, &timepoints).into_iter().rev());
+
+        for rate in page.data {
+            rates.push(util::FundingRate {
+                dt: DateTime::from_timestamp_millis(rate.timepoint).ok_or_else(|| {
+                    anyhow::anyhow!("invalid funding time for {}", contract.symbol)
+                })?,
+                funding_rate: rate.funding_rate,
+                mark_price: None,
+            });
+        }
+    }
+
+    Ok(rates)
+}
+
+/// Split `from..=now` (in ms) into windows of `FUNDING_WINDOW_DAYS`.
+fn windows(from: i64, now: i64) -> Vec<(i64, i64)> {
+    let window = TimeDelta::days(FUNDING_WINDOW_DAYS).num_milliseconds();
+    let mut windows = vec![];
+    let mut from = from;
+    while from < now {
+        let to = (from + window).min(now);
+        windows.push((from, to));
+        from = to + 1;
+    }
+    windows
+}
+
+/// The parts of the window `from..=to` a full page may have left out, i.e. before the earliest &
+/// after the latest event returned, oldest first; none if the page wasn't full.
+fn remaining(from: i64, to: i64, timepoints: &[i64]) -> Vec<(i64, i64)> {
+    if timepoints.len() < FUNDING_PAGE {
+        return vec![];
+    }
+    let (Some(earliest), Some(latest)) = (timepoints.iter().min(), timepoints.iter().max()) else {
+        return vec![];
+    };
+
+    let mut remaining = vec![];
+    if *earliest > from {
+        remaining.push((from, earliest - 1));
+    }
+    if *latest < to {
+        remaining.push((latest + 1, to));
+    }
+    remaining
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// endpoints
+/////////////////////////////////////////////////////////////////////////////////
+//
+// contracts
+// ----------------------------------------------------------------
+// {
+//      "code": "200000",
+//      "data": [
+//          {
+//              "symbol": "XBTUSDTM",
+//              "type": "FFWCSX",           // FFWCSX = perpetual, FFICSX = expiring
+//              "firstOpenDate": 1585555200000,
+//              "baseCurrency": "XBT",
+//              "quoteCurrency": "USDT",
+//              "multiplier": 0.001,        // base units per lot, or USD per lot if inverse
+//              "isInverse": false,
+//              "status": "Open",
+//              "markPrice": 43000.0,
+//              "openInterest": "8724443",  // in lots
+//              ...
+//          },
+//          ...
+//      ]
+// }
+#[derive(Debug, Deserialize)]
+struct Contracts {
+    data: Vec<Contract>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Contract {
+    symbol: String,
+    r#type: String,
+    first_open_date: i64,
+    base_currency: String,
+    quote_currency: String,
+    multiplier: f64,
+    is_inverse: bool,
+    status: String,
+    mark_price: Option<f64>,
+    open_interest: Option<String>,
+}
+
+impl Contract {
+    /// The spot form of the contract symbol, e.g. XBTUSDTM -> BTCUSDT.
+    fn spot_symbol(&self) -> String {
+        spot_symbol(&self.base_currency, &self.quote_currency)
+    }
+
+    /// Convert the open interest from lots into base units & quote value.
+    fn open_interest(&self, dt: DateTime<Utc>) -> util::OpenInterest {
+        let lots = util::parse_f64(&self.open_interest);
+        let (open_interest, open_interest_value) = if self.is_inverse {
+            // inverse contracts are denominated in USD per lot
+            let value = lots.map(|lots| lots * self.multiplier.abs());
+            let base = value
+                .zip(self.mark_price)
+                .filter(|(_, price)| *price != 0.0)
+                .map(|(value, price)| value / price);
+            (base, value)
+        } else {
+            let base = lots.map(|lots| lots * self.multiplier);
+            let value = base.zip(self.mark_price).map(|(base, price)| base * price);
+            (base, value)
+        };
+
+        util::OpenInterest {
+            dt,
+            open_interest,
+            open_interest_value,
+        }
+    }
+}
+
+/// Map a KuCoin Futures base/quote pair to the spot symbol form.
+fn spot_symbol(base: &str, quote: &str) -> String {
+    let base = match base {
+        "XBT" => "BTC",
+        base => base,
+    };
+    format!("{base}{quote}")
+}
+
+// funding rates
+// ----------------------------------------------------------------
+// {
+//      "code": "200000",
+//      "data": [
+//          {
+//              "symbol": "XBTUSDTM",
+//              "fundingRate": 0.0001,
+//              "timepoint": 1702310700000
+//          },
+//          ...
+//      ]
+// }
+#[derive(Debug, Deserialize)]
+struct FundingRates {
+    data: Vec<FundingRateCell>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FundingRateCell {
+    funding_rate: f64,
+    timepoint: i64,
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn futures_symbols_map_to_spot() {
+    assert_eq!(spot_symbol("XBT", "USDT"), "BTCUSDT");
+    assert_eq!(spot_symbol("ETH", "USDT"), "ETHUSDT");
+    assert_eq!(spot_symbol("XBT", "USD"), "BTCUSD");
+}
+
+#[test]
+fn funding_windows_split_full_pages() {
+    let day = TimeDelta::days(1).num_milliseconds();
+    let hour = TimeDelta::hours(1).num_milliseconds();
+
+    // 30 day windows, up to now
+    let windows = windows(0, 45 * day);
+    assert_eq!(windows, [(0, 30 * day), (30 * day + 1, 45 * day)]);
+
+    // a short page covers its window
+    let timepoints: Vec<i64> = (1..=90).map(|i| i * 8 * hour).collect();
+    assert!(remaining(0, 30 * day, &timepoints).is_empty());
+
+    // hourly funding fills a page with the latest 100 events; the rest of the window is requested
+    let timepoints: Vec<i64> = (0..100).map(|i| 30 * day - i * hour).collect();
+    assert_eq!(
+        remaining(0, 30 * day, &timepoints),
+        [(0, 30 * day - 99 * hour - 1)]
+    );
+
+    // ... or with the earliest 100 events
+    let timepoints: Vec<i64> = (0..100).map(|i| i * hour).collect();
+    assert_eq!(
+        remaining(0, 30 * day, &timepoints),
+        [(99 * hour + 1, 30 * day)]
+    );
+}
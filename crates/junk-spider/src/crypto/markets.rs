@@ -0,0 +1,159 @@
+use crate::http::PgClient;
+use std::collections::HashMap;
+use tracing::trace;
+
+// Each exchange names its pairs differently, e.g. BTC/USDT is
+//
+//      Binance = "BTCUSDT"
+//      KuCoin  = "BTC-USDT" (stored as "BTCUSDT")
+//      Kraken  = "XBTUSDT", with assets "XXBT" & "USDT"
+//
+// so markets are matched on their normalised base & quote assets, taken from
+// `crypto.instruments` where available, or else split from the symbol itself.
+
+/// Known quote assets, used to split a symbol when there is no instrument metadata; longer
+/// suffixes are tried first, so that "USDT" wins over "USD".
+const QUOTE_ASSETS: [&str; 18] = [
+    "FDUSD", "USDT", "USDC", "TUSD", "BUSD", "USDE", "EURC", "DAI", "USD", "EUR", "GBP", "TRY",
+    "BRL", "JPY", "BTC", "XBT", "ETH", "BNB",
+];
+
+/// A base/quote pair, e.g. BTC/USDT.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Market {
+    pub base: String,
+    pub quote: String,
+}
+
+impl Market {
+    /// The canonical symbol of the market, as stored in `crypto.symbols`, e.g. "BTCUSDT".
+    pub fn symbol(&self) -> String {
+        format!("{}{}", self.base, self.quote)
+    }
+}
+
+impl std::fmt::Display for Market {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.base, self.quote)
+    }
+}
+
+/// Normalise an asset code to its common name, e.g. Kraken's "XXBT" -> "BTC".
+pub fn normalise_asset(asset: &str) -> String {
+    let asset = asset.to_uppercase();
+    let asset = match asset.as_str() {
+        // Kraken's legacy X (crypto) & Z (fiat) prefixed codes
+        "XXBT" | "XBT" => "BTC",
+        "XXDG" | "XDG" => "DOGE",
+        "XETH" => "ETH",
+        "XETC" => "ETC",
+        "XLTC" => "LTC",
+        "XXRP" => "XRP",
+        "XXLM" => "XLM",
+        "XXMR" => "XMR",
+        "XZEC" => "ZEC",
+        "XREP" => "REP",
+        "XMLN" => "MLN",
+        "ZUSD" => "USD",
+        "ZEUR" => "EUR",
+        "ZGBP" => "GBP",
+        "ZCAD" => "CAD",
+        "ZJPY" => "JPY",
+        "ZAUD" => "AUD",
+        "ZCHF" => "CHF",
+        asset => asset,
+    };
+    asset.to_string()
+}
+
+/// Split a symbol into its market, e.g. "XBTUSDT" -> BTC/USDT, by matching a known quote asset.
+pub fn split_symbol(symbol: &str) -> Option<Market> {
+    let symbol = symbol.to_uppercase().replace(['-', '/', '_'], "");
+    let mut quotes = QUOTE_ASSETS;
+    quotes.sort_by_key(|quote| std::cmp::Reverse(quote.len()));
+
+    quotes.iter().find_map(|quote| {
+        let base = symbol.strip_suffix(quote)?;
+        if base.is_empty() {
+            return None;
+        }
+        Some(Market {
+            base: normalise_asset(base),
+            quote: normalise_asset(quote),
+        })
+    })
+}
+
+/// Retrieve the market of every `(symbol_pk, source_pk)` with price data.
+pub(crate) async fn fetch_markets(
+    pg_client: &mut PgClient,
+) -> anyhow::Result<HashMap<(i32, i16), Market>> {
+    let mut markets = HashMap::new();
+
+    // 1. from instrument metadata
+    for row in pg_client
+        .query(
+            "SELECT symbol_pk, source_pk, base_asset, quote_asset FROM crypto.instruments
+            WHERE base_asset IS NOT NULL AND quote_asset IS NOT NULL",
+            &[],
+        )
+        .await?
+    {
+        let base: String = row.get(2);
+        let quote: String = row.get(3);
+        markets.insert(
+            (row.get(0), row.get(1)),
+            Market {
+                base: normalise_asset(&base),
+                quote: normalise_asset(&quote),
+            },
+        );
+    }
+
+    // 2. else, split from the symbol
+    for row in pg_client
+        .query(
+            "SELECT DISTINCT pr.symbol_pk, pr.source_pk, sy.symbol
+            FROM crypto.prices AS pr
+            INNER JOIN crypto.symbols AS sy ON sy.pk = pr.symbol_pk",
+            &[],
+        )
+        .await?
+    {
+        let key: (i32, i16) = (row.get(0), row.get(1));
+        if markets.contains_key(&key) {
+            continue;
+        }
+
+        let symbol: String = row.get(2);
+        match split_symbol(&symbol) {
+            Some(market) => {
+                markets.insert(key, market);
+            }
+            None => trace!("failed to split {symbol} into a market"),
+        }
+    }
+
+    Ok(markets)
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn symbols_split_into_markets() {
+    let market = |base: &str, quote: &str| Market {
+        base: base.to_string(),
+        quote: quote.to_string(),
+    };
+
+    assert_eq!(split_symbol("BTCUSDT"), Some(market("BTC", "USDT")));
+    assert_eq!(split_symbol("BTC-USDT"), Some(market("BTC", "USDT")));
+    assert_eq!(split_symbol("XBTUSDT"), Some(market("BTC", "USDT")));
+    assert_eq!(split_symbol("XDGEUR"), Some(market("DOGE", "EUR")));
+    assert_eq!(split_symbol("ETHBTC"), Some(market("ETH", "BTC")));
+    assert_eq!(split_symbol("USDT"), None);
+    assert_eq!(normalise_asset("XXBT"), "BTC");
+    assert_eq!(normalise_asset("ZUSD"), "USD");
+}
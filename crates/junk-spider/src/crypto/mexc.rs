@@ -1,5 +1,6 @@
-use super::sql;
+use super::{portfolio, repair, sql, util};
 use crate::http::*;
+use chrono::{DateTime, TimeDelta, Utc};
 use deadpool_postgres::Pool;
 use dotenv::var;
 use futures::{stream, StreamExt};
@@ -7,10 +8,10 @@ use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::header::HeaderValue;
 use serde::de::{IgnoredAny, SeqAccess, Visitor};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
 
 const BROKERAGE: &'static str = "MEXC";
 
@@ -18,6 +19,8 @@ const BROKERAGE: &'static str = "MEXC";
 //
 // tickers = `https://api.mexc.com/api/v3/ticker/bookTicker`
 //
+// 24h tickers = `https://api.mexc.com/api/v3/ticker/24hr`
+//
 // klines = `https://api.mexc.com/api/v3/klines?symbol=BTCUSDT&interval=1d`, per symbol
 
 /////////////////////////////////////////////////////////////////////////////////
@@ -106,6 +109,12 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
         }
     };
 
+    // 3c. snapshot 24h ticker statistics
+    info!("fetching 24h ticker statistics ...");
+    if let Err(err) = snapshot_24h(&http_client, &mut pg_client, &symbol_pks, source_pk).await {
+        error!("failed to snapshot {BROKERAGE} 24h ticker statistics, error({err})");
+    }
+
     drop(pg_client);
 
     // progress bars
@@ -115,7 +124,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
         (None, None, None, None)
     };
 
-    // 3c. fetch prices for tickers
+    // 3d. fetch prices for tickers
     info!("fetching prices ...");
     let stream = stream::iter(&tickers.0);
     stream
@@ -240,7 +249,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn build_client() -> HttpClient {
+pub(super) fn build_client() -> HttpClient {
     let mut headers = reqwest::header::HeaderMap::new();
     headers.insert(
         "apiKey",
@@ -254,6 +263,38 @@ fn build_client() -> HttpClient {
     client
 }
 
+/// Fetch the 24h ticker statistics for every pair, and insert them as a snapshot.
+async fn snapshot_24h(
+    http_client: &HttpClient,
+    pg_client: &mut PgClient,
+    symbol_pks: &HashMap<String, i32>,
+    source_pk: i16,
+) -> anyhow::Result<()> {
+    let stats: Tickers24h = http_client
+        .get("https://api.mexc.com/api/v3/ticker/24hr")
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} 24h tickers, error({err})");
+            err
+        })?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} 24h tickers, error({err})");
+            err
+        })?;
+
+    super::util::insert_tickers_24h(
+        pg_client,
+        &stats.normalise(),
+        symbol_pks,
+        source_pk,
+        BROKERAGE,
+    )
+    .await
+}
+
 /////////////////////////////////////////////////////////////////////////////////
 // endpoints
 /////////////////////////////////////////////////////////////////////////////////
@@ -351,6 +392,59 @@ impl Tickers {
     }
 }
 
+// 24h tickers
+// ----------------------------------------------------------------
+// [
+//  {
+//      "symbol": "BTCUSDT",
+//      "priceChange": "-94.99999800",
+//      "priceChangePercent": "-95.960",
+//      "lastPrice": "4.00000200",
+//      "highPrice": "100.00000000",
+//      "lowPrice": "0.10000000",
+//      "volume": "8913.30000000",
+//      "quoteVolume": "15.30000000",
+//      "count": null,
+//      ...
+//  },
+//  ...
+// ]
+#[derive(Debug, Deserialize)]
+struct Tickers24h(Vec<Ticker24h>);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Ticker24h {
+    symbol: String,
+    price_change: Option<String>,
+    price_change_percent: Option<String>,
+    last_price: Option<String>,
+    high_price: Option<String>,
+    low_price: Option<String>,
+    volume: Option<String>,
+    quote_volume: Option<String>,
+    count: Option<i64>,
+}
+
+impl Tickers24h {
+    fn normalise(self) -> Vec<util::Ticker24h> {
+        self.0
+            .into_iter()
+            .map(|ticker| util::Ticker24h {
+                last_price: util::parse_f64(&ticker.last_price),
+                price_change: util::parse_f64(&ticker.price_change),
+                price_change_perc: util::parse_f64(&ticker.price_change_percent),
+                high: util::parse_f64(&ticker.high_price),
+                low: util::parse_f64(&ticker.low_price),
+                volume: util::parse_f64(&ticker.volume),
+                quote_volume: util::parse_f64(&ticker.quote_volume),
+                trades: ticker.count,
+                symbol: ticker.symbol,
+            })
+            .collect()
+    }
+}
+
 // prices
 // ----------------------------------------------------------------
 //
@@ -423,7 +517,7 @@ impl Klines {
         let time = ::std::time::Instant::now();
 
         // preprocess pg query as transaction
-        let query = pg_client.prepare(&sql::INSERT_PRICE).await?;
+        let query = pg_client.prepare(&sql::UPSERT_PRICE).await?;
         let transaction = Arc::new(pg_client.transaction().await?);
 
         // iterate over the data stream and execute pg rows
@@ -434,12 +528,14 @@ impl Klines {
             let symbol = &symbol;
             let interval_pk: i16 = 3;
             async move {
+                let dt = DateTime::from_timestamp_millis(cell.timestamp);
+                let closed = dt.is_some_and(|dt| util::is_closed(dt, TimeDelta::days(1)));
                 let result = transaction
                     .execute(
                         &query,
                         &[
                             &symbol_pk,
-                            &chrono::DateTime::from_timestamp_millis(cell.timestamp),
+                            &dt,
                             &interval_pk,
                             &cell.opening.parse::<f64>().expect("String -> f64 Opening"),
                             &cell.high.parse::<f64>().expect("String -> f64 Opening"),
@@ -448,6 +544,7 @@ impl Klines {
                             &cell.volume.parse::<f64>().expect("String -> f64 Opening"),
                             &None::<i64>,
                             &source_pk,
+                            &closed,
                         ],
                     )
                     .await;
@@ -480,3 +577,415 @@ impl Klines {
         Ok(())
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////////
+// account
+/////////////////////////////////////////////////////////////////////////////////
+//
+// https://mexcdevelop.github.io/apidocs/spot_v3_en/#account-information
+//
+// balances = `https://api.mexc.com/api/v3/account`
+//
+// deposits = `https://api.mexc.com/api/v3/capital/deposit/hisrec`, per 90 days
+//
+// withdrawals = `https://api.mexc.com/api/v3/capital/withdraw/history`, per 90 days
+//
+// fills = `https://api.mexc.com/api/v3/myTrades`, per symbol
+//
+// NOTE: private endpoints take the key as "X-MEXC-APIKEY"; fills are requested for the pairs of
+// every asset ever in the account (held, transferred or traded), and paged by time, as trade ids
+// aren't numeric
+
+const TRANSFER_WINDOW: TimeDelta = TimeDelta::days(90);
+const FILL_LIMIT: usize = 100;
+
+/// Fetch the account's balances, transfers & the fills of every pair of an asset ever in the
+/// account; `markets` are the `(symbol, base asset, quote asset)` of every MEXC pair, `assets`
+/// those already synced, and `fill_dts` the latest synced fill of each symbol.
+pub(super) async fn account(
+    markets: &[(String, String, String)],
+    assets: &HashSet<String>,
+    fill_dts: &HashMap<String, DateTime<Utc>>,
+    since: &portfolio::Since,
+) -> anyhow::Result<portfolio::Account> {
+    let http_client = build_client();
+    let key = var("MEXC_API")?;
+    let secret = var("MEXC_PRIVATE")?;
+
+    // balances
+    let info: AccountInfo = signed(&http_client, &key, &secret, "/api/v3/account", "").await?;
+    let balances: Vec<portfolio::Balance> = info
+        .balances
+        .into_iter()
+        .filter_map(|balance| {
+            Some(portfolio::Balance {
+                asset: balance.asset,
+                free: balance.free.parse().ok()?,
+                locked: balance.locked.parse().ok()?,
+            })
+        })
+        .collect();
+
+    // transfers
+    let mut transfers = Vec::new();
+    for (start, end) in portfolio::windows(since.transfers, TRANSFER_WINDOW) {
+        let params = format!(
+            "startTime={}&endTime={}",
+            start.timestamp_millis(),
+            end.timestamp_millis()
+        );
+
+        let deposits: Vec<Deposit> = signed(
+            &http_client,
+            &key,
+            &secret,
+            "/api/v3/capital/deposit/hisrec",
+            &params,
+        )
+        .await?;
+        transfers.extend(deposits.into_iter().filter_map(Deposit::normalise));
+
+        let withdrawals: Vec<Withdrawal> = signed(
+            &http_client,
+            &key,
+            &secret,
+            "/api/v3/capital/withdraw/history",
+            &params,
+        )
+        .await?;
+        transfers.extend(withdrawals.into_iter().filter_map(Withdrawal::normalise));
+    }
+
+    // fills, walking outward from the known assets to every pair traded
+    let mut assets: HashSet<String> = assets.clone();
+    assets.extend(balances.iter().map(|b| b.asset.clone()));
+    assets.extend(transfers.iter().map(|t| t.asset.clone()));
+    let first_sync = Utc::now() - portfolio::FIRST_SYNC;
+    let mut walked: HashSet<&str> = HashSet::new();
+    let mut fills = Vec::new();
+    loop {
+        let pending: Vec<&(String, String, String)> = markets
+            .iter()
+            .filter(|(symbol, base, quote)| {
+                !walked.contains(symbol.as_str())
+                    && (assets.contains(base) || assets.contains(quote))
+            })
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+
+        for (symbol, base, quote) in pending {
+            walked.insert(symbol);
+
+            // pages overlap on their last millisecond, so fills are deduplicated by id
+            let mut start = fill_dts
+                .get(symbol)
+                .unwrap_or(&first_sync)
+                .timestamp_millis();
+            let mut seen: HashSet<String> = HashSet::new();
+            loop {
+                let params = format!("symbol={symbol}&startTime={start}&limit={FILL_LIMIT}");
+                let trades: Vec<Trade> =
+                    signed(&http_client, &key, &secret, "/api/v3/myTrades", &params).await?;
+                let times: Vec<i64> = trades.iter().map(|trade| trade.time).collect();
+                if !trades.is_empty() {
+                    assets.insert(base.clone());
+                    assets.insert(quote.clone());
+                }
+                fills.extend(
+                    trades
+                        .into_iter()
+                        .filter(|trade| seen.insert(trade.id.clone()))
+                        .filter_map(Trade::normalise),
+                );
+
+                match next_start(start, &times) {
+                    Some(next) => start = next,
+                    None => break,
+                }
+            }
+        }
+    }
+
+    Ok(portfolio::Account {
+        balances,
+        transfers,
+        fills,
+    })
+}
+
+/// The `startTime` of the page after a full page of fills; inclusive of the page's last
+/// millisecond, as more fills may share it, unless every fill of the page did; `None` after a
+/// short page.
+fn next_start(start: i64, times: &[i64]) -> Option<i64> {
+    if times.len() < FILL_LIMIT {
+        return None;
+    }
+    let last = times.iter().max()?;
+    if *last > start {
+        Some(*last)
+    } else {
+        warn!("{FILL_LIMIT}+ {BROKERAGE} fills at {last}, some may be missed");
+        Some(last + 1)
+    }
+}
+
+/// Send a signed request to a private endpoint.
+async fn signed<T: serde::de::DeserializeOwned>(
+    http_client: &HttpClient,
+    key: &str,
+    secret: &str,
+    path: &str,
+    params: &str,
+) -> anyhow::Result<T> {
+    let timestamp = format!("timestamp={}", Utc::now().timestamp_millis());
+    let query = if params.is_empty() {
+        timestamp
+    } else {
+        format!("{params}&{timestamp}")
+    };
+    let signature = util::hmac_sha256_hex(secret, &query);
+    let data = http_client
+        .get(format!(
+            "https://api.mexc.com{path}?{query}&signature={signature}"
+        ))
+        .header("X-MEXC-APIKEY", key)
+        .send()
+        .await
+        .map_err(|err| {
+            error!("failed to fetch {BROKERAGE} {path}, error({err})");
+            err
+        })?
+        .error_for_status()?
+        .json()
+        .await
+        .map_err(|err| {
+            error!("failed to deserialize {BROKERAGE} {path}, error({err})");
+            err
+        })?;
+    Ok(data)
+}
+
+// {
+//   "balances": [
+//     {
+//       "asset": "NBNTEST",
+//       "free": "1111.3",
+//       "locked": "0"
+//     }
+//   ]
+// }
+#[derive(Debug, Deserialize)]
+struct AccountInfo {
+    balances: Vec<AccountBalance>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountBalance {
+    asset: String,
+    free: String,
+    locked: String,
+}
+
+// [
+//   {
+//     "amount": "50000",
+//     "coin": "EOS-EOS",
+//     "status": 5,
+//     "txId": "ae56a5f1-4c5e-4dea-b5dd-bc4d0d4b3fbc:0",
+//     "insertTime": 1622782565000
+//   }
+// ]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Deposit {
+    amount: String,
+    coin: String,
+    status: i32,
+    tx_id: String,
+    insert_time: i64,
+}
+
+impl Deposit {
+    // coins are suffixed by their network, e.g. "USDT-TRX"
+    fn normalise(self) -> Option<portfolio::Transfer> {
+        Some(portfolio::Transfer {
+            tx_id: self.tx_id,
+            kind: portfolio::DEPOSIT,
+            asset: self.coin.split('-').next()?.to_string(),
+            amount: self.amount.parse().ok()?,
+            fee: None,
+            status: self.status.to_string(),
+            dt: DateTime::from_timestamp_millis(self.insert_time)?,
+        })
+    }
+}
+
+// [
+//   {
+//     "id": "bb17a2d452684f00a523c015d512a341",
+//     "coin": "EOS-EOS",
+//     "amount": "10",
+//     "transactionFee": "0.1",
+//     "status": 3,
+//     "applyTime": 1665300874000
+//   }
+// ]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Withdrawal {
+    id: String,
+    coin: String,
+    amount: String,
+    transaction_fee: Option<String>,
+    status: i32,
+    apply_time: i64,
+}
+
+impl Withdrawal {
+    fn normalise(self) -> Option<portfolio::Transfer> {
+        Some(portfolio::Transfer {
+            tx_id: self.id,
+            kind: portfolio::WITHDRAWAL,
+            asset: self.coin.split('-').next()?.to_string(),
+            amount: self.amount.parse().ok()?,
+            fee: util::parse_f64(&self.transaction_fee),
+            status: self.status.to_string(),
+            dt: DateTime::from_timestamp_millis(self.apply_time)?,
+        })
+    }
+}
+
+// [
+//   {
+//     "symbol": "MXUSDT",
+//     "id": "fad2af9e942049b6adbda1a271f990c6",
+//     "price": "3.6118",
+//     "qty": "1.6",
+//     "commission": "0.0057",
+//     "commissionAsset": "USDT",
+//     "time": 1648547280000,
+//     "isBuyer": true
+//   }
+// ]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Trade {
+    symbol: String,
+    id: String,
+    price: String,
+    qty: String,
+    commission: Option<String>,
+    commission_asset: Option<String>,
+    time: i64,
+    is_buyer: bool,
+}
+
+impl Trade {
+    fn normalise(self) -> Option<portfolio::Fill> {
+        Some(portfolio::Fill {
+            trade_id: self.id,
+            symbol: self.symbol,
+            side: if self.is_buyer {
+                portfolio::BUY
+            } else {
+                portfolio::SELL
+            },
+            price: self.price.parse().ok()?,
+            quantity: self.qty.parse().ok()?,
+            fee: util::parse_f64(&self.commission),
+            fee_asset: self.commission_asset,
+            dt: DateTime::from_timestamp_millis(self.time)?,
+        })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// repair
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Refetch the daily klines missing within a [Gap](repair::Gap), a page at a time.
+pub(super) async fn refetch(
+    http_client: &HttpClient,
+    pool: &Pool,
+    gap: &repair::Gap,
+) -> anyhow::Result<()> {
+    let mut pg_client = pool.get().await?;
+
+    let end = gap.end.timestamp_millis();
+    let mut start = gap.start.timestamp_millis();
+    while start <= end {
+        let url = format!(
+            "https://api.mexc.com/api/v3/klines?symbol={}&interval=1d&startTime={start}&endTime={end}&limit=1000",
+            gap.symbol
+        );
+        let klines: Klines = http_client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // an empty page means the source has nothing left within the gap
+        let last = match klines.0.last() {
+            Some(kline) => kline.timestamp,
+            None => break,
+        };
+        klines
+            .insert(
+                &mut pg_client,
+                gap.symbol.clone(),
+                gap.symbol_pk,
+                gap.source_pk,
+            )
+            .await?;
+        start = last + 1;
+    }
+
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn tickers_24h_normalise() {
+    let json = r#"[
+        {
+            "symbol": "BTCUSDT",
+            "priceChange": "-94.99999800",
+            "priceChangePercent": "-95.960",
+            "lastPrice": "4.00000200",
+            "highPrice": "100.00000000",
+            "lowPrice": "0.10000000",
+            "volume": "8913.30000000",
+            "quoteVolume": "15.30000000",
+            "count": null
+        }
+    ]"#;
+    let tickers: Tickers24h = serde_json::from_str(json).expect("valid 24h tickers");
+    let tickers = tickers.normalise();
+
+    assert_eq!(tickers[0].symbol, "BTCUSDT");
+    assert_eq!(tickers[0].last_price, Some(4.000002));
+    assert_eq!(tickers[0].price_change, Some(-94.999998));
+    assert_eq!(tickers[0].volume, Some(8913.3));
+    assert_eq!(tickers[0].trades, None);
+}
+
+#[test]
+fn fill_pages_overlap_on_their_last_millisecond() {
+    // a short page is the last
+    assert_eq!(next_start(0, &[1, 2, 3]), None);
+
+    // a full page resumes from its last millisecond, which later fills may share
+    let times: Vec<i64> = (0..FILL_LIMIT as i64).map(|i| 1_000 + i / 2).collect();
+    assert_eq!(next_start(1_000, &times), Some(1_049));
+
+    // unless the whole page is a single millisecond, which would never advance
+    let times = vec![1_000; FILL_LIMIT];
+    assert_eq!(next_start(1_000, &times), Some(1_001));
+}
@@ -0,0 +1,365 @@
+use super::markets::normalise_asset;
+use super::{binance, kraken, kucoin, mexc, sql, util};
+use crate::http::PgClient;
+use chrono::{DateTime, TimeDelta, Utc};
+use deadpool_postgres::Pool;
+use dotenv::var;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::{debug, error, info, trace, warn};
+
+// Read-only account sync, i.e. balances, deposits/withdrawals & fills, into the `portfolio`
+// schema; holdings are then valued against `crypto.usd_prices` (see `portfolio.holdings`).
+//
+// Each exchange is only synced when its private key is set
+//
+//      Binance = BINANCE_API & BINANCE_PRIVATE
+//      KuCoin  = KUCOIN_API, KUCOIN_PRIVATE & KUCOIN_PASSPHRASE
+//      Kraken  = KRAKEN_API & KRAKEN_PRIVATE
+//      MEXC    = MEXC_API & MEXC_PRIVATE
+//
+// NOTE: keys should be created with read-only permissions, nothing here places orders or
+// moves funds
+
+/// How far back to look for transfers & fills, on the first sync of an exchange.
+pub(super) const FIRST_SYNC: TimeDelta = TimeDelta::days(365);
+
+/// Free & locked (e.g. in open orders) amount of a single asset.
+#[derive(Debug)]
+pub(super) struct Balance {
+    pub asset: String,
+    pub free: f64,
+    pub locked: f64,
+}
+
+/// A deposit or withdrawal.
+#[derive(Debug)]
+pub(super) struct Transfer {
+    pub tx_id: String,
+    pub kind: &'static str,
+    pub asset: String,
+    pub amount: f64,
+    pub fee: Option<f64>,
+    pub status: String,
+    pub dt: DateTime<Utc>,
+}
+
+pub(super) const DEPOSIT: &str = "deposit";
+pub(super) const WITHDRAWAL: &str = "withdrawal";
+
+/// A single trade execution; `symbol` is as stored in `crypto.symbols`, e.g. "BTCUSDT".
+#[derive(Debug)]
+pub(super) struct Fill {
+    pub trade_id: String,
+    pub symbol: String,
+    pub side: &'static str,
+    pub price: f64,
+    pub quantity: f64,
+    pub fee: Option<f64>,
+    pub fee_asset: Option<String>,
+    pub dt: DateTime<Utc>,
+}
+
+pub(super) const BUY: &str = "buy";
+pub(super) const SELL: &str = "sell";
+
+#[derive(Debug, Default)]
+pub(super) struct Account {
+    pub balances: Vec<Balance>,
+    pub transfers: Vec<Transfer>,
+    pub fills: Vec<Fill>,
+}
+
+/// Where to resume transfers & fills from, i.e. the latest synced of each.
+#[derive(Debug)]
+pub(super) struct Since {
+    pub transfers: DateTime<Utc>,
+    pub fills: DateTime<Utc>,
+}
+
+/// Split `since` -> now into consecutive windows of at most `span`, for endpoints that limit
+/// the time range of a single request.
+pub(super) fn windows(
+    since: DateTime<Utc>,
+    span: TimeDelta,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let now = Utc::now();
+    let mut windows = Vec::new();
+    let mut start = since;
+    while start < now {
+        let end = (start + span).min(now);
+        windows.push((start, end));
+        start = end;
+    }
+    windows
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// core
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Sync every exchange account with its keys set.
+pub async fn sync(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    // wait for a pg client from the pool
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    for (source, key) in [
+        ("Binance", "BINANCE_PRIVATE"),
+        ("KuCoin", "KUCOIN_PRIVATE"),
+        ("Kraken", "KRAKEN_PRIVATE"),
+        ("MEXC", "MEXC_PRIVATE"),
+    ] {
+        if var(key).is_err() {
+            info!("{key} not found, skipping {source} account sync");
+            continue;
+        }
+
+        if tui {
+            println!(
+                "{bar}\n{:^40}\n{bar}",
+                format!("{source} Account"),
+                bar = "=".repeat(40)
+            )
+        }
+        let pb = if tui {
+            let pb = ProgressBar::new_spinner()
+                .with_message("syncing account ...")
+                .with_style(ProgressStyle::default_spinner().template("{msg} {spinner:.magenta}")?);
+            pb.enable_steady_tick(Duration::from_millis(100));
+            pb
+        } else {
+            ProgressBar::hidden()
+        };
+
+        // a failing exchange shouldn't stop the others from syncing
+        match sync_source(&mut pg_client, source).await {
+            Ok(()) => pb.finish_with_message("syncing account ... done"),
+            Err(err) => {
+                error!("failed to sync {source} account, error({err})");
+                pb.finish_with_message("syncing account ... failed");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn sync_source(pg_client: &mut PgClient, source: &str) -> anyhow::Result<()> {
+    let time = std::time::Instant::now();
+
+    // 1. fetch where the last sync left off
+    let source_pk = util::source_pk(pg_client, source).await?;
+    let latest = |row: tokio_postgres::Row| -> DateTime<Utc> {
+        row.get::<_, Option<DateTime<Utc>>>("dt")
+            .unwrap_or_else(|| Utc::now() - FIRST_SYNC)
+    };
+    let since = Since {
+        transfers: latest(
+            pg_client
+                .query_one(sql::LATEST_TRANSFER, &[&source_pk])
+                .await?,
+        ),
+        fills: latest(pg_client.query_one(sql::LATEST_FILL, &[&source_pk]).await?),
+    };
+
+    // 2. fetch the account; Binance & MEXC fills are per symbol, so both request the pairs of
+    // every asset ever in the account, from each pair's latest synced trade
+    info!("syncing {source} account ...");
+    let account = match source {
+        "Binance" | "MEXC" => {
+            let markets: Vec<(String, String, String)> = pg_client
+                .query(sql::SOURCE_MARKETS, &[&source_pk])
+                .await?
+                .into_iter()
+                .map(|row| {
+                    (
+                        row.get("symbol"),
+                        row.get("base_asset"),
+                        row.get("quote_asset"),
+                    )
+                })
+                .collect();
+            let assets: HashSet<String> = pg_client
+                .query(sql::ACCOUNT_ASSETS, &[&source_pk])
+                .await?
+                .into_iter()
+                .map(|row| row.get("asset"))
+                .collect();
+
+            if source == "Binance" {
+                let trade_ids: HashMap<String, i64> = pg_client
+                    .query(sql::LATEST_TRADE_IDS, &[&source_pk])
+                    .await?
+                    .into_iter()
+                    .map(|row| (row.get("symbol"), row.get("trade_id")))
+                    .collect();
+
+                binance::account(&markets, &assets, &trade_ids, &since).await?
+            } else {
+                // MEXC's trade ids aren't numeric, so its fills are paged by time
+                let fill_dts: HashMap<String, DateTime<Utc>> = pg_client
+                    .query(sql::LATEST_FILL_DTS, &[&source_pk])
+                    .await?
+                    .into_iter()
+                    .map(|row| (row.get("symbol"), row.get("dt")))
+                    .collect();
+
+                mexc::account(&markets, &assets, &fill_dts, &since).await?
+            }
+        }
+        "KuCoin" => kucoin::account(&since).await?,
+        "Kraken" => kraken::account(&since).await?,
+        _ => anyhow::bail!("no account sync for {source}"),
+    };
+
+    // 3. insert fill symbols, so that fills can be valued against `crypto.prices`
+    let mut symbols: Vec<String> = account.fills.iter().map(|f| f.symbol.clone()).collect();
+    symbols.sort();
+    symbols.dedup();
+    util::insert_symbols(pg_client, &symbols, source).await?;
+    let symbol_pks: HashMap<String, i32> = util::fetch_pks(
+        pg_client,
+        "SELECT pk, symbol FROM crypto.symbols",
+        "symbol",
+        "pk",
+    )
+    .await?;
+
+    // 4. insert the account
+    insert_account(pg_client, &account, &symbol_pks, source_pk, source).await?;
+
+    debug!(
+        "{source} account synced; {} balances, {} transfers, {} fills, {}",
+        account.balances.len(),
+        account.transfers.len(),
+        account.fills.len(),
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+async fn insert_account(
+    pg_client: &mut PgClient,
+    account: &Account,
+    symbol_pks: &HashMap<String, i32>,
+    source_pk: i16,
+    source: &str,
+) -> anyhow::Result<()> {
+    let dt = Utc::now();
+
+    // preprocess pg queries as transaction
+    let balance_query = pg_client.prepare(sql::INSERT_BALANCE).await?;
+    let transfer_query = pg_client.prepare(sql::UPSERT_TRANSFER).await?;
+    let fill_query = pg_client.prepare(sql::INSERT_FILL).await?;
+    let transaction = pg_client.transaction().await?;
+
+    // balances are snapshotted on every sync
+    for balance in &account.balances {
+        let asset = normalise_asset(&balance.asset);
+        let result = transaction
+            .execute(
+                &balance_query,
+                &[&source_pk, &asset, &dt, &balance.free, &balance.locked],
+            )
+            .await;
+
+        match result {
+            Ok(_) => trace!("inserting {source} balance for {asset}"),
+            Err(err) => error!("failed to insert {source} balance for {asset}, error({err})"),
+        }
+    }
+
+    for transfer in &account.transfers {
+        let asset = normalise_asset(&transfer.asset);
+        let result = transaction
+            .execute(
+                &transfer_query,
+                &[
+                    &source_pk,
+                    &transfer.tx_id,
+                    &transfer.kind,
+                    &asset,
+                    &transfer.amount,
+                    &transfer.fee,
+                    &transfer.status,
+                    &transfer.dt,
+                ],
+            )
+            .await;
+
+        match result {
+            Ok(_) => trace!("inserting {source} {} {}", transfer.kind, transfer.tx_id),
+            Err(err) => error!(
+                "failed to insert {source} {} {}, error({err})",
+                transfer.kind, transfer.tx_id
+            ),
+        }
+    }
+
+    for fill in &account.fills {
+        let symbol_pk = match symbol_pks.get(&fill.symbol) {
+            Some(pk) => pk,
+            None => {
+                warn!("no symbol pk found for {} from {source}", fill.symbol);
+                continue;
+            }
+        };
+        let fee_asset = fill.fee_asset.as_deref().map(normalise_asset);
+
+        let result = transaction
+            .execute(
+                &fill_query,
+                &[
+                    &source_pk,
+                    symbol_pk,
+                    &fill.trade_id,
+                    &fill.side,
+                    &fill.price,
+                    &fill.quantity,
+                    &fill.fee,
+                    &fee_asset,
+                    &fill.dt,
+                ],
+            )
+            .await;
+
+        match result {
+            Ok(_) => trace!(
+                "inserting {source} fill {} for {}",
+                fill.trade_id,
+                fill.symbol
+            ),
+            Err(err) => error!(
+                "failed to insert {source} fill {} for {}, error({err})",
+                fill.trade_id, fill.symbol
+            ),
+        }
+    }
+
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit transaction for {source} account, error({err})");
+        err
+    })?;
+
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn windows_cover_since_to_now() {
+    let since = Utc::now() - TimeDelta::days(20);
+    let windows = windows(since, TimeDelta::days(7));
+
+    assert_eq!(windows.len(), 3);
+    assert_eq!(windows[0].0, since);
+    assert_eq!(windows[1].0, windows[0].1);
+    assert!(windows[2].1 - windows[2].0 <= TimeDelta::days(7));
+}
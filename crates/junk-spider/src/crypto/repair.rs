@@ -0,0 +1,438 @@
+use super::{binance, binance_archive, kraken, kucoin, mexc, sql};
+use crate::http::*;
+use chrono::{DateTime, TimeDelta, Utc};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use std::time::Duration;
+use tracing::{debug, error, info, trace, warn};
+
+// Crypto trades 24/7, so every candle of an interval is expected from the first one stored per
+// symbol, source & interval up to the latest closed one; anything further apart is a gap, as is
+// the span from the latest stored candle to now (a tail gap, e.g. the collector was down).
+//
+// Each gap is refetched from the source it came from, with the ranged klines endpoints
+//
+// Binance = `https://api.binance.com/api/v3/klines?startTime=&endTime=`, or the archive for 1m
+//
+// KuCoin = `https://api.kucoin.com/api/v1/market/candles?startAt=&endAt=`
+//
+// Kraken = `https://api.kraken.com/0/public/OHLC?since=`, only serving the latest 720 candles
+//
+// MEXC = `https://api.mexc.com/api/v3/klines?startTime=&endTime=`
+//
+// Whatever is still missing afterwards can't be filled by the source, and is recorded in
+// `crypto.price_gaps`, so that later runs don't refetch it (unless retried).
+
+const CONCURRENCY: usize = 4;
+
+/// A run of missing candles, between two stored ones.
+#[derive(Debug, Clone)]
+pub struct Gap {
+    pub symbol_pk: i32,
+    pub symbol: String,
+    pub source_pk: i16,
+    pub source: String,
+    pub interval_pk: i16,
+    pub interval: String,
+
+    /// The first missing candle.
+    pub start: DateTime<Utc>,
+
+    /// The last missing candle.
+    pub end: DateTime<Utc>,
+
+    /// Number of missing candles.
+    pub missing: i64,
+}
+
+impl Gap {
+    /// The candles missing after the stored candle `prev`, up to the next stored one, else (a
+    /// tail gap) up to the latest closed candle at `now`; `(start, end, missing)`, if any are.
+    fn span(
+        prev: DateTime<Utc>,
+        next: Option<DateTime<Utc>>,
+        step: TimeDelta,
+        now: DateTime<Utc>,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>, i64)> {
+        let steps = |span: TimeDelta| span.num_seconds() / step.num_seconds();
+
+        let start = prev + step;
+        let end = match next {
+            Some(next) => next - step,
+            None => prev + step * (steps(now - prev) as i32 - 1),
+        };
+        (start <= end).then(|| (start, end, steps(end - start) + 1))
+    }
+
+    /// Whether the candle at `dt` is one of the gap's missing candles.
+    pub fn covers(&self, dt: DateTime<Utc>) -> bool {
+        self.start <= dt && dt <= self.end
+    }
+
+    /// Whether `other` lies within this gap, i.e. what's left of it after a partial refetch.
+    fn contains(&self, other: &Gap) -> bool {
+        self.symbol_pk == other.symbol_pk
+            && self.source_pk == other.source_pk
+            && self.interval_pk == other.interval_pk
+            && self.start <= other.start
+            && other.end <= self.end
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// core
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Scan `crypto.prices` for missing candles, refetch them from their source, and return the gaps
+/// the source couldn't fill.
+///
+/// Gaps already known to be unfillable are skipped, unless `retry` is set.
+pub async fn fill(pool: &Pool, tui: bool, retry: bool) -> anyhow::Result<Vec<Gap>> {
+    if tui {
+        println!(
+            "{bar}\n{:^40}\n{bar}",
+            "Crypto Price Repair",
+            bar = "=".repeat(40)
+        )
+    }
+
+    // 1. scan for gaps
+    info!("scanning crypto.prices for gaps ...");
+    let gaps = scan(pool, retry).await?;
+    info!("{} gaps found in crypto.prices", gaps.len());
+    if gaps.is_empty() {
+        if tui {
+            println!("no gaps found\n");
+        }
+        return Ok(Vec::new());
+    }
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(gaps.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    // 2. refetch every gap from its source
+    let binance_client = binance::build_client();
+    let kraken_client = kraken::build_client();
+    let kucoin_client = kucoin::build_client();
+    let mexc_client = mexc::build_client();
+    let archive_client = crate::std_client_build();
+    stream::iter(&gaps)
+        .for_each_concurrent(CONCURRENCY, |gap| {
+            let binance_client = &binance_client;
+            let kraken_client = &kraken_client;
+            let kucoin_client = &kucoin_client;
+            let mexc_client = &mexc_client;
+            let archive_client = &archive_client;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!(
+                        "refetching {} {} from {}, {} - {}",
+                        gap.symbol, gap.interval, gap.source, gap.start, gap.end
+                    ),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                let result = match (gap.source.as_str(), gap.interval.as_str()) {
+                    ("Binance", "1d") => binance::refetch(binance_client, pool, gap).await,
+                    ("Binance", "1m") => binance_archive::refetch(archive_client, pool, gap).await,
+                    ("Kraken", "1d") => kraken::refetch(kraken_client, pool, gap).await,
+                    ("KuCoin", "1d") => kucoin::refetch(kucoin_client, pool, gap).await,
+                    ("MEXC", "1d") => mexc::refetch(mexc_client, pool, gap).await,
+                    (source, interval) => Err(anyhow::anyhow!(
+                        "{source} {interval} candles can't be refetched"
+                    )),
+                };
+
+                match result {
+                    Ok(_) => {
+                        trace!("refetched {} from {}", gap.symbol, gap.source);
+
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            "failed to refetch {} from {}, {} - {}, error({err})",
+                            gap.symbol, gap.source, gap.start, gap.end
+                        );
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+    }
+
+    // 3. rescan, whatever remains of the refetched gaps can't be filled by the source
+    let unfilled: Vec<Gap> = scan(pool, true)
+        .await?
+        .into_iter()
+        .filter(|remaining| gaps.iter().any(|gap| gap.contains(remaining)))
+        .collect();
+
+    let mut pg_client = pool.get().await?;
+    record(&mut pg_client, &gaps, &unfilled).await?;
+
+    // 4. report the unfillable gaps
+    for gap in &unfilled {
+        warn!(
+            "{} {} from {} is missing {} candles, {} - {}",
+            gap.symbol, gap.interval, gap.source, gap.missing, gap.start, gap.end
+        );
+        if tui {
+            println!(
+                "{:<16} {:<16} {:>4} {:>8} {} - {}",
+                gap.symbol, gap.source, gap.interval, gap.missing, gap.start, gap.end
+            );
+        }
+    }
+    info!(
+        "{} crypto price gaps refetched, {} remain unfilled",
+        gaps.len(),
+        unfilled.len()
+    );
+
+    Ok(unfilled)
+}
+
+/// Every gap in `crypto.prices`.
+async fn scan(pool: &Pool, retry: bool) -> anyhow::Result<Vec<Gap>> {
+    let time = std::time::Instant::now();
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    let now = Utc::now();
+    let gaps = pg_client
+        .query(sql::PRICE_GAPS, &[&retry])
+        .await
+        .map_err(|err| {
+            error!("failed to scan crypto.prices for gaps, error({err})");
+            err
+        })?
+        .into_iter()
+        .filter_map(|row| {
+            let interval: String = row.get("interval");
+            let (start, end, missing) =
+                Gap::span(row.get("prev_dt"), row.get("dt"), step(&interval)?, now)?;
+
+            Some(Gap {
+                symbol_pk: row.get("symbol_pk"),
+                symbol: row.get("symbol"),
+                source_pk: row.get("source_pk"),
+                source: row.get("source"),
+                interval_pk: row.get("interval_pk"),
+                interval,
+                start,
+                end,
+                missing,
+            })
+        })
+        .collect();
+    debug!("crypto.prices scanned. {}", crate::time_elapsed(time));
+
+    Ok(gaps)
+}
+
+/// The length of a candle of `interval`, as in `common.intervals`.
+fn step(interval: &str) -> Option<TimeDelta> {
+    match interval {
+        "1m" => Some(TimeDelta::minutes(1)),
+        "30m" => Some(TimeDelta::minutes(30)),
+        "1h" => Some(TimeDelta::hours(1)),
+        "1d" => Some(TimeDelta::days(1)),
+        "1w" => Some(TimeDelta::weeks(1)),
+        _ => None,
+    }
+}
+
+/// Forget every refetched gap, then record the ones that remain unfilled.
+async fn record(pg_client: &mut PgClient, gaps: &[Gap], unfilled: &[Gap]) -> anyhow::Result<()> {
+    let transaction = pg_client.transaction().await?;
+    for gap in gaps {
+        transaction
+            .execute(
+                sql::DELETE_PRICE_GAP,
+                &[
+                    &gap.symbol_pk,
+                    &gap.source_pk,
+                    &gap.interval_pk,
+                    &gap.start,
+                    &gap.end,
+                ],
+            )
+            .await?;
+    }
+    for gap in unfilled {
+        transaction
+            .execute(
+                sql::UPSERT_PRICE_GAP,
+                &[
+                    &gap.symbol_pk,
+                    &gap.source_pk,
+                    &gap.interval_pk,
+                    &gap.start,
+                    &gap.end,
+                    &gap.missing,
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit crypto.price_gaps, error({err})");
+        err
+    })?;
+
+    Ok(())
+}
+
+// -- TESTS --
+
+#[test]
+fn remaining_gaps_lie_within_the_refetched_gap() {
+    let dt = |day: u32| {
+        chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+            .expect("valid date")
+            .and_hms_opt(0, 0, 0)
+            .expect("valid time")
+            .and_utc()
+    };
+    let gap = Gap {
+        symbol_pk: 1,
+        symbol: "BTCUSDT".to_string(),
+        source_pk: 1,
+        source: "Binance".to_string(),
+        interval_pk: 3,
+        interval: "1d".to_string(),
+        start: dt(2),
+        end: dt(9),
+        missing: 8,
+    };
+
+    // partially refetched, e.g. the exchange was down for a few days
+    let remaining = Gap {
+        start: dt(4),
+        end: dt(5),
+        missing: 2,
+        ..gap.clone()
+    };
+    assert!(gap.contains(&remaining));
+
+    // other sources & gaps aren't part of it
+    assert!(!gap.contains(&Gap {
+        source_pk: 2,
+        ..remaining.clone()
+    }));
+    assert!(!gap.contains(&Gap {
+        end: dt(10),
+        ..remaining
+    }));
+}
+
+#[test]
+fn gaps_between_candles() {
+    let dt = |day: u32| {
+        chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+            .expect("valid date")
+            .and_hms_opt(0, 0, 0)
+            .expect("valid time")
+            .and_utc()
+    };
+    let day = TimeDelta::days(1);
+    let now = dt(31);
+
+    // consecutive candles aren't a gap
+    assert_eq!(Gap::span(dt(1), Some(dt(2)), day, now), None);
+    assert_eq!(
+        Gap::span(dt(1), Some(dt(3)), day, now),
+        Some((dt(2), dt(2), 1))
+    );
+    assert_eq!(
+        Gap::span(dt(1), Some(dt(10)), day, now),
+        Some((dt(2), dt(9), 8))
+    );
+    assert_eq!(
+        Gap::span(
+            dt(1),
+            Some(dt(1) + TimeDelta::hours(3)),
+            TimeDelta::hours(1),
+            now
+        ),
+        Some((dt(1) + TimeDelta::hours(1), dt(1) + TimeDelta::hours(2), 2))
+    );
+}
+
+#[test]
+fn tail_gaps_up_to_the_latest_closed_candle() {
+    let dt = |day: u32, hour: u32| {
+        chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+            .expect("valid date")
+            .and_hms_opt(hour, 0, 0)
+            .expect("valid time")
+            .and_utc()
+    };
+    let day = TimeDelta::days(1);
+
+    // the latest candle, or the one after it, is still in progress
+    assert_eq!(Gap::span(dt(20, 0), None, day, dt(20, 12)), None);
+    assert_eq!(Gap::span(dt(20, 0), None, day, dt(21, 12)), None);
+
+    // the 21st closed at midnight
+    assert_eq!(
+        Gap::span(dt(20, 0), None, day, dt(22, 0)),
+        Some((dt(21, 0), dt(21, 0), 1))
+    );
+
+    // the 25th is still in progress
+    let tail = Gap::span(dt(20, 0), None, day, dt(25, 12));
+    assert_eq!(tail, Some((dt(21, 0), dt(24, 0), 4)));
+
+    // only the candles missing within the gap are refetched
+    let (start, end, missing) = tail.expect("tail gap");
+    let gap = Gap {
+        symbol_pk: 1,
+        symbol: "XBTUSD".to_string(),
+        source_pk: 3,
+        source: "Kraken".to_string(),
+        interval_pk: 3,
+        interval: "1d".to_string(),
+        start,
+        end,
+        missing,
+    };
+    assert!(!gap.covers(dt(20, 0)));
+    assert!(gap.covers(dt(21, 0)));
+    assert!(gap.covers(dt(24, 0)));
+    assert!(!gap.covers(dt(25, 0)));
+}
@@ -9,6 +9,7 @@ lazy_static::lazy_static! {
         map.insert("1h".to_string(), 2);
         map.insert("1d".to_string(), 3);
         map.insert("1w".to_string(), 4);
+        map.insert("1m".to_string(), 5);
         map
     };
 }
@@ -17,25 +18,142 @@ lazy_static::lazy_static! {
 // prices
 ///////////////////////////////////////////////////////
 
-/// insert price cell
-pub(crate) const INSERT_PRICE: &'static str = "
-    INSERT INTO crypto.prices (
-        symbol_pk, 
-        dt, 
-        interval_pk, 
-        opening, 
-        high, 
-        low, 
-        closing, 
-        volume, 
-        trades, 
+/// upsert price cell; a stored candle is only overwritten while it's still open (`closed` is
+/// false), and every change to it is logged in `crypto.price_revisions`
+///
+/// NOTE: every CTE reads the same snapshot, so `prev` holds the candle as it was before the upsert
+pub(crate) const UPSERT_PRICE: &str = "
+    WITH prev AS (
+        SELECT opening, high, low, closing, volume, trades
+        FROM crypto.prices
+        WHERE symbol_pk = $1
+            AND dt = $2
+            AND interval_pk = $3
+            AND source_pk = $10
+            AND NOT closed
+    ),
+    upsert AS (
+        INSERT INTO crypto.prices (
+            symbol_pk,
+            dt,
+            interval_pk,
+            opening,
+            high,
+            low,
+            closing,
+            volume,
+            trades,
+            source_pk,
+            closed
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (symbol_pk, dt, interval_pk, source_pk)
+        DO UPDATE SET
+            opening = EXCLUDED.opening,
+            high = EXCLUDED.high,
+            low = EXCLUDED.low,
+            closing = EXCLUDED.closing,
+            volume = EXCLUDED.volume,
+            trades = EXCLUDED.trades,
+            closed = EXCLUDED.closed
+        WHERE NOT crypto.prices.closed
+    )
+    INSERT INTO crypto.price_revisions (
+        symbol_pk,
+        dt,
+        interval_pk,
+        source_pk,
+        revised,
+        opening,
+        high,
+        low,
+        closing,
+        volume,
+        trades
+    )
+    SELECT $1, $2, $3, $10, CLOCK_TIMESTAMP(), opening, high, low, closing, volume, trades
+    FROM prev
+    WHERE (opening, high, low, closing, volume, trades)
+        IS DISTINCT FROM ($4::FLOAT, $5::FLOAT, $6::FLOAT, $7::FLOAT, $8::FLOAT, $9::BIGINT)
+    ON CONFLICT DO NOTHING
+";
+
+/// latest price timestamp, per symbol, for a single source & interval
+pub(crate) const LATEST_PRICES: &str = "
+    SELECT symbol_pk, MAX(dt) AS dt
+    FROM crypto.prices
+    WHERE source_pk = $1
+        AND interval_pk = $2
+    GROUP BY symbol_pk
+";
+
+/// temporary table for bulk loading prices, dropped on commit
+pub(crate) const CREATE_PRICES_BUFFER: &str = "
+    CREATE TEMP TABLE prices_buffer (LIKE crypto.prices INCLUDING DEFAULTS) ON COMMIT DROP
+";
+
+/// bulk load prices into the temporary table
+pub(crate) const COPY_PRICES_BUFFER: &str = "
+    COPY prices_buffer (
+        symbol_pk,
+        dt,
+        interval_pk,
+        opening,
+        high,
+        low,
+        closing,
+        volume,
+        trades,
         source_pk
     )
-    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+    FROM STDIN WITH (FORMAT binary)
+";
+
+/// move the bulk loaded prices into `crypto.prices`, skipping existing candles; bulk loads are
+/// archived history, so every candle is closed
+pub(crate) const INSERT_PRICES_BUFFER: &str = "
+    INSERT INTO crypto.prices (
+        symbol_pk,
+        dt,
+        interval_pk,
+        opening,
+        high,
+        low,
+        closing,
+        volume,
+        trades,
+        source_pk,
+        closed
+    )
+    SELECT
+        symbol_pk,
+        dt,
+        interval_pk,
+        opening,
+        high,
+        low,
+        closing,
+        volume,
+        trades,
+        source_pk,
+        TRUE
+    FROM prices_buffer
     ON CONFLICT (symbol_pk, dt, interval_pk, source_pk)
     DO NOTHING
 ";
 
+///////////////////////////////////////////////////////
+// sources
+///////////////////////////////////////////////////////
+
+/// insert source
+pub(crate) const INSERT_SOURCE: &str = "
+    INSERT INTO crypto.sources (source)
+    VALUES ($1)
+    ON CONFLICT (source)
+    DO NOTHING
+";
+
 ///////////////////////////////////////////////////////
 // symbols
 ///////////////////////////////////////////////////////
@@ -47,3 +165,498 @@ pub(crate) const INSERT_SYMBOL: &'static str = "
     ON CONFLICT (symbol)
     DO NOTHING
 ";
+
+///////////////////////////////////////////////////////
+// instruments
+///////////////////////////////////////////////////////
+
+/// upsert instrument metadata; `first_seen` is kept from the first insert
+pub(crate) const UPSERT_INSTRUMENT: &str = "
+    INSERT INTO crypto.instruments (
+        symbol_pk,
+        source_pk,
+        exchange_symbol,
+        base_asset,
+        quote_asset,
+        status,
+        is_trading,
+        tick_size,
+        lot_size,
+        first_seen,
+        last_seen
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $10)
+    ON CONFLICT (symbol_pk, source_pk)
+    DO UPDATE SET
+        exchange_symbol = EXCLUDED.exchange_symbol,
+        base_asset = EXCLUDED.base_asset,
+        quote_asset = EXCLUDED.quote_asset,
+        status = EXCLUDED.status,
+        is_trading = EXCLUDED.is_trading,
+        tick_size = EXCLUDED.tick_size,
+        lot_size = EXCLUDED.lot_size,
+        last_seen = EXCLUDED.last_seen
+";
+
+/// mark instruments that have disappeared from a source's listing
+pub(crate) const MARK_DELISTED: &str = "
+    UPDATE crypto.instruments
+    SET status = 'delisted', is_trading = FALSE
+    WHERE source_pk = $1
+        AND last_seen < $2
+        AND status <> 'delisted'
+";
+
+///////////////////////////////////////////////////////
+// 24h tickers
+///////////////////////////////////////////////////////
+
+/// insert 24h ticker statistics snapshot
+pub(crate) const INSERT_TICKER_24H: &str = "
+    INSERT INTO crypto.tickers_24h (
+        symbol_pk,
+        source_pk,
+        dt,
+        last_price,
+        price_change,
+        price_change_perc,
+        high,
+        low,
+        volume,
+        quote_volume,
+        trades
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+    ON CONFLICT (symbol_pk, source_pk, dt)
+    DO NOTHING
+";
+
+///////////////////////////////////////////////////////
+// futures
+///////////////////////////////////////////////////////
+
+/// insert funding rate
+pub(crate) const INSERT_FUNDING_RATE: &str = "
+    INSERT INTO crypto.funding_rates (symbol_pk, dt, funding_rate, mark_price, source_pk)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (symbol_pk, dt, source_pk)
+    DO NOTHING
+";
+
+/// latest funding rate timestamp, per symbol, for a single source
+pub(crate) const LATEST_FUNDING_RATES: &str = "
+    SELECT symbol_pk, MAX(dt) AS dt
+    FROM crypto.funding_rates
+    WHERE source_pk = $1
+    GROUP BY symbol_pk
+";
+
+/// insert open interest
+pub(crate) const INSERT_OPEN_INTEREST: &str = "
+    INSERT INTO crypto.open_interest (
+        symbol_pk,
+        dt,
+        interval_pk,
+        open_interest,
+        open_interest_value,
+        source_pk
+    )
+    VALUES ($1, $2, $3, $4, $5, $6)
+    ON CONFLICT (symbol_pk, dt, interval_pk, source_pk)
+    DO NOTHING
+";
+
+/// latest open interest timestamp, per symbol, for a single source
+pub(crate) const LATEST_OPEN_INTEREST: &str = "
+    SELECT symbol_pk, MAX(dt) AS dt
+    FROM crypto.open_interest
+    WHERE source_pk = $1
+    GROUP BY symbol_pk
+";
+
+///////////////////////////////////////////////////////
+// composite
+///////////////////////////////////////////////////////
+
+/// every candle of a market's member pairs, from a given timestamp
+pub(crate) const MARKET_PRICES: &str = "
+    SELECT symbol_pk, source_pk, dt, opening, high, low, closing, volume
+    FROM crypto.prices
+    WHERE symbol_pk = ANY($1)
+        AND interval_pk = $2
+        AND dt >= $3
+    ORDER BY dt
+";
+
+/// latest composite timestamp, per market, for a single interval
+pub(crate) const LATEST_COMPOSITE_PRICES: &str = "
+    SELECT symbol_pk, MAX(dt) AS dt
+    FROM crypto.composite_prices
+    WHERE interval_pk = $1
+    GROUP BY symbol_pk
+";
+
+/// earliest timestamp of a market with more candles than its composite was built from, e.g.
+/// candles backfilled (or from a new source) since the composite was last built
+pub(crate) const EARLIEST_STALE_COMPOSITE: &str = "
+    SELECT MIN(p.dt) AS dt
+    FROM (
+        SELECT dt, COUNT(*) AS candles
+        FROM crypto.prices
+        WHERE (symbol_pk, source_pk) IN (SELECT * FROM UNNEST($1::INT[], $2::SMALLINT[]))
+            AND interval_pk = $3
+            AND closing IS NOT NULL
+        GROUP BY dt
+    ) p
+    LEFT JOIN crypto.composite_prices cp
+        ON cp.symbol_pk = $4
+        AND cp.dt = p.dt
+        AND cp.interval_pk = $3
+    WHERE cp.sources IS NULL OR cp.sources < p.candles
+";
+
+/// upsert composite price, as the latest candle is rebuilt while it is still in progress
+pub(crate) const UPSERT_COMPOSITE_PRICE: &str = "
+    INSERT INTO crypto.composite_prices (
+        symbol_pk,
+        dt,
+        interval_pk,
+        opening,
+        high,
+        low,
+        closing,
+        volume,
+        sources
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    ON CONFLICT (symbol_pk, dt, interval_pk)
+    DO UPDATE SET
+        opening = EXCLUDED.opening,
+        high = EXCLUDED.high,
+        low = EXCLUDED.low,
+        closing = EXCLUDED.closing,
+        volume = EXCLUDED.volume,
+        sources = EXCLUDED.sources
+";
+
+/// upsert a source's deviation from the composite close
+pub(crate) const UPSERT_COMPOSITE_DEVIATION: &str = "
+    INSERT INTO crypto.composite_deviations (
+        symbol_pk,
+        dt,
+        interval_pk,
+        source_pk,
+        closing,
+        deviation_perc
+    )
+    VALUES ($1, $2, $3, $4, $5, $6)
+    ON CONFLICT (symbol_pk, dt, interval_pk, source_pk)
+    DO UPDATE SET
+        closing = EXCLUDED.closing,
+        deviation_perc = EXCLUDED.deviation_perc
+";
+
+///////////////////////////////////////////////////////
+// usd conversion
+///////////////////////////////////////////////////////
+
+/// upsert the normalised market of a symbol, per source
+pub(crate) const UPSERT_MARKET: &str = "
+    INSERT INTO crypto.markets (symbol_pk, source_pk, base_asset, quote_asset)
+    VALUES ($1, $2, $3, $4)
+    ON CONFLICT (symbol_pk, source_pk)
+    DO UPDATE SET
+        base_asset = EXCLUDED.base_asset,
+        quote_asset = EXCLUDED.quote_asset
+";
+
+/// composite closes of the given markets, from a given timestamp
+pub(crate) const COMPOSITE_CLOSES: &str = "
+    SELECT sy.symbol, cp.dt, cp.closing
+    FROM crypto.composite_prices AS cp
+    INNER JOIN crypto.symbols AS sy ON sy.pk = cp.symbol_pk
+    WHERE sy.symbol = ANY($1)
+        AND cp.interval_pk = $2
+        AND cp.dt >= $3
+        AND cp.closing > 0
+";
+
+/// latest usd rate timestamp of each asset, for a single interval
+pub(crate) const LATEST_USD_RATES: &str = "
+    SELECT asset, MAX(dt) AS dt
+    FROM crypto.usd_rates
+    WHERE interval_pk = $1
+    GROUP BY asset
+";
+
+/// upsert usd rate, as the latest candle is rebuilt while it is still in progress
+pub(crate) const UPSERT_USD_RATE: &str = "
+    INSERT INTO crypto.usd_rates (asset, dt, interval_pk, rate, path)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (asset, dt, interval_pk)
+    DO UPDATE SET
+        rate = EXCLUDED.rate,
+        path = EXCLUDED.path
+";
+
+///////////////////////////////////////////////////////
+// portfolio
+///////////////////////////////////////////////////////
+
+/// latest transfer timestamp, for a single source
+pub(crate) const LATEST_TRANSFER: &str = "
+    SELECT MAX(dt) AS dt
+    FROM portfolio.transfers
+    WHERE source_pk = $1
+";
+
+/// latest fill timestamp, for a single source
+pub(crate) const LATEST_FILL: &str = "
+    SELECT MAX(dt) AS dt
+    FROM portfolio.fills
+    WHERE source_pk = $1
+";
+
+/// symbols & their base & quote assets, for a single source
+pub(crate) const SOURCE_MARKETS: &str = "
+    SELECT sy.symbol, mk.base_asset, mk.quote_asset
+    FROM crypto.markets AS mk
+    INNER JOIN crypto.symbols AS sy ON sy.pk = mk.symbol_pk
+    WHERE mk.source_pk = $1
+";
+
+/// every asset ever in the account, i.e. held, transferred or traded, for a single source
+pub(crate) const ACCOUNT_ASSETS: &str = "
+    SELECT asset FROM portfolio.balances WHERE source_pk = $1
+    UNION
+    SELECT asset FROM portfolio.transfers WHERE source_pk = $1
+    UNION
+    SELECT UNNEST(ARRAY[mk.base_asset, mk.quote_asset]) AS asset
+    FROM portfolio.fills AS fi
+    INNER JOIN crypto.markets AS mk
+        ON mk.symbol_pk = fi.symbol_pk AND mk.source_pk = fi.source_pk
+    WHERE fi.source_pk = $1
+";
+
+/// latest numeric trade id of each symbol, for a single source
+pub(crate) const LATEST_TRADE_IDS: &str = "
+    SELECT sy.symbol, MAX(fi.trade_id::BIGINT) AS trade_id
+    FROM portfolio.fills AS fi
+    INNER JOIN crypto.symbols AS sy ON sy.pk = fi.symbol_pk
+    WHERE fi.source_pk = $1
+    GROUP BY sy.symbol
+";
+
+/// latest fill of each symbol, for a single source
+pub(crate) const LATEST_FILL_DTS: &str = "
+    SELECT sy.symbol, MAX(fi.dt) AS dt
+    FROM portfolio.fills AS fi
+    INNER JOIN crypto.symbols AS sy ON sy.pk = fi.symbol_pk
+    WHERE fi.source_pk = $1
+    GROUP BY sy.symbol
+";
+
+/// insert balance snapshot
+pub(crate) const INSERT_BALANCE: &str = "
+    INSERT INTO portfolio.balances (source_pk, asset, dt, free, locked)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (source_pk, asset, dt)
+    DO NOTHING
+";
+
+/// upsert deposit/withdrawal, as its status changes until it completes
+pub(crate) const UPSERT_TRANSFER: &str = "
+    INSERT INTO portfolio.transfers (source_pk, tx_id, kind, asset, amount, fee, status, dt)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    ON CONFLICT (source_pk, tx_id, kind)
+    DO UPDATE SET
+        fee = EXCLUDED.fee,
+        status = EXCLUDED.status
+";
+
+/// insert fill
+pub(crate) const INSERT_FILL: &str = "
+    INSERT INTO portfolio.fills (
+        source_pk,
+        symbol_pk,
+        trade_id,
+        side,
+        price,
+        quantity,
+        fee,
+        fee_asset,
+        dt
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    ON CONFLICT (source_pk, symbol_pk, trade_id)
+    DO NOTHING
+";
+
+///////////////////////////////////////////////////////
+// repair
+///////////////////////////////////////////////////////
+
+/// missing candles, per symbol, source & interval, i.e. consecutive candles further apart than
+/// their interval, or a latest candle (`dt` NULL) more than one closed candle behind now; gaps
+/// already known to be unfillable are skipped unless `$1` (retry) is set
+pub(crate) const PRICE_GAPS: &str = "
+    WITH steps (interval, step) AS (
+        VALUES
+            ('30m', INTERVAL '30 minutes'),
+            ('1h', INTERVAL '1 hour'),
+            ('1d', INTERVAL '24 hours'),
+            ('1w', INTERVAL '168 hours'),
+            ('1m', INTERVAL '1 minute')
+    ),
+    spans AS (
+        SELECT
+            symbol_pk,
+            source_pk,
+            interval_pk,
+            LAG(dt) OVER (PARTITION BY symbol_pk, source_pk, interval_pk ORDER BY dt) AS prev_dt,
+            dt
+        FROM crypto.prices
+        UNION ALL
+        SELECT
+            symbol_pk,
+            source_pk,
+            interval_pk,
+            MAX(dt) AS prev_dt,
+            NULL AS dt
+        FROM crypto.prices
+        GROUP BY symbol_pk, source_pk, interval_pk
+    )
+    SELECT
+        sp.symbol_pk,
+        sy.symbol,
+        sp.source_pk,
+        so.source,
+        sp.interval_pk,
+        st.interval,
+        sp.prev_dt,
+        sp.dt
+    FROM spans AS sp
+    INNER JOIN common.intervals AS iv ON iv.pk = sp.interval_pk
+    INNER JOIN steps AS st ON st.interval = TRIM(iv.interval)
+    INNER JOIN crypto.symbols AS sy ON sy.pk = sp.symbol_pk
+    INNER JOIN crypto.sources AS so ON so.pk = sp.source_pk
+    WHERE (
+            sp.dt - sp.prev_dt > st.step
+            OR (sp.dt IS NULL AND NOW() - sp.prev_dt >= 2 * st.step)
+        )
+        AND (
+            $1
+            OR NOT EXISTS (
+                SELECT 1
+                FROM crypto.price_gaps AS pg
+                WHERE pg.symbol_pk = sp.symbol_pk
+                    AND pg.source_pk = sp.source_pk
+                    AND pg.interval_pk = sp.interval_pk
+                    AND pg.gap_start = sp.prev_dt + st.step
+            )
+        )
+    ORDER BY so.source, sy.symbol, sp.prev_dt
+";
+
+/// forget a repaired gap, before it's rescanned
+pub(crate) const DELETE_PRICE_GAP: &str = "
+    DELETE FROM crypto.price_gaps
+    WHERE symbol_pk = $1
+        AND source_pk = $2
+        AND interval_pk = $3
+        AND gap_start >= $4
+        AND gap_start <= $5
+";
+
+/// record a gap the source could not fill
+pub(crate) const UPSERT_PRICE_GAP: &str = "
+    INSERT INTO crypto.price_gaps (
+        symbol_pk,
+        source_pk,
+        interval_pk,
+        gap_start,
+        gap_end,
+        missing,
+        checked
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, NOW())
+    ON CONFLICT (symbol_pk, source_pk, interval_pk, gap_start)
+    DO UPDATE SET
+        gap_end = EXCLUDED.gap_end,
+        missing = EXCLUDED.missing,
+        checked = EXCLUDED.checked
+";
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[tokio::test]
+async fn open_candles_revised_until_closed() {
+    dotenv::dotenv().ok();
+
+    // open a connection to the database, using Env Var
+    let (mut pg_client, pg_conn) =
+        tokio_postgres::connect(&dotenv::var("FINDUMP_URL").unwrap(), tokio_postgres::NoTls)
+            .await
+            .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = pg_conn.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+
+    // never committed, so nothing is left behind
+    let transaction = pg_client.transaction().await.unwrap();
+    let (symbol_pk, interval_pk, source_pk) = (-1_i32, 3_i16, -1_i16);
+    let dt = chrono::DateTime::from_timestamp(1_704_067_200, 0).unwrap();
+
+    // fetched twice mid-day, then once closed, then again after it closed
+    for (closing, closed) in [(100.0, false), (105.0, false), (110.0, true), (120.0, true)] {
+        transaction
+            .execute(
+                UPSERT_PRICE,
+                &[
+                    &symbol_pk,
+                    &dt,
+                    &interval_pk,
+                    &closing,
+                    &closing,
+                    &closing,
+                    &closing,
+                    &1.0_f64,
+                    &1_i64,
+                    &source_pk,
+                    &closed,
+                ],
+            )
+            .await
+            .unwrap();
+    }
+
+    // the first closed fetch wins
+    let row = transaction
+        .query_one(
+            "SELECT closing, closed FROM crypto.prices WHERE symbol_pk = $1 AND source_pk = $2",
+            &[&symbol_pk, &source_pk],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, f64>("closing"), 110.0);
+    assert!(row.get::<_, bool>("closed"));
+
+    // every overwritten open candle is kept
+    let revisions: Vec<f64> = transaction
+        .query(
+            "SELECT closing FROM crypto.price_revisions
+            WHERE symbol_pk = $1 AND source_pk = $2
+            ORDER BY revised",
+            &[&symbol_pk, &source_pk],
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.get("closing"))
+        .collect();
+    assert_eq!(revisions, vec![100.0, 105.0]);
+}
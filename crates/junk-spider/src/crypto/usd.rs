@@ -0,0 +1,256 @@
+use super::markets::{self, Market};
+use super::sql;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+use tracing::{debug, error, info, trace, warn};
+
+const SOURCE: &str = "USD Conversion";
+
+// Pairs quoted in BTC, ETH, USDT, EUR, TRY, ... are converted to USD through their quote asset's
+// rate, derived from the composite closes (see `composite`) along the first available path
+//
+//      1. direct           EUR/USD, or its inverse 1 / USD/TRY
+//      2. stablecoin peg   USDT=1, unless priced directly
+//      3. via stablecoin   TRY -> 1 / USDT/TRY * USDT=1
+//      4. via BTC          ETH -> ETH/BTC * BTC/USD
+//
+// The path is chosen per timestamp & recorded alongside each rate in `crypto.usd_rates`.
+
+const CONVERSION_INTERVAL: &str = "1d";
+
+/// Stablecoins assumed to be worth $1, in order of preference.
+const STABLECOINS: [&str; 6] = ["USDT", "USDC", "FDUSD", "TUSD", "BUSD", "DAI"];
+
+/// Composite closes, per canonical symbol, e.g. "BTCUSDT".
+struct Closes(HashMap<String, BTreeMap<DateTime<Utc>, f64>>);
+
+impl Closes {
+    /// Price of `base` in `quote` at `dt`, from either direction of the market.
+    fn pair(&self, base: &str, quote: &str, dt: &DateTime<Utc>) -> Option<(f64, String)> {
+        let close = |symbol: String| self.0.get(&symbol).and_then(|closes| closes.get(dt));
+
+        if let Some(price) = close(format!("{base}{quote}")) {
+            return Some((*price, format!("{base}/{quote}")));
+        }
+        if let Some(price) = close(format!("{quote}{base}")) {
+            return Some((1.0 / price, format!("1 / {quote}/{base}")));
+        }
+        None
+    }
+
+    /// USD value of one unit of `asset` at `dt`, and the path it was derived from.
+    fn usd_rate(&self, asset: &str, dt: &DateTime<Utc>, via_btc: bool) -> Option<(f64, String)> {
+        if asset == "USD" {
+            return Some((1.0, "USD".to_string()));
+        }
+
+        // 1. direct
+        if let Some(rate) = self.pair(asset, "USD", dt) {
+            return Some(rate);
+        }
+
+        // 2. stablecoin peg
+        if STABLECOINS.contains(&asset) {
+            return Some((1.0, format!("{asset}=1")));
+        }
+
+        // 3. via stablecoin
+        for stable in STABLECOINS {
+            if let Some((price, path)) = self.pair(asset, stable, dt) {
+                let (rate, stable_path) = self.usd_rate(stable, dt, false)?;
+                return Some((price * rate, format!("{path} * {stable_path}")));
+            }
+        }
+
+        // 4. via BTC
+        if via_btc && asset != "BTC" {
+            let (price, path) = self.pair(asset, "BTC", dt)?;
+            let (rate, btc_path) = self.usd_rate("BTC", dt, false)?;
+            return Some((price * rate, format!("{path} * {btc_path}")));
+        }
+
+        None
+    }
+}
+
+/// Every symbol that may be on an asset's USD path.
+fn candidate_symbols(asset: &str) -> Vec<String> {
+    let mut quotes = vec!["USD", "BTC"];
+    quotes.extend(STABLECOINS);
+
+    quotes
+        .into_iter()
+        .filter(|quote| *quote != asset)
+        .flat_map(|quote| [format!("{asset}{quote}"), format!("{quote}{asset}")])
+        .collect()
+}
+
+/////////////////////////////////////////////////////////////////////////////////
+// core
+/////////////////////////////////////////////////////////////////////////////////
+
+/// Derive the USD rate of every quote asset, so that `crypto.usd_prices` can convert every pair.
+pub async fn convert(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    let time = std::time::Instant::now();
+
+    // wait for a pg client from the pool
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!("{bar}\n{SOURCE:^40}\n{bar}", bar = "=".repeat(40))
+    }
+    let pb = if tui {
+        let pb = ProgressBar::new_spinner()
+            .with_message("matching markets ...")
+            .with_style(ProgressStyle::default_spinner().template("{msg} {spinner:.magenta}")?);
+        pb.enable_steady_tick(Duration::from_millis(100));
+        pb
+    } else {
+        ProgressBar::hidden()
+    };
+
+    // 1. record the normalised market of each symbol
+    info!("matching markets ...");
+    let markets: HashMap<(i32, i16), Market> = markets::fetch_markets(&mut pg_client).await?;
+    let query = pg_client.prepare(sql::UPSERT_MARKET).await?;
+    let transaction = pg_client.transaction().await?;
+    for ((symbol_pk, source_pk), market) in &markets {
+        let result = transaction
+            .execute(&query, &[symbol_pk, source_pk, &market.base, &market.quote])
+            .await;
+
+        if let Err(err) = result {
+            error!("failed to insert market {market}, error({err})");
+        }
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit transaction for markets, error({err})");
+        err
+    })?;
+
+    // 2. fetch the composite closes on every quote asset's possible paths
+    pb.set_message("fetching composite closes ...");
+    let assets: BTreeSet<&str> = markets.values().map(|m| m.quote.as_str()).collect();
+    let mut symbols: HashSet<String> = HashSet::new();
+    for asset in assets.iter().copied().chain(["BTC"]).chain(STABLECOINS) {
+        symbols.extend(candidate_symbols(asset));
+    }
+    let symbols: Vec<String> = symbols.into_iter().collect();
+
+    let interval_pk = sql::INTERVAL_PKS[CONVERSION_INTERVAL] as i16;
+    // each asset resumes from its own latest rate, so a newly quoted asset is derived in full
+    let latest: HashMap<String, DateTime<Utc>> = pg_client
+        .query(sql::LATEST_USD_RATES, &[&interval_pk])
+        .await?
+        .into_iter()
+        .map(|row| (row.get("asset"), row.get("dt")))
+        .collect();
+    let since = |asset: &str| latest.get(asset).copied().unwrap_or_default();
+    let earliest = assets
+        .iter()
+        .map(|asset| since(asset))
+        .min()
+        .unwrap_or_default();
+
+    let mut closes = Closes(HashMap::new());
+    let mut dts: BTreeSet<DateTime<Utc>> = BTreeSet::new();
+    for row in pg_client
+        .query(sql::COMPOSITE_CLOSES, &[&symbols, &interval_pk, &earliest])
+        .await?
+    {
+        let dt: DateTime<Utc> = row.get("dt");
+        dts.insert(dt);
+        closes
+            .0
+            .entry(row.get("symbol"))
+            .or_default()
+            .insert(dt, row.get("closing"));
+    }
+
+    // 3. derive & insert the usd rates
+    pb.set_message("inserting usd rates ...");
+    let query = pg_client.prepare(sql::UPSERT_USD_RATE).await?;
+    let transaction = pg_client.transaction().await?;
+    let mut unconverted: BTreeSet<&str> = BTreeSet::new();
+    for asset in &assets {
+        for dt in dts.range(since(asset)..) {
+            let (rate, path) = match closes.usd_rate(asset, dt, true) {
+                Some(rate) => rate,
+                None => {
+                    unconverted.insert(asset);
+                    continue;
+                }
+            };
+
+            let result = transaction
+                .execute(&query, &[asset, dt, &interval_pk, &rate, &path])
+                .await;
+
+            match result {
+                Ok(_) => trace!("inserting usd rate for {asset} at {dt}, via {path}"),
+                Err(err) => error!("failed to insert usd rate for {asset} at {dt}, error({err})"),
+            }
+        }
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit transaction for usd rates, error({err})");
+        err
+    })?;
+    pb.finish_with_message("inserting usd rates ... done");
+
+    if !unconverted.is_empty() {
+        warn!(
+            "no usd path found for {} quote assets on some dates: {unconverted:?}",
+            unconverted.len()
+        );
+    }
+
+    debug!(
+        "usd rates derived for {} quote assets, {}",
+        assets.len(),
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[test]
+fn usd_paths() {
+    let dt = DateTime::<Utc>::default();
+    let closes = Closes(
+        [
+            ("BTCUSD", 50_000.0),
+            ("ETHBTC", 0.05),
+            ("USDTTRY", 40.0),
+            ("EURUSDT", 1.1),
+        ]
+        .into_iter()
+        .map(|(symbol, close)| (symbol.to_string(), BTreeMap::from([(dt, close)])))
+        .collect(),
+    );
+
+    let rate = |asset| closes.usd_rate(asset, &dt, true);
+    assert_eq!(rate("USD"), Some((1.0, "USD".to_string())));
+    assert_eq!(rate("USDT"), Some((1.0, "USDT=1".to_string())));
+    assert_eq!(rate("BTC"), Some((50_000.0, "BTC/USD".to_string())));
+    assert_eq!(
+        rate("ETH"),
+        Some((2_500.0, "ETH/BTC * BTC/USD".to_string()))
+    );
+    assert_eq!(
+        rate("TRY"),
+        Some((0.025, "1 / USDT/TRY * USDT=1".to_string()))
+    );
+    assert_eq!(rate("EUR"), Some((1.1, "EUR/USDT * USDT=1".to_string())));
+    assert_eq!(rate("JPY"), None);
+}
@@ -0,0 +1,434 @@
+use super::sql;
+use crate::http::PgClient;
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use hmac::{Hmac, Mac};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use sha2::Sha256;
+use std::collections::{HashMap as Map, HashSet};
+use tokio_postgres::types::FromSql;
+use tracing::{debug, error, info, trace};
+
+/// Retrieve a Map of <Symbol Names: Primary Keys>
+pub(crate) async fn fetch_pks<K, V>(
+    pg_client: &mut PgClient,
+    query: &str,
+    key_col: &str,
+    val_col: &str,
+) -> Result<Map<K, V>>
+where
+    K: Send + Sync + std::hash::Hash + Eq + for<'a> FromSql<'a>,
+    V: Send + Sync + for<'a> FromSql<'a>,
+{
+    let map: Map<K, V> = pg_client
+        .query(query, &[])
+        .await?
+        .into_par_iter()
+        .map(|row| {
+            let key: K = row.get(key_col);
+            let val: V = row.get(val_col);
+            (key, val)
+        })
+        .collect();
+    Ok(map)
+}
+
+/// 24h ticker statistics for a single pair, normalised across exchanges.
+#[derive(Debug)]
+pub(crate) struct Ticker24h {
+    pub symbol: String,
+    pub last_price: Option<f64>,
+    pub price_change: Option<f64>,
+    pub price_change_perc: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub volume: Option<f64>,
+    pub quote_volume: Option<f64>,
+    pub trades: Option<i64>,
+}
+
+/// Sign a query string with HMAC-SHA256, hex encoded, as Binance & MEXC private endpoints expect.
+pub(crate) fn hmac_sha256_hex(secret: &str, query: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC should take a key of any size");
+    mac.update(query.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Whether a candle opened at `dt` has closed, i.e. whether its values are final; candles still
+/// within their `interval` are revised on every fetch.
+pub(crate) fn is_closed(dt: DateTime<Utc>, interval: TimeDelta) -> bool {
+    dt + interval <= Utc::now()
+}
+
+/// Parse an exchange's stringified number, e.g. `"0.01634790"`.
+pub(crate) fn parse_f64(value: &Option<String>) -> Option<f64> {
+    value.as_ref().and_then(|val| val.parse::<f64>().ok())
+}
+
+/// Insert a snapshot of 24h ticker statistics; every row shares the same `dt`, so that each
+/// run can be compared against the last.
+pub(crate) async fn insert_tickers_24h(
+    pg_client: &mut PgClient,
+    tickers: &[Ticker24h],
+    symbol_pks: &Map<String, i32>,
+    source_pk: i16,
+    source: &str,
+) -> Result<()> {
+    let time = std::time::Instant::now();
+    let dt = chrono::Utc::now();
+
+    // preprocess pg query as transaction
+    let query = pg_client.prepare(sql::INSERT_TICKER_24H).await?;
+    let transaction = pg_client.transaction().await?;
+
+    for ticker in tickers {
+        let symbol_pk = match symbol_pks.get(&ticker.symbol) {
+            Some(pk) => pk,
+            None => {
+                trace!("no symbol pk found for {} from {source}", ticker.symbol);
+                continue;
+            }
+        };
+
+        let result = transaction
+            .execute(
+                &query,
+                &[
+                    symbol_pk,
+                    &source_pk,
+                    &dt,
+                    &ticker.last_price,
+                    &ticker.price_change,
+                    &ticker.price_change_perc,
+                    &ticker.high,
+                    &ticker.low,
+                    &ticker.volume,
+                    &ticker.quote_volume,
+                    &ticker.trades,
+                ],
+            )
+            .await;
+
+        match result {
+            Ok(_) => trace!("inserting {source} 24h ticker data for {}", ticker.symbol),
+            Err(err) => error!(
+                "failed to insert 24h ticker data for {} from {source}, error({err})",
+                ticker.symbol
+            ),
+        }
+    }
+
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit transaction for 24h tickers from {source}, error({err})");
+        err
+    })?;
+
+    debug!(
+        "24h ticker data collected from {source}, {}",
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+/// Insert `source` into `crypto.sources` (if it is new), returning its Primary Key.
+pub(crate) async fn source_pk(pg_client: &mut PgClient, source: &str) -> Result<i16> {
+    pg_client
+        .execute(sql::INSERT_SOURCE, &[&source])
+        .await
+        .map_err(|err| {
+            error!("failed to insert {source} as a source, error({err})");
+            err
+        })?;
+
+    let pk: i16 = pg_client
+        .query_one(
+            "SELECT pk FROM crypto.sources WHERE source = $1",
+            &[&source],
+        )
+        .await
+        .map_err(|err| {
+            error!("failed to find {source} source pk, error({err})");
+            err
+        })?
+        .get(0);
+
+    Ok(pk)
+}
+
+/// Insert a list of symbols into `crypto.symbols`; existing symbols are ignored.
+pub(crate) async fn insert_symbols(
+    pg_client: &mut PgClient,
+    symbols: &[String],
+    source: &str,
+) -> Result<()> {
+    let time = std::time::Instant::now();
+
+    // preprocess pg query as transaction
+    let query = pg_client.prepare(sql::INSERT_SYMBOL).await?;
+    let transaction = pg_client.transaction().await?;
+
+    for symbol in symbols {
+        match transaction.execute(&query, &[symbol]).await {
+            Ok(_) => trace!("inserting {source} symbol data for {symbol}"),
+            Err(err) => {
+                error!("failed to insert symbol data for {symbol} from {source}, error({err})")
+            }
+        }
+    }
+
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit transaction for symbols from {source}, error({err})");
+        err
+    })?;
+
+    debug!(
+        "symbol data collected from {source}, {}",
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+/// Retrieve a Map of <Symbol Primary Keys: latest datetime> for a single source; `query` must
+/// return the columns `symbol_pk` & `dt`, taking the source pk as its only parameter.
+pub(crate) async fn fetch_latest_dts(
+    pg_client: &mut PgClient,
+    query: &str,
+    source_pk: i16,
+) -> Result<Map<i32, DateTime<Utc>>> {
+    let map = pg_client
+        .query(query, &[&source_pk])
+        .await?
+        .into_iter()
+        .map(|row| (row.get("symbol_pk"), row.get("dt")))
+        .collect();
+    Ok(map)
+}
+
+/// A single funding event for a perpetual futures contract.
+#[derive(Debug)]
+pub(crate) struct FundingRate {
+    pub dt: DateTime<Utc>,
+    pub funding_rate: f64,
+    pub mark_price: Option<f64>,
+}
+
+/// Insert the funding rate history of a single symbol.
+pub(crate) async fn insert_funding_rates(
+    pg_client: &mut PgClient,
+    rates: &[FundingRate],
+    symbol: &str,
+    symbol_pk: i32,
+    source_pk: i16,
+    source: &str,
+) -> Result<()> {
+    let time = std::time::Instant::now();
+
+    // preprocess pg query as transaction
+    let query = pg_client.prepare(sql::INSERT_FUNDING_RATE).await?;
+    let transaction = pg_client.transaction().await?;
+
+    for rate in rates {
+        let result = transaction
+            .execute(
+                &query,
+                &[
+                    &symbol_pk,
+                    &rate.dt,
+                    &rate.funding_rate,
+                    &rate.mark_price,
+                    &source_pk,
+                ],
+            )
+            .await;
+
+        match result {
+            Ok(_) => trace!("inserting {source} funding rate data for {symbol}"),
+            Err(err) => {
+                error!(
+                    "failed to insert funding rate data for {symbol} from {source}, error({err})"
+                )
+            }
+        }
+    }
+
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit transaction for {symbol} from {source}, error({err})");
+        err
+    })?;
+
+    debug!(
+        "{symbol} funding rate data collected from {source}, {}",
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+/// Open interest of a perpetual futures contract, at a point in time.
+#[derive(Debug)]
+pub(crate) struct OpenInterest {
+    pub dt: DateTime<Utc>,
+    pub open_interest: Option<f64>,
+    pub open_interest_value: Option<f64>,
+}
+
+/// Insert the open interest history of a single symbol.
+pub(crate) async fn insert_open_interest(
+    pg_client: &mut PgClient,
+    rows: &[OpenInterest],
+    symbol: &str,
+    symbol_pk: i32,
+    interval_pk: i16,
+    source_pk: i16,
+    source: &str,
+) -> Result<()> {
+    let time = std::time::Instant::now();
+
+    // preprocess pg query as transaction
+    let query = pg_client.prepare(sql::INSERT_OPEN_INTEREST).await?;
+    let transaction = pg_client.transaction().await?;
+
+    for row in rows {
+        let result = transaction
+            .execute(
+                &query,
+                &[
+                    &symbol_pk,
+                    &row.dt,
+                    &interval_pk,
+                    &row.open_interest,
+                    &row.open_interest_value,
+                    &source_pk,
+                ],
+            )
+            .await;
+
+        match result {
+            Ok(_) => trace!("inserting {source} open interest data for {symbol}"),
+            Err(err) => error!(
+                "failed to insert open interest data for {symbol} from {source}, error({err})"
+            ),
+        }
+    }
+
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit transaction for {symbol} from {source}, error({err})");
+        err
+    })?;
+
+    debug!(
+        "{symbol} open interest data collected from {source}, {}",
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+/// Exchange metadata for a single pair, normalised across exchanges.
+#[derive(Debug)]
+pub(crate) struct Instrument {
+    /// The symbol, as stored in `crypto.symbols`.
+    pub symbol: String,
+    /// The symbol, as named by the exchange, e.g. "BTC-USDT".
+    pub exchange_symbol: String,
+    pub base_asset: Option<String>,
+    pub quote_asset: Option<String>,
+    pub status: String,
+    pub is_trading: bool,
+    pub tick_size: Option<f64>,
+    pub lot_size: Option<f64>,
+}
+
+/// Upsert the full instrument listing of a source, then mark any previously seen instrument,
+/// missing from this listing, as delisted.
+///
+/// Returns the set of symbols currently trading, so that scrapers can skip the rest.
+pub(crate) async fn upsert_instruments(
+    pg_client: &mut PgClient,
+    instruments: &[Instrument],
+    symbol_pks: &Map<String, i32>,
+    source_pk: i16,
+    source: &str,
+) -> Result<HashSet<String>> {
+    let time = std::time::Instant::now();
+    let today = Utc::now().date_naive();
+
+    // preprocess pg query as transaction
+    let query = pg_client.prepare(sql::UPSERT_INSTRUMENT).await?;
+    let transaction = pg_client.transaction().await?;
+
+    for instrument in instruments {
+        let symbol_pk = match symbol_pks.get(&instrument.symbol) {
+            Some(pk) => pk,
+            None => {
+                trace!("no symbol pk found for {} from {source}", instrument.symbol);
+                continue;
+            }
+        };
+
+        let result = transaction
+            .execute(
+                &query,
+                &[
+                    symbol_pk,
+                    &source_pk,
+                    &instrument.exchange_symbol,
+                    &instrument.base_asset,
+                    &instrument.quote_asset,
+                    &instrument.status,
+                    &instrument.is_trading,
+                    &instrument.tick_size,
+                    &instrument.lot_size,
+                    &today,
+                ],
+            )
+            .await;
+
+        match result {
+            Ok(_) => trace!(
+                "upserting {source} instrument data for {}",
+                instrument.symbol
+            ),
+            Err(err) => error!(
+                "failed to upsert instrument data for {} from {source}, error({err})",
+                instrument.symbol
+            ),
+        }
+    }
+
+    // anything not seen today has been removed from the listing
+    let delisted = transaction
+        .execute(sql::MARK_DELISTED, &[&source_pk, &today])
+        .await?;
+    if delisted > 0 {
+        info!("{delisted} instruments marked as delisted from {source}");
+    }
+
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit transaction for instruments from {source}, error({err})");
+        err
+    })?;
+
+    debug!(
+        "instrument data collected from {source}, {}",
+        crate::time_elapsed(time)
+    );
+
+    Ok(instruments
+        .iter()
+        .filter(|instrument| instrument.is_trading)
+        .map(|instrument| instrument.symbol.clone())
+        .collect())
+}
+
+/// Whether `symbol` should be scraped; if the instrument listing could not be collected, every
+/// symbol is.
+pub(crate) fn is_trading(trading: &Option<HashSet<String>>, symbol: &str) -> bool {
+    match trading {
+        Some(trading) => trading.contains(symbol),
+        None => true,
+    }
+}
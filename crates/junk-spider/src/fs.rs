@@ -33,7 +33,7 @@ pub async fn download_file(
         .and_then(|len| len.parse::<u64>().ok())
         .unwrap_or(0);
 
-    println!("{file_size}");
+    trace!("content length of {url}: {file_size} bytes");
 
     // ensure the directory exists
     trace!("checking directory path: {:?}", path);
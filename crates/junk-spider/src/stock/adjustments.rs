@@ -0,0 +1,241 @@
+use super::providers::PRIORITY;
+use super::sql;
+use crate::http::*;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use tracing::{debug, error, info};
+
+// Adjustment factors are computed backwards from the latest price, as in CRSP (& Yahoo):
+//
+// split = the product of every later split's ratio (numerator / denominator); Yahoo's quotes
+// are already split-adjusted, so `closing * split` is the price as traded that day.
+//
+// dividend = the product of every later dividend's `1 - amount / close`, with the close of the
+// last trading day before its ex-date; `closing * dividend` is the total return series.
+//
+// Factors are recomputed whenever a symbol's events change (see `yahoo_finance`), and whenever
+// prices land before its latest event without a factor (see `readjust`), e.g. a repaired gap.
+//
+// NOTE: only the primary provider's prices are adjusted; a fallback's (e.g. Stooq's) are already
+// adjusted for splits & dividends by the provider, so they're left without a factor.
+
+const INTERVAL_PK: i16 = 3;
+
+/// The cumulative adjustment factors of a single price.
+#[derive(Debug, PartialEq)]
+struct Factor {
+    dt: DateTime<Utc>,
+    split: f64,
+    dividend: f64,
+}
+
+/// Recompute the adjustment factors of a symbol's daily prices, from its dividends & splits.
+pub(super) async fn recompute(pg_client: &mut PgClient, symbol_pk: i32) -> anyhow::Result<()> {
+    let time = std::time::Instant::now();
+
+    let closes: Vec<(DateTime<Utc>, f64)> = pg_client
+        .query(
+            sql::SYMBOL_CLOSES,
+            &[&symbol_pk, &INTERVAL_PK, &PRIORITY[0].name()],
+        )
+        .await?
+        .into_iter()
+        .map(|row| (row.get("dt"), row.get("closing")))
+        .collect();
+    let dividends: Vec<(DateTime<Utc>, f64)> = pg_client
+        .query(sql::SYMBOL_DIVIDENDS, &[&symbol_pk])
+        .await?
+        .into_iter()
+        .map(|row| (row.get("dt"), row.get("amount")))
+        .collect();
+    let splits: Vec<(DateTime<Utc>, f64)> = pg_client
+        .query(sql::SYMBOL_SPLITS, &[&symbol_pk])
+        .await?
+        .into_iter()
+        .map(|row| (row.get("dt"), row.get("ratio")))
+        .collect();
+
+    let transaction = pg_client.transaction().await?;
+    transaction
+        .execute(sql::DELETE_ADJUSTMENTS, &[&symbol_pk, &INTERVAL_PK])
+        .await?;
+    let query = transaction.prepare(sql::INSERT_ADJUSTMENT).await?;
+    for factor in factors(&closes, &dividends, &splits) {
+        transaction
+            .execute(
+                &query,
+                &[
+                    &symbol_pk,
+                    &INTERVAL_PK,
+                    &factor.dt,
+                    &factor.split,
+                    &factor.dividend,
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit stock.adjustments for {symbol_pk}, error({err})");
+        err
+    })?;
+
+    debug!(
+        "{symbol_pk} adjustments recomputed. {}",
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+/// Recompute the adjustment factors of every symbol with unadjusted prices before its latest
+/// event.
+pub(super) async fn readjust(pool: &Pool) -> anyhow::Result<()> {
+    let time = std::time::Instant::now();
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    let symbol_pks: Vec<i32> = pg_client
+        .query(
+            sql::UNADJUSTED_SYMBOLS,
+            &[&INTERVAL_PK, &PRIORITY[0].name()],
+        )
+        .await
+        .map_err(|err| {
+            error!("failed to fetch unadjusted symbols, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| row.get("symbol_pk"))
+        .collect();
+
+    for symbol_pk in &symbol_pks {
+        if let Err(err) = recompute(&mut pg_client, *symbol_pk).await {
+            error!("failed to adjust prices for {symbol_pk}, error({err})");
+        }
+    }
+
+    info!(
+        "{} symbols readjusted for new prices. {}",
+        symbol_pks.len(),
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+/// The adjustment factors of every close (oldest first) before the latest event; events apply
+/// to every close dated before their (ex-)date.
+fn factors(
+    closes: &[(DateTime<Utc>, f64)],
+    dividends: &[(DateTime<Utc>, f64)],
+    splits: &[(DateTime<Utc>, f64)],
+) -> Vec<Factor> {
+    let mut dividends: Vec<_> = dividends.to_vec();
+    dividends.sort_by_key(|(dt, _)| std::cmp::Reverse(*dt));
+    let mut splits: Vec<_> = splits.to_vec();
+    splits.sort_by_key(|(dt, _)| std::cmp::Reverse(*dt));
+
+    // walk backwards, applying each event once the closes are before it
+    let (mut dividends, mut splits) = (
+        dividends.into_iter().peekable(),
+        splits.into_iter().peekable(),
+    );
+    let (mut split, mut dividend, mut adjusted) = (1.0, 1.0, false);
+    let mut factors = Vec::new();
+    for (dt, close) in closes.iter().rev() {
+        let date = dt.date_naive();
+        while let Some((_, ratio)) = splits.next_if(|(ex, _)| ex.date_naive() > date) {
+            split *= ratio;
+            adjusted = true;
+        }
+        while let Some((_, amount)) = dividends.next_if(|(ex, _)| ex.date_naive() > date) {
+            adjusted = true;
+            // skip nonsensical amounts, rather than flipping the sign of the series
+            if *close > amount {
+                dividend *= 1.0 - amount / close;
+            }
+        }
+
+        // stored even if the events cancel out, so that every close before the latest event
+        // has a factor (see `readjust`)
+        if adjusted {
+            factors.push(Factor {
+                dt: *dt,
+                split,
+                dividend,
+            });
+        }
+    }
+    factors.reverse();
+
+    factors
+}
+
+// -- TESTS --
+
+#[test]
+fn factors_apply_to_prior_closes() {
+    let dt = |day: u32| {
+        chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+            .expect("valid date")
+            .and_hms_opt(14, 30, 0)
+            .expect("valid time")
+            .and_utc()
+    };
+    let closes = [
+        (dt(1), 50.0),
+        (dt(2), 100.0),
+        (dt(3), 100.0),
+        (dt(4), 99.0),
+        (dt(5), 101.0),
+    ];
+
+    // a 2:1 split on the 2nd, and a dividend of 1 on the 4th (the prior close being 100)
+    let dividends = [(dt(4), 1.0)];
+    let splits = [(dt(2), 2.0)];
+
+    assert_eq!(
+        factors(&closes, &dividends, &splits),
+        vec![
+            Factor {
+                dt: dt(1),
+                split: 2.0,
+                dividend: 0.99
+            },
+            Factor {
+                dt: dt(2),
+                split: 1.0,
+                dividend: 0.99
+            },
+            Factor {
+                dt: dt(3),
+                split: 1.0,
+                dividend: 0.99
+            },
+        ]
+    );
+}
+
+#[test]
+fn factors_of_skipped_dividends() {
+    let dt = |day: u32| {
+        chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+            .expect("valid date")
+            .and_hms_opt(14, 30, 0)
+            .expect("valid time")
+            .and_utc()
+    };
+    let closes = [(dt(1), 1.0), (dt(2), 1.0)];
+
+    // a dividend above the prior close is skipped, but the close before it is still stored
+    assert_eq!(
+        factors(&closes, &[(dt(2), 5.0)], &[]),
+        vec![Factor {
+            dt: dt(1),
+            split: 1.0,
+            dividend: 1.0
+        }]
+    );
+}
@@ -0,0 +1,217 @@
+use super::sql;
+use deadpool_postgres::Pool;
+use tracing::{debug, error, info, trace};
+
+// Benchmarks for relative performance; indices, the Select Sector SPDRs & treasury ETFs, all
+// collected by `yahoo_finance` like any other symbol, but flagged by their asset class.
+//
+// Each US equity is mapped to the sector SPDR of its SIC code (see `SECTORS`), or the S&P 500 if
+// none apply, in `stock.benchmarks`.
+
+/// The S&P 500, the default benchmark.
+const MARKET: &str = "^GSPC";
+
+/// Equity indices, (symbol, title).
+const INDICES: [(&str, &str); 3] = [
+    ("^GSPC", "S&P 500"),
+    ("^NDX", "NASDAQ 100"),
+    ("^RUT", "RUSSELL 2000"),
+];
+
+/// The Select Sector SPDRs, (symbol, title).
+const SECTOR_ETFS: [(&str, &str); 11] = [
+    ("XLB", "MATERIALS SELECT SECTOR SPDR"),
+    ("XLC", "COMMUNICATION SERVICES SELECT SECTOR SPDR"),
+    ("XLE", "ENERGY SELECT SECTOR SPDR"),
+    ("XLF", "FINANCIAL SELECT SECTOR SPDR"),
+    ("XLI", "INDUSTRIAL SELECT SECTOR SPDR"),
+    ("XLK", "TECHNOLOGY SELECT SECTOR SPDR"),
+    ("XLP", "CONSUMER STAPLES SELECT SECTOR SPDR"),
+    ("XLRE", "REAL ESTATE SELECT SECTOR SPDR"),
+    ("XLU", "UTILITIES SELECT SECTOR SPDR"),
+    ("XLV", "HEALTH CARE SELECT SECTOR SPDR"),
+    ("XLY", "CONSUMER DISCRETIONARY SELECT SECTOR SPDR"),
+];
+
+/// Treasury ETFs along the curve, (symbol, title).
+const TREASURY_ETFS: [(&str, &str); 7] = [
+    ("BIL", "SPDR BLOOMBERG 1-3 MONTH T-BILL ETF"),
+    ("SHY", "ISHARES 1-3 YEAR TREASURY BOND ETF"),
+    ("IEI", "ISHARES 3-7 YEAR TREASURY BOND ETF"),
+    ("IEF", "ISHARES 7-10 YEAR TREASURY BOND ETF"),
+    ("TLT", "ISHARES 20+ YEAR TREASURY BOND ETF"),
+    ("GOVT", "ISHARES U.S. TREASURY BOND ETF"),
+    ("TIP", "ISHARES TIPS BOND ETF"),
+];
+
+/// Every benchmark, with its asset class & category (stored as its industry).
+fn universe() -> impl Iterator<Item = (&'static str, &'static str, &'static str, &'static str)> {
+    let tag = |asset_class, category| {
+        move |(symbol, title): &(&'static str, &'static str)| {
+            (*symbol, *title, asset_class, category)
+        }
+    };
+
+    INDICES
+        .iter()
+        .map(tag("index", "Equity Index"))
+        .chain(SECTOR_ETFS.iter().map(tag("etf", "Sector ETF")))
+        .chain(TREASURY_ETFS.iter().map(tag("etf", "Treasury ETF")))
+}
+
+/// SIC code ranges (inclusive) = sector SPDR; the first range containing a code applies, so the
+/// narrower ranges come first.
+const SECTORS: [(u16, u16, &str); 40] = [
+    // mining: oil & gas, else metals & minerals
+    (1300, 1399, "XLE"),
+    (1000, 1499, "XLB"),
+    // manufacturing
+    (2830, 2836, "XLV"),
+    (2840, 2844, "XLP"),
+    (2000, 2199, "XLP"),
+    (2200, 2399, "XLY"),
+    (2500, 2599, "XLY"),
+    (2700, 2799, "XLC"),
+    (2400, 2899, "XLB"),
+    (2900, 2999, "XLE"),
+    (3100, 3199, "XLY"),
+    (3000, 3399, "XLB"),
+    (3570, 3579, "XLK"),
+    (3630, 3639, "XLY"),
+    (3600, 3699, "XLK"),
+    (3711, 3716, "XLY"),
+    (3750, 3751, "XLY"),
+    (3840, 3851, "XLV"),
+    (3400, 3899, "XLI"),
+    (3900, 3999, "XLY"),
+    // agriculture & construction
+    (100, 999, "XLP"),
+    (1500, 1799, "XLI"),
+    // transportation, communications & utilities
+    (4800, 4899, "XLC"),
+    (4900, 4999, "XLU"),
+    (4000, 4799, "XLI"),
+    // wholesale & retail
+    (5122, 5122, "XLV"),
+    (5140, 5149, "XLP"),
+    (5000, 5199, "XLI"),
+    (5400, 5499, "XLP"),
+    (5912, 5912, "XLP"),
+    (5200, 5999, "XLY"),
+    // finance, insurance & real estate
+    (6798, 6798, "XLRE"),
+    (6500, 6599, "XLRE"),
+    (6000, 6799, "XLF"),
+    // services
+    (7370, 7379, "XLK"),
+    (7200, 7299, "XLY"),
+    (7800, 7999, "XLC"),
+    (8000, 8099, "XLV"),
+    (7000, 8999, "XLI"),
+    // public administration & nonclassifiable
+    (9000, 9999, MARKET),
+];
+
+/// Register the benchmark universe in `stock.symbols`, and map every US equity to its default
+/// benchmark.
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    let time = std::time::Instant::now();
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{name:^40}\n{bar}",
+            bar = "=".repeat(40),
+            name = "Stock Benchmarks"
+        );
+    }
+
+    // 1. the benchmarks themselves
+    let transaction = pg_client.transaction().await?;
+    for (symbol, title, asset_class, category) in universe() {
+        transaction
+            .execute(
+                sql::UPSERT_BENCHMARK_SYMBOL,
+                &[&symbol, &title, &category, &asset_class],
+            )
+            .await?;
+        trace!("benchmark [{symbol}] {title} registered");
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit benchmark symbols, error({err})");
+        err
+    })?;
+
+    // 2. each equity's default benchmark
+    info!("mapping stock.symbols to their benchmarks ...");
+    let equities: Vec<(i32, i16)> = pg_client
+        .query(sql::EQUITY_SICS, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.symbols SIC codes, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| (row.get("pk"), row.get("sic")))
+        .collect();
+
+    let transaction = pg_client.transaction().await?;
+    let query = transaction.prepare(sql::UPSERT_BENCHMARK).await?;
+    for (symbol_pk, sic) in &equities {
+        let benchmark = benchmark(*sic as u16);
+        transaction
+            .execute(&query, &[symbol_pk, &benchmark])
+            .await?;
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit stock.benchmarks, error({err})");
+        err
+    })?;
+
+    debug!(
+        "{} stock benchmarks mapped. {}",
+        equities.len(),
+        crate::time_elapsed(time)
+    );
+
+    if tui {
+        println!("mapping benchmarks ... done\n");
+    }
+
+    Ok(())
+}
+
+/// The default benchmark of a SIC code.
+fn benchmark(sic: u16) -> &'static str {
+    SECTORS
+        .iter()
+        .find(|(start, end, _)| (*start..=*end).contains(&sic))
+        .map(|(_, _, benchmark)| *benchmark)
+        .unwrap_or(MARKET)
+}
+
+// -- TESTS --
+
+#[test]
+fn sic_codes_map_to_sector_spdrs() {
+    // Apple (electronic computers), Pfizer (pharmaceutical preparations), Exxon (petroleum
+    // refining), JPMorgan (national commercial banks), Realty Income (REIT), Microsoft
+    // (prepackaged software), Tesla (motor vehicles), Walmart (variety stores)
+    let sics = [3571, 2834, 2911, 6021, 6798, 7372, 3711, 5331];
+    assert_eq!(
+        sics.map(benchmark),
+        ["XLK", "XLV", "XLE", "XLF", "XLRE", "XLK", "XLY", "XLY"]
+    );
+
+    // unclassified & blank codes fall back to the market
+    assert_eq!(benchmark(9995), MARKET);
+    assert_eq!(benchmark(0), MARKET);
+
+    // every benchmark is part of the universe
+    for (_, _, benchmark) in SECTORS {
+        assert!(universe().any(|(symbol, ..)| symbol == benchmark));
+    }
+}
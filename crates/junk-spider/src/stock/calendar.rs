@@ -0,0 +1,154 @@
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+// US equity trading calendar (NYSE & Nasdaq share it)
+//
+// https://www.nyse.com/markets/hours-calendars
+//
+// Weekends & the following holidays are closed; a holiday falling on a Saturday is observed the
+// Friday before, and on a Sunday the Monday after; except New Year's Day, which isn't observed on
+// the Friday before, as that would close the last trading day of the year.
+//
+// New Year's Day, MLK Day (3rd Monday of Jan), Washington's Birthday (3rd Monday of Feb),
+// Good Friday, Memorial Day (last Monday of May), Juneteenth (Jun 19, since 2022),
+// Independence Day (Jul 4), Labor Day (1st Monday of Sep), Thanksgiving (4th Thursday of Nov)
+// & Christmas Day (Dec 25)
+//
+// Early closes (e.g. the day after Thanksgiving) still trade, so aren't listed.
+
+/// Unscheduled closures, e.g. national days of mourning & weather.
+const CLOSURES: [(i32, u32, u32); 10] = [
+    (2001, 9, 11),
+    (2001, 9, 12),
+    (2001, 9, 13),
+    (2001, 9, 14),
+    (2004, 6, 11),
+    (2007, 1, 2),
+    (2012, 10, 29),
+    (2012, 10, 30),
+    (2018, 12, 5),
+    (2025, 1, 9),
+];
+
+/// Whether the US stock exchanges are open on `date`.
+pub fn is_trading_day(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+        && !holidays(date.year()).contains(&date)
+        && !CLOSURES
+            .iter()
+            .any(|(y, m, d)| NaiveDate::from_ymd_opt(*y, *m, *d) == Some(date))
+}
+
+/// Every trading day from `start` to `end`, inclusive.
+pub fn trading_days(start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+    start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .filter(|date| is_trading_day(*date))
+        .collect()
+}
+
+/// The exchange holidays of a single year, as observed.
+fn holidays(year: i32) -> Vec<NaiveDate> {
+    let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).expect("valid holiday");
+
+    let mut holidays = vec![
+        nth_weekday(year, 1, Weekday::Mon, 3),
+        nth_weekday(year, 2, Weekday::Mon, 3),
+        easter(year) - Days::new(2),
+        last_weekday(year, 5, Weekday::Mon),
+        observed(date(7, 4)),
+        nth_weekday(year, 9, Weekday::Mon, 1),
+        nth_weekday(year, 11, Weekday::Thu, 4),
+        observed(date(12, 25)),
+    ];
+
+    // New Year's Day isn't observed on the Friday before
+    let new_year = date(1, 1);
+    if new_year.weekday() != Weekday::Sat {
+        holidays.push(observed(new_year));
+    }
+
+    if year >= 2022 {
+        holidays.push(observed(date(6, 19)));
+    }
+
+    holidays
+}
+
+/// Move a holiday off the weekend.
+fn observed(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date - Days::new(1),
+        Weekday::Sun => date + Days::new(1),
+        _ => date,
+    }
+}
+
+/// The `n`th `weekday` of a month, e.g. the 3rd Monday of January.
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n).expect("valid weekday of month")
+}
+
+/// The last `weekday` of a month, e.g. the last Monday of May.
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, 5)
+        .unwrap_or_else(|| nth_weekday(year, month, weekday, 4))
+}
+
+/// Easter Sunday, by the anonymous Gregorian algorithm.
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).expect("valid easter date")
+}
+
+// -- TESTS --
+
+#[test]
+fn nyse_holidays() {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).expect("valid date");
+
+    // 2024: New Year, MLK, Presidents, Good Friday, Memorial, Juneteenth, Independence, Labor,
+    // Thanksgiving & Christmas
+    let closed = [
+        date(2024, 1, 1),
+        date(2024, 1, 15),
+        date(2024, 2, 19),
+        date(2024, 3, 29),
+        date(2024, 5, 27),
+        date(2024, 6, 19),
+        date(2024, 7, 4),
+        date(2024, 9, 2),
+        date(2024, 11, 28),
+        date(2024, 12, 25),
+    ];
+    for day in closed {
+        assert!(!is_trading_day(day), "{day} should be closed");
+    }
+    assert_eq!(
+        trading_days(date(2024, 1, 1), date(2024, 12, 31)).len(),
+        252
+    );
+
+    // observed on the weekday, bar New Year's Day on a Saturday
+    assert!(!is_trading_day(date(2022, 6, 20)));
+    assert!(!is_trading_day(date(2023, 1, 2)));
+    assert!(is_trading_day(date(2021, 12, 31)));
+
+    // one-off closures
+    assert!(!is_trading_day(date(2012, 10, 29)));
+    assert!(!is_trading_day(date(2025, 1, 9)));
+}
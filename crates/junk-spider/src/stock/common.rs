@@ -1,6 +1,9 @@
+use crate::stock::sql;
 use deadpool_postgres::Pool;
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use tracing::{error, trace};
 
 /// Used within the SEC datasets; each company is given a CIK code (and ticker, and title),
@@ -87,3 +90,66 @@ impl Tickers {
         Ok(Tickers(tickers))
     }
 }
+
+/// The SEC's fair access limit, of 10 requests per second (9, for good measure), shared across
+/// every concurrent request to `www.sec.gov`.
+pub(crate) struct SecThrottle(tokio::sync::Mutex<tokio::time::Interval>);
+
+impl SecThrottle {
+    pub(crate) fn new() -> Self {
+        let mut interval = tokio::time::interval(std::time::Duration::from_millis(1000 / 9));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        Self(tokio::sync::Mutex::new(interval))
+    }
+
+    /// Wait for the next request slot.
+    pub(crate) async fn wait(&self) {
+        self.0.lock().await.tick().await;
+    }
+}
+
+/// The SHA-256 of an SEC file's content, as hex; an unchanged hash means an unchanged company.
+pub(crate) fn content_hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// The content hash of each CIK's file, as of its last successful load by `loader`, e.g.
+/// "sec_metrics".
+pub(crate) async fn loaded_hashes(
+    pool: &Pool,
+    loader: &str,
+) -> anyhow::Result<HashMap<String, String>> {
+    let pg_client = pool.get().await?;
+    let hashes = pg_client
+        .query(sql::SEC_HASHES, &[&loader])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.sec_hashes of {loader}, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| (row.get("cik"), row.get("hash")))
+        .collect();
+
+    Ok(hashes)
+}
+
+/// UPSERT the content hash of each CIK's file loaded by `loader`.
+pub(crate) async fn store_hashes(
+    pool: &Pool,
+    loader: &str,
+    hashes: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    let mut pg_client = pool.get().await?;
+    let transaction = pg_client.transaction().await?;
+    let query = transaction.prepare(sql::UPSERT_SEC_HASH).await?;
+    for (cik, hash) in hashes {
+        transaction.execute(&query, &[cik, &loader, hash]).await?;
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to upsert stock.sec_hashes of {loader}, error({err})");
+        err
+    })?;
+
+    Ok(())
+}
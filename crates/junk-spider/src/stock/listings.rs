@@ -0,0 +1,241 @@
+use super::sql;
+use crate::http::*;
+use chrono::NaiveTime;
+use deadpool_postgres::Pool;
+use serde::Deserialize;
+use tracing::{debug, error, info, trace};
+
+// Listings outside of the SEC's company tickers, e.g. London, Frankfurt & Tokyo, registered from a
+// JSON file (`STOCK_LISTINGS`, else `./listings.json`):
+//
+// [
+//     { "symbol": "SHEL", "title": "SHELL PLC", "exchange": "LSE", "industry": "Oil & Gas" },
+//     { "symbol": "7203", "title": "TOYOTA MOTOR CORP", "exchange": "TSE" },
+//     ...
+// ]
+//
+// The exchange decides the nation, trading currency, Yahoo suffix, timezone & session open (see
+// `EXCHANGES`); `nation`, `currency`, `suffix`, `timezone` & `open` (e.g. "09:00") override it,
+// for exchanges not listed. Yahoo quotes some exchanges in minor units, e.g. "GBp" (pence), which
+// is what `stock.prices.currency` is tagged with.
+
+const LISTINGS: &str = "./listings.json";
+
+/// (nation, trading currency, Yahoo suffix, timezone, session open) of an exchange.
+type Exchange = (
+    &'static str,
+    &'static str,
+    &'static str,
+    &'static str,
+    &'static str,
+);
+
+/// Exchange code = [Exchange].
+const EXCHANGES: [(&str, Exchange); 14] = [
+    ("NYSE", ("US", "USD", "", "America/New_York", "09:30")),
+    ("NASDAQ", ("US", "USD", "", "America/New_York", "09:30")),
+    ("LSE", ("GB", "GBP", ".L", "Europe/London", "08:00")),
+    ("XETRA", ("DE", "EUR", ".DE", "Europe/Berlin", "09:00")),
+    ("FSE", ("DE", "EUR", ".F", "Europe/Berlin", "08:00")),
+    ("EPA", ("FR", "EUR", ".PA", "Europe/Paris", "09:00")),
+    ("AMS", ("NL", "EUR", ".AS", "Europe/Amsterdam", "09:00")),
+    ("SIX", ("CH", "CHF", ".SW", "Europe/Zurich", "09:00")),
+    ("TSE", ("JP", "JPY", ".T", "Asia/Tokyo", "09:00")),
+    ("HKEX", ("HK", "HKD", ".HK", "Asia/Hong_Kong", "09:30")),
+    ("ASX", ("AU", "AUD", ".AX", "Australia/Sydney", "10:00")),
+    ("TSX", ("CA", "CAD", ".TO", "America/Toronto", "09:30")),
+    ("KRX", ("KR", "KRW", ".KS", "Asia/Seoul", "09:00")),
+    ("NSE", ("IN", "INR", ".NS", "Asia/Kolkata", "09:15")),
+];
+
+/// Register every listing of the listings file in `stock.symbols`; a missing file is skipped.
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    let path = var("STOCK_LISTINGS").unwrap_or(LISTINGS.to_string());
+    if !std::path::Path::new(&path).exists() {
+        info!("no stock listings file found at {path}, skipping");
+        return Ok(());
+    }
+
+    if tui {
+        println!(
+            "{bar}\n{name:^40}\n{bar}",
+            bar = "=".repeat(40),
+            name = "Stock Listings"
+        );
+    }
+
+    let listings: Vec<Listing> = crate::fs::read_json(&path).await.map_err(|err| {
+        error!("failed to read stock listings from {path}, error({err})");
+        err
+    })?;
+
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+    let inserted = insert(&mut pg_client, &listings).await?;
+    info!("{inserted} of {} stock listings registered", listings.len());
+
+    if tui {
+        println!("registering listings ... done\n");
+    }
+
+    Ok(())
+}
+
+/// UPSERT each listing to `stock.symbols`, returning how many were registered.
+async fn insert(pg_client: &mut PgClient, listings: &[Listing]) -> anyhow::Result<usize> {
+    let time = std::time::Instant::now();
+
+    let transaction = pg_client.transaction().await?;
+    let query = transaction.prepare(sql::UPSERT_LISTING).await?;
+    let mut inserted = 0;
+    for listing in listings {
+        let Some(listed) = listing.resolve() else {
+            error!(
+                "unknown exchange {} for [{}] {}, set its currency, suffix, timezone & open",
+                listing.exchange, listing.symbol, listing.title
+            );
+            continue;
+        };
+
+        transaction
+            .execute(
+                &query,
+                &[
+                    &listing.symbol.to_uppercase(),
+                    &listing.title.to_uppercase(),
+                    &listing.industry,
+                    &listed.nation,
+                    &listing.exchange.to_uppercase(),
+                    &listed.currency,
+                    &listed.suffix,
+                    &listed.timezone,
+                    &listed.open,
+                ],
+            )
+            .await?;
+        trace!("[{}] {} registered", listing.symbol, listing.title);
+        inserted += 1;
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit stock listings, error({err})");
+        err
+    })?;
+
+    debug!("stock listings inserted. {}", crate::time_elapsed(time));
+
+    Ok(inserted)
+}
+
+// de
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct Listing {
+    symbol: String,
+    title: String,
+    exchange: String,
+
+    /// `stock.symbols` keys on the industry, so it can't be NULL.
+    #[serde(default)]
+    industry: String,
+
+    nation: Option<String>,
+    currency: Option<String>,
+    suffix: Option<String>,
+    timezone: Option<String>,
+    open: Option<String>,
+}
+
+/// Where & how a listing trades, resolved from its exchange & overrides.
+#[derive(Debug, PartialEq)]
+struct Listed {
+    nation: String,
+    currency: String,
+    suffix: String,
+    timezone: String,
+
+    /// Local time the session opens, at which Yahoo dates its daily prices.
+    open: NaiveTime,
+}
+
+impl Listing {
+    /// Where the listing trades, or `None` if neither the exchange is known, nor the currency,
+    /// suffix, timezone & open are set.
+    fn resolve(&self) -> Option<Listed> {
+        let exchange = EXCHANGES
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(&self.exchange))
+            .map(|(_, listed)| *listed);
+
+        let nation = match (&self.nation, exchange) {
+            (Some(nation), _) => nation.to_uppercase(),
+            (None, Some((nation, ..))) => nation.to_string(),
+            (None, None) => "??".to_string(),
+        };
+        let currency = match (&self.currency, exchange) {
+            (Some(currency), _) => currency.clone(),
+            (None, Some((_, currency, ..))) => currency.to_string(),
+            (None, None) => return None,
+        };
+        let suffix = match (&self.suffix, exchange) {
+            (Some(suffix), _) => suffix.clone(),
+            (None, Some((_, _, suffix, ..))) => suffix.to_string(),
+            (None, None) => return None,
+        };
+        let timezone = match (&self.timezone, exchange) {
+            (Some(timezone), _) => timezone.clone(),
+            (None, Some((.., timezone, _))) => timezone.to_string(),
+            (None, None) => return None,
+        };
+        let open = match (&self.open, exchange) {
+            (Some(open), _) => open.as_str(),
+            (None, Some((.., open))) => open,
+            (None, None) => return None,
+        };
+
+        Some(Listed {
+            nation,
+            currency,
+            suffix,
+            timezone,
+            open: NaiveTime::parse_from_str(open, "%H:%M").ok()?,
+        })
+    }
+}
+
+// -- TESTS --
+
+#[test]
+fn listings_resolve_from_exchange() {
+    let listings: Vec<Listing> = serde_json::from_str(
+        r#"[
+            { "symbol": "SHEL", "title": "Shell plc", "exchange": "lse" },
+            { "symbol": "SAP", "title": "SAP SE", "exchange": "XETRA", "currency": "EUR" },
+            { "symbol": "BHP", "title": "BHP Group", "exchange": "JSE" },
+            { "symbol": "NPN", "title": "Naspers", "exchange": "JSE", "nation": "za", "currency": "ZAc", "suffix": ".JO", "timezone": "Africa/Johannesburg", "open": "09:00" }
+        ]"#,
+    )
+    .expect("valid listings");
+
+    let resolved: Vec<_> = listings.iter().map(Listing::resolve).collect();
+    let listed = |nation: &str, currency: &str, suffix: &str, timezone: &str, hour: u32| {
+        Some(Listed {
+            nation: nation.to_string(),
+            currency: currency.to_string(),
+            suffix: suffix.to_string(),
+            timezone: timezone.to_string(),
+            open: NaiveTime::from_hms_opt(hour, 0, 0).expect("valid time"),
+        })
+    };
+    assert_eq!(
+        resolved,
+        vec![
+            listed("GB", "GBP", ".L", "Europe/London", 8),
+            listed("DE", "EUR", ".DE", "Europe/Berlin", 9),
+            None,
+            listed("ZA", "ZAc", ".JO", "Africa/Johannesburg", 9),
+        ]
+    );
+}
@@ -1,16 +1,53 @@
 mod sql;
 
+/// Split & dividend adjustment factors of `stock.prices`, from `stock.splits` & `stock.dividends`.
+mod adjustments;
+
+/// Benchmarks (indices, sector SPDRs & treasury ETFs), and each equity's default benchmark.
+pub mod benchmarks;
+
+/// US exchange trading calendar, i.e. weekends & holidays.
+pub mod calendar;
+
 /// Common utilities for the stock module.
 pub mod common;
 
+/// Non-US listings, e.g. London & Tokyo, registered from a listings file with their exchange,
+/// trading currency & Yahoo suffix.
+pub mod listings;
+
+/// Daily equity price providers in priority order (Yahoo! Finance, then Stooq), falling back per
+/// ticker, and the reconciliation of their closes.
+pub mod providers;
+
+/// Gap detection & repair of `stock.prices`, refetching missing trading days from Yahoo! Finance.
+pub mod repair;
+
 /// US stock information from the [SEC]; all tickers, titles and industries, as well as any metric & filings data.
 ///
 /// [SEC]: https://www.sec.gov/search-filings/edgar-application-programming-interfaces
 pub mod sec_bulks;
+pub mod sec_filings;
+pub mod sec_insiders;
+pub mod sec_institutions;
 pub mod sec_metrics;
+pub mod sec_submissions;
 pub mod sec_tickers;
 
+/// Standardized quarterly financials from `stock.metrics`; synonym concepts mapped to line items,
+/// Q4 derived from the fiscal year, and ratios computed, each with the concepts it came from.
+pub mod std_financials;
+
+/// Daily prices of US listings from Stooq's CSV downloads, the fallback to Yahoo! Finance.
+mod stooq;
+
 /// Price data collected from the Yahoo Finance API; inspiration from Python's [yfinance] library.
 ///
 /// [yfinance]: https://github.com/ranaroussi/yfinance/
 pub mod yahoo_finance;
+
+/// Option chain snapshots from Yahoo Finance, with per expiry put/call ratios & ATM IV.
+pub mod yahoo_options;
+
+/// Company profiles & key statistics from Yahoo Finance's quoteSummary, refreshed weekly.
+pub mod yahoo_profiles;
@@ -0,0 +1,350 @@
+use super::yahoo_finance::{self, Ticker};
+use super::{sql, stooq};
+use crate::http::*;
+use chrono::{Months, NaiveDate, Utc};
+use deadpool_postgres::Pool;
+use std::collections::HashMap;
+use tracing::{debug, error, info, warn};
+
+// Daily equity price providers, tried in priority order per ticker by `yahoo_finance::scrape`,
+// until one has prices. Yahoo! Finance is the primary, with its dividends & splits; fallback
+// prices never overwrite stored ones, are dated at the listing's session open (in its timezone &
+// currency, see `listings`), and are tagged with their provider in `stock.prices.provider`.
+//
+// Reconciliation compares the adjusted closes of the primary against each fallback, flagging every
+// day they disagree on by more than a (relative) tolerance in `stock.price_discrepancies`.
+
+/// Fallback prices are kept to the same history as Yahoo's (`range=10y`).
+const HISTORY: Months = Months::new(120);
+
+/// Daily equity price providers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Provider {
+    Yahoo,
+    Stooq,
+}
+
+/// Every provider, primary first.
+pub const PRIORITY: [Provider; 2] = [Provider::Yahoo, Provider::Stooq];
+
+impl Provider {
+    /// Name of the provider, as stored in `stock.prices.provider`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Provider::Yahoo => "Yahoo",
+            Provider::Stooq => "Stooq",
+        }
+    }
+
+    /// Collect & insert the daily prices of a ticker; an error (or no prices) moves on to the next
+    /// provider.
+    pub(super) async fn collect(
+        self,
+        http_client: &HttpClient,
+        pool: &Pool,
+        ticker: &Ticker,
+    ) -> anyhow::Result<()> {
+        if self == Provider::Yahoo {
+            return yahoo_finance::chart(http_client, pool, ticker).await;
+        }
+
+        let since = Utc::now().date_naive() - HISTORY;
+        let bars: Vec<Bar> = self
+            .daily(http_client, &ticker.ticker, &ticker.yahoo_suffix)
+            .await?
+            .into_iter()
+            .filter(|bar| bar.dated >= since)
+            .collect();
+        if bars.is_empty() {
+            return Err(anyhow::anyhow!("no prices found"));
+        }
+
+        insert(pool, ticker.pk, self, &bars).await
+    }
+
+    /// The daily prices of a ticker.
+    async fn daily(
+        self,
+        http_client: &HttpClient,
+        ticker: &str,
+        yahoo_suffix: &str,
+    ) -> anyhow::Result<Vec<Bar>> {
+        match self {
+            Provider::Yahoo => yahoo_finance::daily(http_client, ticker, yahoo_suffix).await,
+            Provider::Stooq => stooq::daily(http_client, ticker, yahoo_suffix).await,
+        }
+    }
+}
+
+/// A single day's prices, from any provider.
+#[derive(Debug, Clone)]
+pub(super) struct Bar {
+    pub(super) dated: NaiveDate,
+    pub(super) open: f64,
+    pub(super) high: f64,
+    pub(super) low: f64,
+    pub(super) close: f64,
+    pub(super) adj_close: f64,
+    pub(super) volume: i64,
+}
+
+/// A day on which a fallback disagrees with the primary provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discrepancy {
+    pub symbol: String,
+    pub provider: &'static str,
+    pub dated: NaiveDate,
+
+    /// The primary provider's adjusted close.
+    pub primary: f64,
+
+    /// The fallback provider's adjusted close.
+    pub fallback: f64,
+}
+
+// fallback
+// ----------------------------------------------------------------------------
+
+/// INSERT the daily prices of a fallback provider, leaving any stored price as is.
+async fn insert(
+    pool: &Pool,
+    symbol_pk: i32,
+    provider: Provider,
+    bars: &[Bar],
+) -> anyhow::Result<()> {
+    let time = std::time::Instant::now();
+    let today = Utc::now().date_naive();
+
+    let mut pg_client = pool.get().await?;
+    let transaction = pg_client.transaction().await?;
+    let query = transaction.prepare(sql::INSERT_FALLBACK_PRICE).await?;
+    for bar in bars {
+        transaction
+            .execute(
+                &query,
+                &[
+                    &symbol_pk,
+                    &bar.dated,
+                    &bar.open,
+                    &bar.high,
+                    &bar.low,
+                    &bar.close,
+                    &bar.adj_close,
+                    &bar.volume,
+                    &(bar.dated < today),
+                    &provider.name(),
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await?;
+
+    debug!(
+        "{symbol_pk} {} prices inserted. {}",
+        provider.name(),
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+// reconcile
+// ----------------------------------------------------------------------------
+
+/// Compare the primary provider's adjusted closes against every fallback's, for each symbol, and
+/// return (& record) the days they disagree on by more than `tolerance`, e.g. 0.005 (0.5%).
+pub async fn reconcile(
+    pool: &Pool,
+    tui: bool,
+    symbols: &[String],
+    tolerance: f64,
+) -> anyhow::Result<Vec<Discrepancy>> {
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    let symbols: Vec<String> = symbols.iter().map(|s| s.trim().to_uppercase()).collect();
+    let tickers: Vec<Ticker> = pg_client
+        .query(sql::SYMBOLS_BY_TICKER, &[&symbols])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.symbols, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| Ticker {
+            pk: row.get("pk"),
+            ticker: row.get("symbol"),
+            title: row.get("title"),
+            yahoo_suffix: row.get("yahoo_suffix"),
+        })
+        .collect();
+
+    let http_client = crate::std_client_build();
+    let primary = PRIORITY[0];
+    let mut report = Vec::new();
+    for ticker in &tickers {
+        let expected = match primary
+            .daily(&http_client, &ticker.ticker, &ticker.yahoo_suffix)
+            .await
+        {
+            Ok(bars) => bars,
+            Err(err) => {
+                error!(
+                    "failed to fetch {} prices for [{}] {}, error({err})",
+                    primary.name(),
+                    &ticker.ticker,
+                    &ticker.title
+                );
+                continue;
+            }
+        };
+
+        for provider in PRIORITY.iter().skip(1) {
+            let actual = match provider
+                .daily(&http_client, &ticker.ticker, &ticker.yahoo_suffix)
+                .await
+            {
+                Ok(bars) => bars,
+                Err(err) => {
+                    error!(
+                        "failed to fetch {} prices for [{}] {}, error({err})",
+                        provider.name(),
+                        &ticker.ticker,
+                        &ticker.title
+                    );
+                    continue;
+                }
+            };
+
+            let found: Vec<Discrepancy> = discrepancies(&expected, &actual, tolerance)
+                .into_iter()
+                .map(|(dated, primary, fallback)| Discrepancy {
+                    symbol: ticker.ticker.clone(),
+                    provider: provider.name(),
+                    dated,
+                    primary,
+                    fallback,
+                })
+                .collect();
+            record(&mut pg_client, ticker.pk, *provider, &found).await?;
+            info!(
+                "[{}] {} disagrees with {} on {} days",
+                &ticker.ticker,
+                &ticker.title,
+                provider.name(),
+                found.len()
+            );
+            report.extend(found);
+        }
+    }
+
+    // report every discrepancy
+    for discrepancy in &report {
+        warn!(
+            "[{}] {} {}: {} {:.4}, {} {:.4}",
+            discrepancy.symbol,
+            discrepancy.dated,
+            discrepancy.provider,
+            primary.name(),
+            discrepancy.primary,
+            discrepancy.provider,
+            discrepancy.fallback
+        );
+        if tui {
+            println!(
+                "{:<8} {} {:<8} {:>12.4} {:>12.4} {:>8.2}%",
+                discrepancy.symbol,
+                discrepancy.dated,
+                discrepancy.provider,
+                discrepancy.primary,
+                discrepancy.fallback,
+                (discrepancy.fallback / discrepancy.primary - 1.0) * 100.0
+            );
+        }
+    }
+
+    Ok(report)
+}
+
+/// Replace the recorded discrepancies of a symbol & provider.
+async fn record(
+    pg_client: &mut PgClient,
+    symbol_pk: i32,
+    provider: Provider,
+    found: &[Discrepancy],
+) -> anyhow::Result<()> {
+    let transaction = pg_client.transaction().await?;
+    transaction
+        .execute(
+            sql::DELETE_PRICE_DISCREPANCIES,
+            &[&symbol_pk, &provider.name()],
+        )
+        .await?;
+    for discrepancy in found {
+        transaction
+            .execute(
+                sql::INSERT_PRICE_DISCREPANCY,
+                &[
+                    &symbol_pk,
+                    &discrepancy.dated,
+                    &provider.name(),
+                    &discrepancy.primary,
+                    &discrepancy.fallback,
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit stock.price_discrepancies, error({err})");
+        err
+    })?;
+
+    Ok(())
+}
+
+/// Every day both providers have an adjusted close for, that differ by more than `tolerance`
+/// relative to the primary's; (date, primary, fallback).
+fn discrepancies(primary: &[Bar], fallback: &[Bar], tolerance: f64) -> Vec<(NaiveDate, f64, f64)> {
+    let fallback: HashMap<NaiveDate, f64> = fallback
+        .iter()
+        .map(|bar| (bar.dated, bar.adj_close))
+        .collect();
+
+    primary
+        .iter()
+        .filter_map(|bar| {
+            let other = *fallback.get(&bar.dated)?;
+            ((other - bar.adj_close).abs() > tolerance * bar.adj_close.abs()).then_some((
+                bar.dated,
+                bar.adj_close,
+                other,
+            ))
+        })
+        .collect()
+}
+
+// -- TESTS --
+
+#[test]
+fn discrepancies_beyond_tolerance() {
+    let bar = |day: u32, adj_close: f64| Bar {
+        dated: NaiveDate::from_ymd_opt(2024, 1, day).expect("valid date"),
+        open: adj_close,
+        high: adj_close,
+        low: adj_close,
+        close: adj_close,
+        adj_close,
+        volume: 0,
+    };
+    let primary = [bar(2, 100.0), bar(3, 101.0), bar(4, 102.0), bar(5, 103.0)];
+
+    // the 3rd is within 0.5%, the 5th is missing from the fallback
+    let fallback = [bar(2, 100.0), bar(3, 101.4), bar(4, 98.0)];
+
+    assert_eq!(
+        discrepancies(&primary, &fallback, 0.005),
+        vec![(bar(4, 0.0).dated, 102.0, 98.0)]
+    );
+}
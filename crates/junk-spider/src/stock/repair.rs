@@ -0,0 +1,266 @@
+use super::{adjustments, calendar, sql, yahoo_finance};
+use crate::http::*;
+use chrono::{Days, NaiveDate};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use std::collections::HashSet;
+use std::time::Duration;
+use tracing::{debug, error, info, trace, warn};
+
+// Stocks only trade on exchange days, so a gap is any trading day (see `calendar`) missing between
+// two stored prices of a symbol; gaps are refetched from Yahoo! Finance with `period1` & `period2`.
+// The calendar is the US exchanges', so only their listings are scanned, until other exchanges'
+// calendars exist.
+//
+// Whatever is still missing afterwards can't be filled by the source (e.g. trading halts), and is
+// recorded in `stock.price_gaps`, so that later runs don't refetch it (unless retried).
+
+const CONCURRENCY: usize = 4;
+const INTERVAL_PK: i16 = 3;
+
+/// A run of missing trading days, between two stored prices.
+#[derive(Debug, Clone)]
+pub struct Gap {
+    pub symbol_pk: i32,
+    pub symbol: String,
+    pub title: String,
+    pub yahoo_suffix: String,
+
+    /// The first missing trading day.
+    pub start: NaiveDate,
+
+    /// The last missing trading day.
+    pub end: NaiveDate,
+
+    /// Number of missing trading days.
+    pub missing: i32,
+}
+
+impl Gap {
+    /// Whether `other` lies within this gap, i.e. what's left of it after a partial refetch.
+    fn contains(&self, other: &Gap) -> bool {
+        self.symbol_pk == other.symbol_pk && self.start <= other.start && other.end <= self.end
+    }
+}
+
+// core
+// ----------------------------------------------------------------------------
+
+/// Scan `stock.prices` for missing trading days, refetch them from Yahoo! Finance, and return the
+/// gaps it couldn't fill.
+///
+/// Gaps already known to be unfillable are skipped, unless `retry` is set.
+pub async fn fill(pool: &Pool, tui: bool, retry: bool) -> anyhow::Result<Vec<Gap>> {
+    if tui {
+        println!(
+            "{bar}\n{:^40}\n{bar}",
+            "Stock Price Repair",
+            bar = "=".repeat(40)
+        )
+    }
+
+    // 1. scan for gaps
+    info!("scanning stock.prices for gaps ...");
+    let mut gaps = scan(pool).await?;
+    if !retry {
+        let known = known(pool).await?;
+        gaps.retain(|gap| !known.contains(&(gap.symbol_pk, gap.start)));
+    }
+    info!("{} gaps found in stock.prices", gaps.len());
+    if gaps.is_empty() {
+        if tui {
+            println!("no gaps found\n");
+        }
+        return Ok(Vec::new());
+    }
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(gaps.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    // 2. refetch every gap
+    let http_client = crate::std_client_build();
+    stream::iter(&gaps)
+        .for_each_concurrent(CONCURRENCY, |gap| {
+            let http_client = &http_client;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!(
+                        "refetching [{}] {}, {} - {}",
+                        gap.symbol, gap.title, gap.start, gap.end
+                    ),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                match yahoo_finance::refetch(http_client, pool, gap).await {
+                    Ok(_) => {
+                        trace!("refetched [{}] {}", gap.symbol, gap.title);
+
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            "failed to refetch [{}] {}, {} - {}, error({err})",
+                            gap.symbol, gap.title, gap.start, gap.end
+                        );
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+    }
+
+    // 3. rescan, whatever remains of the refetched gaps can't be filled by the source
+    let unfilled: Vec<Gap> = scan(pool)
+        .await?
+        .into_iter()
+        .filter(|remaining| gaps.iter().any(|gap| gap.contains(remaining)))
+        .collect();
+
+    let mut pg_client = pool.get().await?;
+    record(&mut pg_client, &gaps, &unfilled).await?;
+    drop(pg_client);
+
+    // refetched prices before a symbol's latest event need its factors recomputed
+    adjustments::readjust(pool).await?;
+
+    // 4. report the unfillable gaps
+    for gap in &unfilled {
+        warn!(
+            "[{}] {} is missing {} trading days, {} - {}",
+            gap.symbol, gap.title, gap.missing, gap.start, gap.end
+        );
+        if tui {
+            println!(
+                "{:<8} {:<32.32} {:>4} {} - {}",
+                gap.symbol, gap.title, gap.missing, gap.start, gap.end
+            );
+        }
+    }
+    info!(
+        "{} stock price gaps refetched, {} remain unfilled",
+        gaps.len(),
+        unfilled.len()
+    );
+
+    Ok(unfilled)
+}
+
+/// Every gap in `stock.prices`, of the US listings.
+async fn scan(pool: &Pool) -> anyhow::Result<Vec<Gap>> {
+    let time = std::time::Instant::now();
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    let gaps = pg_client
+        .query(sql::PRICE_GAPS, &[&INTERVAL_PK])
+        .await
+        .map_err(|err| {
+            error!("failed to scan stock.prices for gaps, error({err})");
+            err
+        })?
+        .into_iter()
+        .filter_map(|row| {
+            let prev: NaiveDate = row.get("prev_dated");
+            let next: NaiveDate = row.get("dated");
+
+            // only trading days count as missing, e.g. not holidays
+            let missing = calendar::trading_days(prev + Days::new(1), next - Days::new(1));
+            Some(Gap {
+                symbol_pk: row.get("symbol_pk"),
+                symbol: row.get("symbol"),
+                title: row.get("title"),
+                yahoo_suffix: row.get("yahoo_suffix"),
+                start: *missing.first()?,
+                end: *missing.last()?,
+                missing: missing.len() as i32,
+            })
+        })
+        .collect();
+    debug!("stock.prices scanned. {}", crate::time_elapsed(time));
+
+    Ok(gaps)
+}
+
+/// The `(symbol_pk, gap_start)` of every gap already known to be unfillable.
+async fn known(pool: &Pool) -> anyhow::Result<HashSet<(i32, NaiveDate)>> {
+    let pg_client = pool.get().await?;
+    let known = pg_client
+        .query(sql::KNOWN_PRICE_GAPS, &[&INTERVAL_PK])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.price_gaps, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| (row.get("symbol_pk"), row.get("gap_start")))
+        .collect();
+
+    Ok(known)
+}
+
+/// Forget every refetched gap, then record the ones that remain unfilled.
+async fn record(pg_client: &mut PgClient, gaps: &[Gap], unfilled: &[Gap]) -> anyhow::Result<()> {
+    let transaction = pg_client.transaction().await?;
+    for gap in gaps {
+        transaction
+            .execute(
+                sql::DELETE_PRICE_GAP,
+                &[&gap.symbol_pk, &INTERVAL_PK, &gap.start, &gap.end],
+            )
+            .await?;
+    }
+    for gap in unfilled {
+        transaction
+            .execute(
+                sql::UPSERT_PRICE_GAP,
+                &[
+                    &gap.symbol_pk,
+                    &INTERVAL_PK,
+                    &gap.start,
+                    &gap.end,
+                    &gap.missing,
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await.map_err(|err| {
+        error!("failed to commit stock.price_gaps, error({err})");
+        err
+    })?;
+
+    Ok(())
+}
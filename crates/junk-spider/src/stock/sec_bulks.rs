@@ -1,11 +1,18 @@
 use crate::fs::{download_file, unzip};
-use tracing::{debug, error};
+use crate::http::*;
+use crate::stock::sql;
+use deadpool_postgres::Pool;
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use tracing::{debug, error, info};
 
-// 1. check if downloads are necessary
+// 1. check if downloads are necessary; i.e. a conditional HEAD request, with the ETag &
+//    Last-Modified of the last download (`stock.sec_downloads`), is anything but 304 Not Modified,
+//    or the files were never unzipped
 // 2. download if necessary
 //     a. metrics
 //     b. submissions
-// 3. unzip the files; and delete the zips
+// 3. unzip the files; delete the zips, and store their ETag & Last-Modified
 
 const METRICS_URL: &'static str =
     "https://www.sec.gov/Archives/edgar/daily-index/xbrl/companyfacts.zip";
@@ -14,11 +21,10 @@ const SUBMISSIONS_URL: &'static str =
     "https://www.sec.gov/Archives/edgar/daily-index/bulkdata/submissions.zip";
 
 /// Scrape the SEC website for the latest company metrics and filings metadata.
-pub async fn scrape(tui: bool) -> anyhow::Result<()> {
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
     let http_client = crate::std_client_build();
 
     // download companyfacts.zip (the metrics)
-    debug!("downloading metrics.zip");
     if tui {
         println!(
             "{bar}\n{name:^40}\n{bar}",
@@ -26,32 +32,11 @@ pub async fn scrape(tui: bool) -> anyhow::Result<()> {
             name = "SEC companyfacts.zip"
         );
     }
-    download_file(&http_client, METRICS_URL, "./buffer/metrics.zip", tui)
-        .await
-        .map_err(|err| {
-            error!("failed to download metrics.zip: {:?}", err);
-            err
-        })?;
-    debug!("metrics.zip downloaded to {}", "./buffer/metrics.zip");
-
-    debug!("unzipping metrics.zip");
-    unzip("./buffer/metrics.zip", "./buffer/metrics", tui)
-        .await
-        .map_err(|err| {
-            error!("failed to unzip metrics.zip: {:?}", err);
-            err
-        })?;
-    debug!(
-        "metrics.zip unzipped successfully to {}",
-        "./buffer/metrics"
-    );
-
-    if tui {
+    if bulk(&http_client, pool, METRICS_URL, "metrics", tui).await? && tui {
         println!("metrics downloaded\n")
     }
 
     // download submissions.zip (the filings metadata)
-    debug!("downloading submissions.zip");
     if tui {
         println!(
             "{bar}\n{name:^40}\n{bar}",
@@ -59,46 +44,173 @@ pub async fn scrape(tui: bool) -> anyhow::Result<()> {
             name = "SEC submissions.zip"
         )
     }
-    download_file(
-        &http_client,
-        SUBMISSIONS_URL,
-        "./buffer/submissions.zip",
-        tui,
-    )
-    .await
-    .map_err(|err| {
-        error!("failed to download submissions.zip: {:?}", err);
+    if bulk(&http_client, pool, SUBMISSIONS_URL, "submissions", tui).await? && tui {
+        println!("submissions downloaded\n");
+    }
+
+    Ok(())
+}
+
+/// Download & unzip a bulk file to "./buffer/{name}", unless it's unchanged since the last
+/// download; returns whether it was downloaded.
+async fn bulk(
+    http_client: &HttpClient,
+    pool: &Pool,
+    url: &str,
+    name: &str,
+    tui: bool,
+) -> anyhow::Result<bool> {
+    let zip = format!("./buffer/{name}.zip");
+    let dir = format!("./buffer/{name}");
+
+    // the validators of the last download, if its files are still unzipped
+    let pg_client = pool.get().await?;
+    let last = match std::path::Path::new(&dir).exists() {
+        true => pg_client
+            .query_opt(sql::SEC_DOWNLOAD, &[&url])
+            .await
+            .map_err(|err| {
+                error!("failed to fetch stock.sec_downloads of {url}, error({err})");
+                err
+            })?
+            .map(|row| {
+                let etag: Option<String> = row.get("etag");
+                let last_modified: Option<String> = row.get("last_modified");
+                (etag, last_modified)
+            }),
+        false => None,
+    };
+    drop(pg_client);
+
+    debug!("checking {name}.zip for changes");
+    let mut request = http_client.head(url);
+    if let Some((etag, last_modified)) = &last {
+        if let Some(etag) = etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let response = request.send().await.map_err(|err| {
+        error!("failed to check {name}.zip for changes: {:?}", err);
         err
     })?;
-    debug!(
-        "submissions.zip downloaded to {}",
-        "./buffer/submissions.zip"
-    );
+    let validators = validators(response.headers());
+    if unchanged(response.status(), last.as_ref(), &validators) {
+        info!("{name}.zip unchanged since its last download, skipping");
+        if tui {
+            println!("{name}.zip unchanged, skipping\n");
+        }
+        return Ok(false);
+    }
+    response.error_for_status()?;
 
-    debug!("unzipping submissions.zip");
-    unzip("./buffer/submissions.zip", "./buffer/submissions", tui)
+    debug!("downloading {name}.zip");
+    download_file(http_client, url, &zip, tui)
         .await
         .map_err(|err| {
-            error!("failed to unzip submissions.zip: {:?}", err);
+            error!("failed to download {name}.zip: {:?}", err);
             err
         })?;
-    debug!(
-        "submissions.zip unzipped successfully to {}",
-        "./buffer/submissions"
-    );
+    debug!("{name}.zip downloaded to {zip}");
 
-    if tui {
-        println!("submissions downloaded\n");
-    }
+    debug!("unzipping {name}.zip");
+    unzip(&zip, &dir, tui).await.map_err(|err| {
+        error!("failed to unzip {name}.zip: {:?}", err);
+        err
+    })?;
+    debug!("{name}.zip unzipped successfully to {dir}");
 
-    // clean up the zips
-    debug!("deleting metrics.zip and submissions.zip");
-    tokio::fs::remove_file("./buffer/metrics.zip").await?;
-    tokio::fs::remove_file("./buffer/submissions.zip").await?;
+    // clean up the zip
+    debug!("deleting {name}.zip");
+    tokio::fs::remove_file(&zip).await?;
 
-    if tui {
-        println!("metrics.zip and submissions.zip deleted\n");
+    // only stored once unzipped, so a failed download is retried
+    let (etag, last_modified) = validators;
+    let pg_client = pool.get().await?;
+    pg_client
+        .execute(sql::UPSERT_SEC_DOWNLOAD, &[&url, &etag, &last_modified])
+        .await
+        .map_err(|err| {
+            error!("failed to upsert stock.sec_downloads of {url}, error({err})");
+            err
+        })?;
+
+    Ok(true)
+}
+
+/// The ETag & Last-Modified of a response.
+fn validators(headers: &HeaderMap) -> (Option<String>, Option<String>) {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+
+    (header(ETAG), header(LAST_MODIFIED))
+}
+
+/// Whether a bulk file is unchanged since its last download; i.e. 304 Not Modified, else (for a
+/// server ignoring conditional requests) the same validators as the last download.
+fn unchanged(
+    status: StatusCode,
+    last: Option<&(Option<String>, Option<String>)>,
+    validators: &(Option<String>, Option<String>),
+) -> bool {
+    match last {
+        None => false,
+        Some(_) if status == StatusCode::NOT_MODIFIED => true,
+        Some((etag, last_modified)) => match (etag, &validators.0) {
+            (Some(etag), Some(current)) => etag == current,
+            _ => last_modified.is_some() && last_modified == &validators.1,
+        },
     }
+}
 
-    Ok(())
+// -- TESTS --
+
+#[test]
+fn bulk_unchanged_by_validators() {
+    let etag = |etag: &str| (Some(etag.to_string()), None);
+    let modified = |date: &str| (None, Some(date.to_string()));
+
+    // never downloaded (or no longer unzipped)
+    assert!(!unchanged(StatusCode::NOT_MODIFIED, None, &etag("\"a\"")));
+
+    assert!(unchanged(
+        StatusCode::NOT_MODIFIED,
+        Some(&etag("\"a\"")),
+        &(None, None)
+    ));
+    assert!(unchanged(
+        StatusCode::OK,
+        Some(&etag("\"a\"")),
+        &etag("\"a\"")
+    ));
+    assert!(!unchanged(
+        StatusCode::OK,
+        Some(&etag("\"a\"")),
+        &etag("\"b\"")
+    ));
+
+    let date = "Fri, 16 Oct 2026 06:12:41 GMT";
+    assert!(unchanged(
+        StatusCode::OK,
+        Some(&modified(date)),
+        &modified(date)
+    ));
+    assert!(!unchanged(
+        StatusCode::OK,
+        Some(&modified(date)),
+        &modified("Sat, 17 Oct 2026 06:09:02 GMT")
+    ));
+
+    // neither validator was ever sent
+    assert!(!unchanged(
+        StatusCode::OK,
+        Some(&(None, None)),
+        &(None, None)
+    ));
 }
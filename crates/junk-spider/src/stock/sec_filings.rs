@@ -0,0 +1,343 @@
+use crate::http::*;
+use crate::stock::common::SecThrottle;
+use crate::stock::sql;
+use chrono::{Months, NaiveDate, Utc};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use std::time::Duration;
+use tracing::{debug, error, info, trace};
+
+// The primary documents of `stock.filing_index`, for the forms of `FORMS`, downloaded from the SEC
+// as plain text to `stock.filings`; its `content_ts` is the full-text search vector of `search`.
+//
+// Only filings since `SEC_FILINGS_SINCE` (YYYY-MM-DD), else of the last year, that aren't stored
+// yet are downloaded, all within the SEC's 10 requests per second.
+
+/// Form types downloaded.
+pub const FORMS: [&str; 3] = ["10-K", "10-Q", "8-K"];
+
+const LOOKBACK: Months = Months::new(12);
+
+/// Requests are bound by the throttle; this only keeps a few in flight.
+const CONCURRENCY: usize = 9;
+
+/// Elements whose content is never text, e.g. the hidden XBRL header of inline XBRL documents.
+const HIDDEN: [&str; 4] = ["head", "script", "style", "ix:header"];
+
+#[derive(Debug)]
+struct Pending {
+    symbol_pk: i32,
+    symbol: String,
+    accession: String,
+    form: String,
+    filing_date: NaiveDate,
+    url: String,
+}
+
+/// A filing matching a full-text search.
+#[derive(Debug)]
+pub struct Hit {
+    pub symbol: String,
+    pub title: String,
+    pub form: String,
+    pub dated: NaiveDate,
+    pub url: String,
+    pub rank: f32,
+
+    /// Fragments of the content matching the query.
+    pub headline: String,
+}
+
+/// Download every pending filing of `FORMS`, and store its text.
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{name:^40}\n{bar}",
+            bar = "=".repeat(40),
+            name = "SEC Filings"
+        );
+    }
+
+    let since = match var("SEC_FILINGS_SINCE") {
+        Ok(since) => match NaiveDate::parse_from_str(&since, "%Y-%m-%d") {
+            Ok(since) => since,
+            Err(err) => {
+                error!("invalid SEC_FILINGS_SINCE {since}, expected YYYY-MM-DD, error({err})");
+                return Err(err.into());
+            }
+        },
+        Err(_) => Utc::now().date_naive() - LOOKBACK,
+    };
+    let forms: Vec<&str> = FORMS.to_vec();
+    let pending: Vec<Pending> = pg_client
+        .query(sql::PENDING_FILINGS, &[&forms, &since])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch pending stock.filing_index, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| Pending {
+            symbol_pk: row.get("pk"),
+            symbol: row.get("symbol"),
+            accession: row.get("accession"),
+            form: row.get("form"),
+            filing_date: row.get("filing_date"),
+            url: row.get("url"),
+        })
+        .collect();
+
+    drop(pg_client);
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(pending.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    info!("downloading {} filings since {since} ...", pending.len());
+    let http_client = crate::std_client_build();
+    let throttle = SecThrottle::new();
+    stream::iter(&pending)
+        .for_each_concurrent(CONCURRENCY, |filing| {
+            let http_client = &http_client;
+            let throttle = &throttle;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!(
+                        "downloading [{}] {} {}",
+                        &filing.symbol, &filing.form, &filing.filing_date
+                    ),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                throttle.wait().await;
+                match download(http_client, pool, filing).await {
+                    Ok(_) => {
+                        trace!(
+                            "[{}] {} {} stored",
+                            &filing.symbol,
+                            &filing.form,
+                            &filing.accession
+                        );
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            "failed to download [{}] {} {}, error({err})",
+                            &filing.symbol, &filing.form, &filing.url
+                        );
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+                if tui {
+                    total.expect("totalbar should have unwrapped").inc(1);
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+        println!("downloading filings ... done\n");
+    }
+
+    Ok(())
+}
+
+/// Fetch the primary document of a filing, and INSERT it as text; the filename is its accession
+/// number.
+async fn download(http_client: &HttpClient, pool: &Pool, filing: &Pending) -> anyhow::Result<()> {
+    let time = std::time::Instant::now();
+
+    let html = http_client
+        .get(&filing.url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let content = to_text(&html);
+
+    let pg_client = pool.get().await?;
+    pg_client
+        .execute(
+            sql::INSERT_FILING,
+            &[
+                &filing.symbol_pk,
+                &filing.filing_date,
+                &filing.accession,
+                &filing.form,
+                &filing.url,
+                &content,
+            ],
+        )
+        .await?;
+
+    debug!(
+        "{} ({} chars) stored. {}",
+        &filing.accession,
+        content.len(),
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+/// Full-text search of the stored filings, optionally of a single form type, by rank.
+pub async fn search(
+    pool: &Pool,
+    query: &str,
+    form: Option<&str>,
+    limit: i64,
+) -> anyhow::Result<Vec<Hit>> {
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    let hits = pg_client
+        .query(sql::SEARCH_FILINGS, &[&query, &form, &limit])
+        .await
+        .map_err(|err| {
+            error!("failed to search stock.filings, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| Hit {
+            symbol: row.get("symbol"),
+            title: row.get("title"),
+            form: row.get("filetype"),
+            dated: row.get("dated"),
+            url: row.get("url"),
+            rank: row.get("rank"),
+            headline: row.get("headline"),
+        })
+        .collect();
+
+    Ok(hits)
+}
+
+/// Strip an HTML document to its text; hidden elements & tags are dropped, entities decoded and
+/// whitespace collapsed.
+fn to_text(html: &str) -> String {
+    // ASCII lowercasing keeps the byte offsets of `html`
+    let lower = html.to_ascii_lowercase();
+
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut i = 0;
+    while let Some(start) = lower[i..].find('<').map(|start| i + start) {
+        text.push_str(&html[i..start]);
+        text.push(' ');
+
+        // skip the hidden element entirely, else just the tag
+        let hidden = HIDDEN.iter().find(|tag| {
+            lower[start + 1..].starts_with(*tag)
+                && lower[start + 1 + tag.len()..].starts_with(['>', ' ', '\t', '\r', '\n'])
+        });
+        i = match hidden {
+            Some(tag) => lower[start..]
+                .find(&format!("</{tag}>"))
+                .map(|end| start + end + tag.len() + 3),
+            None => lower[start..].find('>').map(|end| start + end + 1),
+        }
+        .unwrap_or(html.len());
+    }
+    text.push_str(&html[i..]);
+
+    decode_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Decode the named entities common to filings, and every numeric entity.
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // entities are short, e.g. "&#8217;"
+        let entity = rest[1..]
+            .char_indices()
+            .take(10)
+            .find(|(_, c)| *c == ';')
+            .map(|(end, _)| &rest[1..end + 1]);
+        let char = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => match entity.strip_prefix('#') {
+                Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16)
+                    .ok()
+                    .and_then(char::from_u32),
+                Some(dec) => dec.parse().ok().and_then(char::from_u32),
+                None => None,
+            },
+        });
+
+        match (entity, char) {
+            (Some(entity), Some(char)) => {
+                // non-breaking spaces are collapsed with every other whitespace
+                decoded.push(if char == '\u{a0}' { ' ' } else { char });
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+// -- TESTS --
+
+#[test]
+fn filings_strip_to_text() {
+    let html = r#"<html><head><title>10-K</title></head><body>
+        <ix:header><ix:hidden>dei:AmendmentFlag false</ix:hidden></ix:header>
+        <div style="font-weight:bold">Goodwill&#160;impairment</div>
+        <script type="text/javascript">var x = 1 < 2;</script>
+        <p>AT&amp;T&#8217;s <b>goodwill</b> was tested &amp; R&D &#x2014; no impairment.</p>
+        </body></html>"#;
+
+    assert_eq!(
+        to_text(html),
+        "Goodwill impairment AT&T\u{2019}s goodwill was tested & R&D \u{2014} no impairment."
+    );
+}
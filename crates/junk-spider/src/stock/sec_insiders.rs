@@ -0,0 +1,630 @@
+use crate::http::*;
+use crate::stock::common::SecThrottle;
+use crate::stock::sql;
+use chrono::{Months, NaiveDate, Utc};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{debug, error, info, trace};
+
+// Insider ownership from the SEC's Forms 3, 4 & 5 of `stock.filing_index`, parsed from each
+// filing's XML to `stock.insider_transactions`, linked to `stock.symbols` by the issuer's CIK.
+//
+// The index's primary document is the XSL rendering, e.g. `xslF345X05/wk-form4_1704240000.xml`,
+// of the raw XML document in the filing's folder, i.e. `wk-form4_1704240000.xml`:
+//
+// <ownershipDocument>
+//     <issuer><issuerCik>0000320193</issuerCik>...</issuer>
+//     <reportingOwner>
+//         <reportingOwnerId><rptOwnerCik>...</rptOwnerCik><rptOwnerName>...</rptOwnerName>
+//         <reportingOwnerRelationship><isOfficer>1</isOfficer><officerTitle>...</officerTitle>
+//     </reportingOwner>
+//     <nonDerivativeTable>
+//         <nonDerivativeTransaction>...</nonDerivativeTransaction>
+//         <nonDerivativeHolding>...</nonDerivativeHolding>
+//     </nonDerivativeTable>
+//     <derivativeTable>...</derivativeTable>
+// </ownershipDocument>
+//
+// Holdings (e.g. every Form 3 line) are stored without a transaction code, dated to the period of
+// the report. Every filing parsed is tracked in `stock.insider_filings`, so filings without any
+// lines aren't fetched again.
+
+/// Form types parsed, & their amendments.
+const FORMS: [&str; 6] = ["3", "4", "5", "3/A", "4/A", "5/A"];
+
+const LOOKBACK: Months = Months::new(12);
+
+/// Requests are bound by the throttle; this only keeps a few in flight.
+const CONCURRENCY: usize = 9;
+
+#[derive(Debug)]
+struct Pending {
+    accession: String,
+    form: String,
+    filing_date: NaiveDate,
+    url: String,
+}
+
+/// Parse the ownership documents of every pending Form 3, 4 & 5 (since `SEC_INSIDERS_SINCE`, as
+/// YYYY-MM-DD, else of the last year).
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{name:^40}\n{bar}",
+            bar = "=".repeat(40),
+            name = "SEC Insider Transactions"
+        );
+    }
+
+    let since = match var("SEC_INSIDERS_SINCE") {
+        Ok(since) => match NaiveDate::parse_from_str(&since, "%Y-%m-%d") {
+            Ok(since) => since,
+            Err(err) => {
+                error!("invalid SEC_INSIDERS_SINCE {since}, expected YYYY-MM-DD, error({err})");
+                return Err(err.into());
+            }
+        },
+        Err(_) => Utc::now().date_naive() - LOOKBACK,
+    };
+    let forms: Vec<&str> = FORMS.to_vec();
+    let pending: Vec<Pending> = pg_client
+        .query(sql::PENDING_INSIDER_FILINGS, &[&forms, &since])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch pending insider filings, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| Pending {
+            accession: row.get("accession"),
+            form: row.get("form"),
+            filing_date: row.get("filing_date"),
+            url: xml_url(
+                &row.get::<_, String>("url"),
+                &row.get::<_, String>("primary_document"),
+            ),
+        })
+        .collect();
+
+    drop(pg_client);
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(pending.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    info!(
+        "parsing {} insider filings since {since} ...",
+        pending.len()
+    );
+    let http_client = crate::std_client_build();
+    let throttle = SecThrottle::new();
+    stream::iter(&pending)
+        .for_each_concurrent(CONCURRENCY, |filing| {
+            let http_client = &http_client;
+            let throttle = &throttle;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("parsing Form {} {}", &filing.form, &filing.accession),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                throttle.wait().await;
+                match collect(http_client, pool, filing).await {
+                    Ok(lines) => {
+                        trace!("{lines} lines of {} inserted", &filing.accession);
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            "failed to parse Form {} {}, error({err})",
+                            &filing.form, &filing.url
+                        );
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+                if tui {
+                    total.expect("totalbar should have unwrapped").inc(1);
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+        println!("parsing insider filings ... done\n");
+    }
+
+    Ok(())
+}
+
+/// The raw XML of an ownership document, from its XSL rendering.
+fn xml_url(url: &str, primary_document: &str) -> String {
+    let folder = url.strip_suffix(primary_document).unwrap_or(url);
+    let file = primary_document
+        .rsplit('/')
+        .next()
+        .unwrap_or(primary_document);
+    format!("{folder}{file}")
+}
+
+/// Fetch & parse an ownership document, and INSERT each of its lines; returns the number of lines.
+async fn collect(http_client: &HttpClient, pool: &Pool, filing: &Pending) -> anyhow::Result<usize> {
+    let time = std::time::Instant::now();
+
+    let xml = http_client
+        .get(&filing.url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let document: Document = quick_xml::de::from_str(&xml)?;
+    let lines = document.lines();
+
+    let mut pg_client = pool.get().await?;
+    let transaction = pg_client.transaction().await?;
+    let query = transaction.prepare(sql::INSERT_INSIDER_TRANSACTION).await?;
+    for (seq, line) in lines.iter().enumerate() {
+        transaction
+            .execute(
+                &query,
+                &[
+                    &filing.accession,
+                    &(seq as i16),
+                    &line.issuer_cik,
+                    &line.owner_cik,
+                    &line.owner,
+                    &line.relationship,
+                    &line.security,
+                    &line.derivative,
+                    &line.code,
+                    &line.dated,
+                    &line.shares,
+                    &line.price,
+                    &line.acquired,
+                    &line.shares_after,
+                    &line.direct,
+                ],
+            )
+            .await?;
+    }
+    transaction
+        .execute(
+            sql::INSERT_INSIDER_FILING,
+            &[
+                &filing.accession,
+                &document.issuer.cik,
+                &filing.form,
+                &filing.filing_date,
+                &(lines.len() as i32),
+            ],
+        )
+        .await?;
+    transaction.commit().await?;
+
+    debug!(
+        "{} insider lines inserted. {}",
+        &filing.accession,
+        crate::time_elapsed(time)
+    );
+
+    Ok(lines.len())
+}
+
+// de
+// ----------------------------------------------------------------------------
+
+/// A single transaction (or holding) of an ownership document.
+#[derive(Debug, PartialEq)]
+struct Line {
+    issuer_cik: String,
+    owner_cik: String,
+
+    /// Every reporting owner of a joint filing, separated by "; ".
+    owner: String,
+
+    /// e.g. "Director, Officer (Chief Executive Officer)".
+    relationship: String,
+    security: String,
+    derivative: bool,
+
+    /// The transaction code, e.g. "P" (purchase) or "S" (sale); `None` for holdings.
+    code: Option<String>,
+    dated: Option<NaiveDate>,
+    shares: Option<f64>,
+    price: Option<f64>,
+
+    /// Shares acquired (A), else disposed (D).
+    acquired: Option<bool>,
+    shares_after: Option<f64>,
+
+    /// Owned directly (D), else indirectly (I).
+    direct: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    period_of_report: Option<String>,
+    issuer: Issuer,
+
+    #[serde(default)]
+    reporting_owner: Vec<Owner>,
+
+    non_derivative_table: Option<Table>,
+    derivative_table: Option<Table>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issuer {
+    #[serde(rename = "issuerCik")]
+    cik: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Owner {
+    reporting_owner_id: OwnerId,
+    reporting_owner_relationship: Option<Relationship>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OwnerId {
+    rpt_owner_cik: String,
+    rpt_owner_name: Option<String>,
+}
+
+/// Flags are "1" or "true".
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Relationship {
+    is_director: Option<String>,
+    is_officer: Option<String>,
+    is_ten_percent_owner: Option<String>,
+    is_other: Option<String>,
+    officer_title: Option<String>,
+    other_text: Option<String>,
+}
+
+/// Both tables' transactions & holdings share the fields parsed.
+#[derive(Debug, Deserialize)]
+struct Table {
+    #[serde(
+        default,
+        alias = "derivativeTransaction",
+        rename = "nonDerivativeTransaction"
+    )]
+    transactions: Vec<Entry>,
+
+    #[serde(default, alias = "derivativeHolding", rename = "nonDerivativeHolding")]
+    holdings: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    security_title: Option<Value>,
+    transaction_date: Option<Value>,
+    transaction_coding: Option<Coding>,
+    transaction_amounts: Option<Amounts>,
+    post_transaction_amounts: Option<PostAmounts>,
+    ownership_nature: Option<Nature>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Coding {
+    transaction_code: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Amounts {
+    transaction_shares: Option<Value>,
+    transaction_price_per_share: Option<Value>,
+    transaction_acquired_disposed_code: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostAmounts {
+    shares_owned_following_transaction: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Nature {
+    direct_or_indirect_ownership: Option<Value>,
+}
+
+/// Values are wrapped, e.g. `<transactionShares><value>100</value></transactionShares>`, and may be
+/// left out for a footnote.
+#[derive(Debug, Deserialize)]
+struct Value {
+    value: Option<String>,
+}
+
+fn text(value: &Option<Value>) -> Option<&str> {
+    value
+        .as_ref()
+        .and_then(|value| value.value.as_deref())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+fn number(value: &Option<Value>) -> Option<f64> {
+    text(value).and_then(|text| text.replace(',', "").parse().ok())
+}
+
+/// Dates may carry a timezone, e.g. "2024-01-02-05:00".
+fn date(text: Option<&str>) -> Option<NaiveDate> {
+    text.and_then(|text| NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok())
+}
+
+fn flag(flag: &Option<String>) -> bool {
+    matches!(flag.as_deref().map(str::trim), Some("1" | "true"))
+}
+
+impl Relationship {
+    fn describe(&self) -> Vec<String> {
+        let mut roles = Vec::new();
+        if flag(&self.is_director) {
+            roles.push("Director".to_string());
+        }
+        if flag(&self.is_officer) {
+            match self.officer_title.as_deref().map(str::trim) {
+                Some(title) if !title.is_empty() => roles.push(format!("Officer ({title})")),
+                _ => roles.push("Officer".to_string()),
+            }
+        }
+        if flag(&self.is_ten_percent_owner) {
+            roles.push("10% Owner".to_string());
+        }
+        if flag(&self.is_other) {
+            match self.other_text.as_deref().map(str::trim) {
+                Some(text) if !text.is_empty() => roles.push(format!("Other ({text})")),
+                _ => roles.push("Other".to_string()),
+            }
+        }
+        roles
+    }
+}
+
+impl Document {
+    /// Every transaction, then every holding, of both tables.
+    fn lines(&self) -> Vec<Line> {
+        let owner_cik = self
+            .reporting_owner
+            .first()
+            .map(|owner| owner.reporting_owner_id.rpt_owner_cik.clone())
+            .unwrap_or_default();
+        let owner = self
+            .reporting_owner
+            .iter()
+            .filter_map(|owner| owner.reporting_owner_id.rpt_owner_name.as_deref())
+            .collect::<Vec<&str>>()
+            .join("; ");
+        let mut relationship: Vec<String> = Vec::new();
+        for role in self
+            .reporting_owner
+            .iter()
+            .filter_map(|owner| owner.reporting_owner_relationship.as_ref())
+            .flat_map(Relationship::describe)
+        {
+            if !relationship.contains(&role) {
+                relationship.push(role);
+            }
+        }
+        let relationship = relationship.join(", ");
+
+        let line = |entry: &Entry, derivative: bool, holding: bool| {
+            let amounts = entry.transaction_amounts.as_ref();
+            Line {
+                issuer_cik: self.issuer.cik.clone(),
+                owner_cik: owner_cik.clone(),
+                owner: owner.clone(),
+                relationship: relationship.clone(),
+                security: text(&entry.security_title).unwrap_or_default().to_string(),
+                derivative,
+                code: match holding {
+                    true => None,
+                    false => entry
+                        .transaction_coding
+                        .as_ref()
+                        .and_then(|coding| coding.transaction_code.clone()),
+                },
+                dated: match holding {
+                    true => date(self.period_of_report.as_deref()),
+                    false => date(text(&entry.transaction_date)),
+                },
+                shares: amounts.and_then(|amounts| number(&amounts.transaction_shares)),
+                price: amounts.and_then(|amounts| number(&amounts.transaction_price_per_share)),
+                acquired: amounts
+                    .and_then(|amounts| text(&amounts.transaction_acquired_disposed_code))
+                    .map(|code| code == "A"),
+                shares_after: entry
+                    .post_transaction_amounts
+                    .as_ref()
+                    .and_then(|amounts| number(&amounts.shares_owned_following_transaction)),
+                direct: entry
+                    .ownership_nature
+                    .as_ref()
+                    .and_then(|nature| text(&nature.direct_or_indirect_ownership))
+                    .map(|code| code == "D"),
+            }
+        };
+
+        let mut lines = Vec::new();
+        for (table, derivative) in [
+            (&self.non_derivative_table, false),
+            (&self.derivative_table, true),
+        ] {
+            if let Some(table) = table {
+                lines.extend(
+                    table
+                        .transactions
+                        .iter()
+                        .map(|entry| line(entry, derivative, false)),
+                );
+            }
+        }
+        for (table, derivative) in [
+            (&self.non_derivative_table, false),
+            (&self.derivative_table, true),
+        ] {
+            if let Some(table) = table {
+                lines.extend(
+                    table
+                        .holdings
+                        .iter()
+                        .map(|entry| line(entry, derivative, true)),
+                );
+            }
+        }
+        lines
+    }
+}
+
+// -- TESTS --
+
+#[test]
+fn form_4_lines() {
+    let xml = r#"<?xml version="1.0"?>
+        <ownershipDocument>
+            <schemaVersion>X0508</schemaVersion>
+            <documentType>4</documentType>
+            <periodOfReport>2024-04-01</periodOfReport>
+            <issuer>
+                <issuerCik>0000320193</issuerCik>
+                <issuerName>Apple Inc.</issuerName>
+                <issuerTradingSymbol>AAPL</issuerTradingSymbol>
+            </issuer>
+            <reportingOwner>
+                <reportingOwnerId>
+                    <rptOwnerCik>0001214156</rptOwnerCik>
+                    <rptOwnerName>COOK TIMOTHY D</rptOwnerName>
+                </reportingOwnerId>
+                <reportingOwnerRelationship>
+                    <isDirector>1</isDirector>
+                    <isOfficer>1</isOfficer>
+                    <officerTitle>Chief Executive Officer</officerTitle>
+                </reportingOwnerRelationship>
+            </reportingOwner>
+            <nonDerivativeTable>
+                <nonDerivativeTransaction>
+                    <securityTitle><value>Common Stock</value></securityTitle>
+                    <transactionDate><value>2024-04-01</value></transactionDate>
+                    <transactionCoding>
+                        <transactionFormType>4</transactionFormType>
+                        <transactionCode>S</transactionCode>
+                    </transactionCoding>
+                    <transactionAmounts>
+                        <transactionShares><value>1,000</value></transactionShares>
+                        <transactionPricePerShare><value>170.5</value><footnoteId id="F1"/></transactionPricePerShare>
+                        <transactionAcquiredDisposedCode><value>D</value></transactionAcquiredDisposedCode>
+                    </transactionAmounts>
+                    <postTransactionAmounts>
+                        <sharesOwnedFollowingTransaction><value>3280180</value></sharesOwnedFollowingTransaction>
+                    </postTransactionAmounts>
+                    <ownershipNature>
+                        <directOrIndirectOwnership><value>D</value></directOrIndirectOwnership>
+                    </ownershipNature>
+                </nonDerivativeTransaction>
+                <nonDerivativeHolding>
+                    <securityTitle><value>Common Stock</value></securityTitle>
+                    <postTransactionAmounts>
+                        <sharesOwnedFollowingTransaction><value>50000</value></sharesOwnedFollowingTransaction>
+                    </postTransactionAmounts>
+                    <ownershipNature>
+                        <directOrIndirectOwnership><value>I</value></directOrIndirectOwnership>
+                        <natureOfOwnership><value>By Trust</value></natureOfOwnership>
+                    </ownershipNature>
+                </nonDerivativeHolding>
+            </nonDerivativeTable>
+            <derivativeTable>
+                <derivativeTransaction>
+                    <securityTitle><value>Restricted Stock Unit</value></securityTitle>
+                    <transactionDate><value>2024-04-01-05:00</value></transactionDate>
+                    <transactionCoding><transactionCode>M</transactionCode></transactionCoding>
+                    <transactionAmounts>
+                        <transactionShares><value>500</value></transactionShares>
+                        <transactionPricePerShare><footnoteId id="F2"/></transactionPricePerShare>
+                        <transactionAcquiredDisposedCode><value>D</value></transactionAcquiredDisposedCode>
+                    </transactionAmounts>
+                </derivativeTransaction>
+            </derivativeTable>
+        </ownershipDocument>"#;
+    let document: Document = quick_xml::de::from_str(xml).expect("valid ownership document");
+    let lines = document.lines();
+    assert_eq!(lines.len(), 3);
+
+    let date = |day| NaiveDate::from_ymd_opt(2024, 4, day);
+    let sale = &lines[0];
+    assert_eq!(
+        (sale.owner.as_str(), sale.relationship.as_str()),
+        (
+            "COOK TIMOTHY D",
+            "Director, Officer (Chief Executive Officer)"
+        )
+    );
+    assert_eq!(
+        (sale.code.as_deref(), sale.dated, sale.shares, sale.price),
+        (Some("S"), date(1), Some(1000.0), Some(170.5))
+    );
+    assert_eq!(
+        (sale.acquired, sale.shares_after, sale.direct),
+        (Some(false), Some(3280180.0), Some(true))
+    );
+
+    // derivative transactions before holdings; a footnoted price has no value
+    let exercise = &lines[1];
+    assert!(exercise.derivative);
+    assert_eq!((exercise.dated, exercise.price), (date(1), None));
+
+    let holding = &lines[2];
+    assert_eq!((holding.code.as_deref(), holding.dated), (None, date(1)));
+    assert_eq!(
+        (holding.shares, holding.shares_after, holding.direct),
+        (None, Some(50000.0), Some(false))
+    );
+
+    assert_eq!(
+        xml_url(
+            "https://www.sec.gov/Archives/edgar/data/320193/000032019324000065/xslF345X05/wk-form4_1712010000.xml",
+            "xslF345X05/wk-form4_1712010000.xml"
+        ),
+        "https://www.sec.gov/Archives/edgar/data/320193/000032019324000065/wk-form4_1712010000.xml"
+    );
+}
@@ -0,0 +1,685 @@
+use crate::http::*;
+use crate::stock::common::SecThrottle;
+use crate::stock::sql;
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::{debug, error, info, trace, warn};
+
+// Institutional holdings from the 13F-HR filings of every investment manager, i.e. which funds own
+// each stock:
+//
+// 1. the quarterly form index lists every 13F-HR filed, e.g.
+//    `https://www.sec.gov/Archives/edgar/full-index/2024/QTR2/form.idx`
+//
+//      Form Type   Company Name          CIK         Date Filed  File Name
+//      ---------------------------------------------------------------------------------------
+//      13F-HR      1060 CAPITAL, LLC     1834484     2024-05-14  edgar/data/1834484/0001834484-24-000003.txt
+//
+// 2. the full submission (.txt) of each holds its period of report, & the information table XML,
+//    of every position (`<infoTable>`) held at the end of the period
+//
+// 3. positions are summed per CUSIP, into `stock.institutional_holdings`, and compared with the
+//    filer's previous period in `stock.institutional_changes`; a filer's first period parsed is a
+//    baseline
+//
+// 4. CUSIPs are mapped to `stock.symbols` through OpenFIGI's mapping API, in `stock.cusips`
+//
+// NOTE: option positions (`<putCall>`) are skipped, & amendments (13F-HR/A) aren't applied. Values
+// are reported in thousands of dollars before 2023, and in dollars since; all are stored in dollars.
+
+const FORM: &str = "13F-HR";
+
+const LOOKBACK: Months = Months::new(12);
+
+/// Requests are bound by the throttle; this only keeps a few in flight.
+const CONCURRENCY: usize = 9;
+
+const OPENFIGI_URL: &str = "https://api.openfigi.com/v3/mapping";
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filing {
+    accession: String,
+    filer_cik: String,
+    filer: String,
+    filing_date: NaiveDate,
+    url: String,
+}
+
+/// A position of the information table, summed per CUSIP.
+#[derive(Debug, Clone, PartialEq)]
+struct Position {
+    cusip: String,
+    issuer: String,
+    class: String,
+    value: f64,
+    shares: f64,
+    share_type: String,
+}
+
+/// The change in a position since the filer's previous period.
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    cusip: String,
+    shares: f64,
+    prev_shares: f64,
+    status: &'static str,
+}
+
+/// Parse the 13F-HR filings of every investment manager (since `SEC_13F_SINCE`, as YYYY-MM-DD,
+/// else of the last year), then map their CUSIPs to symbols.
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    if tui {
+        println!(
+            "{bar}\n{name:^40}\n{bar}",
+            bar = "=".repeat(40),
+            name = "SEC 13F Holdings"
+        );
+    }
+
+    let since = match var("SEC_13F_SINCE") {
+        Ok(since) => match NaiveDate::parse_from_str(&since, "%Y-%m-%d") {
+            Ok(since) => since,
+            Err(err) => {
+                error!("invalid SEC_13F_SINCE {since}, expected YYYY-MM-DD, error({err})");
+                return Err(err.into());
+            }
+        },
+        Err(_) => Utc::now().date_naive() - LOOKBACK,
+    };
+
+    // 1. every 13F-HR filed since, from the quarterly form indices
+    let http_client = crate::std_client_build();
+    let throttle = SecThrottle::new();
+    let mut filings = Vec::new();
+    for (year, quarter) in quarters(since, Utc::now().date_naive()) {
+        throttle.wait().await;
+        let url =
+            format!("https://www.sec.gov/Archives/edgar/full-index/{year}/QTR{quarter}/form.idx");
+        let form_idx = http_client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()
+            .map_err(|err| {
+                error!("failed to fetch the form index of {year} Q{quarter}, error({err})");
+                err
+            })?
+            .text()
+            .await?;
+        filings.extend(
+            index(&form_idx)
+                .into_iter()
+                .filter(|f| f.filing_date >= since),
+        );
+    }
+
+    // 2. less those already parsed, grouped per filer so each period compares with the last
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+    let accessions: Vec<&str> = filings.iter().map(|f| f.accession.as_str()).collect();
+    let parsed: HashSet<String> = pg_client
+        .query(sql::PARSED_13F_FILINGS, &[&accessions])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.institutional_filings, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| row.get("accession"))
+        .collect();
+    drop(pg_client);
+
+    let mut filers: HashMap<String, Vec<Filing>> = HashMap::new();
+    for filing in filings
+        .into_iter()
+        .filter(|f| !parsed.contains(&f.accession))
+    {
+        filers
+            .entry(filing.filer_cik.clone())
+            .or_default()
+            .push(filing);
+    }
+    let filers: Vec<Vec<Filing>> = filers
+        .into_values()
+        .map(|mut filings| {
+            filings.sort_by_key(|f| f.filing_date);
+            filings
+        })
+        .collect();
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(filers.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    // 3. parse each filer's filings, oldest first
+    info!("parsing the 13F-HR filings of {} filers ...", filers.len());
+    stream::iter(&filers)
+        .for_each_concurrent(CONCURRENCY, |filings| {
+            let http_client = &http_client;
+            let throttle = &throttle;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("parsing 13F-HR of {}", &filings[0].filer),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                let mut failed = false;
+                for filing in filings {
+                    throttle.wait().await;
+                    match collect(http_client, pool, filing).await {
+                        Ok(positions) => trace!(
+                            "{positions} positions of {} {} inserted",
+                            &filing.filer,
+                            &filing.accession
+                        ),
+                        Err(err) => {
+                            error!(
+                                "failed to parse 13F-HR of {} {}, error({err})",
+                                &filing.filer, &filing.url
+                            );
+                            failed = true;
+                        }
+                    }
+                }
+                if tui {
+                    if failed {
+                        fail.expect("failbar should have unwrapped").inc(1);
+                    } else {
+                        success.expect("successbar should have unwrapped").inc(1);
+                    }
+                    total.expect("totalbar should have unwrapped").inc(1);
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+        println!("parsing 13F holdings ... done\n");
+    }
+
+    // 4. map new CUSIPs to symbols
+    map_cusips(pool, &http_client).await?;
+
+    Ok(())
+}
+
+/// Every (year, quarter) from the quarter of `since` to that of `to`.
+fn quarters(since: NaiveDate, to: NaiveDate) -> Vec<(i32, u32)> {
+    let quarter = |date: NaiveDate| (date.year(), (date.month() - 1) / 3 + 1);
+    let (mut year, mut q) = quarter(since);
+    let end = quarter(to);
+
+    let mut quarters = Vec::new();
+    while (year, q) <= end {
+        quarters.push((year, q));
+        (year, q) = if q == 4 { (year + 1, 1) } else { (year, q + 1) };
+    }
+    quarters
+}
+
+/// The 13F-HR filings of a form index; the company name is padded with (& may contain) spaces, so
+/// the other fields are taken from either end of each line.
+fn index(form_idx: &str) -> Vec<Filing> {
+    form_idx
+        .lines()
+        .skip_while(|line| !line.starts_with("---"))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [form, filer @ .., cik, filing_date, file] = fields.as_slice() else {
+                return None;
+            };
+            if *form != FORM {
+                return None;
+            }
+            let cik: u64 = cik.parse().ok()?;
+
+            Some(Filing {
+                accession: file.rsplit('/').next()?.strip_suffix(".txt")?.to_string(),
+                filer_cik: format!("{cik:010}"),
+                filer: filer.join(" "),
+                filing_date: NaiveDate::parse_from_str(filing_date, "%Y-%m-%d").ok()?,
+                url: format!("https://www.sec.gov/Archives/{file}"),
+            })
+        })
+        .collect()
+}
+
+/// Fetch & parse a 13F-HR, and INSERT its positions & their changes; returns the number of
+/// positions.
+async fn collect(http_client: &HttpClient, pool: &Pool, filing: &Filing) -> anyhow::Result<usize> {
+    let time = std::time::Instant::now();
+
+    let txt = http_client
+        .get(&filing.url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let period = period(&txt).ok_or_else(|| anyhow::anyhow!("13F-HR has no period of report"))?;
+
+    // a 13F-HR with no positions (e.g. a combination report) has no information table
+    let positions = match information_table(&txt) {
+        Some(table) => positions(quick_xml::de::from_str(table)?, filing.filing_date),
+        None => Vec::new(),
+    };
+
+    // the filer's first period parsed is a baseline, rather than every position being "new"
+    let mut pg_client = pool.get().await?;
+    let previous_period: Option<NaiveDate> = pg_client
+        .query_one(sql::PREVIOUS_13F_PERIOD, &[&filing.filer_cik, &period])
+        .await?
+        .get("period");
+    let previous: Option<HashMap<String, f64>> = match previous_period {
+        Some(previous_period) => Some(
+            pg_client
+                .query(
+                    sql::PREVIOUS_INSTITUTIONAL_HOLDINGS,
+                    &[&filing.filer_cik, &previous_period],
+                )
+                .await?
+                .into_iter()
+                .map(|row| (row.get("cusip"), row.get("shares")))
+                .collect(),
+        ),
+        None => None,
+    };
+    let changes = changes(previous.as_ref(), &positions);
+
+    let transaction = pg_client.transaction().await?;
+    let query = transaction
+        .prepare(sql::UPSERT_INSTITUTIONAL_HOLDING)
+        .await?;
+    for position in &positions {
+        transaction
+            .execute(
+                &query,
+                &[
+                    &filing.filer_cik,
+                    &period,
+                    &position.cusip,
+                    &position.issuer,
+                    &position.class,
+                    &position.value,
+                    &position.shares,
+                    &position.share_type,
+                ],
+            )
+            .await?;
+    }
+    let query = transaction
+        .prepare(sql::UPSERT_INSTITUTIONAL_CHANGE)
+        .await?;
+    for change in &changes {
+        transaction
+            .execute(
+                &query,
+                &[
+                    &filing.filer_cik,
+                    &period,
+                    &change.cusip,
+                    &change.shares,
+                    &change.prev_shares,
+                    &change.status,
+                ],
+            )
+            .await?;
+    }
+    transaction
+        .execute(
+            sql::INSERT_13F_FILING,
+            &[
+                &filing.accession,
+                &filing.filer_cik,
+                &filing.filer,
+                &period,
+                &filing.filing_date,
+                &(positions.len() as i32),
+            ],
+        )
+        .await?;
+    transaction.commit().await?;
+
+    debug!(
+        "{} 13F-HR positions inserted. {}",
+        &filing.accession,
+        crate::time_elapsed(time)
+    );
+
+    Ok(positions.len())
+}
+
+/// The period of report of a full submission's header, e.g. "CONFORMED PERIOD OF REPORT: 20240331".
+fn period(txt: &str) -> Option<NaiveDate> {
+    txt.lines()
+        .take(100)
+        .find_map(|line| line.trim().strip_prefix("CONFORMED PERIOD OF REPORT:"))
+        .and_then(|date| NaiveDate::parse_from_str(date.trim(), "%Y%m%d").ok())
+}
+
+/// The information table XML of a full submission, i.e. the `<XML>` document holding an
+/// `informationTable`.
+fn information_table(txt: &str) -> Option<&str> {
+    txt.split("<XML>")
+        .skip(1)
+        .filter_map(|document| document.split("</XML>").next())
+        .find(|document| document.contains("informationTable>"))
+        .map(str::trim)
+}
+
+/// Sum the positions per CUSIP, in dollars; option positions are skipped.
+fn positions(table: InformationTable, filing_date: NaiveDate) -> Vec<Position> {
+    let in_thousands = filing_date < NaiveDate::from_ymd_opt(2023, 1, 3).expect("valid date");
+    let number = |text: &str| {
+        text.trim()
+            .replace(',', "")
+            .parse::<f64>()
+            .unwrap_or_default()
+    };
+
+    let mut positions: Vec<Position> = Vec::new();
+    for row in table.info_table {
+        if row
+            .put_call
+            .is_some_and(|put_call| !put_call.trim().is_empty())
+        {
+            continue;
+        }
+
+        let cusip = row.cusip.trim().to_uppercase();
+        let value = number(&row.value) * if in_thousands { 1000.0 } else { 1.0 };
+        let shares = number(&row.shrs_or_prn_amt.ssh_prnamt);
+        match positions
+            .iter_mut()
+            .find(|position| position.cusip == cusip)
+        {
+            Some(position) => {
+                position.value += value;
+                position.shares += shares;
+            }
+            None => positions.push(Position {
+                cusip,
+                issuer: row.name_of_issuer.trim().to_string(),
+                class: row.title_of_class.trim().to_string(),
+                value,
+                shares,
+                share_type: row.shrs_or_prn_amt.ssh_prnamt_type.trim().to_string(),
+            }),
+        }
+    }
+    positions
+}
+
+/// Compare each position with the previous period's shares per CUSIP; positions no longer held are
+/// "exited". Without a previous period, every position is a "baseline".
+fn changes(previous: Option<&HashMap<String, f64>>, positions: &[Position]) -> Vec<Change> {
+    let Some(previous) = previous else {
+        return positions
+            .iter()
+            .map(|position| Change {
+                cusip: position.cusip.clone(),
+                shares: position.shares,
+                prev_shares: 0.0,
+                status: "baseline",
+            })
+            .collect();
+    };
+
+    let mut changes: Vec<Change> = positions
+        .iter()
+        .map(|position| {
+            let prev_shares = previous.get(&position.cusip).copied();
+            let status = match prev_shares {
+                None => "new",
+                Some(prev) if position.shares > prev => "increased",
+                Some(prev) if position.shares < prev => "decreased",
+                Some(_) => "unchanged",
+            };
+            Change {
+                cusip: position.cusip.clone(),
+                shares: position.shares,
+                prev_shares: prev_shares.unwrap_or_default(),
+                status,
+            }
+        })
+        .collect();
+
+    let held: HashSet<&str> = positions.iter().map(|p| p.cusip.as_str()).collect();
+    let mut exited: Vec<Change> = previous
+        .iter()
+        .filter(|(cusip, _)| !held.contains(cusip.as_str()))
+        .map(|(cusip, prev_shares)| Change {
+            cusip: cusip.clone(),
+            shares: 0.0,
+            prev_shares: *prev_shares,
+            status: "exited",
+        })
+        .collect();
+    exited.sort_by(|a, b| a.cusip.cmp(&b.cusip));
+    changes.extend(exited);
+
+    changes
+}
+
+// cusips
+// ----------------------------------------------------------------------------
+
+/// Map every CUSIP of `stock.institutional_holdings` not yet in `stock.cusips` to its US ticker, &
+/// symbol, through OpenFIGI; an API key (`OPENFIGI_API_KEY`) raises the rate limit from 25 requests
+/// of 10 jobs per minute, to 25 requests of 100 jobs per 6 seconds.
+async fn map_cusips(pool: &Pool, http_client: &HttpClient) -> anyhow::Result<()> {
+    let time = std::time::Instant::now();
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    let cusips: Vec<String> = pg_client
+        .query(sql::UNMAPPED_CUSIPS, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch unmapped CUSIPs, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| row.get("cusip"))
+        .collect();
+
+    let api_key = var("OPENFIGI_API_KEY").ok();
+    let (jobs, delay) = match api_key {
+        Some(_) => (100, Duration::from_millis(250)),
+        None => (10, Duration::from_millis(2500)),
+    };
+
+    info!("mapping {} CUSIPs through OpenFIGI ...", cusips.len());
+    for chunk in cusips.chunks(jobs) {
+        let body: Vec<serde_json::Value> = chunk
+            .iter()
+            .map(|cusip| {
+                serde_json::json!({ "idType": "ID_CUSIP", "idValue": cusip, "exchCode": "US" })
+            })
+            .collect();
+        let mut request = http_client.post(OPENFIGI_URL).json(&body);
+        if let Some(api_key) = &api_key {
+            request = request.header("X-OPENFIGI-APIKEY", api_key);
+        }
+        let results: Vec<FigiResult> = match request.send().await {
+            // e.g. 429 Too Many Requests; the rest are mapped on the next run
+            Ok(response) => match response.error_for_status() {
+                Ok(response) => response.json().await?,
+                Err(err) => {
+                    warn!("failed to map CUSIPs through OpenFIGI, error({err})");
+                    break;
+                }
+            },
+            Err(err) => {
+                warn!("failed to map CUSIPs through OpenFIGI, error({err})");
+                break;
+            }
+        };
+
+        for (cusip, result) in chunk.iter().zip(results) {
+            // OpenFIGI's share classes are split by a slash, the SEC's by a dash, e.g. BRK/B
+            let figi = result.data.first();
+            let ticker = figi
+                .and_then(|figi| figi.ticker.as_deref())
+                .map(|ticker| ticker.replace('/', "-"));
+            pg_client
+                .execute(
+                    sql::UPSERT_CUSIP,
+                    &[&cusip, &ticker, &figi.map(|figi| figi.figi.as_str())],
+                )
+                .await?;
+        }
+        tokio::time::sleep(delay).await;
+    }
+
+    debug!("CUSIPs mapped. {}", crate::time_elapsed(time));
+
+    Ok(())
+}
+
+// de
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InformationTable {
+    #[serde(default)]
+    info_table: Vec<InfoTable>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InfoTable {
+    name_of_issuer: String,
+    title_of_class: String,
+    cusip: String,
+    value: String,
+    shrs_or_prn_amt: Amount,
+    put_call: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Amount {
+    ssh_prnamt: String,
+
+    /// "SH" (shares), or "PRN" (principal amount).
+    ssh_prnamt_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FigiResult {
+    /// Missing if no FIGI is found, i.e. `{ "warning": "No identifier found." }`.
+    #[serde(default)]
+    data: Vec<Figi>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Figi {
+    figi: String,
+    ticker: Option<String>,
+}
+
+// -- TESTS --
+
+#[test]
+fn thirteen_f_positions_and_changes() {
+    let form_idx = "Form Type   Company Name                                                  CIK         Date Filed  File Name\n\
+        ---------------------------------------------------------------------------------------------------------------------------------------------\n\
+        13F-HR      1060 CAPITAL, LLC                                             1834484     2024-05-14  edgar/data/1834484/0001834484-24-000003.txt\n\
+        13F-HR/A    ABC CAPITAL MANAGEMENT                                        1000001     2024-05-15  edgar/data/1000001/0001000001-24-000001.txt\n";
+    let filings = index(form_idx);
+    assert_eq!(filings.len(), 1);
+    assert_eq!(
+        (filings[0].filer.as_str(), filings[0].filer_cik.as_str()),
+        ("1060 CAPITAL, LLC", "0001834484")
+    );
+    assert_eq!(filings[0].accession, "0001834484-24-000003");
+
+    let txt = "<SEC-HEADER>\nCONFORMED PERIOD OF REPORT:\t20240331\n</SEC-HEADER>\n\
+        <DOCUMENT>\n<TYPE>13F-HR\n<TEXT>\n<XML>\n<edgarSubmission>...</edgarSubmission>\n</XML>\n</TEXT>\n</DOCUMENT>\n\
+        <DOCUMENT>\n<TYPE>INFORMATION TABLE\n<TEXT>\n<XML>\n\
+        <ns1:informationTable xmlns:ns1=\"http://www.sec.gov/edgar/document/thirteenf/informationtable\">\n\
+            <ns1:infoTable><ns1:nameOfIssuer>APPLE INC</ns1:nameOfIssuer><ns1:titleOfClass>COM</ns1:titleOfClass>\
+                <ns1:cusip>037833100</ns1:cusip><ns1:value>1700000</ns1:value>\
+                <ns1:shrsOrPrnAmt><ns1:sshPrnamt>10000</ns1:sshPrnamt><ns1:sshPrnamtType>SH</ns1:sshPrnamtType></ns1:shrsOrPrnAmt>\
+                <ns1:investmentDiscretion>SOLE</ns1:investmentDiscretion></ns1:infoTable>\n\
+            <ns1:infoTable><ns1:nameOfIssuer>APPLE INC</ns1:nameOfIssuer><ns1:titleOfClass>COM</ns1:titleOfClass>\
+                <ns1:cusip>037833100</ns1:cusip><ns1:value>170000</ns1:value>\
+                <ns1:shrsOrPrnAmt><ns1:sshPrnamt>1000</ns1:sshPrnamt><ns1:sshPrnamtType>SH</ns1:sshPrnamtType></ns1:shrsOrPrnAmt>\
+                <ns1:investmentDiscretion>DFND</ns1:investmentDiscretion></ns1:infoTable>\n\
+            <ns1:infoTable><ns1:nameOfIssuer>APPLE INC</ns1:nameOfIssuer><ns1:titleOfClass>PUT</ns1:titleOfClass>\
+                <ns1:cusip>037833950</ns1:cusip><ns1:value>85000</ns1:value>\
+                <ns1:shrsOrPrnAmt><ns1:sshPrnamt>500</ns1:sshPrnamt><ns1:sshPrnamtType>SH</ns1:sshPrnamtType></ns1:shrsOrPrnAmt>\
+                <ns1:putCall>Put</ns1:putCall></ns1:infoTable>\n\
+            <ns1:infoTable><ns1:nameOfIssuer>MICROSOFT CORP</ns1:nameOfIssuer><ns1:titleOfClass>COM</ns1:titleOfClass>\
+                <ns1:cusip>594918104</ns1:cusip><ns1:value>420000</ns1:value>\
+                <ns1:shrsOrPrnAmt><ns1:sshPrnamt>1000</ns1:sshPrnamt><ns1:sshPrnamtType>SH</ns1:sshPrnamtType></ns1:shrsOrPrnAmt></ns1:infoTable>\n\
+        </ns1:informationTable>\n</XML>\n</TEXT>\n</DOCUMENT>";
+    assert_eq!(period(txt), NaiveDate::from_ymd_opt(2024, 3, 31));
+
+    let table = information_table(txt).expect("information table");
+    let table: InformationTable = quick_xml::de::from_str(table).expect("valid information table");
+    let positions = positions(table, filings[0].filing_date);
+
+    // rows of a CUSIP are summed, & options skipped
+    assert_eq!(positions.len(), 2);
+    assert_eq!(
+        (positions[0].value, positions[0].shares),
+        (1870000.0, 11000.0)
+    );
+
+    // a filer's first period is a baseline
+    let baseline = changes(None, &positions);
+    assert!(baseline
+        .iter()
+        .all(|change| change.status == "baseline" && change.prev_shares == 0.0));
+    assert_eq!(baseline.len(), positions.len());
+
+    let previous = HashMap::from([
+        ("037833100".to_string(), 12000.0),
+        ("02079K305".to_string(), 500.0),
+    ]);
+    let changes = changes(Some(&previous), &positions);
+    let statuses: Vec<(&str, &str)> = changes
+        .iter()
+        .map(|change| (change.cusip.as_str(), change.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("037833100", "decreased"),
+            ("594918104", "new"),
+            ("02079K305", "exited")
+        ]
+    );
+}
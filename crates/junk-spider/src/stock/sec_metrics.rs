@@ -1,7 +1,10 @@
 use crate::http::*;
 use crate::key_tracker::KeyTracker;
-use crate::stock::common::convert_date_type;
+use crate::stock::common::{
+    content_hash, convert_date_type, loaded_hashes, store_hashes, SecThrottle,
+};
 use crate::stock::sql;
+use chrono::{Datelike, Days, NaiveDate, Utc, Weekday};
 use deadpool_postgres::Pool;
 use futures::{stream, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
@@ -14,7 +17,19 @@ use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio_postgres::binary_copy::BinaryCopyInWriter;
 use tokio_postgres::types::Type;
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
+
+/// Form types whose filers' companyfacts are refreshed, i.e. those with XBRL financial data.
+const FORMS: [&str; 8] = [
+    "10-K", "10-K/A", "10-Q", "10-Q/A", "20-F", "20-F/A", "40-F", "40-F/A",
+];
+
+/// Requests are bound by the throttle; this only keeps a few in flight.
+const CONCURRENCY: usize = 9;
+
+/// The loader of `stock.sec_hashes`; companies whose companyfacts JSON is unchanged since their
+/// last load are skipped.
+const LOADER: &str = "sec_metrics";
 
 /// The process for copying SEC metric.json files from the "buffer/" directory into the database.
 struct Process {
@@ -32,7 +47,7 @@ impl Process {
         trace!("fetching tickers ...");
         let tickers: Vec<Ticker> = pg_client
             .query(
-                "SELECT pk, file_code, symbol, title FROM stock.symbols",
+                "SELECT pk, file_code, symbol, title FROM stock.symbols WHERE file_code IS NOT NULL",
                 &[],
             )
             .await
@@ -107,6 +122,24 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
         println!("initialising tables ... done");
     }
 
+    // content hashes of each CIK's last load; a CIK's hash is only stored once all of its
+    // symbols are loaded, & a symbol without any rows (e.g. newly listed) is loaded regardless
+    let hashes = loaded_hashes(pool, LOADER).await?;
+    let with_metrics: HashSet<i32> = pool
+        .get()
+        .await?
+        .query(sql::SYMBOLS_WITH_METRICS, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch the symbols of stock.metrics, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| row.get("pk"))
+        .collect();
+    let loaded: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    let failed: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+
     // progress bar
     let (multi, total, success, fail) = if tui {
         crate::tui::multi_progress(pr.tickers.len())?
@@ -122,6 +155,10 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
             // trackers
             let metrics = pr.metrics.clone();
             let stds = pr.acc_stds.clone();
+            let hashes = &hashes;
+            let with_metrics = &with_metrics;
+            let loaded = &loaded;
+            let failed = &failed;
 
             // progress bars
             let multi = multi.clone();
@@ -151,13 +188,48 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
                         &ticker.ticker, &ticker.title
                     ));
                 }
-                let json: Facts = match crate::fs::read_json(&path).await {
-                    Ok(json) => json,
+                let bytes = match tokio::fs::read(&path).await {
+                    Ok(bytes) => bytes,
                     Err(err) => {
                         error!(
                             "failed to read file at \"{path}\" for [{}] {}: {err}",
                             &ticker.ticker, &ticker.title
                         );
+                        failed.lock().await.insert(ticker.cik.clone());
+
+                        if tui {
+                            fail.expect("failed to unwrap failbar").inc(1);
+                            total.expect("failed to unwrap totalbar").inc(1);
+                        }
+
+                        return;
+                    }
+                };
+
+                // skip the symbol if its company's file is unchanged since its last load
+                let hash = content_hash(&bytes);
+                if hashes.get(&ticker.cik) == Some(&hash) && with_metrics.contains(&ticker.pk) {
+                    trace!(
+                        "metrics unchanged for [{}] {}, skipping",
+                        &ticker.ticker,
+                        &ticker.title
+                    );
+                    if tui {
+                        success.expect("successbar should have unwrapped").inc(1);
+                        total.expect("totalbar should have unwrapped").inc(1);
+                    }
+                    spinner.finish_and_clear();
+                    return;
+                }
+
+                let json: Facts = match serde_json::from_slice(&bytes) {
+                    Ok(json) => json,
+                    Err(err) => {
+                        error!(
+                            "failed to deserialize file at \"{path}\" for [{}] {}: {err}",
+                            &ticker.ticker, &ticker.title
+                        );
+                        failed.lock().await.insert(ticker.cik.clone());
 
                         if tui {
                             fail.expect("failed to unwrap failbar").inc(1);
@@ -179,76 +251,8 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
                     &ticker.ticker, &ticker.title
                 ));
 
-                // build a table of unique rows (and keep everything async)
-                let tbl: Arc<Mutex<HashSet<Metric>>> = Arc::new(Mutex::new(HashSet::new()));
-                stream::iter(json.facts)
-                    .for_each(|(acc_std, datasets)| {
-                        let metrics = metrics.clone();
-                        let tbl = tbl.clone();
-                        let stds = stds.clone();
-
-                        async move {
-                            // track the Accounting Standards PK
-                            let std_pk = {
-                                let mut stds = stds.lock().await;
-                                stds.transact(acc_std)
-                            };
-
-                            stream::iter(datasets)
-                                .for_each(|(metric, data)| {
-                                    let metrics = metrics.clone();
-                                    let tbl = tbl.clone();
-
-                                    async move {
-                                        // track the Metric Name PK
-                                        let metric_pk = {
-                                            let mut metrics = metrics.lock().await;
-                                            metrics.transact(metric)
-                                        };
-
-                                        let cells = data
-                                            .units
-                                            .into_iter()
-                                            .flat_map(|(_units, cells)| cells)
-                                            .collect::<Vec<_>>();
-
-                                        let mut batch = vec![];
-                                        for cell in cells {
-                                            batch.push(Metric {
-                                                symbol_pk: ticker.pk,
-                                                metric_pk,
-                                                acc_pk: std_pk,
-                                                start_date: {
-                                                    if let Some(start_date) = cell.start_date {
-                                                        Some(convert_date_type(&start_date)
-                                                            .expect("failed to convert date type"))
-                                                    } else { 
-                                                        None 
-                                                    }
-                                                },
-                                                end_date: convert_date_type(&cell.end_date)
-                                                    .expect("failed to convert date type"),
-                                                filing_date: convert_date_type(&cell.filing_date)
-                                                    .expect("failed to convert date type"),
-                                                year: cell.fy,
-                                                period: cell.fp.unwrap_or_else(|| "".to_string()),
-                                                form: cell.form.unwrap_or_else(|| "".to_string()),
-                                                val: OrderedFloat(cell.val),
-                                                accn: cell.accn.unwrap_or_else(|| "".to_string()),
-                                                frame: Some(cell.frame.unwrap_or_else(|| "".to_string())),
-                                            });
-                                        }
-
-                                        let mut tbl = tbl.lock().await;
-                                        for metric in batch {
-                                            tbl.insert(metric);
-                                        }
-                                    }
-                                })
-                                .await;
-                        }
-                    })
-                    .await;
+                // build a table of unique rows
+                let tbl = tabulate(ticker.pk, json, metrics, stds).await;
 
                 // get a client back from the pool
                 if tui {
@@ -261,6 +265,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
                     Ok(client) => client,
                     Err(err) => {
                         error!("failed to get pg client from pool, error({err})");
+                        failed.lock().await.insert(ticker.cik.clone());
                         if tui {
                             fail.expect("failbar should have unwrapped").inc(1);
                             total.expect("totalbar should have unwrapped").inc(1);
@@ -276,41 +281,9 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
                         &ticker.ticker, &ticker.title
                     ));
                 }
-                let exists: HashSet<MetricPrimaryKey> = pg_client
-                    .query(
-                        "
-                        SELECT symbol_pk, metric_pk, acc_pk, end_date, filing_date, period, form, val, accn FROM stock.metrics
-                        WHERE symbol_pk = $1
-                    ",
-                        &[&ticker.pk],
-                    )
+                let tbl = fresh(&pg_client, ticker.pk, tbl, None)
                     .await
-                    .expect("failed to fetch existing metrics")
-                    .iter()
-                    .map(|row| MetricPrimaryKey {
-                        symbol_pk: row.get(0),
-                        metric_pk: row.get(1),
-                        acc_pk: row.get(2),
-                        end_date: row.get(3),
-                        filing_date: row.get(4),
-                        // year: row.get(5),
-                        period: row.get(5),
-                        form: row.get(6),
-                        val: OrderedFloat(row.get(7)),
-                        accn: row.get(8),
-                    })
-                    .collect();
-
-                let tbl: HashSet<Metric> = Arc::into_inner(tbl)
-                    .expect("failed to unwrap tbl")
-                    .into_inner()
-                    .into_iter()
-                    .filter(|row| {
-                        !exists.contains(&row.pk())
-                    })
-                    .collect();
-
-                drop(exists);
+                    .expect("failed to fetch existing metrics");
 
                 // copy the remaining data in
                 info!(
@@ -331,6 +304,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
                             &ticker.title,
                             crate::time_elapsed(time)
                         );
+                        loaded.lock().await.insert(ticker.cik.clone(), hash);
                         if tui {
                             success.expect("successbar should have unwrapped").inc(1);
                             total.expect("totalbar should have unwrapped").inc(1);
@@ -341,6 +315,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
                             "failed to copy metrics data for [{}] {}, error({err})",
                             &ticker.ticker, &ticker.title
                         );
+                        failed.lock().await.insert(ticker.cik.clone());
                         if tui {
                             fail.expect("failbar should have unwrapped").inc(1);
                             total.expect("totalbar should have unwrapped").inc(1);
@@ -365,9 +340,366 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
         println!("collecting stock metrics ... done");
     }
 
+    store_keys(pool, pr.metrics, pr.acc_stds, tui).await?;
+
+    let failed = failed.into_inner();
+    let loaded: HashMap<String, String> = loaded
+        .into_inner()
+        .into_iter()
+        .filter(|(cik, _)| !failed.contains(cik))
+        .collect();
+    store_hashes(pool, LOADER, &loaded).await?;
+
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////
+// -- REFRESH --
+////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Refresh the metrics of only those companies that filed since the last refresh, rather than
+/// every company of companyfacts.zip:
+///
+/// 1. read the EDGAR daily index of each weekday since the last refresh, else since the latest
+///    filing of `stock.metrics`, for the CIKs that filed any of `FORMS`
+/// 2. fetch the companyfacts/CIK##########.json of each, within the SEC's rate limit
+/// 3. COPY the rows filed since into `stock.metrics`, comparing only against those filed since
+///
+/// Each day is recorded in `stock.metric_refreshes` once read; filers that fail are kept in
+/// `stock.metric_refresh_failures`, and retried by each refresh from the day they first failed.
+pub async fn refresh(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{name:^40}\n{bar}",
+            bar = "=".repeat(40),
+            name = "SEC Metrics (refresh)"
+        );
+    }
+
+    let last: Option<NaiveDate> = pg_client
+        .query_one(sql::LAST_METRIC_REFRESH, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch the last stock.metric_refreshes, error({err})");
+            err
+        })?
+        .get(0);
+    let latest: Option<NaiveDate> = match last {
+        Some(_) => None,
+        None => pg_client
+            .query_one(sql::LATEST_METRIC_FILING, &[])
+            .await?
+            .get(0),
+    };
+    let Some(since) = since(last, latest) else {
+        info!("no metrics to refresh, scrape companyfacts.zip first; skipping");
+        return Ok(());
+    };
+    let retries: HashMap<String, NaiveDate> = pg_client
+        .query(sql::METRIC_REFRESH_FAILURES, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.metric_refresh_failures, error({err})");
+            err
+        })?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+    drop(pg_client);
+
+    // today's index is only published after the SEC closes
+    let to = Utc::now().date_naive() - Days::new(1);
+    let days = days(since, to);
+    if days.is_empty() {
+        info!("metrics already refreshed up to {to}");
+        return Ok(());
+    }
+
+    // the CIKs that filed, per day
+    info!("reading {} daily indices since {since} ...", days.len());
+    let http_client = crate::std_client_build();
+    let throttle = SecThrottle::new();
+    let mut indices: Vec<(NaiveDate, HashSet<String>)> = Vec::with_capacity(days.len());
+    for day in days {
+        throttle.wait().await;
+        match daily_index(&http_client, day).await {
+            Ok(Some(form_idx)) => indices.push((day, filers(&form_idx))),
+            Ok(None) => trace!("no daily index for {day}, e.g. a holiday"),
+            Err(err) => {
+                error!("failed to fetch the daily index of {day}, error({err})");
+                return Err(err);
+            }
+        }
+    }
+    let filed: HashSet<&String> = indices.iter().flat_map(|(_, ciks)| ciks).collect();
+
+    // the symbols of each CIK that filed, or failed a previous refresh
+    let pr = Process::start(pool).await;
+    let mut companies: HashMap<String, Vec<Ticker>> = HashMap::new();
+    for ticker in pr.tickers {
+        if filed.contains(&ticker.cik) || retries.contains_key(&ticker.cik) {
+            companies
+                .entry(ticker.cik.clone())
+                .or_default()
+                .push(ticker);
+        }
+    }
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(companies.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    info!("refreshing metrics of {} companies ...", companies.len());
+    let failed: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+    stream::iter(&companies)
+        .for_each_concurrent(CONCURRENCY, |(cik, tickers)| {
+            let http_client = &http_client;
+            let throttle = &throttle;
+            let failed = &failed;
+            let since = retry_since(since, retries.get(cik).copied());
+
+            // trackers
+            let metrics = pr.metrics.clone();
+            let stds = pr.acc_stds.clone();
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                let title = &tickers[0].title;
+
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("refreshing metrics of [{cik}] {title}"),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                throttle.wait().await;
+                match reload(http_client, pool, cik, tickers, since, metrics, stds).await {
+                    Ok(rows) => {
+                        trace!("{rows} metrics refreshed for [{cik}] {title}");
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!("failed to refresh metrics of [{cik}] {title}, error({err})");
+                        failed.lock().await.insert(cik.clone());
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+                if tui {
+                    total.expect("totalbar should have unwrapped").inc(1);
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+        println!("refreshing stock metrics ... done");
+    }
+
+    store_keys(pool, pr.metrics, pr.acc_stds, tui).await?;
+
+    // failed companies are retried by the next refresh, from the same day, rather than holding
+    // back the days they filed on
+    let failed = failed.into_inner();
+    let pg_client = pool.get().await?;
+    if !failed.is_empty() {
+        warn!(
+            "{} companies failed to refresh, retrying them next refresh",
+            failed.len()
+        );
+    }
+    for cik in &failed {
+        let since = retry_since(since, retries.get(cik).copied());
+        pg_client
+            .execute(sql::UPSERT_METRIC_REFRESH_FAILURE, &[cik, &since])
+            .await
+            .map_err(|err| {
+                error!("failed to insert stock.metric_refresh_failures of {cik}, error({err})");
+                err
+            })?;
+    }
+    let retried: Vec<&str> = retries
+        .keys()
+        .filter(|cik| !failed.contains(*cik))
+        .map(String::as_str)
+        .collect();
+    pg_client
+        .execute(sql::DELETE_METRIC_REFRESH_FAILURES, &[&retried])
+        .await
+        .map_err(|err| {
+            error!("failed to delete stock.metric_refresh_failures, error({err})");
+            err
+        })?;
+
+    for (day, ciks) in &indices {
+        let filers = ciks
+            .iter()
+            .filter(|cik| companies.contains_key(*cik))
+            .count() as i32;
+        pg_client
+            .execute(sql::INSERT_METRIC_REFRESH, &[day, &filers])
+            .await
+            .map_err(|err| {
+                error!("failed to insert stock.metric_refreshes of {day}, error({err})");
+                err
+            })?;
+    }
+
+    Ok(())
+}
+
+/// The first day to refresh; the day after the last refresh, else the latest filing of
+/// `stock.metrics`, else `None` if there are no metrics.
+fn since(last: Option<NaiveDate>, latest: Option<NaiveDate>) -> Option<NaiveDate> {
+    match last {
+        Some(last) => Some(last + Days::new(1)),
+        None => latest,
+    }
+}
+
+/// The first day to refresh a company from; the day it first failed a refresh, if it's retried.
+fn retry_since(since: NaiveDate, retry: Option<NaiveDate>) -> NaiveDate {
+    retry.map_or(since, |retry| retry.min(since))
+}
+
+/// The weekdays from `since` to `to`, inclusive, whose daily indices are read.
+fn days(since: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+    since
+        .iter_days()
+        .take_while(|day| *day <= to)
+        .filter(|day| !matches!(day.weekday(), Weekday::Sat | Weekday::Sun))
+        .collect()
+}
+
+/// Fetch the EDGAR daily form index of a day; `None` if there's no index, e.g. a holiday.
+async fn daily_index(http_client: &HttpClient, day: NaiveDate) -> anyhow::Result<Option<String>> {
+    let url = format!(
+        "https://www.sec.gov/Archives/edgar/daily-index/{}/QTR{}/form.{}.idx",
+        day.year(),
+        day.month0() / 3 + 1,
+        day.format("%Y%m%d")
+    );
+    let response = http_client.get(&url).send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    Ok(Some(response.error_for_status()?.text().await?))
+}
+
+/// The CIKs of a daily form index that filed any of `FORMS`.
+fn filers(form_idx: &str) -> HashSet<String> {
+    form_idx
+        .lines()
+        .skip_while(|line| !line.starts_with("---"))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [form, _company @ .., cik, _filing_date, _file] = fields.as_slice() else {
+                return None;
+            };
+            if !FORMS.contains(form) {
+                return None;
+            }
+            let cik: u64 = cik.parse().ok()?;
+
+            Some(format!("{cik:010}"))
+        })
+        .collect()
+}
+
+/// Fetch a company's facts, and COPY the rows filed since `since` of each of its symbols; returns
+/// the number of rows copied.
+async fn reload(
+    http_client: &HttpClient,
+    pool: &Pool,
+    cik: &str,
+    tickers: &[Ticker],
+    since: NaiveDate,
+    metrics: Arc<Mutex<KeyTracker<i32, String>>>,
+    stds: Arc<Mutex<KeyTracker<i32, String>>>,
+) -> anyhow::Result<usize> {
+    let time = std::time::Instant::now();
+
+    let response = http_client
+        .get(format!(
+            "https://data.sec.gov/api/xbrl/companyfacts/CIK{cik}.json"
+        ))
+        .send()
+        .await?;
+
+    // e.g. a filing without XBRL financial data
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        trace!("no companyfacts for {cik}");
+        return Ok(0);
+    }
+    let json: Facts = response.error_for_status()?.json().await?;
+
+    // the facts are tabulated once per CIK, then copied for each of its symbols
+    let filed: Vec<Metric> = tabulate(tickers[0].pk, json, metrics, stds)
+        .await
+        .into_iter()
+        .filter(|row| row.filing_date >= since)
+        .collect();
+
+    let mut pg_client = pool.get().await?;
+    let mut rows = 0;
+    for ticker in tickers {
+        let tbl: HashSet<Metric> = filed
+            .iter()
+            .map(|row| Metric {
+                symbol_pk: ticker.pk,
+                ..row.clone()
+            })
+            .collect();
+        let tbl = fresh(&pg_client, ticker.pk, tbl, Some(since)).await?;
+        rows += tbl.len();
+        pg_copy(&mut pg_client, tbl).await?;
+    }
+
+    debug!(
+        "{rows} metrics refreshed for {cik}. {}",
+        crate::time_elapsed(time)
+    );
+
+    Ok(rows)
+}
+
+/// INSERT the Primary Keys of the metrics & accounting standards found.
+async fn store_keys(
+    pool: &Pool,
+    metrics: Arc<Mutex<KeyTracker<i32, String>>>,
+    acc_stds: Arc<Mutex<KeyTracker<i32, String>>>,
+    tui: bool,
+) -> anyhow::Result<()> {
     let mut pg_client = pool.get().await?;
 
-    Arc::into_inner(pr.acc_stds)
+    Arc::into_inner(acc_stds)
         .expect("failed to unwrap stds")
         .into_inner()
         .pg_insert(
@@ -380,7 +712,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
         println!("inserted accounting standards");
     }
 
-    Arc::into_inner(pr.metrics)
+    Arc::into_inner(metrics)
         .expect("failed to unwrap metrics")
         .into_inner()
         .pg_insert(
@@ -397,6 +729,122 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Transform a company's facts into a table of unique rows, tracking the Primary Keys of its
+/// metrics & accounting standards.
+async fn tabulate(
+    symbol_pk: i32,
+    json: Facts,
+    metrics: Arc<Mutex<KeyTracker<i32, String>>>,
+    stds: Arc<Mutex<KeyTracker<i32, String>>>,
+) -> HashSet<Metric> {
+    // keep everything async
+    let tbl: Arc<Mutex<HashSet<Metric>>> = Arc::new(Mutex::new(HashSet::new()));
+    stream::iter(json.facts)
+        .for_each(|(acc_std, datasets)| {
+            let metrics = metrics.clone();
+            let tbl = tbl.clone();
+            let stds = stds.clone();
+
+            async move {
+                // track the Accounting Standards PK
+                let std_pk = {
+                    let mut stds = stds.lock().await;
+                    stds.transact(acc_std)
+                };
+
+                stream::iter(datasets)
+                    .for_each(|(metric, data)| {
+                        let metrics = metrics.clone();
+                        let tbl = tbl.clone();
+
+                        async move {
+                            // track the Metric Name PK
+                            let metric_pk = {
+                                let mut metrics = metrics.lock().await;
+                                metrics.transact(metric)
+                            };
+
+                            let cells = data
+                                .units
+                                .into_iter()
+                                .flat_map(|(_units, cells)| cells)
+                                .collect::<Vec<_>>();
+
+                            let mut batch = vec![];
+                            for cell in cells {
+                                batch.push(Metric {
+                                    symbol_pk,
+                                    metric_pk,
+                                    acc_pk: std_pk,
+                                    start_date: {
+                                        if let Some(start_date) = cell.start_date {
+                                            Some(convert_date_type(&start_date)
+                                                .expect("failed to convert date type"))
+                                        } else { 
+                                            None 
+                                        }
+                                    },
+                                    end_date: convert_date_type(&cell.end_date)
+                                        .expect("failed to convert date type"),
+                                    filing_date: convert_date_type(&cell.filing_date)
+                                        .expect("failed to convert date type"),
+                                    year: cell.fy,
+                                    period: cell.fp.unwrap_or_else(|| "".to_string()),
+                                    form: cell.form.unwrap_or_else(|| "".to_string()),
+                                    val: OrderedFloat(cell.val),
+                                    accn: cell.accn.unwrap_or_else(|| "".to_string()),
+                                    frame: Some(cell.frame.unwrap_or_else(|| "".to_string())),
+                                });
+                            }
+
+                            let mut tbl = tbl.lock().await;
+                            for metric in batch {
+                                tbl.insert(metric);
+                            }
+                        }
+                    })
+                    .await;
+            }
+        })
+        .await;
+
+    Arc::into_inner(tbl)
+        .expect("failed to unwrap tbl")
+        .into_inner()
+}
+
+/// Remove the rows of `tbl` already in `stock.metrics`; only rows filed since `since` are
+/// compared, if given.
+async fn fresh(
+    pg_client: &PgClient,
+    symbol_pk: i32,
+    tbl: HashSet<Metric>,
+    since: Option<NaiveDate>,
+) -> anyhow::Result<HashSet<Metric>> {
+    let exists: HashSet<MetricPrimaryKey> = pg_client
+        .query(sql::EXISTING_METRICS, &[&symbol_pk, &since])
+        .await?
+        .iter()
+        .map(|row| MetricPrimaryKey {
+            symbol_pk: row.get(0),
+            metric_pk: row.get(1),
+            acc_pk: row.get(2),
+            end_date: row.get(3),
+            filing_date: row.get(4),
+            // year: row.get(5),
+            period: row.get(5),
+            form: row.get(6),
+            val: OrderedFloat(row.get(7)),
+            accn: row.get(8),
+        })
+        .collect();
+
+    Ok(tbl
+        .into_iter()
+        .filter(|row| !exists.contains(&row.pk()))
+        .collect())
+}
+
 struct Ticker {
     pk: i32,
     cik: String,
@@ -428,7 +876,7 @@ struct Ticker {
 //      },
 //      ...
 // ]
-#[derive(Debug, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Metric {
     pub symbol_pk: i32,
     pub metric_pk: i32,
@@ -612,3 +1060,56 @@ struct DataCell {
 //      }
 // }
 
+// -- TESTS --
+
+#[test]
+fn daily_index_filers() {
+    let form_idx = "Description:           Daily Index of EDGAR Dissemination Feed by Form Type
+Last Data Received:    October 16, 2026
+
+Form Type   Company Name                                                  CIK         Date Filed  File Name
+---------------------------------------------------------------------------------------------------------------------------------------------
+10-K        ACME HOLDINGS, INC.                                           1234567     20261016    edgar/data/1234567/0001234567-26-000010.txt
+10-Q/A      APPLE INC                                                     320193      20261016    edgar/data/320193/0000320193-26-000090.txt
+4           COOK TIMOTHY D                                                1214156     20261016    edgar/data/1214156/0001214156-26-000004.txt
+SC 13G      VANGUARD GROUP INC                                            102909      20261016    edgar/data/102909/0000102909-26-000123.txt
+";
+
+    let filers = filers(form_idx);
+    assert_eq!(filers.len(), 2);
+    assert!(filers.contains("0001234567"));
+    assert!(filers.contains("0000320193"));
+}
+
+#[test]
+fn refresh_days() {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).expect("valid date");
+
+    // resume from the day after the last refresh, else from the latest filing
+    assert_eq!(
+        since(Some(date(2026, 10, 14)), Some(date(2026, 9, 30))),
+        Some(date(2026, 10, 15))
+    );
+    assert_eq!(
+        since(None, Some(date(2026, 9, 30))),
+        Some(date(2026, 9, 30))
+    );
+    assert_eq!(since(None, None), None);
+
+    // a company that failed a previous refresh is retried from the day it first failed
+    assert_eq!(retry_since(date(2026, 10, 15), None), date(2026, 10, 15));
+    assert_eq!(
+        retry_since(date(2026, 10, 15), Some(date(2026, 10, 8))),
+        date(2026, 10, 8)
+    );
+
+    // Thu 15th to Mon 19th skips the weekend
+    assert_eq!(
+        days(date(2026, 10, 15), date(2026, 10, 19)),
+        [date(2026, 10, 15), date(2026, 10, 16), date(2026, 10, 19)]
+    );
+
+    // already refreshed up to yesterday, or only a weekend since
+    assert!(days(date(2026, 10, 19), date(2026, 10, 18)).is_empty());
+    assert!(days(date(2026, 10, 17), date(2026, 10, 18)).is_empty());
+}
@@ -1,17 +1,296 @@
-// Read all files per CIK, ie:
+use crate::stock::sql;
+use chrono::NaiveDate;
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{debug, error, info, trace};
+
+// The filing index of every company in `stock.symbols`, from the submission files `sec_bulks`
+// unzips to `./buffer/submissions/`:
 //
-// 1. read submissions/CIK0000000000000.json
+// 1. read submissions/CIK0000000000.json; its `filings.recent` holds (at least) the latest 1,000
+//    filings, or the last year's, as arrays per column
 //
-// 2. if 'files' IS NOT EMPTY -> read that next file
+// 2. if `filings.files` IS NOT EMPTY, read each of those too, e.g.
+//    submissions/CIK0000000000-submissions-001.json; each holds the same columns, of older filings
 //
-// 3. insert all url endings, per ticker, with file types
+// 3. insert each filing, per CIK, into `stock.filing_index`
 //
-//      symbol_pk | file_type | url
-//      ==================================
-//      1         |    4      | ".../..."
-//
-// 4. webscraping process begins
-//      4a. depend on CLI input for files types
-//      4b. fetch all per symbol_pk at urls <<<<<<< ONLY 10 REQUESTS PER SECOND (9 for good measure)
-//
-// 5. parse xml documents for ownership updates
+//      cik        | accession            | form | filing_date | report_date | primary_document | url
+//      ===============================================================================================
+//      0000320193 | 0000320193-24-000123 | 10-K | 2024-11-01  | 2024-09-28  | aapl-20240928.htm | ...
+
+const SUBMISSIONS: &str = "./buffer/submissions";
+
+/// Filings are indexed from disk, so this is bound by the pg pool, not the SEC's rate limit.
+const CONCURRENCY: usize = 8;
+
+/// Index the filings of every CIK in `stock.symbols`, from the unzipped submission files.
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    if !std::path::Path::new(SUBMISSIONS).exists() {
+        info!("no submission files found at {SUBMISSIONS}, skipping");
+        return Ok(());
+    }
+
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{name:^40}\n{bar}",
+            bar = "=".repeat(40),
+            name = "SEC Submissions"
+        );
+    }
+
+    let companies: Vec<(String, String)> = pg_client
+        .query(sql::SEC_CIKS, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.symbols CIKs, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| (row.get("file_code"), row.get("title")))
+        .collect();
+
+    drop(pg_client);
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(companies.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    info!("indexing filings of {} companies ...", companies.len());
+    stream::iter(&companies)
+        .for_each_concurrent(CONCURRENCY, |(cik, title)| {
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("indexing filings of [{cik}] {title}"),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                match index(pool, cik).await {
+                    Ok(filings) => {
+                        trace!("{filings} filings indexed for [{cik}] {title}");
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!("failed to index filings of [{cik}] {title}, error({err})");
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+                if tui {
+                    total.expect("totalbar should have unwrapped").inc(1);
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+        println!("indexing filings ... done\n");
+    }
+
+    Ok(())
+}
+
+/// Read the submission files of a CIK, and INSERT each filing; returns the number of filings.
+async fn index(pool: &Pool, cik: &str) -> anyhow::Result<usize> {
+    let time = std::time::Instant::now();
+
+    let submissions: Submissions =
+        crate::fs::read_json(&format!("{SUBMISSIONS}/CIK{cik}.json")).await?;
+    let mut filings = submissions.filings.recent.index(cik);
+    for file in &submissions.filings.files {
+        let older: Columns = crate::fs::read_json(&format!("{SUBMISSIONS}/{}", file.name)).await?;
+        filings.extend(older.index(cik));
+    }
+
+    let mut pg_client = pool.get().await?;
+    let transaction = pg_client.transaction().await?;
+    let query = transaction.prepare(sql::INSERT_FILING_INDEX).await?;
+    for filing in &filings {
+        transaction
+            .execute(
+                &query,
+                &[
+                    &cik,
+                    &filing.accession,
+                    &filing.form,
+                    &filing.filing_date,
+                    &filing.report_date,
+                    &filing.primary_document,
+                    &filing.url,
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await?;
+
+    debug!(
+        "{} filings indexed for {cik}. {}",
+        filings.len(),
+        crate::time_elapsed(time)
+    );
+
+    Ok(filings.len())
+}
+
+// de
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+struct Submissions {
+    filings: Filings,
+}
+
+#[derive(Debug, Deserialize)]
+struct Filings {
+    recent: Columns,
+
+    /// The overflow files of older filings.
+    #[serde(default)]
+    files: Vec<File>,
+}
+
+#[derive(Debug, Deserialize)]
+struct File {
+    name: String,
+}
+
+/// Filings as arrays per column, i.e. the nth filing is the nth element of each.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Columns {
+    accession_number: Vec<String>,
+    form: Vec<String>,
+    filing_date: Vec<String>,
+
+    /// Blank for forms without a reporting period, e.g. 8-K.
+    #[serde(default)]
+    report_date: Vec<String>,
+
+    #[serde(default)]
+    primary_document: Vec<String>,
+}
+
+/// A single filing of the index.
+#[derive(Debug, PartialEq)]
+struct Filing {
+    accession: String,
+    form: String,
+    filing_date: NaiveDate,
+    report_date: Option<NaiveDate>,
+    primary_document: Option<String>,
+    url: String,
+}
+
+impl Columns {
+    /// Zip the columns into filings; filings without a valid filing date are skipped.
+    fn index(self, cik: &str) -> Vec<Filing> {
+        let cik_num = cik.trim_start_matches('0');
+        let mut report_dates = self.report_date.into_iter();
+        let mut documents = self.primary_document.into_iter();
+
+        self.accession_number
+            .into_iter()
+            .zip(self.form)
+            .zip(self.filing_date)
+            .filter_map(|((accession, form), filing_date)| {
+                let report_date = report_dates.next().unwrap_or_default();
+                let document = documents.next().filter(|document| !document.is_empty());
+                let Ok(filing_date) = NaiveDate::parse_from_str(&filing_date, "%Y-%m-%d") else {
+                    error!("invalid filing date {filing_date} of {accession}, skipping");
+                    return None;
+                };
+
+                // the filing's folder, else its primary document
+                let folder = format!(
+                    "https://www.sec.gov/Archives/edgar/data/{cik_num}/{}",
+                    accession.replace('-', "")
+                );
+                let url = match &document {
+                    Some(document) => format!("{folder}/{document}"),
+                    None => folder,
+                };
+
+                Some(Filing {
+                    accession,
+                    form,
+                    filing_date,
+                    report_date: NaiveDate::parse_from_str(&report_date, "%Y-%m-%d").ok(),
+                    primary_document: document,
+                    url,
+                })
+            })
+            .collect()
+    }
+}
+
+// -- TESTS --
+
+#[test]
+fn submission_columns_zip_into_filings() {
+    let submissions: Submissions = serde_json::from_str(
+        r#"{
+            "cik": "320193",
+            "filings": {
+                "recent": {
+                    "accessionNumber": ["0000320193-24-000123", "0001140361-24-045123"],
+                    "filingDate": ["2024-11-01", "2024-10-30"],
+                    "reportDate": ["2024-09-28", ""],
+                    "form": ["10-K", "8-K"],
+                    "primaryDocument": ["aapl-20240928.htm", ""]
+                },
+                "files": [{ "name": "CIK0000320193-submissions-001.json", "filingCount": 1200 }]
+            }
+        }"#,
+    )
+    .expect("valid submissions");
+    assert_eq!(
+        submissions.filings.files[0].name,
+        "CIK0000320193-submissions-001.json"
+    );
+
+    let filings = submissions.filings.recent.index("0000320193");
+    assert_eq!(filings.len(), 2);
+    assert_eq!(
+        filings[0].url,
+        "https://www.sec.gov/Archives/edgar/data/320193/000032019324000123/aapl-20240928.htm"
+    );
+    assert_eq!(filings[0].report_date, NaiveDate::from_ymd_opt(2024, 9, 28));
+
+    // no reporting period, nor primary document
+    assert_eq!(filings[1].report_date, None);
+    assert_eq!(filings[1].primary_document, None);
+    assert_eq!(
+        filings[1].url,
+        "https://www.sec.gov/Archives/edgar/data/320193/000114036124045123"
+    );
+}
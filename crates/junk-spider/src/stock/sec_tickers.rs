@@ -1,14 +1,19 @@
-use crate::stock::common::de_cik;
+use crate::stock::common::{content_hash, de_cik, loaded_hashes, store_hashes};
 use crate::{http::*, stock::sql};
 use deadpool_postgres::Pool;
 use futures::{stream, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
 use serde::de::Visitor;
 use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, trace};
 
+/// The loader of `stock.sec_hashes`; tickers already in `stock.symbols`, whose submissions JSON is
+/// unchanged since their last load, are skipped.
+const LOADER: &str = "sec_tickers";
+
 pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
     let client = build_client();
 
@@ -35,8 +40,22 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
             err
         })?;
 
+    // the symbols of each CIK, & the hash of its submissions JSON, as of the last load
+    let hashes = loaded_hashes(pool, LOADER).await?;
     let pg_client = &mut pool.get().await?;
-    tickers.insert(pg_client, tui).await?;
+    let symbols: HashSet<(String, String)> = pg_client
+        .query(sql::SEC_SYMBOLS, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.symbols of the SEC, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| (row.get("file_code"), row.get("symbol")))
+        .collect();
+
+    let loaded = tickers.insert(pg_client, &hashes, &symbols, tui).await?;
+    store_hashes(pool, LOADER, &loaded).await?;
 
     Ok(())
 }
@@ -101,7 +120,15 @@ impl<'de> Deserialize<'de> for Tickers {
 }
 
 impl Tickers {
-    async fn insert(&self, pg_client: &mut PgClient, tui: bool) -> anyhow::Result<()> {
+    /// INSERT each ticker, with the SIC of its submissions JSON; returns the hash of each CIK's
+    /// JSON loaded.
+    async fn insert(
+        &self,
+        pg_client: &mut PgClient,
+        hashes: &HashMap<String, String>,
+        symbols: &HashSet<(String, String)>,
+        tui: bool,
+    ) -> anyhow::Result<HashMap<String, String>> {
         let time = std::time::Instant::now();
 
         // preprocess pg query as transaction
@@ -127,43 +154,61 @@ impl Tickers {
         };
 
         // iterate over the data stream and execute pg rows
+        let mut loaded = HashMap::new();
         let mut stream = stream::iter(&self.0);
         while let Some(cell) = stream.next().await {
             let path = format!("./buffer/submissions/CIK{}.json", cell.pk);
-            let file: Sic = match crate::fs::read_json(&path).await {
-                Ok(data) => data,
+            let bytes = match tokio::fs::read(&path).await {
+                Ok(bytes) => bytes,
                 Err(err) => {
                     error!("failed to read file, error({err})");
                     continue;
                 }
             };
 
-            let query = query.clone();
-            let transaction = transaction.clone();
-
-            let pb = pb.clone();
-            async move {
-                match transaction
-                    .execute(
-                        &query,
-                        &[
-                            &cell.pk,
-                            &cell.ticker,
-                            &cell.title.to_uppercase(),
-                            &file.sic_description,
-                            &"US",
-                        ],
-                    )
-                    .await
-                {
-                    Ok(_) => {
-                        trace!("stock tickers inserted");
-                        pb.inc(1)
-                    }
-                    Err(err) => error!("failed to insert SEC Company Tickers, error({err})"),
+            // skip known tickers of unchanged companies
+            let hash = content_hash(&bytes);
+            if hashes.get(&cell.pk) == Some(&hash)
+                && symbols.contains(&(cell.pk.clone(), cell.ticker.clone()))
+            {
+                trace!("[{}] unchanged, skipping", &cell.ticker);
+                pb.inc(1);
+                continue;
+            }
+
+            let file: Sic = match serde_json::from_slice(&bytes) {
+                Ok(data) => data,
+                Err(err) => {
+                    error!("failed to deserialize file, error({err})");
+                    continue;
+                }
+            };
+            // a failed INSERT aborts the transaction, so the COMMIT would roll back every ticker;
+            // the hash of a CIK is only recorded once its tickers are inserted
+            match transaction
+                .execute(
+                    &query,
+                    &[
+                        &cell.pk,
+                        &cell.ticker,
+                        &cell.title.to_uppercase(),
+                        &file.sic_description,
+                        &"US",
+                        &file.sic.parse::<i16>().ok(),
+                    ],
+                )
+                .await
+            {
+                Ok(_) => {
+                    trace!("stock tickers inserted");
+                    loaded.insert(cell.pk.clone(), hash);
+                    pb.inc(1)
+                }
+                Err(err) => {
+                    error!("failed to insert SEC Company Tickers, error({err})");
+                    return Err(err.into());
                 }
             }
-            .await;
         }
 
         // unpack the transcation and commit it to the database
@@ -184,7 +229,7 @@ impl Tickers {
             println!("collecting tickers ... done\n");
         }
 
-        Ok(())
+        Ok(loaded)
     }
 }
 
@@ -192,5 +237,8 @@ impl Tickers {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct Sic {
+    /// 4-digit code, e.g. "3571"; blank for some filers.
+    #[serde(default)]
+    sic: String,
     sic_description: String,
 }
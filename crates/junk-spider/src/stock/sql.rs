@@ -7,11 +7,70 @@ use std::collections::HashMap;
 //////////////////////////////////////////////////////////////////
 
 /// `stock.tickers` is the master table for stock tickers, their title, industry labels, and their
-/// nation.
+/// nation; the SIC code is filled in for tickers stored before it was collected.
 pub(crate) static INSERT_TICKER: &'static str = "
-    INSERT INTO stock.symbols (file_code, symbol, title, industry, nation)
-    VALUES ($1, $2, $3, $4, $5)
-    ON CONFLICT (symbol, title, nation, industry) DO NOTHING
+    INSERT INTO stock.symbols (file_code, symbol, title, industry, nation, sic)
+    VALUES ($1, $2, $3, $4, $5, $6)
+    ON CONFLICT (symbol, title, nation, industry)
+    DO UPDATE SET sic = EXCLUDED.sic
+    WHERE stock.symbols.sic IS DISTINCT FROM EXCLUDED.sic
+";
+
+/// Every SEC registrant's CIK, once per company (a company may list multiple symbols).
+pub(crate) static SEC_CIKS: &str = "
+    SELECT file_code, MIN(title) AS title
+    FROM stock.symbols
+    WHERE file_code IS NOT NULL
+    GROUP BY file_code
+";
+
+/// Listings outside of the SEC, with their exchange, trading currency, Yahoo suffix, timezone &
+/// session open.
+pub(crate) static UPSERT_LISTING: &str = "
+    INSERT INTO stock.symbols (symbol, title, industry, nation, exchange, currency, yahoo_suffix, timezone, session_open)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+    ON CONFLICT (symbol, title, nation, industry)
+    DO UPDATE SET
+        exchange = EXCLUDED.exchange,
+        currency = EXCLUDED.currency,
+        yahoo_suffix = EXCLUDED.yahoo_suffix,
+        timezone = EXCLUDED.timezone,
+        session_open = EXCLUDED.session_open
+";
+
+/// Benchmarks, i.e. indices & ETFs, flagged by their asset class.
+pub(crate) static UPSERT_BENCHMARK_SYMBOL: &str = "
+    INSERT INTO stock.symbols (symbol, title, industry, nation, asset_class)
+    VALUES ($1, $2, $3, 'US', $4)
+    ON CONFLICT (symbol, title, nation, industry)
+    DO UPDATE SET asset_class = EXCLUDED.asset_class
+";
+
+/// The SIC code of every US equity.
+pub(crate) static EQUITY_SICS: &str = "
+    SELECT pk, sic
+    FROM stock.symbols
+    WHERE asset_class = 'equity'
+        AND nation = 'US'
+        AND sic IS NOT NULL
+";
+
+/// `stock.benchmarks` maps each equity to its default benchmark, by symbol ($2).
+pub(crate) static UPSERT_BENCHMARK: &str = "
+    INSERT INTO stock.benchmarks (symbol_pk, benchmark_pk)
+    SELECT $1, pk
+    FROM stock.symbols
+    WHERE symbol = $2
+        AND asset_class <> 'equity'
+    ON CONFLICT (symbol_pk)
+    DO UPDATE SET benchmark_pk = EXCLUDED.benchmark_pk
+";
+
+/// The symbols of the SEC's CIKs.
+pub(crate) static SEC_SYMBOLS: &str = "
+    SELECT file_code, symbol
+    FROM stock.symbols
+    WHERE file_code IS NOT NULL
 ";
 
 //////////////////////////////////////////////////////////////////
@@ -19,10 +78,363 @@ pub(crate) static INSERT_TICKER: &'static str = "
 //////////////////////////////////////////////////////////////////
 
 /// `stock.prices` is the master table for stock prices.
-pub(crate) static INSERT_PRICE: &'static str = "
-    INSERT INTO stock.prices (symbol_pk, dt, interval_pk, opening, high, low, closing, adj_close, volume)
-    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-    ON CONFLICT (symbol_pk, dt, interval_pk) DO NOTHING
+///
+/// A stored price is only overwritten while it's still open (`closed` is false), bar `adj_close`,
+/// which is refreshed on every fetch, as later dividends & splits adjust the whole history; every
+/// change is logged in `stock.price_revisions`. A fallback provider's price is overwritten
+/// entirely, so that a row's values are always those of its `provider`.
+///
+/// `local_dt` is `dt` on the exchange's wall clock, from its timezone name ($11), and `currency`
+/// the currency the price is quoted in ($12).
+pub(crate) static UPSERT_PRICE: &str = "
+    WITH prev AS (
+        SELECT opening, high, low, closing, adj_close, volume, closed
+        FROM stock.prices
+        WHERE symbol_pk = $1
+            AND dt = $2
+            AND interval_pk = $3
+    ),
+    upsert AS (
+        INSERT INTO stock.prices (symbol_pk, dt, interval_pk, opening, high, low, closing, adj_close, volume, closed, local_dt, currency)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $2 AT TIME ZONE $11, $12)
+        ON CONFLICT (symbol_pk, dt, interval_pk)
+        DO UPDATE SET
+            opening = CASE WHEN stock.prices.closed AND stock.prices.provider = EXCLUDED.provider THEN stock.prices.opening ELSE EXCLUDED.opening END,
+            high = CASE WHEN stock.prices.closed AND stock.prices.provider = EXCLUDED.provider THEN stock.prices.high ELSE EXCLUDED.high END,
+            low = CASE WHEN stock.prices.closed AND stock.prices.provider = EXCLUDED.provider THEN stock.prices.low ELSE EXCLUDED.low END,
+            closing = CASE WHEN stock.prices.closed AND stock.prices.provider = EXCLUDED.provider THEN stock.prices.closing ELSE EXCLUDED.closing END,
+            volume = CASE WHEN stock.prices.closed AND stock.prices.provider = EXCLUDED.provider THEN stock.prices.volume ELSE EXCLUDED.volume END,
+            closed = (stock.prices.closed AND stock.prices.provider = EXCLUDED.provider) OR EXCLUDED.closed,
+            adj_close = EXCLUDED.adj_close,
+            local_dt = EXCLUDED.local_dt,
+            currency = EXCLUDED.currency,
+            provider = EXCLUDED.provider
+        WHERE NOT stock.prices.closed
+            OR stock.prices.adj_close IS DISTINCT FROM EXCLUDED.adj_close
+            OR stock.prices.currency IS NULL
+            OR stock.prices.provider <> EXCLUDED.provider
+    )
+    INSERT INTO stock.price_revisions (symbol_pk, dt, interval_pk, revised, opening, high, low, closing, adj_close, volume)
+    SELECT $1, $2, $3, CLOCK_TIMESTAMP(), opening, high, low, closing, adj_close, volume
+    FROM prev
+    WHERE adj_close IS DISTINCT FROM $8::FLOAT
+        OR (
+            NOT closed
+            AND (opening, high, low, closing, volume)
+                IS DISTINCT FROM ($4::FLOAT, $5::FLOAT, $6::FLOAT, $7::FLOAT, $9::BIGINT)
+        )
+    ON CONFLICT DO NOTHING
+";
+
+/// Symbols by ticker, e.g. the intraday tickers.
+pub(crate) static SYMBOLS_BY_TICKER: &str = "
+    SELECT pk, symbol, title, yahoo_suffix
+    FROM stock.symbols
+    WHERE symbol = ANY($1)
+";
+
+/// The latest price per symbol, for a single interval.
+pub(crate) static LATEST_PRICES: &str = "
+    SELECT symbol_pk, MAX(dt) AS dt
+    FROM stock.prices
+    WHERE interval_pk = $1
+    GROUP BY symbol_pk
+";
+
+/// Consecutive prices more than a day apart, per symbol, for a single interval; plain weekends
+/// (Friday to Monday) are skipped, the trading calendar decides the rest. Only listings on the US
+/// exchanges (no Yahoo suffix) are scanned, as the calendar is theirs; a listing elsewhere, even
+/// of a US company, trades on its exchange's holidays.
+pub(crate) static PRICE_GAPS: &str = "
+    WITH candles AS (
+        SELECT
+            symbol_pk,
+            (dt AT TIME ZONE 'UTC')::DATE AS dated,
+            LAG((dt AT TIME ZONE 'UTC')::DATE) OVER (PARTITION BY symbol_pk ORDER BY dt) AS prev_dated
+        FROM stock.prices
+        WHERE interval_pk = $1
+    )
+    SELECT ca.symbol_pk, sy.symbol, sy.title, sy.yahoo_suffix, ca.prev_dated, ca.dated
+    FROM candles AS ca
+    INNER JOIN stock.symbols AS sy ON sy.pk = ca.symbol_pk
+    WHERE sy.nation = 'US'
+        AND sy.yahoo_suffix = ''
+        AND ca.dated - ca.prev_dated > 1
+        AND NOT (EXTRACT(ISODOW FROM ca.prev_dated) = 5 AND ca.dated - ca.prev_dated = 3)
+    ORDER BY sy.symbol, ca.dated
+";
+
+/// Gaps already known to be unfillable, for a single interval.
+pub(crate) static KNOWN_PRICE_GAPS: &str = "
+    SELECT symbol_pk, gap_start
+    FROM stock.price_gaps
+    WHERE interval_pk = $1
+";
+
+/// Forget a repaired gap, before it's rescanned.
+pub(crate) static DELETE_PRICE_GAP: &str = "
+    DELETE FROM stock.price_gaps
+    WHERE symbol_pk = $1
+        AND interval_pk = $2
+        AND gap_start >= $3
+        AND gap_start <= $4
+";
+
+/// Record a gap the source could not fill.
+pub(crate) static UPSERT_PRICE_GAP: &str = "
+    INSERT INTO stock.price_gaps (symbol_pk, interval_pk, gap_start, gap_end, missing, checked)
+    VALUES ($1, $2, $3, $4, $5, NOW())
+    ON CONFLICT (symbol_pk, interval_pk, gap_start)
+    DO UPDATE SET
+        gap_end = EXCLUDED.gap_end,
+        missing = EXCLUDED.missing,
+        checked = EXCLUDED.checked
+";
+
+/// `stock.chart_errors` holds the latest error Yahoo's chart responded with, per symbol.
+pub(crate) static UPSERT_CHART_ERROR: &str = "
+    INSERT INTO stock.chart_errors (symbol_pk, code, description, checked)
+    VALUES ($1, $2, $3, NOW())
+    ON CONFLICT (symbol_pk)
+    DO UPDATE SET
+        code = EXCLUDED.code,
+        description = EXCLUDED.description,
+        checked = EXCLUDED.checked
+";
+
+/// Forget the chart error of a symbol, once its prices are fetched again.
+pub(crate) static DELETE_CHART_ERROR: &str = "
+    DELETE FROM stock.chart_errors
+    WHERE symbol_pk = $1
+";
+
+/// Daily prices of a fallback provider, dated at the listing's session open in its timezone &
+/// quoted in its currency; never overwrites a stored price.
+pub(crate) static INSERT_FALLBACK_PRICE: &str = "
+    INSERT INTO stock.prices (symbol_pk, dt, interval_pk, opening, high, low, closing, adj_close, volume, closed, local_dt, currency, provider)
+    SELECT
+        sy.pk,
+        ($2::DATE + sy.session_open) AT TIME ZONE sy.timezone,
+        iv.pk,
+        $3::FLOAT,
+        $4::FLOAT,
+        $5::FLOAT,
+        $6::FLOAT,
+        $7::FLOAT,
+        $8::BIGINT,
+        $9::BOOLEAN,
+        $2::DATE + sy.session_open,
+        sy.currency,
+        $10::VARCHAR
+    FROM stock.symbols AS sy
+    INNER JOIN common.intervals AS iv ON iv.interval = '1d'
+    WHERE sy.pk = $1
+    ON CONFLICT (symbol_pk, dt, interval_pk)
+    DO NOTHING
+";
+
+/// Forget the discrepancies of a symbol & provider, before they're reconciled again.
+pub(crate) static DELETE_PRICE_DISCREPANCIES: &str = "
+    DELETE FROM stock.price_discrepancies
+    WHERE symbol_pk = $1
+        AND provider = $2
+";
+
+/// A day on which a fallback provider's adjusted close disagrees with the primary's.
+pub(crate) static INSERT_PRICE_DISCREPANCY: &str = "
+    INSERT INTO stock.price_discrepancies (symbol_pk, dated, provider, primary_close, closing, checked)
+    VALUES ($1, $2, $3, $4, $5, NOW())
+";
+
+//////////////////////////////////////////////////////////////////
+// profiles
+//////////////////////////////////////////////////////////////////
+
+/// Equities without a profile, or with one older than a week.
+pub(crate) static STALE_PROFILES: &str = "
+    SELECT sy.pk, sy.symbol, sy.title, sy.yahoo_suffix
+    FROM stock.symbols AS sy
+    LEFT JOIN stock.profiles AS pr ON pr.symbol_pk = sy.pk
+    WHERE sy.asset_class = 'equity'
+        AND (pr.updated IS NULL OR pr.updated < NOW() - INTERVAL '7 days')
+    ORDER BY pr.updated NULLS FIRST
+";
+
+/// `stock.profiles` holds the latest quoteSummary of each symbol.
+pub(crate) static UPSERT_PROFILE: &str = "
+    INSERT INTO stock.profiles (
+        symbol_pk, sector, industry, employees, beta, float_shares, shares_short, short_ratio,
+        short_percent_of_float, target_low, target_mean, target_median, target_high, analysts,
+        recommendation, updated
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, NOW())
+    ON CONFLICT (symbol_pk)
+    DO UPDATE SET
+        sector = EXCLUDED.sector,
+        industry = EXCLUDED.industry,
+        employees = EXCLUDED.employees,
+        beta = EXCLUDED.beta,
+        float_shares = EXCLUDED.float_shares,
+        shares_short = EXCLUDED.shares_short,
+        short_ratio = EXCLUDED.short_ratio,
+        short_percent_of_float = EXCLUDED.short_percent_of_float,
+        target_low = EXCLUDED.target_low,
+        target_mean = EXCLUDED.target_mean,
+        target_median = EXCLUDED.target_median,
+        target_high = EXCLUDED.target_high,
+        analysts = EXCLUDED.analysts,
+        recommendation = EXCLUDED.recommendation,
+        updated = EXCLUDED.updated
+";
+
+//////////////////////////////////////////////////////////////////
+// options
+//////////////////////////////////////////////////////////////////
+
+/// `stock.option_chains` holds every contract of an option chain snapshot.
+pub(crate) static INSERT_OPTION_CONTRACT: &str = "
+    INSERT INTO stock.option_chains (
+        symbol_pk, snapshot, expiry, put, contract, strike, bid, ask, last_price, volume,
+        open_interest, implied_volatility, in_the_money
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+    ON CONFLICT DO NOTHING
+";
+
+/// `stock.option_expiries` holds the aggregates of each expiry of a snapshot.
+pub(crate) static INSERT_OPTION_EXPIRY: &str = "
+    INSERT INTO stock.option_expiries (
+        symbol_pk, snapshot, expiry, underlying, call_volume, put_volume, call_open_interest,
+        put_open_interest, put_call_volume, put_call_open_interest, atm_strike, atm_iv
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+    ON CONFLICT DO NOTHING
+";
+
+//////////////////////////////////////////////////////////////////
+// corporate actions
+//////////////////////////////////////////////////////////////////
+
+/// `stock.dividends` holds every dividend per ex-date; Yahoo revises past amounts after a split.
+pub(crate) static UPSERT_DIVIDEND: &str = "
+    INSERT INTO stock.dividends (symbol_pk, dt, amount)
+    VALUES ($1, $2, $3)
+    ON CONFLICT (symbol_pk, dt)
+    DO UPDATE SET amount = EXCLUDED.amount
+    WHERE stock.dividends.amount IS DISTINCT FROM EXCLUDED.amount
+";
+
+/// `stock.splits` holds every split, e.g. 4:1 is a numerator of 4 & a denominator of 1.
+pub(crate) static UPSERT_SPLIT: &str = "
+    INSERT INTO stock.splits (symbol_pk, dt, numerator, denominator, ratio)
+    VALUES ($1, $2, $3, $4, $5)
+    ON CONFLICT (symbol_pk, dt)
+    DO UPDATE SET
+        numerator = EXCLUDED.numerator,
+        denominator = EXCLUDED.denominator,
+        ratio = EXCLUDED.ratio
+    WHERE (stock.splits.numerator, stock.splits.denominator)
+        IS DISTINCT FROM (EXCLUDED.numerator, EXCLUDED.denominator)
+";
+
+/// Daily closes of a single symbol & provider, oldest first.
+pub(crate) static SYMBOL_CLOSES: &str = "
+    SELECT dt, closing
+    FROM stock.prices
+    WHERE symbol_pk = $1
+        AND interval_pk = $2
+        AND provider = $3
+        AND closing IS NOT NULL
+    ORDER BY dt
+";
+
+pub(crate) static SYMBOL_DIVIDENDS: &str = "
+    SELECT dt, amount
+    FROM stock.dividends
+    WHERE symbol_pk = $1
+";
+
+pub(crate) static SYMBOL_SPLITS: &str = "
+    SELECT dt, numerator / denominator AS ratio
+    FROM stock.splits
+    WHERE symbol_pk = $1
+        AND denominator > 0
+";
+
+pub(crate) static DELETE_ADJUSTMENTS: &str = "
+    DELETE FROM stock.adjustments
+    WHERE symbol_pk = $1
+        AND interval_pk = $2
+";
+
+/// Symbols with a price of the provider given before their latest split or dividend but without
+/// an adjustment factor, e.g. backfilled by a repair after the factors were computed.
+pub(crate) static UNADJUSTED_SYMBOLS: &str = "
+    WITH latest AS (
+        SELECT symbol_pk, MAX(dt) AS dt
+        FROM (
+            SELECT symbol_pk, dt FROM stock.dividends
+            UNION ALL
+            SELECT symbol_pk, dt FROM stock.splits
+        ) AS events
+        GROUP BY symbol_pk
+    )
+    SELECT DISTINCT pr.symbol_pk
+    FROM stock.prices AS pr
+    INNER JOIN latest AS la ON la.symbol_pk = pr.symbol_pk
+    WHERE pr.interval_pk = $1
+        AND pr.provider = $2
+        AND pr.closing IS NOT NULL
+        AND (pr.dt AT TIME ZONE 'UTC')::DATE < (la.dt AT TIME ZONE 'UTC')::DATE
+        AND NOT EXISTS (
+            SELECT 1
+            FROM stock.adjustments AS ad
+            WHERE ad.symbol_pk = pr.symbol_pk
+                AND ad.interval_pk = pr.interval_pk
+                AND ad.dt = pr.dt
+        )
+";
+
+/// `stock.adjustments` holds the cumulative split & dividend factors of every price before the
+/// latest event; later prices are unadjusted, i.e. a factor of 1.
+pub(crate) static INSERT_ADJUSTMENT: &str = "
+    INSERT INTO stock.adjustments (symbol_pk, interval_pk, dt, split_factor, dividend_factor)
+    VALUES ($1, $2, $3, $4, $5)
+";
+
+//////////////////////////////////////////////////////////////////
+// bulk downloads
+//////////////////////////////////////////////////////////////////
+/// The validators of a bulk file's last download.
+pub(crate) static SEC_DOWNLOAD: &str = "
+    SELECT etag, last_modified
+    FROM stock.sec_downloads
+    WHERE url = $1
+";
+
+pub(crate) static UPSERT_SEC_DOWNLOAD: &str = "
+    INSERT INTO stock.sec_downloads (url, etag, last_modified, downloaded)
+    VALUES ($1, $2, $3, NOW())
+    ON CONFLICT (url)
+    DO UPDATE SET
+        etag = EXCLUDED.etag,
+        last_modified = EXCLUDED.last_modified,
+        downloaded = EXCLUDED.downloaded
+";
+
+pub(crate) static SEC_HASHES: &str = "
+    SELECT cik, hash
+    FROM stock.sec_hashes
+    WHERE loader = $1
+";
+
+pub(crate) static UPSERT_SEC_HASH: &str = "
+    INSERT INTO stock.sec_hashes (cik, loader, hash, loaded)
+    VALUES ($1, $2, $3, NOW())
+    ON CONFLICT (cik, loader)
+    DO UPDATE SET
+        hash = EXCLUDED.hash,
+        loaded = EXCLUDED.loaded
 ";
 
 //////////////////////////////////////////////////////////////////
@@ -42,6 +454,69 @@ pub(crate) static COPY_METRIC: &'static str = "
     FROM STDIN WITH (FORMAT binary)
 ";
 
+/// The rows of a symbol that identify a metric, filed since the date given, if any.
+pub(crate) static EXISTING_METRICS: &str = "
+    SELECT symbol_pk, metric_pk, acc_pk, end_date, filing_date, period, form, val, accn
+    FROM stock.metrics
+    WHERE symbol_pk = $1
+        AND ($2::DATE IS NULL OR filing_date >= $2)
+";
+
+/// The symbols with any rows in `stock.metrics`.
+pub(crate) static SYMBOLS_WITH_METRICS: &str = "
+    SELECT s.pk
+    FROM stock.symbols s
+    WHERE EXISTS (
+        SELECT 1
+        FROM stock.metrics m
+        WHERE m.symbol_pk = s.pk
+    )
+";
+
+/// The last day of the EDGAR daily index refreshed.
+pub(crate) static LAST_METRIC_REFRESH: &str = "
+    SELECT MAX(dated)
+    FROM stock.metric_refreshes
+";
+
+/// The latest filing of `stock.metrics`, i.e. of the last companyfacts.zip scraped.
+pub(crate) static LATEST_METRIC_FILING: &str = "
+    SELECT MAX(filing_date)
+    FROM stock.metrics
+    WHERE period <> 'I'
+";
+
+pub(crate) static INSERT_METRIC_REFRESH: &str = "
+    INSERT INTO stock.metric_refreshes (dated, filers, refreshed)
+    VALUES ($1, $2, NOW())
+    ON CONFLICT (dated)
+    DO UPDATE SET
+        filers = EXCLUDED.filers,
+        refreshed = EXCLUDED.refreshed
+";
+
+/// The filers that failed a refresh, & the day they're refreshed from.
+pub(crate) static METRIC_REFRESH_FAILURES: &str = "
+    SELECT cik, since
+    FROM stock.metric_refresh_failures
+";
+
+/// A filer that failed to refresh; a failed retry keeps its original `since`.
+pub(crate) static UPSERT_METRIC_REFRESH_FAILURE: &str = "
+    INSERT INTO stock.metric_refresh_failures (cik, since, failed)
+    VALUES ($1, $2, NOW())
+    ON CONFLICT (cik)
+    DO UPDATE SET
+        since = LEAST(stock.metric_refresh_failures.since, EXCLUDED.since),
+        failed = EXCLUDED.failed
+";
+
+/// The retried filers that have since refreshed.
+pub(crate) static DELETE_METRIC_REFRESH_FAILURES: &str = "
+    DELETE FROM stock.metric_refresh_failures
+    WHERE cik = ANY($1)
+";
+
 /// When a new metric is found (when scraping the SEC's compfanyfacts.zip), insert it into
 /// the metrics library, giving it a Primary Key.
 pub(crate) static INSERT_METRIC_PK: &'static str = "
@@ -76,11 +551,541 @@ pub(crate) static INSERT_METRIC_ACC_PK: &'static str = "
     ON CONFLICT (pk) DO NOTHING
 ";
 
+//////////////////////////////////////////////////////////////////
+// standardized financials
+//////////////////////////////////////////////////////////////////
+/// Symbols with any metrics.
+pub(crate) static METRIC_SYMBOLS: &str = "
+    SELECT sy.pk, sy.symbol, sy.title
+    FROM stock.symbols sy
+    WHERE EXISTS (
+        SELECT 1
+        FROM stock.metrics m
+        WHERE m.symbol_pk = sy.pk
+    )
+    ORDER BY sy.pk
+";
+
+/// The filed (i.e. not inferred) values of a symbol's metrics, of the concepts given.
+pub(crate) static STD_FINANCIAL_FACTS: &str = "
+    SELECT lib.metric, m.start_date, m.end_date, m.filing_date, m.val
+    FROM stock.metrics m
+    INNER JOIN stock.metrics_lib lib
+        ON m.metric_pk = lib.pk
+    WHERE m.symbol_pk = $1
+        AND lib.metric = ANY($2)
+        AND m.period <> 'I'
+";
+
+pub(crate) static DELETE_STD_FINANCIAL_ITEMS: &str = "
+    DELETE FROM stock.std_financial_items
+    WHERE symbol_pk = $1
+";
+
+pub(crate) static INSERT_STD_FINANCIAL_ITEM: &str = "
+    INSERT INTO stock.std_financial_items (symbol_pk, end_date, line_item, val, concept, derivation)
+    VALUES ($1, $2, $3, $4, $5, $6)
+";
+
+pub(crate) static REFRESH_STD_FINANCIALS: &str = "
+    REFRESH MATERIALIZED VIEW stock.std_financials
+";
+
 //////////////////////////////////////////////////////////////////
 // filings
 //////////////////////////////////////////////////////////////////
+/// A filing of a CIK's submissions; accession numbers are unique, so filings are only inserted.
+pub(crate) static INSERT_FILING_INDEX: &str = "
+    INSERT INTO stock.filing_index (cik, accession, form, filing_date, report_date, primary_document, url)
+    VALUES ($1, $2, $3, $4, $5, $6, $7)
+    ON CONFLICT (cik, accession) DO NOTHING
+";
+
+/// Filings of `stock.filing_index` not downloaded yet, of the form types & since the date given;
+/// stored under the first symbol of their CIK.
+pub(crate) static PENDING_FILINGS: &str = "
+    SELECT sy.pk, sy.symbol, fi.accession, fi.form, fi.filing_date, fi.url
+    FROM stock.filing_index fi
+    JOIN LATERAL (
+        SELECT pk, symbol
+        FROM stock.symbols
+        WHERE file_code = fi.cik
+        ORDER BY pk
+        LIMIT 1
+    ) sy ON TRUE
+    WHERE fi.form = ANY($1)
+        AND fi.filing_date >= $2
+        AND fi.primary_document IS NOT NULL
+        AND NOT EXISTS (
+            SELECT 1
+            FROM stock.filings f
+            WHERE f.stock_id = sy.pk
+                AND f.filename = fi.accession
+        )
+    ORDER BY fi.filing_date DESC
+";
+
+/// A filing's text, & its search vector; `filename` is the accession number.
 pub(crate) static INSERT_FILING: &'static str = "
     INSERT INTO stock.filings (stock_id, dated, filename, filetype, url, content, content_ts)
-    VALUES ($1, $2, $3, $4, $5, $6, to_tsvector($6))
+    VALUES ($1, $2, $3, $4, $5, $6, to_tsvector('english', $6::TEXT))
     ON CONFLICT (stock_id, filename) DO NOTHING
 ";
+
+/// Full-text search of `stock.filings` (web search syntax, e.g. `"goodwill impairment" -lease`),
+/// optionally of a single form type; headlines are only built for the top ranked filings.
+pub(crate) static SEARCH_FILINGS: &str = "
+    WITH hits AS (
+        SELECT f.stock_id, f.dated, f.filetype, f.url, f.content, q.query,
+            ts_rank(f.content_ts, q.query) AS rank
+        FROM stock.filings f
+        CROSS JOIN websearch_to_tsquery('english', $1) AS q(query)
+        WHERE f.content_ts @@ q.query
+            AND ($2::VARCHAR IS NULL OR f.filetype = $2)
+        ORDER BY rank DESC
+        LIMIT $3
+    )
+    SELECT sy.symbol, sy.title, hits.filetype, hits.dated, hits.url, hits.rank,
+        ts_headline('english', hits.content, hits.query, 'MaxFragments=2, MaxWords=25, MinWords=10') AS headline
+    FROM hits
+    JOIN stock.symbols sy ON sy.pk = hits.stock_id
+    ORDER BY hits.rank DESC
+";
+
+//////////////////////////////////////////////////////////////////
+// insiders
+//////////////////////////////////////////////////////////////////
+/// Ownership filings of `stock.filing_index` not parsed yet, of the form types & since the date
+/// given; a filing indexed under both its issuer & owner is only parsed once.
+pub(crate) static PENDING_INSIDER_FILINGS: &str = "
+    SELECT DISTINCT ON (fi.accession) fi.accession, fi.form, fi.filing_date, fi.url, fi.primary_document
+    FROM stock.filing_index fi
+    WHERE fi.form = ANY($1)
+        AND fi.filing_date >= $2
+        AND fi.primary_document IS NOT NULL
+        AND NOT EXISTS (
+            SELECT 1
+            FROM stock.insider_filings f
+            WHERE f.accession = fi.accession
+        )
+    ORDER BY fi.accession
+";
+
+/// A transaction (or holding) of an ownership filing, linked to the issuer's first symbol.
+pub(crate) static INSERT_INSIDER_TRANSACTION: &str = "
+    INSERT INTO stock.insider_transactions (accession, seq, symbol_pk, issuer_cik, owner_cik, owner, relationship, security, derivative, transaction_code, dated, shares, price, acquired, shares_after, direct)
+    VALUES ($1, $2, (SELECT MIN(pk) FROM stock.symbols WHERE file_code = $3), $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+    ON CONFLICT (accession, seq) DO NOTHING
+";
+
+pub(crate) static INSERT_INSIDER_FILING: &str = "
+    INSERT INTO stock.insider_filings (accession, issuer_cik, form, filing_date, lines, parsed)
+    VALUES ($1, $2, $3, $4, $5, NOW())
+    ON CONFLICT (accession) DO NOTHING
+";
+
+//////////////////////////////////////////////////////////////////
+// institutions
+//////////////////////////////////////////////////////////////////
+/// The 13F-HR filings of those given that were already parsed.
+pub(crate) static PARSED_13F_FILINGS: &str = "
+    SELECT accession
+    FROM stock.institutional_filings
+    WHERE accession = ANY($1)
+";
+
+/// A filer's latest period parsed before the period given; NULL if none.
+pub(crate) static PREVIOUS_13F_PERIOD: &str = "
+    SELECT MAX(period) AS period
+    FROM stock.institutional_filings
+    WHERE filer_cik = $1
+        AND period < $2
+";
+
+/// The positions of a filer's period.
+pub(crate) static PREVIOUS_INSTITUTIONAL_HOLDINGS: &str = "
+    SELECT cusip, shares
+    FROM stock.institutional_holdings
+    WHERE filer_cik = $1
+        AND period = $2
+";
+
+/// A position of a filer's period, summed per CUSIP; a later filing of the period replaces it.
+pub(crate) static UPSERT_INSTITUTIONAL_HOLDING: &str = "
+    INSERT INTO stock.institutional_holdings (filer_cik, period, cusip, issuer, class, value, shares, share_type)
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+    ON CONFLICT (filer_cik, period, cusip)
+    DO UPDATE SET
+        issuer = EXCLUDED.issuer,
+        class = EXCLUDED.class,
+        value = EXCLUDED.value,
+        shares = EXCLUDED.shares,
+        share_type = EXCLUDED.share_type
+";
+
+/// The change in a position since the filer's previous period.
+pub(crate) static UPSERT_INSTITUTIONAL_CHANGE: &str = "
+    INSERT INTO stock.institutional_changes (filer_cik, period, cusip, shares, prev_shares, change, status)
+    VALUES ($1, $2, $3, $4, $5, $4 - $5, $6)
+    ON CONFLICT (filer_cik, period, cusip)
+    DO UPDATE SET
+        shares = EXCLUDED.shares,
+        prev_shares = EXCLUDED.prev_shares,
+        change = EXCLUDED.change,
+        status = EXCLUDED.status
+";
+
+pub(crate) static INSERT_13F_FILING: &str = "
+    INSERT INTO stock.institutional_filings (accession, filer_cik, filer, period, filing_date, positions, parsed)
+    VALUES ($1, $2, $3, $4, $5, $6, NOW())
+    ON CONFLICT (accession) DO NOTHING
+";
+
+/// CUSIPs held that haven't been mapped yet.
+pub(crate) static UNMAPPED_CUSIPS: &str = "
+    SELECT DISTINCT ih.cusip
+    FROM stock.institutional_holdings ih
+    WHERE NOT EXISTS (
+        SELECT 1
+        FROM stock.cusips c
+        WHERE c.cusip = ih.cusip
+    )
+";
+
+/// A CUSIP's US ticker (if OpenFIGI knows it), linked to its symbol.
+pub(crate) static UPSERT_CUSIP: &str = "
+    INSERT INTO stock.cusips (cusip, ticker, figi, symbol_pk, checked)
+    VALUES ($1, $2, $3, (SELECT MIN(pk) FROM stock.symbols WHERE symbol = $2 AND nation = 'US'), NOW())
+    ON CONFLICT (cusip)
+    DO UPDATE SET
+        ticker = EXCLUDED.ticker,
+        figi = EXCLUDED.figi,
+        symbol_pk = EXCLUDED.symbol_pk,
+        checked = EXCLUDED.checked
+";
+
+//////////////////////////////////////////////////////////////
+// -- TESTS --
+//////////////////////////////////////////////////////////////
+
+#[tokio::test]
+async fn closed_prices_only_revise_adj_close() {
+    dotenv::dotenv().ok();
+
+    // open a connection to the database, using Env Var
+    let (mut pg_client, pg_conn) =
+        tokio_postgres::connect(&dotenv::var("FINDUMP_URL").unwrap(), tokio_postgres::NoTls)
+            .await
+            .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = pg_conn.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+
+    // never committed, so nothing is left behind
+    let transaction = pg_client.transaction().await.unwrap();
+    let (symbol_pk, interval_pk) = (-1_i32, 3_i16);
+    let open_day = chrono::DateTime::from_timestamp(1_704_205_800, 0).unwrap();
+    let closed_day = chrono::DateTime::from_timestamp(1_704_119_400, 0).unwrap();
+
+    // (dt, closing, adj_close, closed); the closed day is refetched after a dividend, and the
+    // open day mid-session, then once closed
+    for (dt, closing, adj_close, closed) in [
+        (closed_day, 100.0, 90.0, true),
+        (closed_day, 101.0, 85.0, true),
+        (open_day, 50.0, 50.0, false),
+        (open_day, 55.0, 55.0, true),
+    ] {
+        transaction
+            .execute(
+                UPSERT_PRICE,
+                &[
+                    &symbol_pk,
+                    &dt,
+                    &interval_pk,
+                    &closing,
+                    &closing,
+                    &closing,
+                    &closing,
+                    &adj_close,
+                    &1_i64,
+                    &closed,
+                    &"America/New_York",
+                    &"USD",
+                ],
+            )
+            .await
+            .unwrap();
+    }
+
+    // a closed price keeps its values, bar the restated `adj_close`
+    let prices: Vec<(f64, f64)> = transaction
+        .query(
+            "SELECT closing, adj_close FROM stock.prices WHERE symbol_pk = $1 ORDER BY dt",
+            &[&symbol_pk],
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.get("closing"), row.get("adj_close")))
+        .collect();
+    assert_eq!(prices, vec![(100.0, 85.0), (55.0, 55.0)]);
+
+    // the values each fetch overwrote
+    let revisions: Vec<(f64, f64)> = transaction
+        .query(
+            "SELECT closing, adj_close FROM stock.price_revisions
+            WHERE symbol_pk = $1
+            ORDER BY revised",
+            &[&symbol_pk],
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.get("closing"), row.get("adj_close")))
+        .collect();
+    assert_eq!(revisions, vec![(100.0, 90.0), (50.0, 50.0)]);
+}
+
+#[tokio::test]
+async fn fallback_prices_revised_by_the_primary() {
+    dotenv::dotenv().ok();
+
+    // open a connection to the database, using Env Var
+    let (mut pg_client, pg_conn) =
+        tokio_postgres::connect(&dotenv::var("FINDUMP_URL").unwrap(), tokio_postgres::NoTls)
+            .await
+            .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = pg_conn.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+
+    // never committed, so nothing is left behind
+    let transaction = pg_client.transaction().await.unwrap();
+    let (symbol_pk, interval_pk) = (-1_i32, 3_i16);
+    let day = chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+    transaction
+        .execute(
+            "INSERT INTO stock.symbols (pk, symbol, title, industry, nation)
+            VALUES ($1, 'TEST', 'TEST', 'TEST', 'US')",
+            &[&symbol_pk],
+        )
+        .await
+        .unwrap();
+
+    // Stooq fills the day, closed, then Yahoo returns it
+    transaction
+        .execute(
+            INSERT_FALLBACK_PRICE,
+            &[
+                &symbol_pk, &day, &100.0, &100.0, &100.0, &100.0, &100.0, &1_i64, &true, &"Stooq",
+            ],
+        )
+        .await
+        .unwrap();
+    let dt = chrono::DateTime::from_timestamp(1_704_205_800, 0).unwrap();
+    transaction
+        .execute(
+            UPSERT_PRICE,
+            &[
+                &symbol_pk,
+                &dt,
+                &interval_pk,
+                &101.0,
+                &102.0,
+                &99.0,
+                &101.5,
+                &98.0,
+                &2_i64,
+                &true,
+                &"America/New_York",
+                &"USD",
+            ],
+        )
+        .await
+        .unwrap();
+
+    // the row is Yahoo's, with Yahoo's values
+    let row = transaction
+        .query_one(
+            "SELECT provider, opening, high, low, closing, adj_close, volume, closed
+            FROM stock.prices
+            WHERE symbol_pk = $1",
+            &[&symbol_pk],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, String>("provider"), "Yahoo");
+    let ohlc: (f64, f64, f64, f64, f64, i64) = (
+        row.get("opening"),
+        row.get("high"),
+        row.get("low"),
+        row.get("closing"),
+        row.get("adj_close"),
+        row.get("volume"),
+    );
+    assert_eq!(ohlc, (101.0, 102.0, 99.0, 101.5, 98.0, 2));
+    assert!(row.get::<_, bool>("closed"));
+}
+
+#[tokio::test]
+async fn fallback_prices_left_unadjusted() {
+    dotenv::dotenv().ok();
+
+    // open a connection to the database, using Env Var
+    let (mut pg_client, pg_conn) =
+        tokio_postgres::connect(&dotenv::var("FINDUMP_URL").unwrap(), tokio_postgres::NoTls)
+            .await
+            .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = pg_conn.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+
+    // never committed, so nothing is left behind
+    let transaction = pg_client.transaction().await.unwrap();
+    let (symbol_pk, interval_pk) = (-1_i32, 3_i16);
+    let day = |day: u32| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+    transaction
+        .execute(
+            "INSERT INTO stock.symbols (pk, symbol, title, industry, nation)
+            VALUES ($1, 'TEST', 'TEST', 'TEST', 'US')",
+            &[&symbol_pk],
+        )
+        .await
+        .unwrap();
+
+    // a 2:1 split on the 5th; Yahoo has the 2nd & 4th, Stooq (already adjusted) the 3rd
+    for (dated, closing, provider) in [
+        (day(2), 50.0, "Yahoo"),
+        (day(3), 51.0, "Stooq"),
+        (day(4), 52.0, "Yahoo"),
+        (day(5), 104.0, "Yahoo"),
+    ] {
+        transaction
+            .execute(
+                INSERT_FALLBACK_PRICE,
+                &[
+                    &symbol_pk, &dated, &closing, &closing, &closing, &closing, &closing, &1_i64,
+                    &true, &provider,
+                ],
+            )
+            .await
+            .unwrap();
+    }
+    let split_dt = day(5).and_hms_opt(14, 30, 0).unwrap().and_utc();
+    transaction
+        .execute(
+            "INSERT INTO stock.splits (symbol_pk, dt, numerator, denominator)
+            VALUES ($1, $2, 2, 1)",
+            &[&symbol_pk, &split_dt],
+        )
+        .await
+        .unwrap();
+
+    // only Yahoo's closes are adjusted
+    let closes: Vec<f64> = transaction
+        .query(SYMBOL_CLOSES, &[&symbol_pk, &interval_pk, &"Yahoo"])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.get("closing"))
+        .collect();
+    assert_eq!(closes, vec![50.0, 52.0, 104.0]);
+
+    // once they have factors, the symbol is adjusted, even though Stooq's close has none
+    transaction
+        .execute(
+            "INSERT INTO stock.adjustments (symbol_pk, interval_pk, dt, split_factor, dividend_factor)
+            SELECT symbol_pk, interval_pk, dt, 2, 1
+            FROM stock.prices
+            WHERE symbol_pk = $1
+                AND provider = 'Yahoo'
+                AND dt < $2",
+            &[&symbol_pk, &split_dt],
+        )
+        .await
+        .unwrap();
+    let unadjusted: Vec<i32> = transaction
+        .query(UNADJUSTED_SYMBOLS, &[&interval_pk, &"Yahoo"])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| row.get("symbol_pk"))
+        .collect();
+    assert!(!unadjusted.contains(&symbol_pk));
+
+    // so Stooq's close isn't adjusted a second time
+    let adj_closing: f64 = transaction
+        .query_one(
+            "SELECT ap.adj_closing
+            FROM stock.adjusted_prices AS ap
+            INNER JOIN stock.prices AS pr USING (symbol_pk, interval_pk, dt)
+            WHERE pr.symbol_pk = $1
+                AND pr.provider = 'Stooq'",
+            &[&symbol_pk],
+        )
+        .await
+        .unwrap()
+        .get("adj_closing");
+    assert_eq!(adj_closing, 51.0);
+}
+
+#[tokio::test]
+async fn price_gaps_of_us_listings() {
+    dotenv::dotenv().ok();
+
+    // open a connection to the database, using Env Var
+    let (mut pg_client, pg_conn) =
+        tokio_postgres::connect(&dotenv::var("FINDUMP_URL").unwrap(), tokio_postgres::NoTls)
+            .await
+            .unwrap();
+    tokio::spawn(async move {
+        if let Err(e) = pg_conn.await {
+            eprintln!("connection error: {}", e);
+        }
+    });
+
+    // never committed, so nothing is left behind
+    let transaction = pg_client.transaction().await.unwrap();
+    let interval_pk = 3_i16;
+    let day = |day: u32| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+
+    // the same company on Nasdaq & the TSX, both missing Wed 3rd to Fri 5th
+    for (symbol_pk, suffix) in [(-1_i32, ""), (-2_i32, ".TO")] {
+        transaction
+            .execute(
+                "INSERT INTO stock.symbols (pk, symbol, title, industry, nation, yahoo_suffix)
+                VALUES ($1, 'TEST', 'TEST', $2, 'US', $2)",
+                &[&symbol_pk, &suffix],
+            )
+            .await
+            .unwrap();
+        for dated in [day(2), day(8)] {
+            transaction
+                .execute(
+                    INSERT_FALLBACK_PRICE,
+                    &[
+                        &symbol_pk, &dated, &1.0, &1.0, &1.0, &1.0, &1.0, &1_i64, &true, &"Yahoo",
+                    ],
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    // only the US listing is scanned
+    let gaps: Vec<(i32, chrono::NaiveDate)> = transaction
+        .query(PRICE_GAPS, &[&interval_pk])
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.get("symbol_pk"), row.get("prev_dated")))
+        .filter(|(symbol_pk, _)| *symbol_pk < 0)
+        .collect();
+    assert_eq!(gaps, vec![(-1, day(2))]);
+}
@@ -0,0 +1,692 @@
+use crate::stock::sql;
+use chrono::NaiveDate;
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+// Standardized financials, from the raw `stock.metrics` of each symbol's SEC filings:
+//
+// 1. synonym concepts map to a single line item (see `LINE_ITEMS`), e.g. `Revenues`,
+//    `RevenueFromContractWithCustomerExcludingAssessedTax` & `SalesRevenueNet` are all revenue;
+//    the first concept (by priority) reported for a period is used, and the latest filing of a
+//    period's value wins (i.e. restatements)
+//
+// 2. only quarters are kept; flows are rarely filed for the 4th quarter, as the 10-K reports the
+//    fiscal year instead, so Q4 is derived as FY - 9M (else FY - Q1 - Q2 - Q3), of the same concept
+//
+// 3. debt, EPS, margins & returns are computed from the line items of each quarter
+//
+// Each line item is stored in `stock.std_financial_items`, with the concept(s) it came from, and
+// how it was derived; `stock.std_financials` pivots them per quarter, and is refreshed after.
+
+/// How a line item is reported, and so standardized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// A total over a duration, e.g. revenue; Q4 is derived from the fiscal year.
+    Flow,
+
+    /// An average over a duration, e.g. weighted average shares; never derived.
+    Average,
+
+    /// A balance at an instant, e.g. assets.
+    Balance,
+}
+
+/// Line item = its kind, & synonym concepts by priority.
+const LINE_ITEMS: [(&str, Kind, &[&str]); 34] = [
+    // income statement
+    (
+        "revenue",
+        Kind::Flow,
+        &[
+            "Revenues",
+            "RevenueFromContractWithCustomerExcludingAssessedTax",
+            "RevenueFromContractWithCustomerIncludingAssessedTax",
+            "SalesRevenueNet",
+            "SalesRevenueGoodsNet",
+        ],
+    ),
+    ("gross_profit", Kind::Flow, &["GrossProfit"]),
+    ("operating_income", Kind::Flow, &["OperatingIncomeLoss"]),
+    (
+        "earnings",
+        Kind::Flow,
+        &[
+            "NetIncomeLoss",
+            "ProfitLoss",
+            "NetIncomeLossAvailableToCommonStockholdersBasic",
+        ],
+    ),
+    (
+        "avg_shares",
+        Kind::Average,
+        &["WeightedAverageNumberOfSharesOutstandingBasic"],
+    ),
+    (
+        "value_of_shares_bought_back",
+        Kind::Flow,
+        &[
+            "PaymentsForRepurchaseOfCommonStock",
+            "StockRepurchasedDuringPeriodValue",
+            "StockRepurchasedAndRetiredDuringPeriodValue",
+        ],
+    ),
+    (
+        "dividend_payout",
+        Kind::Flow,
+        &["PaymentsOfDividends", "PaymentsOfDividendsCommonStock"],
+    ),
+    // assets
+    ("assets", Kind::Balance, &["Assets"]),
+    ("assets_current", Kind::Balance, &["AssetsCurrent"]),
+    ("assets_non_current", Kind::Balance, &["AssetsNoncurrent"]),
+    (
+        "cash",
+        Kind::Balance,
+        &[
+            "CashAndCashEquivalentsAtCarryingValue",
+            "CashCashEquivalentsRestrictedCashAndRestrictedCashEquivalents",
+            "Cash",
+        ],
+    ),
+    (
+        "marketable_securities_current",
+        Kind::Balance,
+        &[
+            "MarketableSecuritiesCurrent",
+            "AvailableForSaleSecuritiesDebtSecuritiesCurrent",
+        ],
+    ),
+    (
+        "nontrade_receivable_current",
+        Kind::Balance,
+        &["NontradeReceivablesCurrent"],
+    ),
+    (
+        "nontrade_receivable_non_current",
+        Kind::Balance,
+        &["NontradeReceivablesNoncurrent"],
+    ),
+    ("inventory_net", Kind::Balance, &["InventoryNet"]),
+    (
+        "property_plant_and_equipment_net",
+        Kind::Balance,
+        &["PropertyPlantAndEquipmentNet"],
+    ),
+    (
+        "other_assets_current",
+        Kind::Balance,
+        &["OtherAssetsCurrent"],
+    ),
+    (
+        "other_assets_non_current",
+        Kind::Balance,
+        &["OtherAssetsNoncurrent"],
+    ),
+    (
+        "accounts_receivable_current",
+        Kind::Balance,
+        &["AccountsReceivableNetCurrent"],
+    ),
+    // liabilities
+    ("liabilities", Kind::Balance, &["Liabilities"]),
+    (
+        "liabilities_current",
+        Kind::Balance,
+        &["LiabilitiesCurrent"],
+    ),
+    (
+        "liabilities_non_current",
+        Kind::Balance,
+        &["LiabilitiesNoncurrent"],
+    ),
+    (
+        "accounts_payable_current",
+        Kind::Balance,
+        &["AccountsPayableCurrent"],
+    ),
+    (
+        "contracts_with_customer_current",
+        Kind::Balance,
+        &["ContractWithCustomerLiabilityCurrent"],
+    ),
+    (
+        "contracts_with_customer_non_current",
+        Kind::Balance,
+        &["ContractWithCustomerLiabilityNoncurrent"],
+    ),
+    ("commercial_paper", Kind::Balance, &["CommercialPaper"]),
+    (
+        "long_term_debt_current",
+        Kind::Balance,
+        &["LongTermDebtCurrent"],
+    ),
+    (
+        "long_term_debt_non_current",
+        Kind::Balance,
+        &["LongTermDebtNoncurrent"],
+    ),
+    (
+        "other_liabilities_current",
+        Kind::Balance,
+        &["OtherLiabilitiesCurrent"],
+    ),
+    (
+        "other_liabilities_non_current",
+        Kind::Balance,
+        &["OtherLiabilitiesNoncurrent"],
+    ),
+    // equity & market mechanics
+    (
+        "equity",
+        Kind::Balance,
+        &[
+            "StockholdersEquity",
+            "StockholdersEquityIncludingPortionAttributableToNoncontrollingInterest",
+        ],
+    ),
+    (
+        "accumulated_earnings",
+        Kind::Balance,
+        &["RetainedEarningsAccumulatedDeficit"],
+    ),
+    (
+        "shares_outstanding",
+        Kind::Balance,
+        &[
+            "EntityCommonStockSharesOutstanding",
+            "CommonStockSharesOutstanding",
+        ],
+    ),
+    ("float", Kind::Balance, &["EntityPublicFloat"]),
+];
+
+/// Durations (in days) of a quarter, 9 months & a fiscal year; fiscal calendars of 52-53 weeks
+/// don't line up with months.
+const QUARTER: (i64, i64) = (80, 100);
+const NINE_MONTHS: (i64, i64) = (260, 290);
+const FISCAL_YEAR: (i64, i64) = (350, 380);
+
+/// A value of `stock.metrics`.
+#[derive(Debug, Clone)]
+struct Fact {
+    concept: String,
+    start_date: Option<NaiveDate>,
+    end_date: NaiveDate,
+    filing_date: NaiveDate,
+    val: f64,
+}
+
+/// A standardized line item of a quarter, & where it came from.
+#[derive(Debug, Clone, PartialEq)]
+struct Item {
+    end_date: NaiveDate,
+    line_item: &'static str,
+    val: f64,
+
+    /// The concept reported, e.g. "SalesRevenueNet", or those computed from, e.g.
+    /// "NetIncomeLoss / WeightedAverageNumberOfSharesOutstandingBasic".
+    concept: String,
+
+    /// "reported", "FY - 9M", "FY - Q1 - Q2 - Q3", or "computed".
+    derivation: &'static str,
+}
+
+/// Standardize the metrics of every symbol, and refresh `stock.std_financials`.
+pub async fn build(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{name:^40}\n{bar}",
+            bar = "=".repeat(40),
+            name = "Standardized Financials"
+        );
+    }
+
+    let symbols: Vec<(i32, String, String)> = pg_client
+        .query(sql::METRIC_SYMBOLS, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch symbols of stock.metrics, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| (row.get("pk"), row.get("symbol"), row.get("title")))
+        .collect();
+    drop(pg_client);
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(symbols.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    info!(
+        "standardizing the financials of {} symbols ...",
+        symbols.len()
+    );
+    stream::iter(&symbols)
+        .for_each_concurrent(num_cpus::get(), |(symbol_pk, symbol, title)| {
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("standardizing financials of [{symbol}] {title}"),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                match standardize_symbol(pool, *symbol_pk).await {
+                    Ok(_) => {
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            "failed to standardize financials of [{symbol}] {title}, error({err})"
+                        );
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+                if tui {
+                    total.expect("totalbar should have unwrapped").inc(1);
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+    }
+
+    let pg_client = pool.get().await?;
+    pg_client
+        .execute(sql::REFRESH_STD_FINANCIALS, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to refresh stock.std_financials, error({err})");
+            err
+        })?;
+
+    if tui {
+        println!("standardizing financials ... done\n");
+    }
+
+    Ok(())
+}
+
+/// Replace the standardized line items of a symbol.
+async fn standardize_symbol(pool: &Pool, symbol_pk: i32) -> anyhow::Result<()> {
+    let time = std::time::Instant::now();
+
+    let concepts: Vec<&str> = LINE_ITEMS
+        .iter()
+        .flat_map(|(_, _, concepts)| concepts.iter().copied())
+        .collect();
+
+    let mut pg_client = pool.get().await?;
+    let facts: Vec<Fact> = pg_client
+        .query(sql::STD_FINANCIAL_FACTS, &[&symbol_pk, &concepts])
+        .await?
+        .into_iter()
+        .map(|row| Fact {
+            concept: row.get("metric"),
+            start_date: row.get("start_date"),
+            end_date: row.get("end_date"),
+            filing_date: row.get("filing_date"),
+            val: row.get("val"),
+        })
+        .collect();
+    let items = standardize(&facts);
+
+    let transaction = pg_client.transaction().await?;
+    transaction
+        .execute(sql::DELETE_STD_FINANCIAL_ITEMS, &[&symbol_pk])
+        .await?;
+    let query = transaction.prepare(sql::INSERT_STD_FINANCIAL_ITEM).await?;
+    for item in &items {
+        transaction
+            .execute(
+                &query,
+                &[
+                    &symbol_pk,
+                    &item.end_date,
+                    &item.line_item,
+                    &item.val,
+                    &item.concept,
+                    &item.derivation,
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await?;
+
+    debug!(
+        "{} standardized line items of {symbol_pk} inserted. {}",
+        items.len(),
+        crate::time_elapsed(time)
+    );
+
+    Ok(())
+}
+
+/// Standardize the facts of a symbol into quarterly line items, ordered by quarter.
+fn standardize(facts: &[Fact]) -> Vec<Item> {
+    let within = |fact: &Fact, (min, max): (i64, i64)| match fact.start_date {
+        Some(start) => (min..=max).contains(&(fact.end_date - start).num_days()),
+        None => false,
+    };
+
+    // the latest filing of each concept's period
+    let mut latest: HashMap<(&str, Option<NaiveDate>, NaiveDate), &Fact> = HashMap::new();
+    for fact in facts {
+        let key = (fact.concept.as_str(), fact.start_date, fact.end_date);
+        match latest.get(&key) {
+            Some(filed) if filed.filing_date >= fact.filing_date => (),
+            _ => {
+                latest.insert(key, fact);
+            }
+        }
+    }
+    let of = |concept: &str| {
+        let mut facts: Vec<&Fact> = latest
+            .values()
+            .filter(|fact| fact.concept == concept)
+            .copied()
+            .collect();
+        facts.sort_by_key(|fact| (fact.end_date, fact.start_date));
+        facts
+    };
+
+    // end date = line item = item; concepts by priority, so the first per quarter is kept
+    let mut quarters: BTreeMap<NaiveDate, HashMap<&'static str, Item>> = BTreeMap::new();
+    for (line_item, kind, concepts) in LINE_ITEMS {
+        for concept in concepts {
+            let facts = of(concept);
+            for fact in &facts {
+                let reported = match kind {
+                    Kind::Balance => fact.start_date.is_none(),
+                    Kind::Flow | Kind::Average => within(fact, QUARTER),
+                };
+                if !reported {
+                    continue;
+                }
+                quarters
+                    .entry(fact.end_date)
+                    .or_default()
+                    .entry(line_item)
+                    .or_insert_with(|| Item {
+                        end_date: fact.end_date,
+                        line_item,
+                        val: fact.val,
+                        concept: concept.to_string(),
+                        derivation: "reported",
+                    });
+            }
+
+            // Q4 = FY - 9M, else FY - Q1 - Q2 - Q3, of the same concept
+            if kind != Kind::Flow {
+                continue;
+            }
+            for year in facts.iter().filter(|fact| within(fact, FISCAL_YEAR)) {
+                let start = year.start_date;
+                let nine_months = facts.iter().find(|fact| {
+                    fact.start_date == start
+                        && fact.end_date < year.end_date
+                        && within(fact, NINE_MONTHS)
+                });
+                let three_quarters: Vec<&&Fact> = facts
+                    .iter()
+                    .filter(|fact| {
+                        fact.start_date >= start
+                            && fact.end_date < year.end_date
+                            && within(fact, QUARTER)
+                    })
+                    .collect();
+                let (val, derivation) = match (nine_months, three_quarters.len()) {
+                    (Some(nine_months), _) => (year.val - nine_months.val, "FY - 9M"),
+                    (None, 3) => (
+                        year.val - three_quarters.iter().map(|q| q.val).sum::<f64>(),
+                        "FY - Q1 - Q2 - Q3",
+                    ),
+                    _ => continue,
+                };
+                quarters
+                    .entry(year.end_date)
+                    .or_default()
+                    .entry(line_item)
+                    .or_insert_with(|| Item {
+                        end_date: year.end_date,
+                        line_item,
+                        val,
+                        concept: concept.to_string(),
+                        derivation,
+                    });
+            }
+        }
+    }
+
+    // computed line items of each quarter
+    for (end_date, items) in quarters.iter_mut() {
+        let debt: Vec<&Item> = ["long_term_debt_current", "long_term_debt_non_current"]
+            .iter()
+            .filter_map(|line_item| items.get(line_item))
+            .collect();
+        if !debt.is_empty() {
+            let debt = Item {
+                end_date: *end_date,
+                line_item: "debt",
+                val: debt.iter().map(|item| item.val).sum(),
+                concept: debt
+                    .iter()
+                    .map(|item| item.concept.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(" + "),
+                derivation: "computed",
+            };
+            items.insert("debt", debt);
+        }
+
+        for (line_item, numerator, denominator) in [
+            ("earnings_perc", "earnings", "revenue"),
+            ("eps", "earnings", "avg_shares"),
+            ("return_on_equity", "earnings", "equity"),
+            ("debt_to_equity", "debt", "equity"),
+            ("return_on_assets", "earnings", "assets"),
+        ] {
+            let (Some(numerator), Some(denominator)) =
+                (items.get(numerator), items.get(denominator))
+            else {
+                continue;
+            };
+            if denominator.val == 0.0 {
+                continue;
+            }
+            let ratio = Item {
+                end_date: *end_date,
+                line_item,
+                val: numerator.val / denominator.val,
+                concept: format!("{} / {}", numerator.concept, denominator.concept),
+                derivation: "computed",
+            };
+            items.insert(line_item, ratio);
+        }
+    }
+
+    quarters
+        .into_values()
+        .flat_map(|items| {
+            let mut items: Vec<Item> = items.into_values().collect();
+            items.sort_by_key(|item| item.line_item);
+            items
+        })
+        .collect()
+}
+
+// -- TESTS --
+
+#[test]
+fn standardized_quarters() {
+    let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).expect("valid date");
+    let fact = |concept: &str, start: Option<NaiveDate>, end, filed, val| Fact {
+        concept: concept.to_string(),
+        start_date: start,
+        end_date: end,
+        filing_date: filed,
+        val,
+    };
+
+    // a fiscal year of Oct - Sep, revenue filed as SalesRevenueNet, bar Q2 (as Revenues)
+    let facts = [
+        fact(
+            "SalesRevenueNet",
+            Some(date(2022, 10, 1)),
+            date(2022, 12, 31),
+            date(2023, 2, 1),
+            100.0,
+        ),
+        fact(
+            "SalesRevenueNet",
+            Some(date(2023, 1, 1)),
+            date(2023, 3, 31),
+            date(2023, 5, 1),
+            90.0,
+        ),
+        fact(
+            "Revenues",
+            Some(date(2023, 1, 1)),
+            date(2023, 3, 31),
+            date(2023, 5, 1),
+            95.0,
+        ),
+        fact(
+            "SalesRevenueNet",
+            Some(date(2022, 10, 1)),
+            date(2023, 6, 30),
+            date(2023, 8, 1),
+            300.0,
+        ),
+        fact(
+            "SalesRevenueNet",
+            Some(date(2022, 10, 1)),
+            date(2023, 9, 30),
+            date(2023, 11, 1),
+            420.0,
+        ),
+        // the FY restated in the next 10-K wins
+        fact(
+            "NetIncomeLoss",
+            Some(date(2022, 10, 1)),
+            date(2023, 9, 30),
+            date(2023, 11, 1),
+            40.0,
+        ),
+        fact(
+            "NetIncomeLoss",
+            Some(date(2022, 10, 1)),
+            date(2023, 9, 30),
+            date(2024, 11, 1),
+            44.0,
+        ),
+        fact(
+            "NetIncomeLoss",
+            Some(date(2022, 10, 1)),
+            date(2023, 6, 30),
+            date(2023, 8, 1),
+            30.0,
+        ),
+        fact(
+            "WeightedAverageNumberOfSharesOutstandingBasic",
+            Some(date(2023, 7, 1)),
+            date(2023, 9, 30),
+            date(2023, 11, 1),
+            7.0,
+        ),
+        fact(
+            "StockholdersEquity",
+            None,
+            date(2023, 9, 30),
+            date(2023, 11, 1),
+            140.0,
+        ),
+        fact(
+            "LongTermDebtNoncurrent",
+            None,
+            date(2023, 9, 30),
+            date(2023, 11, 1),
+            70.0,
+        ),
+    ];
+    let items = standardize(&facts);
+    let item = |end_date, line_item| {
+        items
+            .iter()
+            .find(|item| item.end_date == end_date && item.line_item == line_item)
+            .map(|item| (item.val, item.concept.as_str(), item.derivation))
+    };
+
+    assert_eq!(
+        item(date(2022, 12, 31), "revenue"),
+        Some((100.0, "SalesRevenueNet", "reported"))
+    );
+    assert_eq!(
+        item(date(2023, 3, 31), "revenue"),
+        Some((95.0, "Revenues", "reported"))
+    );
+
+    // Q3 is only filed as 9 months, so no quarter is reported
+    assert_eq!(item(date(2023, 6, 30), "revenue"), None);
+
+    let q4 = date(2023, 9, 30);
+    assert_eq!(
+        item(q4, "revenue"),
+        Some((120.0, "SalesRevenueNet", "FY - 9M"))
+    );
+    assert_eq!(
+        item(q4, "earnings"),
+        Some((14.0, "NetIncomeLoss", "FY - 9M"))
+    );
+    assert_eq!(
+        item(q4, "eps"),
+        Some((
+            2.0,
+            "NetIncomeLoss / WeightedAverageNumberOfSharesOutstandingBasic",
+            "computed"
+        ))
+    );
+    assert_eq!(
+        item(q4, "debt_to_equity"),
+        Some((
+            0.5,
+            "LongTermDebtNoncurrent / StockholdersEquity",
+            "computed"
+        ))
+    );
+    assert_eq!(
+        item(q4, "return_on_equity"),
+        Some((0.1, "NetIncomeLoss / StockholdersEquity", "computed"))
+    );
+}
@@ -0,0 +1,111 @@
+use super::providers::Bar;
+use crate::http::*;
+use chrono::NaiveDate;
+use tracing::trace;
+
+// Daily prices from Stooq, as CSV
+//
+// `https://stooq.com/q/d/l/?s={ticker}.us&i=d`
+//
+// Date,Open,High,Low,Close,Volume
+// 2024-01-02,187.15,188.44,183.885,185.64,82488674
+// ...
+//
+// NOTE: Stooq's prices are adjusted for splits & dividends, so the close is also the adjusted
+// close, and Stooq's rows are left out of `adjustments`; only US listings are mapped (the `.us`
+// suffix).
+
+/// The full daily history of a US ticker.
+pub(super) async fn daily(
+    http_client: &HttpClient,
+    ticker: &str,
+    yahoo_suffix: &str,
+) -> anyhow::Result<Vec<Bar>> {
+    if !yahoo_suffix.is_empty() {
+        return Err(anyhow::anyhow!("only US listings are mapped to Stooq"));
+    }
+
+    let url = format!("https://stooq.com/q/d/l/?s={}&i=d", symbol(ticker));
+    let csv = http_client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    parse(&csv)
+}
+
+/// Map a ticker to Stooq's, e.g. BRK.B = brk-b.us.
+fn symbol(ticker: &str) -> String {
+    format!(
+        "{}.us",
+        ticker.trim().to_lowercase().replace(['.', '/'], "-")
+    )
+}
+
+/// Parse the CSV, skipping malformed rows; anything but the expected header (e.g. "No data") is an
+/// error.
+fn parse(csv: &str) -> anyhow::Result<Vec<Bar>> {
+    let mut lines = csv.lines();
+    match lines.next() {
+        Some(header) if header.starts_with("Date,Open,High,Low,Close") => (),
+        other => {
+            return Err(anyhow::anyhow!(
+                "unexpected Stooq response({})",
+                other.unwrap_or_default()
+            ))
+        }
+    }
+
+    let bars = lines
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            let bar = (|| {
+                let close: f64 = fields.get(4)?.parse().ok()?;
+                Some(Bar {
+                    dated: NaiveDate::parse_from_str(fields.first()?, "%Y-%m-%d").ok()?,
+                    open: fields.get(1)?.parse().ok()?,
+                    high: fields.get(2)?.parse().ok()?,
+                    low: fields.get(3)?.parse().ok()?,
+                    close,
+                    adj_close: close,
+                    volume: fields
+                        .get(5)
+                        .and_then(|volume| volume.parse::<f64>().ok())
+                        .unwrap_or_default() as i64,
+                })
+            })();
+            if bar.is_none() {
+                trace!("skipping malformed Stooq row({line})");
+            }
+            bar
+        })
+        .collect();
+
+    Ok(bars)
+}
+
+// -- TESTS --
+
+#[test]
+fn stooq_csv() {
+    let csv = "Date,Open,High,Low,Close,Volume\n\
+        2024-01-02,187.15,188.44,183.885,185.64,82488674\n\
+        2024-01-03,184.22,185.88,183.43,184.25\n\
+        2024-01-04,,,,\n";
+    let bars = parse(csv).expect("valid csv");
+
+    // volume is optional, e.g. for indices; empty rows are skipped
+    assert_eq!(bars.len(), 2);
+    assert_eq!(
+        bars[0].dated,
+        NaiveDate::from_ymd_opt(2024, 1, 2).expect("valid date")
+    );
+    assert_eq!((bars[0].close, bars[0].volume), (185.64, 82488674));
+    assert_eq!((bars[1].adj_close, bars[1].volume), (184.25, 0));
+
+    assert!(parse("No data").is_err());
+    assert_eq!(symbol("BRK.B"), "brk-b.us");
+}
@@ -1,10 +1,12 @@
-use super::sql;
+use super::{adjustments, providers, sql};
 use crate::http::*;
+use chrono::{DateTime, Days, NaiveTime, TimeDelta, Utc};
 use deadpool_postgres::Pool;
 use futures::{stream, StreamExt};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, info, trace};
@@ -29,7 +31,10 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
     }
     info!("fetching stock.tickers ...");
     let tickers: Vec<Ticker> = pg_client
-        .query("SELECT pk, symbol, title FROM stock.symbols", &[])
+        .query(
+            "SELECT pk, symbol, title, yahoo_suffix FROM stock.symbols",
+            &[],
+        )
         .await
         .map_err(|err| {
             error!("failed to fetch stock.symbols, error({err})");
@@ -40,6 +45,7 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
             pk: row.get(0),
             ticker: row.get(1),
             title: row.get(2),
+            yahoo_suffix: row.get(3),
         })
         .collect();
 
@@ -91,212 +97,586 @@ pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
         (None, None, None, None)
     };
 
-    // stream over tickers and fetch prices from Yahoo Finance
+    // stream over tickers, trying each provider in priority order until one has prices
     info!("fetching Yahoo Finance prices ...");
     let http_client = crate::std_client_build();
     let stream = stream::iter(tickers);
     stream
         .for_each_concurrent(num_cpus::get(), |ticker| {
-        let http_client = &http_client;
-
-        // progress bars
-        let multi = multi.clone();
-        let total = total.clone();
-        let success = success.clone();
-        let fail = fail.clone();
-        async move {
-            // if tui is enabled, create a progress bar, per task currently being executed
-            let spinner = multi.unwrap_or_default().add(
-                ProgressBar::new_spinner()
-                    .with_message(format!("fetching [{}] {}", &ticker.ticker, &ticker.title))
-                    .with_style(
-                        ProgressStyle::default_spinner()
-                            .template("\t   > {msg}")
-                            .expect("failed to set spinner style"),
-                    ),
-            );
-            spinner.enable_steady_tick(Duration::from_millis(50));
+            let http_client = &http_client;
 
-            let url = format!(
-                "https://query2.finance.yahoo.com/v8/finance/chart/{}?range=10y&interval=1d",
-                &ticker.ticker
-            );
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = multi.unwrap_or_default().add(
+                    ProgressBar::new_spinner()
+                        .with_message(format!("fetching [{}] {}", &ticker.ticker, &ticker.title))
+                        .with_style(
+                            ProgressStyle::default_spinner()
+                                .template("\t   > {msg}")
+                                .expect("failed to set spinner style"),
+                        ),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
 
-            // fetch raw http response
-            let response = match http_client.get(url).send().await {
-                Ok(response) => response,
-                Err(err) => {
-                    error!(
-                        "failed to fetch Yahoo Finance prices for [{}] {}, error({err})",
-                        &ticker.ticker, &ticker.title
-                    );
+                let mut collected = false;
+                for provider in providers::PRIORITY {
+                    spinner.set_message(format!(
+                        "fetching [{}] {} from {}",
+                        &ticker.ticker,
+                        &ticker.title,
+                        provider.name()
+                    ));
+                    match provider.collect(http_client, pool, &ticker).await {
+                        Ok(()) => {
+                            collected = true;
+                            break;
+                        }
+                        Err(err) => error!(
+                            "failed to collect {} prices for [{}] {}, error({err})",
+                            provider.name(),
+                            &ticker.ticker,
+                            &ticker.title
+                        ),
+                    }
+                }
 
-                    if tui {
+                if tui {
+                    if collected {
+                        success.unwrap().inc(1);
+                    } else {
                         fail.unwrap().inc(1);
-                        total.unwrap().inc(1);
                     }
-
-                    return;
+                    total.unwrap().inc(1);
                 }
-            };
 
-            // deserialize the response to JSON
-            spinner.set_message(format!(
-                "deserializing [{}] {} reponse",
+                spinner.set_message(format!("{} collected", &ticker.ticker));
+            }
+        })
+        .await;
+
+    fail.expect("fail bar should have unwrapped")
+        .finish_and_clear();
+    success
+        .expect("success bar should have unwrapped")
+        .finish_and_clear();
+    total
+        .expect("total bar should have unwrapped")
+        .finish_and_clear();
+
+    if tui {
+        println!("collecting stock prices ... done")
+    }
+
+    // readjust the symbols whose new prices landed before their latest event
+    adjustments::readjust(pool).await?;
+
+    Ok(())
+}
+
+/// Collect the last 10 years of daily prices of a ticker, with its dividends & splits, as the
+/// primary [Provider](providers::Provider); the prices are readjusted if any events are new or
+/// revised.
+pub(super) async fn chart(
+    http_client: &HttpClient,
+    pool: &Pool,
+    ticker: &Ticker,
+) -> anyhow::Result<()> {
+    let url = format!(
+        "https://query2.finance.yahoo.com/v8/finance/chart/{}?range=10y&interval=1d&events=div,splits",
+        symbol(&ticker.ticker, &ticker.yahoo_suffix)
+    );
+    let mut price_response: PriceResponse = http_client.get(url).send().await?.json().await?;
+
+    // Yahoo explains failures per ticker, e.g. "Not Found: No data found, symbol may be delisted"
+    if let Some(err) = price_response.chart.error.take() {
+        if let Err(err) = record_error(pool, ticker.pk, Some(&err)).await {
+            error!(
+                "failed to record chart error for [{}] {}, error({err})",
                 &ticker.ticker, &ticker.title
-            ));
-            let price_response: PriceResponse = match response.json().await {
-                Ok(json) => json,
-                Err(err) => {
-                    error!(
-                        "failed to parse Yahoo Finance prices for [{}] {}, error({err})",
-                        &ticker.ticker, &ticker.title
-                    );
+            );
+        }
+        return Err(err.into());
+    }
 
-                    if tui {
-                        fail.unwrap().inc(1);
-                        total.unwrap().inc(1);
-                    }
+    let events = price_response.take_events();
+    let prices = price_response
+        .into_prices(ticker.pk, Interval::Day)
+        .ok_or(anyhow::anyhow!("no results found within http response"))?;
 
-                    return;
-                }
-            };
-
-            // transform deserialized response
-            spinner.set_message(format!("transforming {}", &ticker.ticker));
-            let prices = if let Some(data) = price_response.chart.result {
-                trace!(
-                    "price results found; transforming price data for [{}] {}",
-                    &ticker.ticker,
-                    &ticker.title
+    let mut pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+    prices
+        .insert(&mut pg_client, &ticker.pk, &ticker.ticker, &ticker.title)
+        .await?;
+    trace!(
+        "price data inserted successfully for [{}] {}",
+        &ticker.ticker,
+        &ticker.title
+    );
+    if let Err(err) = record_error(pool, ticker.pk, None).await {
+        error!(
+            "failed to clear chart error for [{}] {}, error({err})",
+            &ticker.ticker, &ticker.title
+        );
+    }
+
+    // insert dividends & splits, and readjust the prices if any are new or revised
+    match events.insert(&mut pg_client, ticker.pk).await {
+        Ok(0) => trace!("no new events for [{}] {}", &ticker.ticker, &ticker.title),
+        Ok(changed) => {
+            trace!(
+                "{changed} new events for [{}] {}, readjusting prices",
+                &ticker.ticker,
+                &ticker.title
+            );
+            if let Err(err) = adjustments::recompute(&mut pg_client, ticker.pk).await {
+                error!(
+                    "failed to adjust prices for [{}] {}, error({err})",
+                    &ticker.ticker, &ticker.title
                 );
+            }
+        }
+        Err(err) => error!(
+            "failed to insert events for [{}] {}, error({err})",
+            &ticker.ticker, &ticker.title
+        ),
+    }
 
-                let base = &data[0];
-                let price = &base.indicators.quote[0];
-                let adjclose = &base.indicators.adjclose[0].adjclose;
-                let timestamps = &base.timestamp;
-                let prices = stream::iter(
-                    price
-                        .open
-                        .iter()
-                        .zip(price.high.iter())
-                        .zip(price.low.iter())
-                        .zip(price.close.iter())
-                        .zip(price.volume.iter())
-                        .zip(adjclose.iter())
-                        .zip(timestamps.iter()),
-                )
-                .then(
-                    |((((((open, high), low), close), volume), adj_close), timestamp)| async move {
-                        Price {
-                            stock_pk: ticker.pk,
-                            time: chrono::DateTime::from_timestamp(*timestamp, 0)
-                                .expect("invalid timestamp"),
-                            interval_pk: 3,
-                            open: *open,
-                            high: *high,
-                            low: *low,
-                            close: *close,
-                            adj_close: *adj_close,
-                            volume: *volume,
-                        }
-                    },
-                )
-                .collect::<Vec<Price>>()
-                .await;
+    Ok(())
+}
 
-                trace!(
-                    "price data transformation succesful for [{}], {}",
-                    &ticker.ticker,
-                    &ticker.title
-                );
-                Prices(prices)
-            } else {
-                error!(
-                        "failed to parse Yahoo Finance prices for [{}] {}, error(no results found within http response)",
-                        &ticker.ticker, &ticker.title
-                    );
+#[derive(Debug)]
+pub(super) struct Ticker {
+    pub(super) pk: i32,
+    pub(super) ticker: String,
+    pub(super) title: String,
+    pub(super) yahoo_suffix: String,
+}
 
-                if tui {
-                    fail.unwrap().inc(1);
-                    total.unwrap().inc(1);
-                }
+// intraday
+// ----------------------------------------------------------------------------
 
-                return;
-            };
+/// Tickers to collect intraday prices for, unless set (comma separated) by
+/// `YAHOO_INTRADAY_SYMBOLS`.
+const DEFAULT_INTRADAY_SYMBOLS: [&str; 3] = ["AAPL", "MSFT", "NVDA"];
 
-            // insert price data to pg
-            spinner.set_message(format!(
-                "waiting to insert [{}] {}",
-                &ticker.ticker, &ticker.title
-            ));
-            let mut pg_client = pool
-                .get()
-                .await
-                .map_err(|err| {
-                    error!("failed to get pg client from pool, error({err})");
-                    err
-                })
-                .unwrap();
-            spinner.set_message(format!("inserting [{}] {}", &ticker.ticker, &ticker.title));
-
-            match prices
-                .insert(&mut pg_client, &ticker.pk, &ticker.ticker, &ticker.title)
-                .await
-            {
-                Ok(_) => {
-                    trace!(
-                        "price data inserted successfully for [{}] {}",
+/// The intraday intervals collected by [scrape_intraday].
+pub const INTRADAY: [Interval; 4] = [
+    Interval::Minute,
+    Interval::FiveMinutes,
+    Interval::FifteenMinutes,
+    Interval::Hour,
+];
+
+/// Yahoo chart intervals.
+///
+/// Intraday intervals are only served for a limited lookback, and a limited window per request:
+///
+/// - 1m, within the last 30 days, 7 days per request;
+/// - 5m & 15m, within the last 60 days;
+/// - 1h, within the last 730 days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    Minute,
+    FiveMinutes,
+    FifteenMinutes,
+    Hour,
+    Day,
+}
+
+impl Interval {
+    /// Yahoo's name of the interval.
+    fn name(self) -> &'static str {
+        match self {
+            Interval::Minute => "1m",
+            Interval::FiveMinutes => "5m",
+            Interval::FifteenMinutes => "15m",
+            Interval::Hour => "60m",
+            Interval::Day => "1d",
+        }
+    }
+
+    /// Primary key of the interval in `common.intervals`.
+    fn pk(self) -> i16 {
+        match self {
+            Interval::Minute => 5,
+            Interval::FiveMinutes => 6,
+            Interval::FifteenMinutes => 7,
+            Interval::Hour => 2,
+            Interval::Day => 3,
+        }
+    }
+
+    /// Length of a single candle.
+    fn length(self) -> TimeDelta {
+        match self {
+            Interval::Minute => TimeDelta::minutes(1),
+            Interval::FiveMinutes => TimeDelta::minutes(5),
+            Interval::FifteenMinutes => TimeDelta::minutes(15),
+            Interval::Hour => TimeDelta::hours(1),
+            Interval::Day => TimeDelta::days(1),
+        }
+    }
+
+    /// How far back the interval is served, and the longest window per request; a day short of
+    /// Yahoo's limits, as the lookback is checked against Yahoo's clock.
+    fn limits(self) -> (TimeDelta, TimeDelta) {
+        match self {
+            Interval::Minute => (TimeDelta::days(29), TimeDelta::days(7)),
+            Interval::FiveMinutes | Interval::FifteenMinutes => {
+                (TimeDelta::days(59), TimeDelta::days(59))
+            }
+            Interval::Hour => (TimeDelta::days(729), TimeDelta::days(729)),
+            Interval::Day => (TimeDelta::days(3650), TimeDelta::days(3650)),
+        }
+    }
+}
+
+/// Split the window from `since` (the latest stored candle) to `now` into the chunks Yahoo
+/// allows for the interval; anything before its lookback is unavailable.
+fn windows(
+    interval: Interval,
+    since: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let (lookback, chunk) = interval.limits();
+    let mut start = match since {
+        Some(since) => since.max(now - lookback),
+        None => now - lookback,
+    };
+
+    let mut windows = Vec::new();
+    while start < now {
+        let end = (start + chunk).min(now);
+        windows.push((start, end));
+        start = end;
+    }
+
+    windows
+}
+
+/// Collect the intraday prices of every intraday ticker, resuming from the latest stored candle
+/// of each interval.
+pub async fn scrape_intraday(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    // wait for a pg client from the pool
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{source:^40}\n{bar}",
+            bar = "=".repeat(40),
+            source = "Yahoo! Finance Intraday"
+        )
+    }
+
+    let symbols: Vec<String> = match var("YAHOO_INTRADAY_SYMBOLS") {
+        Ok(symbols) => symbols
+            .split(',')
+            .map(|symbol| symbol.trim().to_uppercase())
+            .filter(|symbol| !symbol.is_empty())
+            .collect(),
+        Err(_) => DEFAULT_INTRADAY_SYMBOLS
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    };
+
+    info!("fetching intraday stock.symbols ...");
+    let tickers: Vec<Ticker> = pg_client
+        .query(sql::SYMBOLS_BY_TICKER, &[&symbols])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.symbols, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| Ticker {
+            pk: row.get("pk"),
+            ticker: row.get("symbol"),
+            title: row.get("title"),
+            yahoo_suffix: row.get("yahoo_suffix"),
+        })
+        .collect();
+
+    // every (ticker, interval), with the latest candle stored
+    let mut tasks = Vec::new();
+    for interval in INTRADAY {
+        let latest: HashMap<i32, DateTime<Utc>> = pg_client
+            .query(sql::LATEST_PRICES, &[&interval.pk()])
+            .await?
+            .into_iter()
+            .map(|row| (row.get("symbol_pk"), row.get("dt")))
+            .collect();
+        for ticker in &tickers {
+            tasks.push((ticker, interval, latest.get(&ticker.pk).copied()));
+        }
+    }
+
+    drop(pg_client);
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(tasks.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    info!("fetching Yahoo Finance intraday prices ...");
+    let http_client = crate::std_client_build();
+    let now = Utc::now();
+    stream::iter(&tasks)
+        .for_each_concurrent(num_cpus::get(), |(ticker, interval, since)| {
+            let http_client = &http_client;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!(
+                        "fetching [{}] {} {} prices",
                         &ticker.ticker,
-                        &ticker.title
-                    );
+                        &ticker.title,
+                        interval.name()
+                    ),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
 
-                    if tui {
-                        success.unwrap().inc(1);
-                        total.unwrap().inc(1);
+                let mut failed = false;
+                for (start, end) in windows(*interval, *since, now) {
+                    let query = format!(
+                        "period1={}&period2={}&interval={}",
+                        start.timestamp(),
+                        end.timestamp(),
+                        interval.name()
+                    );
+                    if let Err(err) = collect(http_client, pool, ticker, &query, *interval).await {
+                        error!(
+                            "failed to collect {} prices for [{}] {}, {start} - {end}, error({err})",
+                            interval.name(),
+                            &ticker.ticker,
+                            &ticker.title
+                        );
+                        failed = true;
                     }
                 }
-                Err(err) => {
-                    error!(
-                        "failed to insert price data for [{}] {}, error({err})",
-                        &ticker.ticker, &ticker.title
-                    );
 
-                    if tui {
-                        fail.unwrap().inc(1);
-                        total.unwrap().inc(1);
+                if tui {
+                    if failed {
+                        fail.expect("failbar should have unwrapped").inc(1);
+                    } else {
+                        success.expect("successbar should have unwrapped").inc(1);
                     }
-
-                    return;
+                    total.expect("totalbar should have unwrapped").inc(1);
                 }
+
+                spinner.finish_and_clear();
             }
+        })
+        .await;
 
-            spinner.set_message(format!("{} collected", &ticker.ticker));
-        }
-    })
-    .await;
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+    }
 
-    fail.expect("fail bar should have unwrapped")
-        .finish_and_clear();
-    success
-        .expect("success bar should have unwrapped")
-        .finish_and_clear();
-    total
-        .expect("total bar should have unwrapped")
-        .finish_and_clear();
+    Ok(())
+}
 
-    if tui {
-        println!("collecting stock prices ... done")
+/// The daily prices of the last 10 years, as a [Provider](providers::Provider).
+pub(super) async fn daily(
+    http_client: &HttpClient,
+    ticker: &str,
+    yahoo_suffix: &str,
+) -> anyhow::Result<Vec<providers::Bar>> {
+    let url = format!(
+        "https://query2.finance.yahoo.com/v8/finance/chart/{}?range=10y&interval=1d",
+        symbol(ticker, yahoo_suffix)
+    );
+    let mut price_response: PriceResponse = http_client.get(url).send().await?.json().await?;
+    if let Some(err) = price_response.chart.error.take() {
+        return Err(err.into());
     }
 
+    let bars = match price_response.into_prices(0, Interval::Day) {
+        Some(prices) => prices
+            .prices
+            .into_iter()
+            .map(|price| providers::Bar {
+                dated: price.time.date_naive(),
+                open: price.open,
+                high: price.high,
+                low: price.low,
+                close: price.close,
+                adj_close: price.adj_close,
+                volume: price.volume,
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(bars)
+}
+
+/// Fetch a single chart `query` of a ticker, and insert its prices.
+async fn collect(
+    http_client: &HttpClient,
+    pool: &Pool,
+    ticker: &Ticker,
+    query: &str,
+    interval: Interval,
+) -> anyhow::Result<()> {
+    let url = format!(
+        "https://query2.finance.yahoo.com/v8/finance/chart/{}?{query}",
+        symbol(&ticker.ticker, &ticker.yahoo_suffix)
+    );
+
+    // unknown symbols respond 404, with the reason in `chart.error`
+    let mut price_response: PriceResponse = http_client.get(url).send().await?.json().await?;
+    if let Some(err) = price_response.chart.error.take() {
+        record_error(pool, ticker.pk, Some(&err)).await?;
+        return Err(err.into());
+    }
+    let prices = match price_response.into_prices(ticker.pk, interval) {
+        Some(prices) => prices,
+        None => return Ok(()),
+    };
+
+    let mut pg_client = pool.get().await?;
+    prices
+        .insert(&mut pg_client, &ticker.pk, &ticker.ticker, &ticker.title)
+        .await?;
+    record_error(pool, ticker.pk, None).await
+}
+
+/// Record the latest chart error of a symbol, or clear it once its prices are fetched again.
+async fn record_error(pool: &Pool, symbol_pk: i32, err: Option<&ChartError>) -> anyhow::Result<()> {
+    let pg_client = pool.get().await?;
+    match err {
+        Some(err) => {
+            pg_client
+                .execute(
+                    sql::UPSERT_CHART_ERROR,
+                    &[&symbol_pk, &err.code, &err.description],
+                )
+                .await?
+        }
+        None => {
+            pg_client
+                .execute(sql::DELETE_CHART_ERROR, &[&symbol_pk])
+                .await?
+        }
+    };
+
     Ok(())
 }
 
-#[derive(Debug)]
-struct Ticker {
-    pk: i32,
-    ticker: String,
-    title: String,
+/// Map a ticker to Yahoo's, i.e. share classes & suffixes are dashed rather than dotted
+/// (BRK.B = BRK-B), US units are `-UN` & warrants `-WT`; non-US listings end with the
+/// exchange's suffix, e.g. `.L`, and keep their own unit & warrant suffixes (DLR-U.TO).
+pub(super) fn symbol(ticker: &str, yahoo_suffix: &str) -> String {
+    let ticker = ticker.trim().to_uppercase().replace(['.', '/'], "-");
+    let ticker = match ticker.rsplit_once('-') {
+        Some((base, "U")) if yahoo_suffix.is_empty() => format!("{base}-UN"),
+        Some((base, "WS")) if yahoo_suffix.is_empty() => format!("{base}-WT"),
+        _ => ticker,
+    };
+
+    format!("{ticker}{yahoo_suffix}")
+}
+
+// session
+// ----------------------------------------------------------------------------
+
+/// A Yahoo session; `quoteSummary` (& `options`) reject requests without a cookie & its crumb.
+#[derive(Debug, Clone)]
+pub(super) struct Session {
+    /// Every cookie set by Yahoo, as a `Cookie` header value.
+    pub(super) cookie: String,
+    pub(super) crumb: String,
+}
+
+/// Handshake for a [Session]: `fc.yahoo.com` sets the cookies (while responding 404), which are
+/// then exchanged for a crumb.
+pub(super) async fn session(http_client: &HttpClient) -> anyhow::Result<Session> {
+    let response = http_client.get("https://fc.yahoo.com").send().await?;
+    let cookie = response
+        .headers()
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next())
+        .collect::<Vec<&str>>()
+        .join("; ");
+    if cookie.is_empty() {
+        return Err(anyhow::anyhow!("no cookie set by fc.yahoo.com"));
+    }
+
+    let crumb = http_client
+        .get("https://query1.finance.yahoo.com/v1/test/getcrumb")
+        .header(reqwest::header::COOKIE, &cookie)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    if crumb.is_empty() || crumb.contains('<') {
+        return Err(anyhow::anyhow!("invalid crumb({crumb})"));
+    }
+    debug!("Yahoo session established");
+
+    Ok(Session { cookie, crumb })
+}
+
+// repair
+// ----------------------------------------------------------------------------
+
+/// Refetch the daily prices missing within a [Gap](super::repair::Gap).
+pub(super) async fn refetch(
+    http_client: &HttpClient,
+    pool: &Pool,
+    gap: &super::repair::Gap,
+) -> anyhow::Result<()> {
+    // `period2` is exclusive
+    let period1 = gap.start.and_time(NaiveTime::MIN).and_utc().timestamp();
+    let period2 = (gap.end + Days::new(1))
+        .and_time(NaiveTime::MIN)
+        .and_utc()
+        .timestamp();
+    let ticker = Ticker {
+        pk: gap.symbol_pk,
+        ticker: gap.symbol.clone(),
+        title: gap.title.clone(),
+        yahoo_suffix: gap.yahoo_suffix.clone(),
+    };
+    collect(
+        http_client,
+        pool,
+        &ticker,
+        &format!("period1={period1}&period2={period2}&interval=1d"),
+        Interval::Day,
+    )
+    .await
 }
 
 // de
@@ -304,7 +684,14 @@ struct Ticker {
 
 // output
 #[derive(Debug)]
-struct Prices(Vec<Price>);
+struct Prices {
+    /// The exchange's timezone, e.g. "America/New_York", for the exchange-local timestamps.
+    timezone: Option<String>,
+
+    /// The currency the prices are quoted in, e.g. "USD", or "GBp" for pence.
+    currency: Option<String>,
+    prices: Vec<Price>,
+}
 
 #[derive(Debug)]
 struct Price {
@@ -312,6 +699,7 @@ struct Price {
     stock_pk: i32,
     time: chrono::DateTime<chrono::Utc>,
     interval_pk: i16,
+    closed: bool,
     open: f64,
     high: f64,
     low: f64,
@@ -332,11 +720,11 @@ impl Prices {
         let time = std::time::Instant::now();
 
         // preprocess pg query as transaction
-        let query = pg_client.prepare(&sql::INSERT_PRICE).await?;
+        let query = pg_client.prepare(&sql::UPSERT_PRICE).await?;
         let transaction = Arc::new(pg_client.transaction().await?);
 
         // iterate over the data stream and execute pg rows
-        let mut stream = stream::iter(&self.0);
+        let mut stream = stream::iter(&self.prices);
         while let Some(cell) = stream.next().await {
             let query = query.clone();
             let transaction = transaction.clone();
@@ -354,6 +742,9 @@ impl Prices {
                             &cell.close,
                             &cell.adj_close,
                             &cell.volume,
+                            &cell.closed,
+                            &self.timezone,
+                            &self.currency,
                         ],
                     )
                     .await
@@ -403,36 +794,308 @@ impl Prices {
 #[derive(Debug, Deserialize)]
 struct PriceResponse {
     chart: Chart,
-    // error: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Chart {
     result: Option<Vec<Result>>,
+    error: Option<ChartError>,
+}
+
+// "error": {
+//     "code": "Not Found",
+//     "description": "No data found, symbol may be delisted"
+// }
+#[derive(Debug, Deserialize, thiserror::Error)]
+#[error("{code}: {description}")]
+struct ChartError {
+    code: String,
+    #[serde(default)]
+    description: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct Result {
+    meta: Option<Meta>,
+
+    /// Missing when there are no prices within the range.
+    #[serde(default)]
     timestamp: Vec<i64>,
     indicators: Indicators,
+    events: Option<Events>,
+}
+
+// "meta": {
+//     "currency": "USD",
+//     "symbol": "NVDA",
+//     "exchangeName": "NMS",
+//     "exchangeTimezoneName": "America/New_York",
+//     "gmtoffset": -14400,
+//     ...
+// }
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    currency: Option<String>,
+    exchange_timezone_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct Indicators {
     quote: Vec<Quote>,
+
+    /// Only returned for daily & longer intervals.
+    #[serde(default)]
     adjclose: Vec<AdjClose>,
 }
 
+// every value is `null` for halted sessions
 #[derive(Debug, Deserialize)]
 struct Quote {
-    open: Vec<f64>,
-    high: Vec<f64>,
-    low: Vec<f64>,
-    close: Vec<f64>,
-    volume: Vec<i64>,
+    #[serde(default)]
+    open: Vec<Option<f64>>,
+    #[serde(default)]
+    high: Vec<Option<f64>>,
+    #[serde(default)]
+    low: Vec<Option<f64>>,
+    #[serde(default)]
+    close: Vec<Option<f64>>,
+    #[serde(default)]
+    volume: Vec<Option<i64>>,
 }
 
 #[derive(Debug, Deserialize)]
 struct AdjClose {
-    adjclose: Vec<f64>,
+    #[serde(default)]
+    adjclose: Vec<Option<f64>>,
+}
+
+// "events": {
+//     "dividends": {
+//         "1707489000": { "amount": 0.24, "date": 1707489000 },
+//         ...
+//     },
+//     "splits": {
+//         "1598880600": {
+//             "date": 1598880600,
+//             "numerator": 4.0,
+//             "denominator": 1.0,
+//             "splitRatio": "4:1"
+//         },
+//         ...
+//     }
+// }
+//
+// NOTE: like the quotes, dividend amounts are split-adjusted by Yahoo
+#[derive(Debug, Default, Deserialize)]
+struct Events {
+    #[serde(default)]
+    dividends: HashMap<String, Dividend>,
+    #[serde(default)]
+    splits: HashMap<String, Split>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dividend {
+    amount: f64,
+    date: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Split {
+    date: i64,
+    numerator: f64,
+    denominator: f64,
+    #[serde(rename = "splitRatio")]
+    split_ratio: String,
+}
+
+impl Events {
+    /// UPSERT self to pg rows, returning the number of new or revised events.
+    async fn insert(&self, pg_client: &mut PgClient, stock_pk: i32) -> anyhow::Result<u64> {
+        let transaction = pg_client.transaction().await?;
+
+        let mut changed = 0;
+        for dividend in self.dividends.values() {
+            let dt = chrono::DateTime::from_timestamp(dividend.date, 0).expect("invalid timestamp");
+            changed += transaction
+                .execute(sql::UPSERT_DIVIDEND, &[&stock_pk, &dt, &dividend.amount])
+                .await?;
+        }
+        for split in self.splits.values() {
+            let dt = chrono::DateTime::from_timestamp(split.date, 0).expect("invalid timestamp");
+            changed += transaction
+                .execute(
+                    sql::UPSERT_SPLIT,
+                    &[
+                        &stock_pk,
+                        &dt,
+                        &split.numerator,
+                        &split.denominator,
+                        &split.split_ratio,
+                    ],
+                )
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(changed)
+    }
+}
+
+impl PriceResponse {
+    /// Take the dividend & split events out of the chart, if any were returned.
+    fn take_events(&mut self) -> Events {
+        self.chart
+            .result
+            .as_mut()
+            .and_then(|result| result.first_mut())
+            .and_then(|result| result.events.take())
+            .unwrap_or_default()
+    }
+
+    /// Transform the chart into prices of an interval, or `None` if there are no results.
+    ///
+    /// Sessions without a close (i.e. halted) are skipped, any other missing value is filled from
+    /// the close, and missing volume is 0.
+    fn into_prices(self, stock_pk: i32, interval: Interval) -> Option<Prices> {
+        let base = self.chart.result?.into_iter().next()?;
+        let (timezone, currency) = match base.meta {
+            Some(meta) => (meta.exchange_timezone_name, meta.currency),
+            None => (None, None),
+        };
+        let now = Utc::now();
+        let price = base.indicators.quote.into_iter().next()?;
+        let adjclose = match base.indicators.adjclose.into_iter().next() {
+            Some(adjclose) => adjclose.adjclose,
+            None => price.close.clone(),
+        };
+
+        let prices = price
+            .open
+            .into_iter()
+            .zip(price.high)
+            .zip(price.low)
+            .zip(price.close)
+            .zip(price.volume)
+            .zip(adjclose)
+            .zip(base.timestamp)
+            .filter_map(
+                |((((((open, high), low), close), volume), adj_close), timestamp)| {
+                    let close = close?;
+                    let time = chrono::DateTime::from_timestamp(timestamp, 0)?;
+                    Some(Price {
+                        stock_pk,
+                        time,
+                        interval_pk: interval.pk(),
+                        closed: time + interval.length() <= now,
+                        open: open.unwrap_or(close),
+                        high: high.unwrap_or(close),
+                        low: low.unwrap_or(close),
+                        close,
+                        adj_close: adj_close.unwrap_or(close),
+                        volume: volume.unwrap_or(0),
+                    })
+                },
+            )
+            .collect();
+
+        Some(Prices {
+            timezone,
+            currency,
+            prices,
+        })
+    }
+}
+
+// -- TESTS --
+
+#[test]
+fn intraday_windows_respect_lookback() {
+    let now = chrono::NaiveDate::from_ymd_opt(2024, 3, 1)
+        .expect("valid date")
+        .and_hms_opt(21, 0, 0)
+        .expect("valid time")
+        .and_utc();
+
+    // 1m: the last 29 days, 7 days per request
+    let windows_1m = windows(Interval::Minute, None, now);
+    assert_eq!(windows_1m.len(), 5);
+    assert_eq!(windows_1m[0].0, now - TimeDelta::days(29));
+    assert_eq!(windows_1m[0].1, now - TimeDelta::days(22));
+    assert_eq!(windows_1m[4], (now - TimeDelta::days(1), now));
+
+    // resume from the latest candle, if it's within the lookback
+    let since = now - TimeDelta::hours(3);
+    assert_eq!(
+        windows(Interval::FiveMinutes, Some(since), now),
+        vec![(since, now)]
+    );
+    assert_eq!(
+        windows(
+            Interval::FifteenMinutes,
+            Some(now - TimeDelta::days(90)),
+            now
+        ),
+        vec![(now - TimeDelta::days(59), now)]
+    );
+}
+
+#[test]
+fn chart_tolerates_halted_sessions() {
+    let json = r#"{
+        "chart": {
+            "result": [{
+                "meta": {"exchangeTimezoneName": "America/New_York"},
+                "timestamp": [1704205800, 1704292200, 1704378600],
+                "indicators": {
+                    "quote": [{
+                        "open": [10.0, null, null],
+                        "high": [11.0, null, 12.5],
+                        "low": [9.5, null, null],
+                        "close": [10.5, null, 12.0],
+                        "volume": [1000, null, null]
+                    }]
+                }
+            }],
+            "error": null
+        }
+    }"#;
+    let response: PriceResponse = serde_json::from_str(json).expect("valid chart");
+    let prices = response
+        .into_prices(1, Interval::Day)
+        .expect("prices within chart");
+
+    // the halted session is skipped, the rest filled from the close
+    assert_eq!(prices.prices.len(), 2);
+    let last = &prices.prices[1];
+    assert_eq!(
+        (
+            last.open,
+            last.high,
+            last.low,
+            last.close,
+            last.adj_close,
+            last.volume
+        ),
+        (12.0, 12.5, 12.0, 12.0, 12.0, 0)
+    );
+
+    // Yahoo explains unknown symbols
+    let json = r#"{"chart": {"result": null, "error": {"code": "Not Found", "description": "No data found, symbol may be delisted"}}}"#;
+    let response: PriceResponse = serde_json::from_str(json).expect("valid chart");
+    assert_eq!(
+        response.chart.error.expect("chart error").to_string(),
+        "Not Found: No data found, symbol may be delisted"
+    );
+
+    // SEC to Yahoo symbols
+    assert_eq!(symbol("BRK.B", ""), "BRK-B");
+    assert_eq!(symbol("BF/B", ""), "BF-B");
+    assert_eq!(symbol("SPCE.WS", ""), "SPCE-WT");
+    assert_eq!(symbol("NVDA", ""), "NVDA");
+    assert_eq!(symbol("BT.A", ".L"), "BT-A.L");
+    assert_eq!(symbol("DLR.U", ".TO"), "DLR-U.TO");
+    assert_eq!(symbol("DLR-U", ".TO"), "DLR-U.TO");
 }
@@ -0,0 +1,476 @@
+use super::{sql, yahoo_finance};
+use crate::http::*;
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{debug, error, info, trace};
+
+// Option chain snapshots, from the options endpoint (with the same cookie & crumb as quoteSummary)
+//
+// `https://query2.finance.yahoo.com/v7/finance/options/{ticker}?crumb=`
+//
+// responds with every expiration date & the nearest expiry's chain; every other expiry is then
+// fetched with `&date={expiration}`.
+//
+// Each snapshot stores every contract in `stock.option_chains`, and per expiry aggregates in
+// `stock.option_expiries`; put/call ratios of volume & open interest, and the ATM implied
+// volatility (the mean of the call & put IV at the strike nearest the underlying's price), which
+// across expiries is the ATM IV term structure.
+
+const CONCURRENCY: usize = 4;
+
+/// Underlyings to snapshot, unless set (comma separated) by `YAHOO_OPTION_SYMBOLS`.
+const DEFAULT_OPTION_SYMBOLS: [&str; 3] = ["AAPL", "MSFT", "NVDA"];
+
+#[derive(Debug)]
+struct Ticker {
+    pk: i32,
+    ticker: String,
+    title: String,
+    yahoo_suffix: String,
+}
+
+// scrape
+// ----------------------------------------------------------------------------
+
+/// Snapshot the option chain of every expiry, of every underlying.
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    // wait for a pg client from the pool
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{source:^40}\n{bar}",
+            bar = "=".repeat(40),
+            source = "Yahoo! Finance Options"
+        )
+    }
+
+    let symbols: Vec<String> = match var("YAHOO_OPTION_SYMBOLS") {
+        Ok(symbols) => symbols
+            .split(',')
+            .map(|symbol| symbol.trim().to_uppercase())
+            .filter(|symbol| !symbol.is_empty())
+            .collect(),
+        Err(_) => DEFAULT_OPTION_SYMBOLS
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    };
+
+    info!("fetching option underlyings from stock.symbols ...");
+    let tickers: Vec<Ticker> = pg_client
+        .query(sql::SYMBOLS_BY_TICKER, &[&symbols])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stock.symbols, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| Ticker {
+            pk: row.get("pk"),
+            ticker: row.get("symbol"),
+            title: row.get("title"),
+            yahoo_suffix: row.get("yahoo_suffix"),
+        })
+        .collect();
+
+    drop(pg_client);
+
+    // cookie & crumb handshake, shared by every request
+    let http_client = crate::std_client_build();
+    // options are optional, so a failed handshake skips them rather than the whole pipeline
+    let session = match yahoo_finance::session(&http_client).await {
+        Ok(session) => session,
+        Err(err) => {
+            error!("failed to establish a Yahoo session, skipping options, error({err})");
+            return Ok(());
+        }
+    };
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(tickers.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    info!("fetching Yahoo Finance option chains ...");
+    stream::iter(&tickers)
+        .for_each_concurrent(CONCURRENCY, |ticker| {
+            let http_client = &http_client;
+            let session = &session;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("fetching [{}] {} options", &ticker.ticker, &ticker.title),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                match snapshot(http_client, pool, session, ticker).await {
+                    Ok(expiries) => {
+                        trace!(
+                            "{expiries} option expiries collected for [{}] {}",
+                            &ticker.ticker,
+                            &ticker.title
+                        );
+
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            "failed to collect options for [{}] {}, error({err})",
+                            &ticker.ticker, &ticker.title
+                        );
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+    }
+
+    Ok(())
+}
+
+/// Fetch & insert every expiry of a single underlying, returning the number of expiries.
+async fn snapshot(
+    http_client: &HttpClient,
+    pool: &Pool,
+    session: &yahoo_finance::Session,
+    ticker: &Ticker,
+) -> anyhow::Result<usize> {
+    let time = std::time::Instant::now();
+    let snapshot = Utc::now();
+    let url = format!(
+        "https://query2.finance.yahoo.com/v7/finance/options/{}?crumb={}",
+        yahoo_finance::symbol(&ticker.ticker, &ticker.yahoo_suffix),
+        &session.crumb
+    );
+
+    // the first response lists every expiry, and holds the nearest one's chain
+    let first = fetch(http_client, session, &url).await?;
+    let mut chains = first.options;
+    for expiration in first.expiration_dates.iter().skip(1) {
+        let chain = fetch(http_client, session, &format!("{url}&date={expiration}")).await?;
+        chains.extend(chain.options);
+    }
+
+    let underlying = first.quote.regular_market_price;
+    let mut pg_client = pool.get().await?;
+    let transaction = pg_client.transaction().await?;
+    let contract_query = transaction.prepare(sql::INSERT_OPTION_CONTRACT).await?;
+    let expiry_query = transaction.prepare(sql::INSERT_OPTION_EXPIRY).await?;
+    for chain in &chains {
+        let expiry = match DateTime::from_timestamp(chain.expiration_date, 0) {
+            Some(dt) => dt.date_naive(),
+            None => {
+                error!(
+                    "invalid expiry {} for [{}] {}, skipping its chain",
+                    chain.expiration_date, &ticker.ticker, &ticker.title
+                );
+                continue;
+            }
+        };
+
+        for (put, contract) in chain
+            .calls
+            .iter()
+            .map(|call| (false, call))
+            .chain(chain.puts.iter().map(|put| (true, put)))
+        {
+            transaction
+                .execute(
+                    &contract_query,
+                    &[
+                        &ticker.pk,
+                        &snapshot,
+                        &expiry,
+                        &put,
+                        &contract.contract_symbol,
+                        &contract.strike,
+                        &contract.bid,
+                        &contract.ask,
+                        &contract.last_price,
+                        &contract.volume,
+                        &contract.open_interest,
+                        &contract.implied_volatility,
+                        &contract.in_the_money,
+                    ],
+                )
+                .await?;
+        }
+
+        let aggregate = Aggregate::of(chain, underlying);
+        transaction
+            .execute(
+                &expiry_query,
+                &[
+                    &ticker.pk,
+                    &snapshot,
+                    &expiry,
+                    &underlying,
+                    &aggregate.call_volume,
+                    &aggregate.put_volume,
+                    &aggregate.call_open_interest,
+                    &aggregate.put_open_interest,
+                    &aggregate.put_call_volume,
+                    &aggregate.put_call_open_interest,
+                    &aggregate.atm_strike,
+                    &aggregate.atm_iv,
+                ],
+            )
+            .await?;
+    }
+    transaction.commit().await.map_err(|err| {
+        error!(
+            "failed to commit option chains for [{}] {}, error({err})",
+            &ticker.ticker, &ticker.title
+        );
+        err
+    })?;
+
+    debug!(
+        "[{}] {} option chains inserted. {}",
+        &ticker.ticker,
+        &ticker.title,
+        crate::time_elapsed(time)
+    );
+
+    Ok(chains.len())
+}
+
+/// Fetch a single options response.
+async fn fetch(
+    http_client: &HttpClient,
+    session: &yahoo_finance::Session,
+    url: &str,
+) -> anyhow::Result<OptionResult> {
+    let response: OptionResponse = http_client
+        .get(url)
+        .header(reqwest::header::COOKIE, &session.cookie)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    response
+        .option_chain
+        .result
+        .into_iter()
+        .next()
+        .ok_or(anyhow::anyhow!("no results found within http response"))
+}
+
+// aggregates
+// ----------------------------------------------------------------------------
+
+/// Per expiry aggregates of a chain.
+#[derive(Debug, PartialEq)]
+struct Aggregate {
+    call_volume: i64,
+    put_volume: i64,
+    call_open_interest: i64,
+    put_open_interest: i64,
+
+    /// Put volume / call volume, `None` without any call volume.
+    put_call_volume: Option<f64>,
+
+    /// Put open interest / call open interest, `None` without any call open interest.
+    put_call_open_interest: Option<f64>,
+
+    /// The strike nearest the underlying's price, quoted for both calls & puts.
+    atm_strike: Option<f64>,
+
+    /// The mean of the call & put implied volatility at the ATM strike.
+    atm_iv: Option<f64>,
+}
+
+impl Aggregate {
+    fn of(chain: &Chain, underlying: Option<f64>) -> Aggregate {
+        let sum = |contracts: &[Contract], field: fn(&Contract) -> Option<i64>| -> i64 {
+            contracts.iter().filter_map(field).sum()
+        };
+        let ratio = |puts: i64, calls: i64| (calls > 0).then(|| puts as f64 / calls as f64);
+
+        let call_volume = sum(&chain.calls, |c| c.volume);
+        let put_volume = sum(&chain.puts, |c| c.volume);
+        let call_open_interest = sum(&chain.calls, |c| c.open_interest);
+        let put_open_interest = sum(&chain.puts, |c| c.open_interest);
+
+        // the nearest strike with both a call & a put IV
+        let atm = underlying.and_then(|price| {
+            chain
+                .calls
+                .iter()
+                .filter_map(|call| {
+                    let put = chain.puts.iter().find(|put| put.strike == call.strike)?;
+                    Some((
+                        call.strike,
+                        call.implied_volatility?,
+                        put.implied_volatility?,
+                    ))
+                })
+                .min_by(|(a, ..), (b, ..)| (a - price).abs().total_cmp(&(b - price).abs()))
+        });
+
+        Aggregate {
+            call_volume,
+            put_volume,
+            call_open_interest,
+            put_open_interest,
+            put_call_volume: ratio(put_volume, call_volume),
+            put_call_open_interest: ratio(put_open_interest, call_open_interest),
+            atm_strike: atm.map(|(strike, ..)| strike),
+            atm_iv: atm.map(|(_, call_iv, put_iv)| (call_iv + put_iv) / 2.0),
+        }
+    }
+}
+
+// de
+// ----------------------------------------------------------------------------
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OptionResponse {
+    option_chain: OptionChain,
+}
+
+#[derive(Debug, Deserialize)]
+struct OptionChain {
+    #[serde(default)]
+    result: Vec<OptionResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OptionResult {
+    #[serde(default)]
+    expiration_dates: Vec<i64>,
+    quote: Quote,
+    #[serde(default)]
+    options: Vec<Chain>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Quote {
+    regular_market_price: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Chain {
+    expiration_date: i64,
+    #[serde(default)]
+    calls: Vec<Contract>,
+    #[serde(default)]
+    puts: Vec<Contract>,
+}
+
+// {
+//     "contractSymbol": "AAPL240621C00190000",
+//     "strike": 190.0,
+//     "currency": "USD",
+//     "lastPrice": 4.35,
+//     "volume": 10422,
+//     "openInterest": 48213,
+//     "bid": 4.3,
+//     "ask": 4.4,
+//     "impliedVolatility": 0.2187,
+//     "inTheMoney": true,
+//     ...
+// }
+//
+// NOTE: volume & open interest are left out, rather than 0, for untraded contracts
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Contract {
+    contract_symbol: String,
+    strike: f64,
+    last_price: Option<f64>,
+    bid: Option<f64>,
+    ask: Option<f64>,
+    volume: Option<i64>,
+    open_interest: Option<i64>,
+    implied_volatility: Option<f64>,
+    in_the_money: Option<bool>,
+}
+
+// -- TESTS --
+
+#[test]
+fn expiry_aggregates() {
+    let contract = |strike: f64, volume, open_interest, implied_volatility| Contract {
+        contract_symbol: format!("AAPL240621C{strike}"),
+        strike,
+        last_price: None,
+        bid: None,
+        ask: None,
+        volume,
+        open_interest: Some(open_interest),
+        implied_volatility: Some(implied_volatility),
+        in_the_money: None,
+    };
+    let chain = Chain {
+        expiration_date: 1718928000,
+        calls: vec![
+            contract(180.0, Some(100), 1000, 0.30),
+            contract(190.0, Some(300), 2000, 0.20),
+            contract(200.0, None, 500, 0.20),
+        ],
+        puts: vec![
+            contract(180.0, Some(200), 1500, 0.34),
+            contract(190.0, Some(200), 1500, 0.30),
+        ],
+    };
+
+    assert_eq!(
+        Aggregate::of(&chain, Some(192.5)),
+        Aggregate {
+            call_volume: 400,
+            put_volume: 400,
+            call_open_interest: 3500,
+            put_open_interest: 3000,
+            put_call_volume: Some(1.0),
+            put_call_open_interest: Some(3000.0 / 3500.0),
+            atm_strike: Some(190.0),
+            atm_iv: Some(0.25),
+        }
+    );
+
+    // without a price, there's no ATM
+    assert_eq!(Aggregate::of(&chain, None).atm_iv, None);
+}
@@ -0,0 +1,380 @@
+use super::{sql, yahoo_finance};
+use crate::http::*;
+use deadpool_postgres::Pool;
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{debug, error, info, trace};
+
+// Company profiles & key statistics, from the quoteSummary endpoint
+//
+// `https://query2.finance.yahoo.com/v10/finance/quoteSummary/{ticker}?modules=&crumb=`
+//
+// assetProfile = sector, industry & employees
+//
+// defaultKeyStatistics = beta, float & short interest
+//
+// financialData = analyst price targets & recommendation
+//
+// Each symbol is refreshed once its profile is a week old; see `sql::STALE_PROFILES`.
+
+const CONCURRENCY: usize = 4;
+const MODULES: &str = "assetProfile,defaultKeyStatistics,financialData";
+
+#[derive(Debug)]
+struct Ticker {
+    pk: i32,
+    ticker: String,
+    title: String,
+    yahoo_suffix: String,
+}
+
+// scrape
+// ----------------------------------------------------------------------------
+
+/// Collect the profile of every symbol without one, or with one older than a week.
+pub async fn scrape(pool: &Pool, tui: bool) -> anyhow::Result<()> {
+    // wait for a pg client from the pool
+    let pg_client = pool.get().await.map_err(|err| {
+        error!("failed to get pg client from pool, error({err})");
+        err
+    })?;
+
+    if tui {
+        println!(
+            "{bar}\n{source:^40}\n{bar}",
+            bar = "=".repeat(40),
+            source = "Yahoo! Finance Profiles"
+        )
+    }
+
+    info!("fetching stale stock.profiles ...");
+    let tickers: Vec<Ticker> = pg_client
+        .query(sql::STALE_PROFILES, &[])
+        .await
+        .map_err(|err| {
+            error!("failed to fetch stale stock.profiles, error({err})");
+            err
+        })?
+        .into_iter()
+        .map(|row| Ticker {
+            pk: row.get("pk"),
+            ticker: row.get("symbol"),
+            title: row.get("title"),
+            yahoo_suffix: row.get("yahoo_suffix"),
+        })
+        .collect();
+
+    drop(pg_client);
+
+    if tickers.is_empty() {
+        info!("every stock profile is up to date");
+        return Ok(());
+    }
+
+    // cookie & crumb handshake, shared by every request
+    let http_client = crate::std_client_build();
+    // profiles are optional, so a failed handshake skips them rather than the whole pipeline
+    let session = match yahoo_finance::session(&http_client).await {
+        Ok(session) => session,
+        Err(err) => {
+            error!("failed to establish a Yahoo session, skipping profiles, error({err})");
+            return Ok(());
+        }
+    };
+
+    // progress bars
+    let (multi, total, success, fail) = if tui {
+        crate::tui::multi_progress(tickers.len())?
+    } else {
+        (None, None, None, None)
+    };
+
+    info!("fetching Yahoo Finance profiles ...");
+    stream::iter(&tickers)
+        .for_each_concurrent(CONCURRENCY, |ticker| {
+            let http_client = &http_client;
+            let session = &session;
+
+            // progress bars
+            let multi = multi.clone();
+            let total = total.clone();
+            let success = success.clone();
+            let fail = fail.clone();
+            async move {
+                // if tui is enabled, create a progress bar, per task currently being executed
+                let spinner = crate::tui::multi_progress_spinner(
+                    multi,
+                    format!("fetching [{}] {} profile", &ticker.ticker, &ticker.title),
+                );
+                spinner.enable_steady_tick(Duration::from_millis(50));
+
+                match collect(http_client, pool, session, ticker).await {
+                    Ok(_) => {
+                        trace!(
+                            "profile collected for [{}] {}",
+                            &ticker.ticker,
+                            &ticker.title
+                        );
+
+                        if tui {
+                            success.expect("successbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                    Err(err) => {
+                        error!(
+                            "failed to collect profile for [{}] {}, error({err})",
+                            &ticker.ticker, &ticker.title
+                        );
+
+                        if tui {
+                            fail.expect("failbar should have unwrapped").inc(1);
+                            total.expect("totalbar should have unwrapped").inc(1);
+                        }
+                    }
+                }
+
+                spinner.finish_and_clear();
+            }
+        })
+        .await;
+
+    if tui {
+        fail.expect("failbar should have unwrapped")
+            .finish_with_message("failed");
+        success
+            .expect("successbar should have unwrapped")
+            .finish_with_message("success");
+        total
+            .expect("totalbar should have unwrapped")
+            .finish_with_message("done");
+    }
+
+    Ok(())
+}
+
+/// Fetch & upsert the profile of a single ticker; a ticker Yahoo doesn't know is stored empty.
+async fn collect(
+    http_client: &HttpClient,
+    pool: &Pool,
+    session: &yahoo_finance::Session,
+    ticker: &Ticker,
+) -> anyhow::Result<()> {
+    let url = format!(
+        "https://query2.finance.yahoo.com/v10/finance/quoteSummary/{}?modules={MODULES}&crumb={}",
+        yahoo_finance::symbol(&ticker.ticker, &ticker.yahoo_suffix),
+        &session.crumb
+    );
+    let response = http_client
+        .get(url)
+        .header(reqwest::header::COOKIE, &session.cookie)
+        .send()
+        .await?;
+
+    let profile = if response.status() == reqwest::StatusCode::NOT_FOUND {
+        debug!(
+            "no profile found for [{}] {}",
+            &ticker.ticker, &ticker.title
+        );
+        Profile::default()
+    } else {
+        let summary: SummaryResponse = response.error_for_status()?.json().await?;
+        summary.into_profile().unwrap_or_default()
+    };
+
+    let pg_client = pool.get().await?;
+    profile.insert(&pg_client, ticker.pk).await
+}
+
+// de
+// ----------------------------------------------------------------------------
+
+// output
+#[derive(Debug, Default, PartialEq)]
+struct Profile {
+    sector: Option<String>,
+    industry: Option<String>,
+    employees: Option<i32>,
+    beta: Option<f64>,
+    float_shares: Option<i64>,
+    shares_short: Option<i64>,
+    short_ratio: Option<f64>,
+    short_percent_of_float: Option<f64>,
+    target_low: Option<f64>,
+    target_mean: Option<f64>,
+    target_median: Option<f64>,
+    target_high: Option<f64>,
+    analysts: Option<i32>,
+    recommendation: Option<String>,
+}
+
+impl Profile {
+    /// UPSERT self to pg rows.
+    async fn insert(&self, pg_client: &PgClient, symbol_pk: i32) -> anyhow::Result<()> {
+        pg_client
+            .execute(
+                sql::UPSERT_PROFILE,
+                &[
+                    &symbol_pk,
+                    &self.sector,
+                    &self.industry,
+                    &self.employees,
+                    &self.beta,
+                    &self.float_shares,
+                    &self.shares_short,
+                    &self.short_ratio,
+                    &self.short_percent_of_float,
+                    &self.target_low,
+                    &self.target_mean,
+                    &self.target_median,
+                    &self.target_high,
+                    &self.analysts,
+                    &self.recommendation,
+                ],
+            )
+            .await
+            .map_err(|err| {
+                error!("failed to upsert stock.profiles for {symbol_pk}, error({err})");
+                err
+            })?;
+
+        Ok(())
+    }
+}
+
+// input
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SummaryResponse {
+    quote_summary: QuoteSummary,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuoteSummary {
+    result: Option<Vec<Summary>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Summary {
+    asset_profile: AssetProfile,
+    default_key_statistics: KeyStatistics,
+    financial_data: FinancialData,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct AssetProfile {
+    sector: Option<String>,
+    industry: Option<String>,
+    full_time_employees: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct KeyStatistics {
+    beta: Raw<f64>,
+    float_shares: Raw<i64>,
+    shares_short: Raw<i64>,
+    short_ratio: Raw<f64>,
+    short_percent_of_float: Raw<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FinancialData {
+    target_low_price: Raw<f64>,
+    target_mean_price: Raw<f64>,
+    target_median_price: Raw<f64>,
+    target_high_price: Raw<f64>,
+    number_of_analyst_opinions: Raw<i32>,
+    recommendation_key: Option<String>,
+}
+
+/// Numbers are returned as `{"raw": 1.25, "fmt": "1.25"}`, or `{}` if missing.
+#[derive(Debug, Deserialize)]
+struct Raw<T> {
+    raw: Option<T>,
+}
+
+impl<T> Default for Raw<T> {
+    fn default() -> Self {
+        Raw { raw: None }
+    }
+}
+
+impl SummaryResponse {
+    /// Flatten the quoteSummary modules into a profile, or `None` if there are no results.
+    fn into_profile(self) -> Option<Profile> {
+        let summary = self.quote_summary.result?.into_iter().next()?;
+        let (profile, stats, financials) = (
+            summary.asset_profile,
+            summary.default_key_statistics,
+            summary.financial_data,
+        );
+
+        Some(Profile {
+            sector: profile.sector.filter(|s| !s.is_empty()),
+            industry: profile.industry.filter(|s| !s.is_empty()),
+            employees: profile.full_time_employees,
+            beta: stats.beta.raw,
+            float_shares: stats.float_shares.raw,
+            shares_short: stats.shares_short.raw,
+            short_ratio: stats.short_ratio.raw,
+            short_percent_of_float: stats.short_percent_of_float.raw,
+            target_low: financials.target_low_price.raw,
+            target_mean: financials.target_mean_price.raw,
+            target_median: financials.target_median_price.raw,
+            target_high: financials.target_high_price.raw,
+            analysts: financials.number_of_analyst_opinions.raw,
+            recommendation: financials.recommendation_key.filter(|s| s != "none"),
+        })
+    }
+}
+
+// -- TESTS --
+
+#[test]
+fn profile_from_quote_summary() {
+    let json = r#"{
+        "quoteSummary": {
+            "result": [{
+                "assetProfile": {
+                    "sector": "Technology",
+                    "industry": "Consumer Electronics",
+                    "fullTimeEmployees": 164000
+                },
+                "defaultKeyStatistics": {
+                    "beta": {"raw": 1.24, "fmt": "1.24"},
+                    "floatShares": {"raw": 15204137000, "fmt": "15.2B"},
+                    "sharesShort": {},
+                    "shortRatio": {"raw": 1.8, "fmt": "1.8"}
+                },
+                "financialData": {
+                    "targetMeanPrice": {"raw": 235.5, "fmt": "235.50"},
+                    "numberOfAnalystOpinions": {"raw": 38, "fmt": "38"},
+                    "recommendationKey": "buy"
+                }
+            }],
+            "error": null
+        }
+    }"#;
+    let summary: SummaryResponse = serde_json::from_str(json).expect("valid quoteSummary");
+
+    assert_eq!(
+        summary.into_profile(),
+        Some(Profile {
+            sector: Some("Technology".to_string()),
+            industry: Some("Consumer Electronics".to_string()),
+            employees: Some(164000),
+            beta: Some(1.24),
+            float_shares: Some(15204137000),
+            short_ratio: Some(1.8),
+            target_mean: Some(235.5),
+            analysts: Some(38),
+            recommendation: Some("buy".to_string()),
+            ..Default::default()
+        })
+    );
+}
@@ -23,6 +23,50 @@ pub enum Commands {
         endpoints: Option<Vec<Endpoint>>,
     },
 
+    /// Scan the price series for missing candles, and refetch them from their source.
+    ///
+    /// Gaps the source itself can't fill are reported, and skipped by later repairs.
+    Repair {
+        /// Specify the endpoints to repair, i.e. crypto and/or stocks.
+        ///
+        /// If no endpoints are provided, repair will scan all.
+        #[arg(short, long)]
+        endpoints: Option<Vec<Endpoint>>,
+
+        /// Refetch the gaps already known to be unfillable, too.
+        #[arg(short, long)]
+        retry: bool,
+    },
+
+    /// Compare the daily closes of each price provider, flagging the days they disagree on.
+    ///
+    /// Yahoo! Finance is the primary provider; each fallback (i.e. Stooq) is compared against it.
+    Reconcile {
+        /// Specify the stock symbols to reconcile, e.g. AAPL MSFT.
+        #[arg(required = true)]
+        symbols: Vec<String>,
+
+        /// Relative difference of the adjusted closes tolerated, e.g. 0.005 (0.5%).
+        #[arg(long, default_value_t = 0.005)]
+        tolerance: f64,
+    },
+
+    /// Full-text search of the downloaded SEC filings, e.g. `junk search "goodwill impairment"`.
+    ///
+    /// Supports web search syntax, i.e. "quoted phrases", `or` & -excluded words.
+    Search {
+        /// The search query.
+        query: String,
+
+        /// Only search filings of this form type, e.g. 10-K.
+        #[arg(short, long)]
+        form: Option<String>,
+
+        /// The number of filings returned, by rank.
+        #[arg(short, long, default_value_t = 20)]
+        limit: i64,
+    },
+
     /// Test suite.
     Test,
 }
@@ -45,6 +89,9 @@ pub enum Endpoint {
     /// Economic data.
     Econ,
 
+    /// Exchange account balances, transfers & fills (read-only).
+    Portfolio,
+
     /// Stock price & filings data.
     Stocks,
 }
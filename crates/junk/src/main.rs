@@ -1,4 +1,7 @@
 mod cli;
+mod reconcile;
+mod repair;
+mod search;
 mod spider;
 
 // remote imports
@@ -53,10 +56,25 @@ async fn main() -> anyhow::Result<()> {
             // if no endpoints provided, scrape all
             match endpoints {
                 Some(endpoints) => spider::run(endpoints, tui).await?,
-                None => spider::run(vec![Crypto, Econ, Stocks], tui).await?,
+                None => spider::run(vec![Crypto, Econ, Portfolio, Stocks], tui).await?,
             }
         }
 
+        // `junk repair <Option<Vec<Endpoint>>> [--retry]`: refetch missing prices
+        Repair { endpoints, retry } => {
+            // if no endpoints provided, repair all
+            match endpoints {
+                Some(endpoints) => repair::run(endpoints, tui, retry).await?,
+                None => repair::run(vec![Crypto, Stocks], tui, retry).await?,
+            }
+        }
+
+        // `junk reconcile <Vec<String>> [--tolerance]`: compare price providers
+        Reconcile { symbols, tolerance } => reconcile::run(symbols, tui, tolerance).await?,
+
+        // `junk search <String> [--form] [--limit]`: full-text search of filings
+        Search { query, form, limit } => search::run(query, form, limit).await?,
+
         // test env
         Test => {
             // use junk_spider::stock::common::Ticker;
@@ -0,0 +1,23 @@
+use tracing::info;
+
+/// Compare the daily closes of each price provider for the symbols, recording every day they
+/// disagree on beyond the tolerance.
+pub(crate) async fn run(symbols: Vec<String>, tui: bool, tolerance: f64) -> anyhow::Result<()> {
+    use junk_spider::stock;
+
+    // 1. build pg pool connection
+    let pool = crate::spider::pool()?;
+
+    // start reconciling providers
+    let time = std::time::Instant::now();
+    let discrepancies = stock::providers::reconcile(&pool, tui, &symbols, tolerance).await?;
+
+    info!(
+        "{} stock symbols reconciled, {} discrepancies found, time elapsed: {:?}",
+        symbols.len(),
+        discrepancies.len(),
+        time.elapsed()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use crate::cli::Endpoint;
+use tracing::{info, warn};
+
+/// Scan the price series of each endpoint for gaps, and refetch them from their source.
+pub(crate) async fn run(endpoints: Vec<Endpoint>, tui: bool, retry: bool) -> anyhow::Result<()> {
+    // 1. build pg pool connection
+    let pool = crate::spider::pool()?;
+
+    // start repairing data
+    let time = std::time::Instant::now();
+    for endpoint in endpoints {
+        match endpoint {
+            Endpoint::Crypto => {
+                use junk_spider::crypto;
+                let time = std::time::Instant::now();
+
+                let unfilled = crypto::repair::fill(&pool, tui, retry).await?;
+
+                info!(
+                    "crypto prices repaired, {} gaps unfillable, time elapsed: {:?}",
+                    unfilled.len(),
+                    time.elapsed()
+                );
+            }
+
+            Endpoint::Stocks => {
+                use junk_spider::stock;
+                let time = std::time::Instant::now();
+
+                let unfilled = stock::repair::fill(&pool, tui, retry).await?;
+
+                info!(
+                    "stock prices repaired, {} gaps unfillable, time elapsed: {:?}",
+                    unfilled.len(),
+                    time.elapsed()
+                );
+            }
+
+            Endpoint::Econ | Endpoint::Portfolio => {
+                warn!("{endpoint:?} has no price series to repair");
+            }
+        }
+    }
+
+    info!("repair finished, time elapsed: {:?}", time.elapsed());
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use tracing::info;
+
+/// Full-text search of the downloaded SEC filings, printing each filing found by rank.
+pub(crate) async fn run(query: String, form: Option<String>, limit: i64) -> anyhow::Result<()> {
+    use junk_spider::stock;
+
+    // 1. build pg pool connection
+    let pool = crate::spider::pool()?;
+
+    // search filings
+    let time = std::time::Instant::now();
+    let hits = stock::sec_filings::search(&pool, &query, form.as_deref(), limit).await?;
+
+    for hit in &hits {
+        println!(
+            "[{}] {} | {} {} | rank {:.3}\n{}\n... {} ...\n",
+            hit.symbol, hit.title, hit.form, hit.dated, hit.rank, hit.url, hit.headline
+        );
+    }
+
+    info!(
+        "{} filings found for \"{query}\", time elapsed: {:?}",
+        hits.len(),
+        time.elapsed()
+    );
+
+    Ok(())
+}
@@ -1,12 +1,11 @@
 use crate::cli::Endpoint;
-use deadpool_postgres::{ManagerConfig, RecyclingMethod};
+use deadpool_postgres::{ManagerConfig, Pool, RecyclingMethod};
 use dotenv::var;
 // use junk_spider as spider;
 use tracing::{debug, info, trace};
 
-/// Run all working spider processes.
-pub(crate) async fn run(endpoints: Vec<Endpoint>, tui: bool) -> anyhow::Result<()> {
-    // 1. build pg pool connection
+/// Build the findump connection pool.
+pub(crate) fn pool() -> anyhow::Result<Pool> {
     trace!("creating postgres connection pool config");
     let mut pg_config = deadpool_postgres::Config::new();
     pg_config.url = Some(var("FINDUMP_URL")?);
@@ -21,6 +20,14 @@ pub(crate) async fn run(endpoints: Vec<Endpoint>, tui: bool) -> anyhow::Result<(
     )?;
     debug!("findump connection pool established");
 
+    Ok(pool)
+}
+
+/// Run all working spider processes.
+pub(crate) async fn run(endpoints: Vec<Endpoint>, tui: bool) -> anyhow::Result<()> {
+    // 1. build pg pool connection
+    let pool = pool()?;
+
     // let pool = sqlx::postgres::PgPoolOptions::new()
     //     .max_connections(num_cpus::get())
     //     .connect(&var("FINDUMP_URL")?)
@@ -38,7 +45,13 @@ pub(crate) async fn run(endpoints: Vec<Endpoint>, tui: bool) -> anyhow::Result<(
                 crypto::mexc::scrape(&pool, tui).await?;
                 crypto::kraken::scrape(&pool, tui).await?;
                 crypto::binance::scrape(&pool, tui).await?;
+                crypto::binance_archive::scrape(&pool, tui).await?;
                 crypto::kucoin::scrape(&pool, tui).await?;
+                crypto::binance_futures::scrape(&pool, tui).await?;
+                crypto::kucoin_futures::scrape(&pool, tui).await?;
+                crypto::composite::build(&pool, tui, crypto::composite::DIVERGENCE_THRESHOLD)
+                    .await?;
+                crypto::usd::convert(&pool, tui).await?;
 
                 info!("crypto data collected, time elapsed: {:?}", time.elapsed());
             }
@@ -55,14 +68,37 @@ pub(crate) async fn run(endpoints: Vec<Endpoint>, tui: bool) -> anyhow::Result<(
                 );
             }
 
+            Endpoint::Portfolio => {
+                use junk_spider::crypto;
+                let time = std::time::Instant::now();
+
+                crypto::portfolio::sync(&pool, tui).await?;
+
+                info!(
+                    "portfolio data collected, time elapsed: {:?}",
+                    time.elapsed()
+                );
+            }
+
             Endpoint::Stocks => {
                 use junk_spider::stock;
                 let time = std::time::Instant::now();
 
-                // stock::sec_bulks::scrape(tui).await?;
+                // stock::sec_bulks::scrape(&pool, tui).await?;
                 stock::sec_tickers::scrape(&pool, tui).await?;
+                stock::sec_submissions::scrape(&pool, tui).await?;
+                stock::sec_filings::scrape(&pool, tui).await?;
+                stock::sec_insiders::scrape(&pool, tui).await?;
+                stock::sec_institutions::scrape(&pool, tui).await?;
+                stock::listings::scrape(&pool, tui).await?;
+                stock::benchmarks::scrape(&pool, tui).await?;
                 stock::yahoo_finance::scrape(&pool, tui).await?;
+                stock::yahoo_finance::scrape_intraday(&pool, tui).await?;
+                stock::yahoo_profiles::scrape(&pool, tui).await?;
+                stock::yahoo_options::scrape(&pool, tui).await?;
                 // stock::sec_metrics::scrape(&pool, tui).await?;
+                stock::sec_metrics::refresh(&pool, tui).await?;
+                stock::std_financials::build(&pool, tui).await?;
 
                 info!("stock data collected, time elapsed: {:?}", time.elapsed());
             }